@@ -0,0 +1,71 @@
+use crate::{Stats, Window};
+use std::time::{Duration, SystemTime};
+
+impl<T> Window<T> {
+    /// Number of samples covering `span` at the rate the samples of the window were
+    /// recorded at, at least two so that the rate can still be estimated.
+    pub(crate) fn adaptive_size(&self, span: Duration, max_samples: usize) -> usize {
+        let (times, _) = self.between(SystemTime::UNIX_EPOCH, None);
+        let covered = match (times.first(), times.last()) {
+            (Some(first), Some(last)) if times.len() >= 2 => {
+                last.duration_since(*first).unwrap_or_default()
+            }
+            _ => return max_samples.max(2),
+        };
+        if covered.is_zero() {
+            return max_samples.max(2);
+        }
+        let rate = (times.len() - 1) as f64 / covered.as_secs_f64();
+        let size = (rate * span.as_secs_f64()).ceil() + 1.0;
+        (size.min(max_samples as f64) as usize).max(2)
+    }
+}
+
+impl Stats {
+    /// Sizes the ping and transmission rate windows of each peer to hold about `span` of
+    /// its samples at the rate they arrive, at most `max_samples`, instead of `window_size`.
+    /// Memory then follows the activity of the peers, while rarely sampled peers still
+    /// keep two samples.
+    pub fn with_adaptive_windows(mut self, span: Duration, max_samples: usize) -> Self {
+        self.adaptive_windows = Some((span, max_samples));
+        self
+    }
+
+    /// Size of `window` after its next sample.
+    pub(crate) fn window_size_of<T>(&self, window: &Window<T>) -> usize {
+        let window_size = match self.adaptive_windows {
+            Some((span, max_samples)) => window.adaptive_size(span, max_samples),
+            None => self.window_size,
+        };
+        self.pressure_window_size(window_size)
+    }
+}
+
+#[test]
+fn windows_adapt_to_sample_rate() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock.clone())
+        .with_adaptive_windows(Duration::from_secs(60), 1_000);
+    for _ in 0..1_000 {
+        stats.add_ping("busy".to_string(), Duration::from_millis(10));
+        stats.add_transmission("busy".to_string(), Duration::from_secs(1), 1_000);
+        clock.advance(Duration::from_millis(100));
+    }
+    for _ in 0..100 {
+        stats.add_ping("quiet".to_string(), Duration::from_millis(10));
+        clock.advance(Duration::from_secs(10));
+    }
+    assert_eq!(stats.pings_to_peers.get("busy").unwrap().len(), 601);
+    assert_eq!(stats.transmissions_rates.get("busy").unwrap().len(), 601);
+    assert_eq!(stats.pings_to_peers.get("quiet").unwrap().len(), 7);
+    // Windows shrink when the peer slows down
+    for _ in 0..100 {
+        stats.add_ping("busy".to_string(), Duration::from_millis(10));
+        clock.advance(Duration::from_secs(10));
+    }
+    assert_eq!(stats.pings_to_peers.get("busy").unwrap().len(), 7);
+}
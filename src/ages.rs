@@ -0,0 +1,72 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+/// Ages of the samples of a window when its summary was taken, to tell ten samples of
+/// the last minute from ten samples spread over six hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SampleAges {
+    pub oldest: Duration,
+    pub newest: Duration,
+    pub median: Duration,
+}
+
+impl SampleAges {
+    /// Ages at `now` of samples recorded at `times`, which only grow. Samples from the
+    /// future of a clock set back are of age zero.
+    pub(crate) fn from_times(times: &[SystemTime], now: SystemTime) -> Option<Self> {
+        let age = |time: &SystemTime| now.duration_since(*time).unwrap_or_default();
+        let middle = times.len() / 2;
+        let median = if times.len().is_multiple_of(2) {
+            (age(times.get(middle.checked_sub(1)?)?) + age(&times[middle])) / 2
+        } else {
+            age(&times[middle])
+        };
+        Some(Self {
+            oldest: age(times.first()?),
+            newest: age(times.last()?),
+            median,
+        })
+    }
+}
+
+impl fmt::Display for SampleAges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "oldest {:?} median {:?} newest {:?}",
+            self.oldest, self.median, self.newest
+        )
+    }
+}
+
+#[test]
+fn ages_of_the_windows_are_summarized() {
+    use crate::{ManualClock, Stats};
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    for _ in 0..3 {
+        stats.add_ping("2".to_string(), Duration::from_millis(10));
+        clock.advance(Duration::from_secs(3_600));
+    }
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    clock.advance(Duration::from_secs(60));
+    let peer = stats.summarize_peer("2").unwrap();
+    let hours = |hours: u64| Duration::from_secs(hours * 3_600 + 60);
+    assert_eq!(
+        peer.ping_ages,
+        Some(SampleAges {
+            oldest: hours(3),
+            newest: hours(1),
+            median: hours(2),
+        })
+    );
+    let rate_ages = peer.transmission_rate_ages.unwrap();
+    assert_eq!(rate_ages.oldest, Duration::from_secs(60));
+    assert_eq!(rate_ages.to_string(), "oldest 60s median 60s newest 60s");
+    assert_eq!(SampleAges::from_times(&[], SystemTime::UNIX_EPOCH), None);
+}
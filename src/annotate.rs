@@ -0,0 +1,102 @@
+use crate::{PeerState, Rfc3339, Stats};
+use std::{collections::VecDeque, fmt, sync::PoisonError, time::SystemTime};
+
+/// Number of annotations kept for each peer, the oldest are dropped first.
+const PEER_ANNOTATIONS: usize = 16;
+/// Number of annotations kept for the whole node.
+const ANNOTATIONS: usize = 64;
+
+/// Note by an operator, e.g. "manually throttled".
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    pub time: SystemTime,
+    pub note: String,
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", Rfc3339(self.time), self.note)
+    }
+}
+
+fn push_annotation(annotations: &mut VecDeque<Annotation>, annotation: Annotation, limit: usize) {
+    if annotations.len() >= limit {
+        annotations.pop_front();
+    }
+    annotations.push_back(annotation);
+}
+
+impl Stats {
+    /// Records an event of the node, e.g. "deployed v1.4", which is included in snapshots
+    /// to explain discontinuities of the metrics.
+    pub fn annotate(&self, note: &str, time: SystemTime) {
+        let annotation = Annotation {
+            time,
+            note: note.to_string(),
+        };
+        let mut annotations = self
+            .annotations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        push_annotation(&mut annotations, annotation, ANNOTATIONS);
+    }
+
+    /// Events of the node, oldest first.
+    pub fn annotations(&self) -> Vec<Annotation> {
+        let annotations = self
+            .annotations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        annotations.iter().cloned().collect()
+    }
+
+    /// Attaches a note to the peer which is reported next to its metrics,
+    /// annotations do not count as samples for `PeerSummary::last_seen`.
+    pub fn annotate_peer(&self, peer_id: String, note: &str, time: SystemTime) {
+        trace_span!("annotate_peer");
+        let annotation = Annotation {
+            time,
+            note: note.to_string(),
+        };
+        let _recording = self.recording(&peer_id);
+        self.peers.alter(peer_id, |peer| {
+            let mut peer = peer.unwrap_or_else(|| PeerState::new(time));
+            push_annotation(&mut peer.annotations, annotation, PEER_ANNOTATIONS);
+            Some(peer)
+        });
+    }
+}
+
+#[test]
+fn peer_annotations_are_bounded() {
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    let last_seen = stats.snapshot().peers[0].last_seen;
+    for minute in 0..20 {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(60 * minute);
+        stats.annotate_peer("2".to_string(), &format!("note {}", minute), time);
+    }
+    let snapshot = stats.snapshot();
+    let annotations = &snapshot.peers[0].annotations;
+    assert_eq!(annotations.len(), PEER_ANNOTATIONS);
+    assert_eq!(annotations[0].note, "note 4");
+    assert_eq!(snapshot.peers[0].last_seen, last_seen);
+    assert!(snapshot
+        .to_string()
+        .contains("\"2\" 1970-01-01T00:19:00Z note 19\n"));
+}
+
+#[test]
+fn node_annotations_are_in_snapshots() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.annotate("deployed v1.4", SystemTime::UNIX_EPOCH);
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.annotations, stats.annotations());
+    assert_eq!(snapshot.annotations[0].note, "deployed v1.4");
+    assert!(snapshot
+        .to_string()
+        .contains("Annotations:\n1970-01-01T00:00:00Z deployed v1.4\n"));
+}
@@ -0,0 +1,224 @@
+//! Arrow IPC stream encoding of snapshots and samples through the Arrow crates.
+//!
+//! A stream is a schema message, a single record batch and the end of stream marker.
+
+use crate::{Rfc3339, Stats, StatsSnapshot};
+use arrow_array::{
+    ArrayRef, DurationNanosecondArray, Float64Array, RecordBatch, StringArray,
+    TimestampNanosecondArray, UInt64Array,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, Field, Schema};
+use std::{
+    convert::TryFrom,
+    io::{self, prelude::*},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Column of a record batch, all but `Utf8` are nullable.
+enum Column {
+    Utf8(&'static str, Vec<String>),
+    UInt64(&'static str, Vec<Option<u64>>),
+    Float64(&'static str, Vec<Option<f64>>),
+    /// Nanoseconds
+    Duration(&'static str, Vec<Option<Duration>>),
+    /// Nanoseconds since the epoch in UTC
+    Timestamp(&'static str, Vec<Option<SystemTime>>),
+}
+
+impl Column {
+    fn into_array(self) -> (Field, ArrayRef) {
+        let nanos = |duration: Duration| i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX);
+        let (name, array): (_, ArrayRef) = match self {
+            Column::Utf8(name, values) => (name, Arc::new(StringArray::from(values))),
+            Column::UInt64(name, values) => (name, Arc::new(UInt64Array::from(values))),
+            Column::Float64(name, values) => (name, Arc::new(Float64Array::from(values))),
+            Column::Duration(name, values) => {
+                let values: Vec<_> = values.into_iter().map(|value| value.map(nanos)).collect();
+                (name, Arc::new(DurationNanosecondArray::from(values)))
+            }
+            Column::Timestamp(name, values) => {
+                let values: Vec<_> = values
+                    .into_iter()
+                    .map(|time| Some(nanos(time?.duration_since(UNIX_EPOCH).unwrap_or_default())))
+                    .collect();
+                let array = TimestampNanosecondArray::from(values).with_timezone("UTC");
+                (name, Arc::new(array))
+            }
+        };
+        let nullable = !matches!(array.data_type(), arrow_schema::DataType::Utf8);
+        (Field::new(name, array.data_type().clone(), nullable), array)
+    }
+}
+
+/// Writes the columns as a stream, `metadata` is attached to the schema.
+fn write_stream<W: Write>(
+    writer: W,
+    columns: Vec<Column>,
+    metadata: &[(&str, String)],
+) -> io::Result<()> {
+    let (fields, arrays): (Vec<_>, Vec<_>) = columns.into_iter().map(Column::into_array).unzip();
+    let metadata = metadata
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect();
+    let schema = Arc::new(Schema::new(fields).with_metadata(metadata));
+    let batch = RecordBatch::try_new(schema.clone(), arrays).map_err(io_error)?;
+    let mut writer = StreamWriter::try_new(writer, &schema).map_err(io_error)?;
+    writer.write(&batch).map_err(io_error)?;
+    writer.finish().map_err(io_error)
+}
+
+fn io_error(error: ArrowError) -> io::Error {
+    match error {
+        ArrowError::IoError(_, error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
+
+impl StatsSnapshot {
+    /// One row for each peer with its ping and transmission rate summaries,
+    /// the id of the node and the time of the snapshot are in the schema metadata.
+    pub(crate) fn encode_arrow<W: Write>(&self, writer: W) -> io::Result<()> {
+        let peers = &self.peers;
+        let pings = || peers.iter().map(|peer| peer.ping.as_ref());
+        let rates = || peers.iter().map(|peer| peer.transmission_rate.as_ref());
+        let columns = vec![
+            Column::Utf8(
+                "peer_id",
+                peers.iter().map(|peer| peer.peer_id.clone()).collect(),
+            ),
+            Column::Timestamp(
+                "last_seen",
+                peers.iter().map(|peer| peer.last_seen).collect(),
+            ),
+            Column::UInt64(
+                "ping_samples",
+                pings().map(|ping| Some(ping?.samples as u64)).collect(),
+            ),
+            Column::Duration("ping_mean", pings().map(|ping| Some(ping?.mean)).collect()),
+            Column::Duration(
+                "ping_std_dev",
+                pings().map(|ping| Some(ping?.std_dev)).collect(),
+            ),
+            Column::Duration(
+                "ping_error",
+                pings().map(|ping| Some(ping?.error)).collect(),
+            ),
+            Column::UInt64(
+                "transmission_rate_samples",
+                rates().map(|rate| Some(rate?.samples as u64)).collect(),
+            ),
+            Column::Float64(
+                "transmission_rate_mean_bytes_per_sec",
+                rates()
+                    .map(|rate| Some(rate?.mean.bytes_per_sec()))
+                    .collect(),
+            ),
+            Column::Float64(
+                "transmission_rate_std_dev_bytes_per_sec",
+                rates()
+                    .map(|rate| Some(rate?.std_dev.bytes_per_sec()))
+                    .collect(),
+            ),
+            Column::Float64(
+                "transmission_rate_error_bytes_per_sec",
+                rates()
+                    .map(|rate| Some(rate?.error.bytes_per_sec()))
+                    .collect(),
+            ),
+        ];
+        let mut metadata = vec![("node_peer_id", self.peer_id.clone())];
+        if let Some(time) = self.time {
+            metadata.push(("snapshot_time", Rfc3339(time).to_string()));
+        }
+        if let Some(epoch) = self.epoch {
+            metadata.push(("epoch", Rfc3339(epoch).to_string()));
+        }
+        if let Some(epsilon) = self.noise_epsilon {
+            metadata.push(("noise_epsilon", epsilon.to_string()));
+        }
+        if let Some(level) = self.confidence {
+            metadata.push(("confidence", level.to_string()));
+        }
+        write_stream(writer, columns, &metadata)
+    }
+}
+
+impl Stats {
+    /// Writes the windowed samples of all peers as an Arrow IPC stream with one row
+    /// for each sample, either a `ping` or a `transmission_rate_bytes_per_sec`.
+    pub fn export_arrow_samples<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut peer_ids = Vec::new();
+        let mut pings = Vec::new();
+        let mut rates = Vec::new();
+        for peer_id in self.peer_ids() {
+            self.expire_samples(&peer_id);
+            if let Some(window) = self.pings_to_peers.get(&peer_id) {
+                for rtt in window.iter() {
+                    peer_ids.push(peer_id.clone());
+                    pings.push(Some(*rtt));
+                    rates.push(None);
+                }
+            }
+            if let Some(window) = self.transmissions_rates.get(&peer_id) {
+                for rate in window.iter() {
+                    peer_ids.push(peer_id.clone());
+                    pings.push(None);
+                    rates.push(Some(rate.bytes_per_sec()));
+                }
+            }
+        }
+        let columns = vec![
+            Column::Utf8("peer_id", peer_ids),
+            Column::Duration("ping", pings),
+            Column::Float64("transmission_rate_bytes_per_sec", rates),
+        ];
+        write_stream(writer, columns, &[("node_peer_id", self.peer_id.clone())])
+    }
+}
+
+#[test]
+fn arrow_stream_is_readable() {
+    use arrow_array::types::{DurationNanosecondType, Float64Type};
+    use arrow_array::{cast::AsArray, Array};
+    use arrow_ipc::reader::StreamReader;
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_transmission("3".to_string(), Duration::from_secs(1), 1_000);
+    stats.add_transmission("3".to_string(), Duration::from_secs(1), 3_000);
+    let mut bytes = Vec::new();
+    stats.snapshot().encode_arrow(&mut bytes).unwrap();
+    let mut reader = StreamReader::try_new(&bytes[..], None).unwrap();
+    assert_eq!(reader.schema().metadata()["node_peer_id"], "1");
+    let batch = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    assert_eq!(batch.num_rows(), 2);
+    let ping_mean = batch.column_by_name("ping_mean").unwrap();
+    let ping_mean = ping_mean.as_primitive::<DurationNanosecondType>();
+    assert_eq!(ping_mean.value(0), 10_000_000);
+    assert!(ping_mean.is_null(1));
+    let std_dev = batch
+        .column_by_name("transmission_rate_std_dev_bytes_per_sec")
+        .unwrap();
+    let std_dev = std_dev.as_primitive::<Float64Type>();
+    assert!(std_dev.is_null(0));
+    let expected = stats
+        .summarize_peer("3")
+        .unwrap()
+        .transmission_rate
+        .unwrap();
+    assert_eq!(std_dev.value(1), expected.std_dev.bytes_per_sec());
+    assert!(std_dev.value(1) > 0.0);
+
+    let mut samples = Vec::new();
+    stats.export_arrow_samples(&mut samples).unwrap();
+    let batch = StreamReader::try_new(&samples[..], None)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(batch.num_rows(), 3);
+}
@@ -0,0 +1,292 @@
+use crate::{ByteSize, Stats};
+#[cfg(feature = "libp2p")]
+use futures::{AsyncRead, AsyncWrite};
+#[cfg(feature = "libp2p")]
+use libp2p_core::muxing::{StreamMuxer, StreamMuxerEvent, StreamMuxerExt};
+#[cfg(feature = "libp2p")]
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use std::{mem, time::SystemTime};
+
+/// Bytes counted for a peer since `since` which are not recorded as a transmission yet.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PendingBytes {
+    bytes: ByteSize,
+    since: SystemTime,
+}
+
+impl Stats {
+    /// Counts bytes sent to or received from the peer, e.g. by a counting wrapper of the
+    /// streams of a libp2p transport, until `flush_bytes` records them as a transmission:
+    ///
+    /// ```ignore
+    /// fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+    ///     let read = ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+    ///     self.stats.count_bytes(self.peer.to_string(), read as u64);
+    ///     Poll::Ready(Ok(read))
+    /// }
+    /// ```
+    pub fn count_bytes(&self, peer_id: String, n_bytes: impl Into<ByteSize>) {
+        self.record_bytes(&peer_id, n_bytes.into())
+    }
+
+    fn record_bytes(&self, peer_id: &str, n_bytes: ByteSize) {
+        trace_span!("count_bytes");
+        let _recording = self.recording(peer_id);
+        let now = self.clock.now();
+        self.update_peer(peer_id, |peer| {
+            let pending = peer.pending_bytes.get_or_insert(PendingBytes {
+                bytes: ByteSize(0),
+                since: now,
+            });
+            pending.bytes += n_bytes;
+        });
+    }
+
+    /// Records the bytes counted for each peer since its last flush, or since its first
+    /// count, as a transmission over that time, e.g. once a second. Returns the number of
+    /// transmissions recorded. Peers without bytes since the last flush are skipped.
+    pub fn flush_bytes(&self) -> usize {
+        trace_span!("flush_bytes");
+        let now = self.clock.now();
+        let mut flushed = 0;
+        for peer_id in self.peer_ids() {
+            // The counted bytes leave the state and enter the window at once
+            let _recording = self.recording(&peer_id);
+            let mut transmission = None;
+            self.peers.alter(peer_id.clone(), |peer| {
+                let mut peer = peer?;
+                if let Some(pending) = peer.pending_bytes.as_mut() {
+                    let time = now.duration_since(pending.since).unwrap_or_default();
+                    if pending.bytes.0 == 0 {
+                        peer.pending_bytes = None;
+                    } else if !time.is_zero() {
+                        let bytes = mem::replace(&mut pending.bytes, ByteSize(0));
+                        pending.since = now;
+                        transmission = Some((time, bytes));
+                    }
+                }
+                Some(peer)
+            });
+            if let Some((time, bytes)) = transmission {
+                let _ = self.write_transmission(&peer_id, time, bytes);
+                flushed += 1;
+            }
+        }
+        flushed
+    }
+}
+
+/// Muxer of a libp2p connection which counts the bytes of its substreams with
+/// `Stats::count_bytes`, for a transport of the swarm mapped like:
+///
+/// ```ignore
+/// let transport = transport.map(move |(peer, muxer), _| {
+///     let muxer = CountingMuxer::new(stats.clone(), peer.to_string(), muxer);
+///     (peer, StreamMuxerBox::new(muxer))
+/// });
+/// ```
+///
+/// The counted bytes become transmissions at `Stats::flush_bytes`.
+#[cfg(feature = "libp2p")]
+pub struct CountingMuxer<M> {
+    inner: M,
+    peer_id: Arc<str>,
+    stats: Arc<Stats>,
+}
+
+#[cfg(feature = "libp2p")]
+impl<M> CountingMuxer<M> {
+    pub fn new(stats: Arc<Stats>, peer_id: String, inner: M) -> Self {
+        Self {
+            inner,
+            peer_id: peer_id.into(),
+            stats,
+        }
+    }
+
+    fn substream<S>(&self, inner: S) -> CountingSubstream<S> {
+        CountingSubstream {
+            inner,
+            peer_id: self.peer_id.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "libp2p")]
+impl<M> StreamMuxer for CountingMuxer<M>
+where
+    M: StreamMuxer + Unpin,
+    M::Substream: Unpin,
+{
+    type Substream = CountingSubstream<M::Substream>;
+    type Error = M::Error;
+
+    fn poll_inbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        this.inner
+            .poll_inbound_unpin(cx)
+            .map_ok(|inner| this.substream(inner))
+    }
+
+    fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        this.inner
+            .poll_outbound_unpin(cx)
+            .map_ok(|inner| this.substream(inner))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_close_unpin(cx)
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        self.get_mut().inner.poll_unpin(cx)
+    }
+}
+
+/// Substream of a `CountingMuxer`, counts the bytes read and written.
+#[cfg(feature = "libp2p")]
+pub struct CountingSubstream<S> {
+    inner: S,
+    peer_id: Arc<str>,
+    stats: Arc<Stats>,
+}
+
+#[cfg(feature = "libp2p")]
+impl<S> CountingSubstream<S> {
+    fn count(&self, poll: Poll<io::Result<usize>>) -> Poll<io::Result<usize>> {
+        if let Poll::Ready(Ok(n_bytes)) = poll {
+            if n_bytes > 0 {
+                self.stats
+                    .record_bytes(&self.peer_id, ByteSize(n_bytes as u64));
+            }
+        }
+        poll
+    }
+}
+
+#[cfg(feature = "libp2p")]
+impl<S: AsyncRead + Unpin> AsyncRead for CountingSubstream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        this.count(poll)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [io::IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_read_vectored(cx, bufs);
+        this.count(poll)
+    }
+}
+
+#[cfg(feature = "libp2p")]
+impl<S: AsyncWrite + Unpin> AsyncWrite for CountingSubstream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        this.count(poll)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+        this.count(poll)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[test]
+fn counted_bytes_become_transmissions() {
+    use crate::{ManualClock, Rate};
+    use std::{sync::Arc, time::Duration};
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    stats.count_bytes("2".to_string(), 1_000u64);
+    stats.count_bytes("2".to_string(), 3_000u64);
+    assert_eq!(stats.flush_bytes(), 0);
+    clock.advance(Duration::from_secs(2));
+    stats.count_bytes("3".to_string(), 500u64);
+    assert_eq!(stats.flush_bytes(), 1);
+    clock.advance(Duration::from_secs(1));
+    stats.count_bytes("2".to_string(), 1_000u64);
+    assert_eq!(stats.flush_bytes(), 2);
+    assert_eq!(stats.flush_bytes(), 0);
+    let peer = stats.summarize_peer("2").unwrap();
+    let rate = peer.transmission_rate.unwrap();
+    assert_eq!(rate.samples, 2);
+    assert_eq!(rate.mean, Rate::from_bytes_per_sec(1_500.0));
+    assert_eq!(
+        stats
+            .summarize_peer("3")
+            .unwrap()
+            .transmission_rate
+            .unwrap()
+            .mean,
+        Rate::from_bytes_per_sec(500.0)
+    );
+
+    #[cfg(feature = "libp2p")]
+    {
+        use futures::{executor::block_on, io::Cursor, AsyncReadExt, AsyncWriteExt};
+
+        let stats = Arc::new(Stats::new(100, "1".to_string()).with_clock(clock.clone()));
+        let muxer = CountingMuxer::new(stats.clone(), "4".to_string(), ());
+        let mut substream = muxer.substream(Cursor::new(vec![0; 1_000]));
+        block_on(async {
+            let mut buf = [0; 600];
+            substream.read_exact(&mut buf).await.unwrap();
+            substream.write_all(&buf).await.unwrap();
+        });
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(stats.flush_bytes(), 1);
+        assert_eq!(
+            stats
+                .summarize_peer("4")
+                .unwrap()
+                .transmission_rate
+                .unwrap()
+                .mean,
+            Rate::from_bytes_per_sec(600.0)
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    confidence::{scaled, scaled_rate},
+    durations_percentile,
+    map::Keys,
+    ByteSize, PushLossy, Rate, Stats, Summary, Window,
+};
+use std::{fmt, time::Duration};
+
+/// Samples of all peers recorded over one transport configuration.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TransportSamples {
+    pings: Window<Duration>,
+    rates: Window<Rate>,
+}
+
+/// Stats of one transport configuration compared with the baseline.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransportBenchmark {
+    pub label: String,
+    pub ping: Option<Summary>,
+    pub ping_p50: Option<Duration>,
+    pub ping_p90: Option<Duration>,
+    pub ping_p99: Option<Duration>,
+    pub transmission_rate: Option<Summary<Rate>>,
+    /// Mean ping differs from the baseline beyond both confidence intervals
+    pub ping_differs: bool,
+    /// Mean transmission rate differs from the baseline beyond both confidence intervals
+    pub transmission_rate_differs: bool,
+}
+
+/// Side-by-side stats of transport configurations, ordered by label.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BenchmarkReport {
+    pub baseline: String,
+    pub transports: Vec<TransportBenchmark>,
+}
+
+impl BenchmarkReport {
+    pub fn get(&self, label: &str) -> Option<&TransportBenchmark> {
+        self.transports
+            .iter()
+            .find(|transport| transport.label == label)
+    }
+}
+
+/// Whether the means of two windows differ beyond their combined confidence interval.
+fn differs(a: f64, a_error: f64, b: f64, b_error: f64) -> bool {
+    (a - b).abs() > a_error.hypot(b_error)
+}
+
+impl TransportBenchmark {
+    /// Benchmark with errors scaled by `confidence_scale` like the peer summaries.
+    fn new(label: String, samples: &TransportSamples, confidence_scale: f64) -> Self {
+        Self {
+            label,
+            ping: scaled(Summary::from_durations(&samples.pings), confidence_scale),
+            ping_p50: durations_percentile(&samples.pings, 0.5),
+            ping_p90: durations_percentile(&samples.pings, 0.9),
+            ping_p99: durations_percentile(&samples.pings, 0.99),
+            transmission_rate: scaled_rate(Summary::from_rates(&samples.rates), confidence_scale),
+            ping_differs: false,
+            transmission_rate_differs: false,
+        }
+    }
+
+    fn compare(&mut self, baseline: &TransportBenchmark) {
+        if let (Some(ping), Some(base)) = (&self.ping, &baseline.ping) {
+            self.ping_differs = differs(
+                ping.mean.as_secs_f64(),
+                ping.error.as_secs_f64(),
+                base.mean.as_secs_f64(),
+                base.error.as_secs_f64(),
+            );
+        }
+        if let (Some(rate), Some(base)) = (&self.transmission_rate, &baseline.transmission_rate) {
+            self.transmission_rate_differs = differs(
+                rate.mean.bytes_per_sec(),
+                rate.error.bytes_per_sec(),
+                base.mean.bytes_per_sec(),
+                base.error.bytes_per_sec(),
+            );
+        }
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transport benchmark against {:?}:", self.baseline)?;
+        for transport in &self.transports {
+            write!(f, "{:?}", transport.label)?;
+            if let Some(ping) = &transport.ping {
+                write!(f, " ping {:?}±{:?}", ping.mean, ping.error)?;
+                if let (Some(p50), Some(p90), Some(p99)) =
+                    (transport.ping_p50, transport.ping_p90, transport.ping_p99)
+                {
+                    write!(f, " p50 {:?} p90 {:?} p99 {:?}", p50, p90, p99)?;
+                }
+                if transport.ping_differs {
+                    write!(f, " (significant)")?;
+                }
+            }
+            if let Some(rate) = &transport.transmission_rate {
+                write!(f, " rate {}±{}", rate.mean, rate.error)?;
+                if transport.transmission_rate_differs {
+                    write!(f, " (significant)")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Records the ping like `add_ping` and, unless it was quarantined, also for the
+    /// transport configuration `label`, e.g. `"quic"` or `"tcp+bbr"`.
+    pub fn add_ping_over(&self, peer_id: String, rtt: Duration, label: &str) {
+        if self.record_ping(&peer_id, rtt).is_err() {
+            return;
+        }
+        let window_size = self.window_size;
+        self.update_transport(label, |samples| samples.pings.push_lossy(rtt, window_size));
+    }
+
+    /// Records the transmission like `add_transmission` and, unless it was quarantined,
+    /// also for the transport configuration `label`.
+    pub fn add_transmission_over(
+        &self,
+        peer_id: String,
+        time: Duration,
+        n_bytes: impl Into<ByteSize>,
+        label: &str,
+    ) {
+        let n_bytes = n_bytes.into();
+        if self.record_transmission(&peer_id, time, n_bytes).is_err() {
+            return;
+        }
+        let window_size = self.window_size;
+        self.update_transport(label, |samples| {
+            samples.rates.push_lossy(n_bytes / time, window_size)
+        });
+    }
+
+    /// Compares the samples of every transport configuration with those of `baseline`.
+    pub fn benchmark_report(&self, baseline: &str) -> BenchmarkReport {
+        trace_span!("benchmark_report");
+        let mut labels = self.transports.keys();
+        labels.sort();
+        let mut transports: Vec<_> = labels
+            .into_iter()
+            .filter_map(|label| {
+                let samples = self.transports.get(&label)?;
+                Some(TransportBenchmark::new(
+                    label.clone(),
+                    &samples,
+                    self.confidence_scale(),
+                ))
+            })
+            .collect();
+        if let Some(base) = transports
+            .iter()
+            .find(|transport| transport.label == baseline)
+            .cloned()
+        {
+            for transport in transports.iter_mut() {
+                transport.compare(&base);
+            }
+        }
+        BenchmarkReport {
+            baseline: baseline.to_string(),
+            transports,
+        }
+    }
+
+    fn update_transport<F: FnOnce(&mut TransportSamples)>(&self, label: &str, update: F) {
+        trace_span!("map_access");
+        self.transports.alter(label.to_string(), |samples| {
+            let mut samples = samples.unwrap_or_default();
+            update(&mut samples);
+            Some(samples)
+        });
+    }
+}
+
+#[test]
+fn transports_are_compared_with_baseline() {
+    let stats = Stats::new(100, "1".to_string());
+    for millis in &[50, 52, 48, 51, 49] {
+        let rtt = Duration::from_millis(*millis);
+        stats.add_ping_over("2".to_string(), rtt, "tcp");
+        stats.add_ping_over("2".to_string(), rtt / 2, "quic");
+        stats.add_ping_over("2".to_string(), rtt + Duration::from_micros(100), "tcp+bbr");
+    }
+    stats.add_transmission_over("2".to_string(), Duration::from_secs(1), 1_000, "quic");
+    let report = stats.benchmark_report("tcp");
+    let labels: Vec<_> = report.transports.iter().map(|t| t.label.as_str()).collect();
+    assert_eq!(labels, vec!["quic", "tcp", "tcp+bbr"]);
+    assert!(report.get("quic").unwrap().ping_differs);
+    assert!(!report.get("tcp").unwrap().ping_differs);
+    assert!(!report.get("tcp+bbr").unwrap().ping_differs);
+    assert_eq!(
+        report.get("tcp").unwrap().ping_p50,
+        Some(Duration::from_millis(50))
+    );
+    assert_eq!(
+        report
+            .get("quic")
+            .unwrap()
+            .transmission_rate
+            .as_ref()
+            .unwrap()
+            .mean,
+        Rate::from_bytes_per_sec(1_000.0)
+    );
+    assert_eq!(stats.snapshot().peers[0].ping.as_ref().unwrap().samples, 15);
+}
+
+#[test]
+fn quarantined_samples_are_left_out_of_transports() {
+    use crate::OutlierPolicy;
+
+    let stats = Stats::new(100, "1".to_string()).with_outlier_policy(OutlierPolicy::std_devs(3.0));
+    for millis in [50, 52, 48, 51, 49, 50, 52, 48, 51, 49] {
+        stats.add_ping_over("2".to_string(), Duration::from_millis(millis), "tcp");
+    }
+    stats.add_ping_over("2".to_string(), Duration::from_secs(5), "tcp");
+    let ping = stats
+        .benchmark_report("tcp")
+        .get("tcp")
+        .unwrap()
+        .ping
+        .clone()
+        .unwrap();
+    assert_eq!(ping.samples, 10);
+    assert_eq!(stats.snapshot().peers[0].ping.as_ref().unwrap().samples, 10);
+}
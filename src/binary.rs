@@ -0,0 +1,200 @@
+use crate::{Stats, StatsSnapshot};
+use ciborium::Value;
+use std::{
+    convert::TryFrom,
+    fs,
+    io::{self, prelude::*, BufReader, BufWriter},
+};
+
+/// First bytes of a binary snapshot
+const MAGIC: &[u8; 4] = b"P2PS";
+
+/// CBOR tag of the maps whose names are replaced by their index in the keys, unassigned by
+/// IANA, so that they are not confused with integer keys such as `Histogram::buckets`
+const KEY_TAG: u64 = 6;
+
+/// Version of the binary snapshots written by this crate. Fields added to the snapshot do
+/// not change it, since readers skip unknown fields and default missing ones.
+const VERSION: u8 = 1;
+
+impl StatsSnapshot {
+    /// Writes the snapshot as a version header followed by its CBOR, for frequent
+    /// checkpoints. Unlike the text report it keeps every value exactly, and unlike
+    /// `Encoding::Cbor` it names each field once instead of once per peer, which makes it
+    /// a fraction of the size.
+    pub fn encode_binary<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        let mut value = Value::serialized(self).map_err(invalid_data)?;
+        let mut keys = Vec::new();
+        compact(&mut value, &mut keys);
+        ciborium::ser::into_writer(&(keys, value), writer).map_err(invalid_data)
+    }
+
+    /// Reads a snapshot written by `encode_binary` of this or another version of the crate.
+    pub fn decode_binary<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC[..] {
+            return Err(invalid_data("not a binary snapshot"));
+        }
+        if header[4] > VERSION {
+            return Err(invalid_data(format!(
+                "binary snapshot version {} is newer than {}",
+                header[4], VERSION
+            )));
+        }
+        let (keys, mut value): (Vec<String>, Value) =
+            ciborium::de::from_reader(reader).map_err(invalid_data)?;
+        expand(&mut value, &keys)?;
+        value.deserialized().map_err(invalid_data)
+    }
+
+    pub fn load_binary(filename: &str) -> io::Result<Self> {
+        Self::decode_binary(BufReader::new(fs::File::open(filename)?))
+    }
+}
+
+impl Stats {
+    /// Saves the snapshot with `StatsSnapshot::encode_binary`.
+    pub fn save_binary(&self, filename: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(filename)?);
+        self.snapshot().encode_binary(&mut writer)?;
+        writer.flush()
+    }
+}
+
+/// Drops the absent values of the snapshot, which are most of its fields, so that
+/// deserialization defaults them again. Maps with only text keys, such as the fields of
+/// a struct, get their keys replaced with their index in `keys`, to which each name is
+/// added once, and are tagged with `KEY_TAG`.
+fn compact(value: &mut Value, keys: &mut Vec<String>) {
+    match value {
+        Value::Map(entries) => {
+            entries.retain(|(_, value)| !value.is_null());
+            for (_, value) in entries.iter_mut() {
+                compact(value, keys);
+            }
+            if !entries.iter().all(|(key, _)| key.is_text()) {
+                return;
+            }
+            for (key, _) in entries.iter_mut() {
+                if let Value::Text(name) = key {
+                    let index = match keys.iter().position(|key| key == name) {
+                        Some(index) => index,
+                        None => {
+                            keys.push(std::mem::take(name));
+                            keys.len() - 1
+                        }
+                    };
+                    *key = Value::from(index as u64);
+                }
+            }
+            let map = std::mem::replace(value, Value::Null);
+            *value = Value::Tag(KEY_TAG, Box::new(map));
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| compact(value, keys)),
+        _ => {}
+    }
+}
+
+/// Puts the names of the maps tagged by `compact` back.
+fn expand(value: &mut Value, keys: &[String]) -> io::Result<()> {
+    if let Value::Tag(KEY_TAG, map) = value {
+        let mut map = std::mem::replace(&mut **map, Value::Null);
+        if let Value::Map(entries) = &mut map {
+            for (key, _) in entries.iter_mut() {
+                let name = key
+                    .as_integer()
+                    .and_then(|index| usize::try_from(i128::from(index)).ok())
+                    .and_then(|index| keys.get(index))
+                    .ok_or_else(|| invalid_data("field name out of the keys"))?;
+                *key = Value::Text(name.clone());
+            }
+        } else {
+            return Err(invalid_data("tagged keys outside of a map"));
+        }
+        *value = map;
+    }
+    match value {
+        Value::Map(entries) => {
+            for (_, value) in entries {
+                expand(value, keys)?;
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                expand(value, keys)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn invalid_data<E: ToString>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+#[test]
+fn binary_snapshots_are_small_and_exact() {
+    use crate::ManualClock;
+    use std::{sync::Arc, time::Duration};
+
+    let filename = std::env::temp_dir().join(format!("p2p_node_stats_{}.bin", std::process::id()));
+    let filename = filename.to_str().unwrap();
+    let clock = Arc::new(ManualClock::new(std::time::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock)
+        .with_ping_histograms();
+    for peer in 2..20 {
+        stats.add_ping(peer.to_string(), Duration::from_nanos(10_000_123));
+        // Buckets with bounds as small as the indexes of the field names
+        stats.add_ping(peer.to_string(), Duration::from_micros(peer));
+        stats.add_transmission(peer.to_string(), Duration::from_millis(3), 1_000u64);
+    }
+    stats.save_binary(filename).unwrap();
+    let size = fs::metadata(filename).unwrap().len() as usize;
+    let snapshot = StatsSnapshot::load_binary(filename).unwrap();
+    fs::remove_file(filename).unwrap();
+    assert_eq!(snapshot, stats.snapshot());
+    assert!(!snapshot.peers[0]
+        .ping_histogram
+        .as_ref()
+        .unwrap()
+        .buckets
+        .is_empty());
+    let mut cbor = Vec::new();
+    snapshot.encode(&mut cbor, crate::Encoding::Cbor).unwrap();
+    assert!(size * 3 < cbor.len());
+
+    let mut newer = Vec::from(&MAGIC[..]);
+    newer.push(VERSION + 1);
+    assert!(StatsSnapshot::decode_binary(&newer[..]).is_err());
+    assert!(StatsSnapshot::decode_binary(&b"p2p node stats"[..]).is_err());
+
+    // Snapshots of older versions lack the fields added since
+    #[derive(serde::Serialize)]
+    struct Older {
+        peer_id: &'static str,
+        peers: Vec<OlderPeer>,
+    }
+    #[derive(serde::Serialize)]
+    struct OlderPeer {
+        peer_id: &'static str,
+    }
+    let mut older = Vec::from(&MAGIC[..]);
+    older.push(VERSION);
+    let peers = vec![OlderPeer { peer_id: "2" }];
+    let mut value = Value::serialized(&Older {
+        peer_id: "1",
+        peers,
+    })
+    .unwrap();
+    let mut keys = Vec::new();
+    compact(&mut value, &mut keys);
+    ciborium::ser::into_writer(&(keys, value), &mut older).unwrap();
+    let snapshot = StatsSnapshot::decode_binary(&older[..]).unwrap();
+    assert_eq!(snapshot.peers[0].peer_id, "2");
+    assert_eq!(snapshot.peers[0].ping, None);
+}
@@ -0,0 +1,274 @@
+use crate::{FirstContactReport, Recommendation};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    time::{Duration, SystemTime},
+};
+#[cfg(feature = "json")]
+use std::{
+    fs,
+    io::{self, prelude::*},
+    time::UNIX_EPOCH,
+};
+
+/// Why and until when a peer is blocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockEntry {
+    pub reason: String,
+    pub blocked_at: SystemTime,
+    /// `None` blocks the peer until it is unblocked
+    pub expires_at: Option<SystemTime>,
+}
+
+impl BlockEntry {
+    pub fn is_active(&self, now: SystemTime) -> bool {
+        self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+}
+
+/// Peers not to connect to, kept by the node and shared between nodes as JSON.
+///
+/// The JSON is an object with an `entries` array of objects with `peer_id`, `reason`,
+/// `blocked_at` and `expires_at`, times are in whole seconds since the Unix epoch and
+/// `expires_at` is `null` for blocks without expiry.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Blocklist {
+    entries: BTreeMap<String, BlockEntry>,
+}
+
+impl Blocklist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Blocks the peer for `ttl` from `now`, or without expiry, replacing any earlier block.
+    pub fn block(
+        &mut self,
+        peer_id: String,
+        reason: impl Into<String>,
+        now: SystemTime,
+        ttl: Option<Duration>,
+    ) {
+        let entry = BlockEntry {
+            reason: reason.into(),
+            blocked_at: now,
+            // A ttl past the end of time blocks for good
+            expires_at: ttl.and_then(|ttl| now.checked_add(ttl)),
+        };
+        self.entries.insert(peer_id, entry);
+    }
+
+    pub fn unblock(&mut self, peer_id: &str) -> Option<BlockEntry> {
+        self.entries.remove(peer_id)
+    }
+
+    pub fn get(&self, peer_id: &str) -> Option<&BlockEntry> {
+        self.entries.get(peer_id)
+    }
+
+    pub fn is_blocked(&self, peer_id: &str, now: SystemTime) -> bool {
+        self.get(peer_id).is_some_and(|entry| entry.is_active(now))
+    }
+
+    /// Entries ordered by peer id, including expired ones until `expire`.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &BlockEntry)> {
+        self.entries
+            .iter()
+            .map(|(peer_id, entry)| (peer_id.as_str(), entry))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes the entries expired at `now`, returns how many were removed.
+    pub fn expire(&mut self, now: SystemTime) -> usize {
+        let len = self.entries.len();
+        self.entries.retain(|_, entry| entry.is_active(now));
+        len - self.entries.len()
+    }
+
+    /// Blocks the peers the report recommends to drop for `ttl` from the time of the report,
+    /// returns how many were blocked.
+    pub fn block_recommended(
+        &mut self,
+        report: &FirstContactReport,
+        ttl: Option<Duration>,
+    ) -> usize {
+        let mut blocked = 0;
+        for peer in report
+            .peers
+            .iter()
+            .filter(|peer| peer.recommendation == Recommendation::Drop)
+        {
+            let mut reason = "dropped at first contact".to_string();
+            if let Some(ping) = &peer.ping {
+                let _ = write!(reason, ", ping {:?} of {}", ping.mean, ping.samples);
+            }
+            if let Some(rate) = &peer.transmission_rate {
+                let _ = write!(reason, ", rate {} of {}", rate.mean, rate.samples);
+            }
+            if let Some(success_rate) = peer.success_rate {
+                let _ = write!(reason, ", success {:.1}%", success_rate * 100.0);
+            }
+            self.block(peer.peer_id.clone(), reason, report.time, ttl);
+            blocked += 1;
+        }
+        blocked
+    }
+
+    /// Adds the entries of `other`, e.g. a list imported from another node. Of two entries
+    /// of the same peer the one which expires later is kept.
+    pub fn merge(&mut self, other: Blocklist) {
+        for (peer_id, entry) in other.entries {
+            match self.entries.get(&peer_id) {
+                Some(existing) if !expires_later(&entry, existing) => {}
+                _ => {
+                    self.entries.insert(peer_id, entry);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(peer_id, entry)| JsonEntry {
+                peer_id: peer_id.clone(),
+                reason: entry.reason.clone(),
+                blocked_at: unix_secs(entry.blocked_at),
+                expires_at: entry.expires_at.map(unix_secs),
+            })
+            .collect();
+        // Strings and integers always serialize
+        serde_json::to_string(&JsonBlocklist { entries }).unwrap_or_default()
+    }
+
+    /// Reads `to_json`, malformed input and times out of range are `ErrorKind::InvalidData`.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let json: JsonBlocklist = serde_json::from_str(json).map_err(io::Error::from)?;
+        let mut blocklist = Self::new();
+        for entry in json.entries {
+            let block = BlockEntry {
+                reason: entry.reason,
+                blocked_at: from_unix_secs(entry.blocked_at)?,
+                expires_at: entry.expires_at.map(from_unix_secs).transpose()?,
+            };
+            blocklist.entries.insert(entry.peer_id, block);
+        }
+        Ok(blocklist)
+    }
+
+    /// Writes the JSON of the blocklist to `filename`.
+    #[cfg(feature = "json")]
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let mut file = fs::File::create(filename)?;
+        file.write_all(self.to_json().as_bytes())?;
+        file.flush()
+    }
+
+    #[cfg(feature = "json")]
+    pub fn load(filename: &str) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(filename)?)
+    }
+}
+
+/// Entry of `Blocklist::to_json`, with times in whole seconds since the Unix epoch.
+#[cfg(feature = "json")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct JsonEntry {
+    peer_id: String,
+    reason: String,
+    blocked_at: u64,
+    #[serde(default)]
+    expires_at: Option<u64>,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct JsonBlocklist {
+    entries: Vec<JsonEntry>,
+}
+
+fn expires_later(entry: &BlockEntry, than: &BlockEntry) -> bool {
+    match (entry.expires_at, than.expires_at) {
+        (None, _) => than.expires_at.is_some(),
+        (Some(_), None) => false,
+        (Some(entry), Some(than)) => entry > than,
+    }
+}
+
+#[cfg(feature = "json")]
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(feature = "json")]
+fn from_unix_secs(secs: u64) -> io::Result<SystemTime> {
+    UNIX_EPOCH
+        .checked_add(Duration::from_secs(secs))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "entry time out of range"))
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn blocklists_roundtrip_through_json() {
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    let mut blocklist = Blocklist::new();
+    blocklist.block("2".to_string(), "spam \"relay\"", at(100), None);
+    blocklist.block(
+        "3".to_string(),
+        "broken",
+        at(100),
+        Some(Duration::from_secs(50)),
+    );
+    assert!(blocklist.is_blocked("3", at(149)));
+    assert!(!blocklist.is_blocked("3", at(150)));
+    let json = blocklist.to_json();
+    assert_eq!(
+        json,
+        r#"{"entries":[{"peer_id":"2","reason":"spam \"relay\"","blocked_at":100,"expires_at":null},{"peer_id":"3","reason":"broken","blocked_at":100,"expires_at":150}]}"#
+    );
+    assert_eq!(Blocklist::from_json(&json).unwrap(), blocklist);
+    let spaced = " { \"entries\" : [ { \"peer_id\" : \"4\\u00e9\", \"reason\" : \"\", \
+                  \"blocked_at\" : 1 , \"extra\" : [true] } ] } ";
+    let imported = Blocklist::from_json(spaced).unwrap();
+    assert_eq!(imported.get("4é").unwrap().expires_at, None);
+    assert!(Blocklist::from_json("{\"entries\":[{}]}").is_err());
+    for blocked_at in ["1e20", "18446744073709551615"] {
+        let json = format!(
+            "{{\"entries\":[{{\"peer_id\":\"5\",\"reason\":\"\",\"blocked_at\":{}}}]}}",
+            blocked_at
+        );
+        let error = Blocklist::from_json(&json).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+    let nested = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+    assert!(Blocklist::from_json(&nested).is_err());
+    let mut forever = Blocklist::new();
+    forever.block("6".to_string(), "", at(100), Some(Duration::MAX));
+    assert!(forever.is_blocked("6", at(u32::MAX.into())));
+
+    let mut other = Blocklist::new();
+    other.block(
+        "3".to_string(),
+        "abusive",
+        at(120),
+        Some(Duration::from_secs(100)),
+    );
+    blocklist.merge(other);
+    assert_eq!(blocklist.get("3").unwrap().reason, "abusive");
+    assert_eq!(blocklist.expire(at(300)), 1);
+    assert_eq!(blocklist.len(), 1);
+}
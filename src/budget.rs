@@ -0,0 +1,111 @@
+use crate::{Encoding, StatsSnapshot};
+use std::io::{self, Write};
+
+/// Writer which only counts the bytes written to it.
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0 += bytes.len();
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl StatsSnapshot {
+    /// Number of bytes of the snapshot in `encoding`, without allocating the output.
+    pub fn estimated_size(&self, encoding: Encoding) -> usize {
+        let mut counter = Counter(0);
+        // Snapshots always encode, a failure would only make the estimate smaller
+        let _ = self.encode(&mut counter, encoding);
+        counter.0
+    }
+
+    /// Drops the least important parts of the snapshot until it fits into `max_size` bytes
+    /// in `encoding`: first session histories, then breakdowns like gauges, stages and upgrades,
+    /// and at last whole peers, least recently seen first.
+    pub fn truncated(mut self, max_size: usize, encoding: Encoding) -> Self {
+        if self.estimated_size(encoding) <= max_size {
+            return self;
+        }
+        for peer in self.peers.iter_mut() {
+            peer.sessions.clear();
+        }
+        if self.estimated_size(encoding) <= max_size {
+            return self;
+        }
+        for peer in self.peers.iter_mut() {
+            peer.ping_by_size = None;
+            peer.ping_histogram = None;
+            peer.ping_robust = None;
+            peer.ping_min_max = None;
+            peer.ping_ages = None;
+            peer.transmission_rate_ages = None;
+            peer.transmission_rate_min_max = None;
+            peer.cold_ping = None;
+            peer.stages = None;
+            peer.upgrades = None;
+            peer.keep_alive = None;
+            peer.gauges.clear();
+            peer.derived.clear();
+            peer.views.clear();
+            peer.ewma = None;
+        }
+        if self.estimated_size(encoding) <= max_size {
+            return self;
+        }
+        // Keep the largest number of the most recently seen peers which fits
+        let mut peers = std::mem::take(&mut self.peers);
+        peers.sort_by_key(|peer| std::cmp::Reverse(peer.last_seen));
+        let (mut fits, mut exceeds) = (0, peers.len());
+        while exceeds - fits > 1 {
+            let middle = (fits + exceeds) / 2;
+            self.peers = peers[..middle].to_vec();
+            if self.estimated_size(encoding) <= max_size {
+                fits = middle;
+            } else {
+                exceeds = middle;
+            }
+        }
+        peers.truncate(fits);
+        peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        self.peers = peers;
+        self
+    }
+}
+
+#[test]
+fn truncation_drops_detail_then_cold_peers() {
+    use crate::{DisconnectReason, ManualClock, Stats};
+    use std::{
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    for peer in 2..12 {
+        clock.advance(Duration::from_secs(1));
+        stats.add_ping(peer.to_string(), Duration::from_millis(10));
+        stats.record_connected(peer.to_string());
+        stats.record_disconnected(peer.to_string(), DisconnectReason::Idle);
+    }
+    let snapshot = stats.snapshot();
+    let size = snapshot.estimated_size(Encoding::Text);
+    assert_eq!(size, snapshot.to_string().len());
+    assert_eq!(snapshot.clone().truncated(size, Encoding::Text), snapshot);
+
+    let without_sessions = snapshot.clone().truncated(size - 1, Encoding::Text);
+    assert_eq!(without_sessions.peers.len(), 10);
+    assert!(without_sessions.peers[0].sessions.is_empty());
+    assert!(without_sessions.peers[0].keep_alive.is_some());
+
+    let truncated = snapshot.truncated(size / 3, Encoding::Text);
+    assert!(truncated.estimated_size(Encoding::Text) <= size / 3);
+    assert!(!truncated.peers.is_empty() && truncated.peers.len() < 10);
+    assert!(truncated.peers.iter().any(|peer| peer.peer_id == "11"));
+    assert!(truncated.peers.iter().all(|peer| peer.peer_id != "2"));
+}
@@ -0,0 +1,244 @@
+use crate::{Clock, ConfigError, ConfigProblem, OutlierPolicy, Stats};
+use std::{sync::Arc, time::Duration};
+
+/// Configuration of `Stats` option by option, so that new options do not change the
+/// signature of `Stats::new`. Each option applies the `Stats::with_` method of the same
+/// name, and `build` reports every problem of the final options at once, so that a later
+/// option can fix an earlier one.
+pub struct StatsBuilder {
+    stats: Stats,
+}
+
+impl Stats {
+    /// Builder of stats with windows of 100 samples. The peer id of the node must be set.
+    pub fn builder() -> StatsBuilder {
+        StatsBuilder {
+            stats: Stats::new(100, String::new()),
+        }
+    }
+
+    /// These stats, or every problem of their options like `StatsBuilder::build`, for
+    /// stats configured with the `with_` methods directly.
+    pub fn validate(self) -> Result<Stats, ConfigError> {
+        let mut problems = self.config_problems();
+        if self.peer_id.is_empty() {
+            problems.push(ConfigProblem::EmptyPeerId);
+        }
+        if problems.is_empty() {
+            Ok(self)
+        } else {
+            Err(ConfigError { problems })
+        }
+    }
+
+    /// Problems of the options other than the peer id.
+    fn config_problems(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        if self.window_size == 0 {
+            problems.push(ConfigProblem::EmptyWindow);
+        }
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            problems.push(ConfigProblem::ConfidenceLevel(self.confidence));
+        }
+        if self.max_sample_age.is_some_and(|max_age| max_age.is_zero()) {
+            problems.push(ConfigProblem::ZeroTimeWindow);
+        }
+        if let Some(alpha) = self
+            .ewma_alpha
+            .filter(|alpha| !(*alpha > 0.0 && *alpha <= 1.0))
+        {
+            problems.push(ConfigProblem::EwmaAlpha(alpha));
+        }
+        let (min, max) = self.probe_interval_bounds;
+        if min.is_zero() || min > max {
+            problems.push(ConfigProblem::ProbeIntervalBounds { min, max });
+        }
+        if let Some(policy) = self
+            .outlier_policy
+            .filter(|policy| policy.max_std_devs.is_nan() || policy.max_std_devs <= 0.0)
+        {
+            problems.push(ConfigProblem::OutlierStdDevs(policy.max_std_devs));
+        }
+        if self.decay.is_some_and(|decay| decay.half_life.is_zero()) {
+            problems.push(ConfigProblem::ZeroHalfLife);
+        }
+        if self.epochs.is_some_and(|epochs| epochs.interval.is_zero()) {
+            problems.push(ConfigProblem::ZeroEpochInterval);
+        }
+        problems
+    }
+}
+
+impl StatsBuilder {
+    /// Samples kept per peer and metric, at least 1.
+    pub fn window_size(self, window_size: usize) -> Self {
+        self.map(|mut stats| {
+            stats.window_size = window_size;
+            stats
+        })
+    }
+
+    /// Nonempty peer id of the node itself.
+    pub fn peer_id(mut self, peer_id: impl Into<String>) -> Self {
+        self.stats.peer_id = peer_id.into();
+        self
+    }
+
+    pub fn clock(self, clock: Arc<dyn Clock>) -> Self {
+        self.map(|stats| stats.with_clock(clock))
+    }
+
+    /// Confidence level of the errors in (0, 1), see `Stats::with_confidence`.
+    pub fn confidence(self, level: f64) -> Self {
+        self.map(|stats| stats.with_confidence(level))
+    }
+
+    /// Nonzero maximum age of the samples in the windows, see `Stats::with_max_sample_age`.
+    pub fn time_window(self, max_age: Duration) -> Self {
+        self.map(|stats| stats.with_max_sample_age(max_age))
+    }
+
+    /// Smoothing factor in (0, 1], see `Stats::with_ewma`.
+    pub fn ewma(self, alpha: f64) -> Self {
+        self.map(|stats| stats.with_ewma(alpha))
+    }
+
+    pub fn warm_up(self, min_samples: usize) -> Self {
+        self.map(|stats| stats.with_warm_up(min_samples))
+    }
+
+    pub fn session_history(self, sessions: usize) -> Self {
+        self.map(|stats| stats.with_session_history(sessions))
+    }
+
+    /// Nonzero bounds of `suggested_probe_interval`, see `Stats::with_probe_interval_bounds`.
+    pub fn probe_interval_bounds(self, min: Duration, max: Duration) -> Self {
+        self.map(|stats| stats.with_probe_interval_bounds(min, max))
+    }
+
+    /// Quarantines outliers of their window, see `Stats::with_outlier_policy`.
+    pub fn outlier_policy(self, policy: OutlierPolicy) -> Self {
+        self.map(|stats| stats.with_outlier_policy(policy))
+    }
+
+    pub fn streaming(self) -> Self {
+        self.map(Stats::with_streaming)
+    }
+
+    pub fn lite(self) -> Self {
+        self.map(Stats::with_lite)
+    }
+
+    /// Applies any other `Stats::with_` method, e.g. `|stats| stats.with_decay(decay)`.
+    /// Its options are checked by `build` like those of the other methods.
+    pub fn with(self, option: impl FnOnce(Stats) -> Stats) -> Self {
+        self.map(option)
+    }
+
+    /// The stats, or every problem of the final options like `Stats::validate`.
+    pub fn build(self) -> Result<Stats, ConfigError> {
+        self.stats.validate()
+    }
+
+    fn map(mut self, option: impl FnOnce(Stats) -> Stats) -> Self {
+        self.stats = option(self.stats);
+        self
+    }
+}
+
+#[test]
+fn builder_applies_options() {
+    use crate::ManualClock;
+    use std::time::UNIX_EPOCH;
+
+    let clock = Arc::new(ManualClock::new(UNIX_EPOCH));
+    let stats = Stats::builder()
+        .window_size(2)
+        .peer_id("1")
+        .clock(clock.clone())
+        .confidence(0.99)
+        .time_window(Duration::from_secs(60))
+        .ewma(0.5)
+        .with(|stats| stats.with_warm_up(3))
+        .build()
+        .unwrap();
+    for millis in [10, 20, 30] {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+    }
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.peer_id, "1");
+    let ping = snapshot.peers[0].ping.as_ref().unwrap();
+    assert_eq!(ping.samples, 2);
+    assert!(ping.warming_up);
+    assert_eq!(stats.ewma_ping("2"), Some(Duration::from_micros(22_500)));
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(stats.snapshot().peers[0].ping, None);
+
+    let error = Stats::builder()
+        .window_size(0)
+        .confidence(1.0)
+        .probe_interval_bounds(Duration::from_secs(10), Duration::from_secs(1))
+        .with(|stats| stats.with_ewma(2.0))
+        .with(|stats| {
+            stats.with_decay(crate::Decay {
+                grace: Duration::from_secs(1),
+                half_life: Duration::ZERO,
+            })
+        })
+        .with(|stats| stats.with_epochs(Duration::ZERO, Duration::ZERO))
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.problems,
+        vec![
+            ConfigProblem::EmptyWindow,
+            ConfigProblem::ConfidenceLevel(1.0),
+            ConfigProblem::EwmaAlpha(2.0),
+            ConfigProblem::ProbeIntervalBounds {
+                min: Duration::from_secs(10),
+                max: Duration::from_secs(1),
+            },
+            ConfigProblem::ZeroHalfLife,
+            ConfigProblem::ZeroEpochInterval,
+            ConfigProblem::EmptyPeerId,
+        ]
+    );
+    assert_eq!(
+        error.to_string(),
+        "invalid stats configuration: window size must be at least 1; confidence level 1 \
+         out of (0, 1); EWMA alpha 2 out of (0, 1]; probe interval bounds 10s to 1s must \
+         be nonzero and increasing; decay half-life must not be zero; epoch interval must \
+         not be zero; peer id must not be empty"
+    );
+    assert_eq!(
+        Stats::new(100, "1".to_string())
+            .with_confidence(0.0)
+            .validate()
+            .err()
+            .unwrap()
+            .problems,
+        vec![ConfigProblem::ConfidenceLevel(0.0)]
+    );
+}
+
+#[test]
+fn builder_checks_the_final_options() {
+    assert!(Stats::builder()
+        .window_size(0)
+        .peer_id("1")
+        .window_size(10)
+        .build()
+        .is_ok());
+
+    let error = Stats::builder()
+        .peer_id("1")
+        .confidence(f64::NAN)
+        .ewma(0.5)
+        .warm_up(3)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(error.problems.len(), 1);
+    assert!(matches!(error.problems[0], ConfigProblem::ConfidenceLevel(level) if level.is_nan()));
+}
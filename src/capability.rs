@@ -0,0 +1,167 @@
+use crate::{
+    confidence::{scaled, scaled_rate},
+    PeerState, PeerSummary, Rate, Stats, StatsSnapshot, Summary,
+};
+use std::{collections::BTreeSet, fmt, time::Duration};
+
+/// Performance of the peers supporting a capability next to the peers which do not.
+/// Summaries are over the means of the peers, so `samples` is a number of peers.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilityComparison {
+    pub capability: String,
+    pub ping_with: Option<Summary>,
+    pub ping_without: Option<Summary>,
+    pub transmission_rate_with: Option<Summary<Rate>>,
+    pub transmission_rate_without: Option<Summary<Rate>>,
+}
+
+/// Comparisons of every capability of the peers of a snapshot, ordered by capability.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilityReport {
+    pub capabilities: Vec<CapabilityComparison>,
+}
+
+impl CapabilityReport {
+    pub fn get(&self, capability: &str) -> Option<&CapabilityComparison> {
+        self.capabilities
+            .iter()
+            .find(|comparison| comparison.capability == capability)
+    }
+}
+
+impl CapabilityComparison {
+    /// Comparison with errors scaled by `confidence_scale` like the peer summaries.
+    fn new(capability: &str, peers: &[PeerSummary], confidence_scale: f64) -> Self {
+        let (with, without): (Vec<_>, Vec<_>) = peers
+            .iter()
+            .partition(|peer| peer.capabilities.contains(capability));
+        let pings = |peers: &[&PeerSummary]| -> Vec<Duration> {
+            peers
+                .iter()
+                .filter_map(|peer| Some(peer.ping.as_ref()?.mean))
+                .collect()
+        };
+        let rates = |peers: &[&PeerSummary]| -> Vec<Rate> {
+            peers
+                .iter()
+                .filter_map(|peer| Some(peer.transmission_rate.as_ref()?.mean))
+                .collect()
+        };
+        Self {
+            capability: capability.to_string(),
+            ping_with: scaled(Summary::from_durations(&pings(&with)), confidence_scale),
+            ping_without: scaled(Summary::from_durations(&pings(&without)), confidence_scale),
+            transmission_rate_with: scaled_rate(
+                Summary::from_rates(&rates(&with)),
+                confidence_scale,
+            ),
+            transmission_rate_without: scaled_rate(
+                Summary::from_rates(&rates(&without)),
+                confidence_scale,
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CapabilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Peer means with and without capability:")?;
+        for comparison in &self.capabilities {
+            write!(f, "{:?}", comparison.capability)?;
+            if let Some(ping) = &comparison.ping_with {
+                write!(
+                    f,
+                    " ping {:?}±{:?} of {}",
+                    ping.mean, ping.error, ping.samples
+                )?;
+            }
+            if let Some(ping) = &comparison.ping_without {
+                write!(
+                    f,
+                    " vs {:?}±{:?} of {}",
+                    ping.mean, ping.error, ping.samples
+                )?;
+            }
+            if let Some(rate) = &comparison.transmission_rate_with {
+                write!(f, " rate {}±{} of {}", rate.mean, rate.error, rate.samples)?;
+            }
+            if let Some(rate) = &comparison.transmission_rate_without {
+                write!(f, " vs {}±{} of {}", rate.mean, rate.error, rate.samples)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl StatsSnapshot {
+    /// Compares the peers by each of their negotiated capabilities.
+    pub fn capability_report(&self) -> CapabilityReport {
+        let capabilities: BTreeSet<&String> = self
+            .peers
+            .iter()
+            .flat_map(|peer| peer.capabilities.iter())
+            .collect();
+        CapabilityReport {
+            capabilities: capabilities
+                .into_iter()
+                .map(|capability| {
+                    CapabilityComparison::new(capability, &self.peers, self.confidence_scale())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Stats {
+    /// Replaces the protocol capabilities negotiated with the peer, e.g. `"compression"`,
+    /// capabilities do not count as samples for `PeerSummary::last_seen`.
+    pub fn set_capabilities<I, S>(&self, peer_id: String, capabilities: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        trace_span!("set_capabilities");
+        let capabilities: BTreeSet<String> = capabilities.into_iter().map(Into::into).collect();
+        let _recording = self.recording(&peer_id);
+        let now = self.clock.now();
+        self.peers.alter(peer_id, |peer| {
+            let mut peer = peer.unwrap_or_else(|| PeerState::new(now));
+            peer.capabilities = capabilities;
+            Some(peer)
+        });
+    }
+}
+
+#[test]
+fn peers_are_compared_by_capability() {
+    let stats = Stats::new(100, "1".to_string());
+    let millis = Duration::from_millis;
+    stats.set_capabilities("2".to_string(), vec!["compression", "relay"]);
+    stats.set_capabilities("3".to_string(), vec!["compression"]);
+    stats.set_capabilities("4".to_string(), Vec::<String>::new());
+    stats.add_ping("2".to_string(), millis(10));
+    stats.add_ping("3".to_string(), millis(30));
+    stats.add_ping("4".to_string(), millis(80));
+    let snapshot = stats.snapshot();
+    assert!(snapshot.peers[0].capabilities.contains("relay"));
+    let report = snapshot.capability_report();
+    assert_eq!(report.capabilities.len(), 2);
+    let compression = report.get("compression").unwrap();
+    assert_eq!(compression.ping_with.as_ref().unwrap().mean, millis(20));
+    assert_eq!(compression.ping_with.as_ref().unwrap().samples, 2);
+    assert_eq!(compression.ping_without.as_ref().unwrap().mean, millis(80));
+    assert_eq!(
+        report
+            .get("relay")
+            .unwrap()
+            .ping_without
+            .as_ref()
+            .unwrap()
+            .mean,
+        millis(55)
+    );
+    assert!(report.to_string().contains("\"relay\" ping 10ms"));
+}
@@ -0,0 +1,352 @@
+use crate::{watchdog, PeerState, Rate, Stats};
+use std::{
+    convert::TryFrom,
+    fs,
+    io::{self, prelude::*, BufWriter},
+    net::IpAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// First line of the files of `Stats::save_windows`, with the version of the format
+const HEADER: &str = "p2p-node-stats windows 2";
+
+impl Stats {
+    /// Saves the ping and transmission rate windows of every peer with the times of their
+    /// samples and the metadata of the peer, for `load_from_file` after a restart of the
+    /// node. Peers are identified by their peer ids, which stay the same across restarts.
+    ///
+    /// The file is UTF-8 with a header line and tab separated lines: `node`, the window
+    /// size and the peer id of the node, then `ping`, a peer id, the time of the sample in
+    /// nanoseconds since the Unix epoch and the round trip time in nanoseconds, or `rate`
+    /// and the rate in bytes per second instead of the round trip time. The samples of a
+    /// peer are followed by `peer`, its peer id, the times it was first and last seen, its
+    /// total, failed and transmission counts, its address or nothing and its capabilities.
+    /// Tabs, newlines and backslashes in peer ids and capabilities are escaped with a
+    /// backslash.
+    ///
+    /// Stats `with_streaming` or `with_lite` keep no windows to save and are rejected
+    /// with `InvalidInput`.
+    pub fn save_windows(&self, filename: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(filename)?);
+        self.write_windows(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the windows like `save_windows`.
+    pub fn write_windows<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        if self.streaming || self.lite {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "streaming and lite stats keep no windows to save",
+            ));
+        }
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(
+            writer,
+            "node\t{}\t{}",
+            self.window_size,
+            escape(&self.peer_id)
+        )?;
+        for peer_id in self.peer_ids() {
+            let capture = match self.capture_peer(&peer_id) {
+                Some(capture) => capture,
+                None => continue,
+            };
+            let peer_id = escape(&peer_id);
+            let pings = capture.pings.unwrap_or_default();
+            let (times, pings) = pings.between(UNIX_EPOCH, None);
+            for (time, ping) in times.iter().zip(pings) {
+                writeln!(
+                    writer,
+                    "ping\t{}\t{}\t{}",
+                    peer_id,
+                    watchdog::to_nanos(*time),
+                    nanos(*ping)
+                )?;
+            }
+            let rates = capture.rates.unwrap_or_default();
+            let (times, rates) = rates.between(UNIX_EPOCH, None);
+            for (time, rate) in times.iter().zip(rates) {
+                writeln!(
+                    writer,
+                    "rate\t{}\t{}\t{}",
+                    peer_id,
+                    watchdog::to_nanos(*time),
+                    rate.bytes_per_sec()
+                )?;
+            }
+            let peer = &capture.state;
+            write!(
+                writer,
+                "peer\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                peer_id,
+                watchdog::to_nanos(peer.first_seen),
+                watchdog::to_nanos(peer.last_seen),
+                peer.total_pings,
+                peer.failed_pings,
+                peer.total_transmissions,
+                peer.address
+                    .map(|address| address.to_string())
+                    .unwrap_or_default()
+            )?;
+            for capability in &peer.capabilities {
+                write!(writer, "\t{}", escape(capability))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Stats with the window size, node peer id and windows saved by `save_windows`, so
+    /// that a restart does not forget the quality of the peers. Use `read_windows` to
+    /// restore the windows into stats with other options.
+    pub fn load_from_file(filename: &str) -> io::Result<Self> {
+        let contents = fs::read_to_string(filename)?;
+        let node = contents.lines().nth(1).unwrap_or_default();
+        let stats = match node.split('\t').collect::<Vec<_>>()[..] {
+            ["node", window_size, peer_id] => Stats::new(parse(window_size)?, unescape(peer_id)?)
+                .validate()
+                .map_err(|error| invalid_data(&error.to_string()))?,
+            _ => return Err(invalid_data("expected the node line")),
+        };
+        stats.read_windows(contents.as_bytes())?;
+        Ok(stats)
+    }
+
+    /// Restores the windows written by `write_windows` into these stats, which keep their
+    /// own window size and peer id, and returns the number of samples read. The samples
+    /// are recorded at their saved times like new ones, so that they also reach moving
+    /// averages, histograms and streaming summaries, but they were accepted when first
+    /// recorded and are not quarantined again. Samples which have expired since they were
+    /// saved are dropped again. The counts of a peer are raised to the saved ones and the
+    /// saved address and capabilities are added.
+    pub fn read_windows<R: BufRead>(&self, reader: R) -> io::Result<usize> {
+        let mut lines = reader.lines();
+        match lines.next() {
+            Some(Ok(header)) if header == HEADER => {}
+            Some(Err(error)) => return Err(error),
+            _ => return Err(invalid_data("not a windows file of this version")),
+        }
+        let mut samples = 0;
+        for line in lines {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                ["node", _, _] => {}
+                ["ping", peer_id, time, rtt] => {
+                    let rtt = Duration::from_nanos(parse(rtt)?);
+                    self.restore_ping(&unescape(peer_id)?, time_of(time)?, rtt);
+                    samples += 1;
+                }
+                ["rate", peer_id, time, rate] => {
+                    let rate = Rate::from_bytes_per_sec(parse(rate)?);
+                    self.restore_rate(&unescape(peer_id)?, time_of(time)?, rate);
+                    samples += 1;
+                }
+                ["peer", peer_id, first_seen, last_seen, total_pings, failed_pings, total_transmissions, address, ref capabilities @ ..] =>
+                {
+                    let saved = SavedPeer {
+                        first_seen: time_of(first_seen)?,
+                        last_seen: time_of(last_seen)?,
+                        total_pings: parse(total_pings)?,
+                        failed_pings: parse(failed_pings)?,
+                        total_transmissions: parse(total_transmissions)?,
+                        address: Some(address)
+                            .filter(|a| !a.is_empty())
+                            .map(parse)
+                            .transpose()?,
+                        capabilities: capabilities
+                            .iter()
+                            .map(|capability| unescape(capability))
+                            .collect::<io::Result<_>>()?,
+                    };
+                    self.restore_metadata(&unescape(peer_id)?, saved);
+                }
+                _ => return Err(invalid_data(&format!("invalid line {:?}", line))),
+            }
+        }
+        Ok(samples)
+    }
+
+    fn restore_ping(&self, peer_id: &str, time: SystemTime, rtt: Duration) {
+        let _recording = self.recording(peer_id);
+        self.write_ping(peer_id, rtt, time);
+    }
+
+    fn restore_rate(&self, peer_id: &str, time: SystemTime, rate: Rate) {
+        let _recording = self.recording(peer_id);
+        self.write_rate(peer_id, rate, None, time);
+    }
+
+    fn restore_metadata(&self, peer_id: &str, saved: SavedPeer) {
+        let _recording = self.recording(peer_id);
+        self.peers.alter(peer_id.to_string(), |peer| {
+            let mut peer = peer.unwrap_or_else(|| PeerState::new(saved.last_seen));
+            peer.first_seen = peer.first_seen.min(saved.first_seen);
+            peer.last_seen = peer.last_seen.max(saved.last_seen);
+            peer.total_pings = peer.total_pings.max(saved.total_pings);
+            peer.failed_pings = peer.failed_pings.max(saved.failed_pings);
+            peer.total_transmissions = peer.total_transmissions.max(saved.total_transmissions);
+            peer.address = peer.address.or(saved.address);
+            peer.capabilities.extend(saved.capabilities);
+            Some(peer)
+        });
+    }
+}
+
+/// Metadata of a `peer` line
+struct SavedPeer {
+    first_seen: SystemTime,
+    last_seen: SystemTime,
+    total_pings: u64,
+    failed_pings: u64,
+    total_transmissions: u64,
+    address: Option<IpAddr>,
+    capabilities: Vec<String>,
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+fn time_of(nanos: &str) -> io::Result<SystemTime> {
+    Ok(UNIX_EPOCH + Duration::from_nanos(parse(nanos)?))
+}
+
+fn parse<T: std::str::FromStr>(field: &str) -> io::Result<T> {
+    field
+        .parse()
+        .map_err(|_| invalid_data(&format!("invalid number {:?}", field)))
+}
+
+fn escape(peer_id: &str) -> String {
+    let mut escaped = String::with_capacity(peer_id.len());
+    for c in peer_id.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(field: &str) -> io::Result<String> {
+    let mut peer_id = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            peer_id.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => peer_id.push('\\'),
+            Some('t') => peer_id.push('\t'),
+            Some('n') => peer_id.push('\n'),
+            _ => return Err(invalid_data(&format!("invalid escape in {:?}", field))),
+        }
+    }
+    Ok(peer_id)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[test]
+fn windows_survive_a_restart() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let filename =
+        std::env::temp_dir().join(format!("p2p_node_stats_{}.windows", std::process::id()));
+    let filename = filename.to_str().unwrap();
+    let clock = Arc::new(ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_000)));
+    let stats = Stats::new(2, "1".to_string()).with_clock(clock.clone());
+    for millis in [10, 20, 30] {
+        stats.add_ping("2\tb".to_string(), Duration::from_millis(millis));
+        clock.advance(Duration::from_secs(1));
+    }
+    stats.add_transmission("3".to_string(), Duration::from_millis(3), 1_000u64);
+    stats.record_ping_failure("3");
+    stats.set_address("3".to_string(), "10.0.0.3".parse().unwrap());
+    stats.set_capabilities("3".to_string(), ["relay", "a\tb"]);
+    stats.save_windows(filename).unwrap();
+
+    let loaded = Stats::load_from_file(filename).unwrap();
+    std::fs::remove_file(filename).unwrap();
+    assert_eq!(loaded.peer_ids(), stats.peer_ids());
+    let peer = loaded.summarize_peer("2\tb").unwrap();
+    assert_eq!(peer.ping, stats.summarize_peer("2\tb").unwrap().ping);
+    assert_eq!(
+        peer.last_seen,
+        Some(UNIX_EPOCH + Duration::from_secs(1_002))
+    );
+    let peer = loaded.summarize_peer("3").unwrap();
+    assert_eq!(
+        peer.transmission_rate.unwrap().mean,
+        Rate::from_bytes_per_sec(1_000.0 / 0.003)
+    );
+    assert_eq!(peer.address, Some("10.0.0.3".parse().unwrap()));
+    assert_eq!(
+        peer.capabilities,
+        stats.summarize_peer("3").unwrap().capabilities
+    );
+    assert_eq!(loaded.failed_pings("3"), 1);
+    assert_eq!(
+        loaded.first_seen("2\tb"),
+        Some(UNIX_EPOCH + Duration::from_secs(1_000))
+    );
+
+    let expiring = Stats::new(100, "1".to_string())
+        .with_clock(clock.clone())
+        .with_max_sample_age(Duration::from_millis(1_500));
+    let mut saved = Vec::new();
+    stats.write_windows(&mut saved).unwrap();
+    assert_eq!(expiring.read_windows(&saved[..]).unwrap(), 3);
+    assert_eq!(
+        expiring
+            .summarize_peer("2\tb")
+            .unwrap()
+            .ping
+            .unwrap()
+            .samples,
+        1
+    );
+    assert!(expiring.read_windows(&b"windows"[..]).is_err());
+
+    let averaged = Stats::new(100, "1".to_string()).with_ewma(0.5);
+    averaged.read_windows(&saved[..]).unwrap();
+    assert_eq!(
+        averaged.ewma_ping("2\tb"),
+        Some(Duration::from_micros(25_000))
+    );
+    let streaming = Stats::new(100, "1".to_string()).with_streaming();
+    streaming.read_windows(&saved[..]).unwrap();
+    let ping = streaming.summarize_peer("2\tb").unwrap().ping.unwrap();
+    assert_eq!(ping.samples, 2);
+    let error = streaming.write_windows(Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(Stats::new(100, "1".to_string())
+        .with_lite()
+        .write_windows(Vec::new())
+        .is_err());
+}
+
+#[test]
+fn invalid_node_lines_are_rejected() {
+    let filename = std::env::temp_dir().join(format!(
+        "p2p_node_stats_{}_invalid.windows",
+        std::process::id()
+    ));
+    let filename = filename.to_str().unwrap();
+    fs::write(filename, format!("{}\nnode\t0\t\n", HEADER)).unwrap();
+    let error = Stats::load_from_file(filename).err().unwrap();
+    fs::remove_file(filename).unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        error.to_string(),
+        "invalid stats configuration: window size must be at least 1; peer id must not be empty"
+    );
+}
@@ -0,0 +1,130 @@
+use crate::{epoch::fnv1a, redact::pooled, PeerSummary, Rate, StatsSnapshot, Summary};
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+/// Cohort of the peer among `n_buckets`, e.g. of a staged rollout applying a protocol
+/// change to the peers of cohort 0. The peer id is hashed with FNV-1a, so every node and
+/// build assigns a peer to the same cohort without bookkeeping. 0 for no buckets.
+pub fn cohort(peer_id: &str, n_buckets: u32) -> u32 {
+    match n_buckets {
+        0 => 0,
+        n => (fnv1a(peer_id.as_bytes()) % u64::from(n)) as u32,
+    }
+}
+
+/// Stats of all samples of the peers in a cohort, as if they were one window.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CohortSummary {
+    pub cohort: u32,
+    /// Ids of the peers in the cohort, ordered
+    pub peers: Vec<String>,
+    pub ping: Option<Summary>,
+    pub transmission_rate: Option<Summary<Rate>>,
+}
+
+/// Summaries of the cohorts with peers in a snapshot, ordered by cohort.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CohortReport {
+    pub n_buckets: u32,
+    pub cohorts: Vec<CohortSummary>,
+}
+
+impl CohortReport {
+    pub fn get(&self, cohort: u32) -> Option<&CohortSummary> {
+        self.cohorts.iter().find(|summary| summary.cohort == cohort)
+    }
+}
+
+impl CohortSummary {
+    fn new(cohort: u32, peers: &[&PeerSummary], confidence_scale: f64) -> Self {
+        Self {
+            cohort,
+            peers: peers.iter().map(|peer| peer.peer_id.clone()).collect(),
+            ping: pooled(
+                peers.iter().filter_map(|peer| peer.ping.as_ref()),
+                |duration| duration.as_secs_f64(),
+                |secs| Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX),
+                confidence_scale,
+            ),
+            transmission_rate: pooled(
+                peers
+                    .iter()
+                    .filter_map(|peer| peer.transmission_rate.as_ref()),
+                Rate::bytes_per_sec,
+                Rate::from_bytes_per_sec,
+                confidence_scale,
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CohortReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Peers by cohort of {}:", self.n_buckets)?;
+        for summary in &self.cohorts {
+            write!(f, "{} {} peers", summary.cohort, summary.peers.len())?;
+            if let Some(ping) = &summary.ping {
+                write!(f, " ping {:?}±{:?}", ping.mean, ping.error)?;
+            }
+            if let Some(rate) = &summary.transmission_rate {
+                write!(f, " rate {}±{}", rate.mean, rate.error)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl StatsSnapshot {
+    /// Groups the peers by their `cohort` among `n_buckets`, to compare the cohorts of a
+    /// staged rollout with the rest of the peers.
+    pub fn cohort_report(&self, n_buckets: u32) -> CohortReport {
+        let mut cohorts: BTreeMap<u32, Vec<&PeerSummary>> = BTreeMap::new();
+        for peer in &self.peers {
+            cohorts
+                .entry(cohort(&peer.peer_id, n_buckets))
+                .or_default()
+                .push(peer);
+        }
+        CohortReport {
+            n_buckets,
+            cohorts: cohorts
+                .into_iter()
+                .map(|(cohort, peers)| CohortSummary::new(cohort, &peers, self.confidence_scale()))
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn cohorts_are_stable_and_aggregated() {
+    use crate::Stats;
+
+    assert_eq!(cohort("2", 4), cohort("2", 4));
+    assert_eq!(cohort("2", 1), 0);
+    assert_eq!(cohort("2", 0), 0);
+    let stats = Stats::new(100, "1".to_string());
+    for peer in 2..50 {
+        let millis = if cohort(&peer.to_string(), 2) == 0 {
+            10
+        } else {
+            30
+        };
+        stats.add_ping(peer.to_string(), Duration::from_millis(millis));
+    }
+    let report = stats.snapshot().cohort_report(2);
+    assert_eq!(report.cohorts.len(), 2);
+    let treated = report.get(0).unwrap();
+    assert!(treated.peers.iter().all(|peer| cohort(peer, 2) == 0));
+    assert_eq!(
+        treated.ping.as_ref().unwrap().mean,
+        Duration::from_millis(10)
+    );
+    assert_eq!(
+        report.get(1).unwrap().ping.as_ref().unwrap().mean,
+        Duration::from_millis(30)
+    );
+    assert_eq!(treated.peers.len() + report.get(1).unwrap().peers.len(), 48);
+    assert!(report.to_string().starts_with("Peers by cohort of 2:\n0 "));
+}
@@ -0,0 +1,92 @@
+//! Merging snapshots of many nodes.
+
+use crate::{PeerSummary, SignedDigest, StatsSnapshot, Verifier};
+use std::{collections::BTreeMap, fmt, io};
+
+/// Merges snapshots pushed by many nodes into a view of the whole network.
+#[derive(Debug, Clone, Default)]
+pub struct Collector {
+    snapshots: BTreeMap<String, StatsSnapshot>,
+}
+
+impl Collector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the previous snapshot of the same node.
+    pub fn ingest(&mut self, snapshot: StatsSnapshot) {
+        self.snapshots.insert(snapshot.peer_id.clone(), snapshot);
+    }
+
+    pub fn ingest_signed<V: Verifier>(
+        &mut self,
+        digest: &SignedDigest,
+        verifier: &V,
+    ) -> io::Result<()> {
+        self.ingest(digest.verify(verifier)?);
+        Ok(())
+    }
+
+    /// Latest snapshots ordered by node id.
+    pub fn snapshots(&self) -> impl Iterator<Item = &StatsSnapshot> {
+        self.snapshots.values()
+    }
+
+    /// Stats of `to` as measured by `from`.
+    pub fn link(&self, from: &str, to: &str) -> Option<&PeerSummary> {
+        self.snapshots
+            .get(from)?
+            .peers
+            .iter()
+            .find(|peer| peer.peer_id == to)
+    }
+
+    /// Stats of `peer_id` as measured by each node.
+    pub fn peer_view<'a>(
+        &'a self,
+        peer_id: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a PeerSummary)> + 'a {
+        self.snapshots
+            .keys()
+            .filter_map(move |node| Some((node.as_str(), self.link(node, peer_id)?)))
+    }
+}
+
+impl fmt::Display for Collector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for snapshot in self.snapshots() {
+            write!(f, "{}", snapshot)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn collector_merges_nodes() {
+    use crate::Stats;
+    use std::time::Duration;
+
+    let mut collector = Collector::new();
+    for node in &["1", "2"] {
+        let stats = Stats::new(100, node.to_string());
+        stats.add_ping("3".to_string(), Duration::from_millis(10));
+        collector.ingest(stats.snapshot());
+    }
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("3".to_string(), Duration::from_millis(30));
+    collector.ingest(stats.snapshot());
+
+    let view: Vec<_> = collector
+        .peer_view("3")
+        .map(|(node, peer)| (node, peer.ping.as_ref().unwrap().mean))
+        .collect();
+    assert_eq!(
+        view,
+        vec![
+            ("1", Duration::from_millis(30)),
+            ("2", Duration::from_millis(10))
+        ]
+    );
+    assert!(collector.link("3", "1").is_none());
+}
@@ -0,0 +1,145 @@
+use crate::{PeerState, Rate, Stats};
+use std::{
+    collections::VecDeque,
+    fmt, mem,
+    time::{Duration, SystemTime},
+};
+
+/// What `Stats::compact` reclaimed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Compaction {
+    pub peers_removed: usize,
+    /// Ping and transmission samples of the removed peers
+    pub samples_removed: usize,
+    /// Approximate heap memory freed by removed peers and by shrunk buffers
+    pub bytes_reclaimed: usize,
+}
+
+impl fmt::Display for Compaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} peers with {} samples removed, {} bytes reclaimed",
+            self.peers_removed, self.samples_removed, self.bytes_reclaimed
+        )
+    }
+}
+
+/// Buffer which can release its unused capacity.
+pub(crate) trait ShrinkToFit {
+    /// Returns the number of bytes released.
+    fn shrink(&mut self) -> usize;
+}
+
+impl<T> ShrinkToFit for Vec<T> {
+    fn shrink(&mut self) -> usize {
+        let unused = self.capacity() - self.len();
+        self.shrink_to_fit();
+        (unused - (self.capacity() - self.len())) * mem::size_of::<T>()
+    }
+}
+
+impl<T> ShrinkToFit for VecDeque<T> {
+    fn shrink(&mut self) -> usize {
+        let unused = self.capacity() - self.len();
+        self.shrink_to_fit();
+        (unused - (self.capacity() - self.len())) * mem::size_of::<T>()
+    }
+}
+
+impl PeerState {
+    fn shrink(&mut self) -> usize {
+        let windows = self.pings_by_size.iter_mut().chain(self.stages.iter_mut());
+        windows.map(ShrinkToFit::shrink).sum::<usize>()
+            + self
+                .gauges
+                .values_mut()
+                .map(ShrinkToFit::shrink)
+                .sum::<usize>()
+            + self.cold_pings.shrink()
+            + self.sessions.shrink()
+            + self.annotations.shrink()
+            + self.incidents.shrink()
+            + self.requests.shrink()
+            + self.idle_disconnects.shrink()
+    }
+}
+
+impl Stats {
+    /// Removes the peers not seen for `stale_after` with all their samples
+    /// and releases the unused capacity of the remaining buffers, e.g. before taking a snapshot.
+    /// Nothing is compacted automatically.
+    pub fn compact(&self, stale_after: Duration) -> Compaction {
+        trace_span!("compact");
+        let cutoff = self
+            .clock
+            .now()
+            .checked_sub(stale_after)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut compaction = Compaction::default();
+        for peer_id in self.peer_ids() {
+            // Removed with its windows at once, unless a sample arrived meanwhile
+            let _recording = self.recording(&peer_id);
+            let mut stale = false;
+            self.peers.alter(peer_id.clone(), |peer| {
+                let peer = peer?;
+                stale = peer.last_seen < cutoff;
+                Some(peer).filter(|_| !stale)
+            });
+            if !stale {
+                continue;
+            }
+            compaction.peers_removed += 1;
+            compaction.bytes_reclaimed += mem::size_of::<PeerState>();
+            if let Some(pings) = self.pings_to_peers.remove(&peer_id) {
+                compaction.samples_removed += pings.len();
+                compaction.bytes_reclaimed += pings.capacity() * mem::size_of::<Duration>();
+            }
+            if let Some(rates) = self.transmissions_rates.remove(&peer_id) {
+                compaction.samples_removed += rates.len();
+                compaction.bytes_reclaimed += rates.capacity() * mem::size_of::<Rate>();
+            }
+        }
+        for peer_id in self.peer_ids() {
+            if let Some(mut pings) = self.pings_to_peers.get_mut(&peer_id) {
+                compaction.bytes_reclaimed += pings.shrink();
+            }
+            if let Some(mut rates) = self.transmissions_rates.get_mut(&peer_id) {
+                compaction.bytes_reclaimed += rates.shrink();
+            }
+            if let Some(mut peer) = self.peers.get_mut(&peer_id) {
+                compaction.bytes_reclaimed += peer.shrink();
+            }
+        }
+        self.peers.shrink_to_fit();
+        self.pings_to_peers.shrink_to_fit();
+        self.transmissions_rates.shrink_to_fit();
+        compaction
+    }
+}
+
+#[test]
+fn stale_peers_are_removed() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_transmission("2".to_string(), Duration::from_millis(10), 100);
+    clock.advance(Duration::from_secs(600));
+    let mut pings = Vec::with_capacity(64);
+    pings.push(Duration::from_millis(20));
+    stats.pings_to_peers.insert("3".to_string(), pings.into());
+    stats.add_ping("3".to_string(), Duration::from_millis(20));
+    let compaction = stats.compact(Duration::from_secs(60));
+    assert_eq!(compaction.peers_removed, 1);
+    assert_eq!(compaction.samples_removed, 2);
+    assert!(compaction.bytes_reclaimed >= 62 * mem::size_of::<Duration>());
+    assert_eq!(stats.peer_ids(), vec!["3".to_string()]);
+    assert_eq!(stats.pings_to_peers.get("3").unwrap().capacity(), 2);
+    assert_eq!(
+        stats.compact(Duration::from_secs(60)),
+        Compaction::default()
+    );
+}
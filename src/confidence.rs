@@ -0,0 +1,176 @@
+use crate::{Rate, Stats, StatsSnapshot, Summary};
+use std::time::Duration;
+
+/// Confidence level of the errors computed with a z-value of `1.96`
+pub(crate) const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+impl Stats {
+    /// Confidence level of the errors of the peer summaries, 95% by default, e.g. `0.99` for
+    /// wider intervals. Errors are scaled by the ratio of the z-values of the levels.
+    /// Levels out of (0, 1) are rejected by `StatsBuilder::build` and `Stats::validate`.
+    pub fn with_confidence(mut self, level: f64) -> Self {
+        self.confidence = level;
+        self
+    }
+
+    /// Factor from errors at the default to errors at the configured confidence level.
+    pub(crate) fn confidence_scale(&self) -> f64 {
+        confidence_scale(self.confidence)
+    }
+
+    /// Level of the snapshots, `None` for the default.
+    pub(crate) fn snapshot_confidence(&self) -> Option<f64> {
+        Some(self.confidence).filter(|level| *level != DEFAULT_CONFIDENCE)
+    }
+}
+
+impl StatsSnapshot {
+    /// Factor from errors at the default to errors at the confidence level of the snapshot,
+    /// for summaries computed from the peer summaries.
+    pub(crate) fn confidence_scale(&self) -> f64 {
+        self.confidence.map_or(1.0, confidence_scale)
+    }
+}
+
+fn confidence_scale(level: f64) -> f64 {
+    if level == DEFAULT_CONFIDENCE {
+        1.0
+    } else {
+        z_value(level) / z_value(DEFAULT_CONFIDENCE)
+    }
+}
+
+/// Summary with its error scaled by `scale`, e.g. of `Stats::confidence_scale`.
+pub(crate) fn scaled(summary: Option<Summary>, scale: f64) -> Option<Summary> {
+    summary.map(|summary| Summary {
+        error: scaled_error(summary.error, scale),
+        ..summary
+    })
+}
+
+/// Like `scaled`, for transmission rates.
+pub(crate) fn scaled_rate(summary: Option<Summary<Rate>>, scale: f64) -> Option<Summary<Rate>> {
+    summary.map(|summary| Summary {
+        error: summary.error * scale,
+        ..summary
+    })
+}
+
+pub(crate) fn scaled_error(error: Duration, scale: f64) -> Duration {
+    if scale == 1.0 {
+        return error;
+    }
+    Duration::try_from_secs_f64(error.as_secs_f64() * scale).unwrap_or(Duration::MAX)
+}
+
+/// Two-sided z-value of the confidence level.
+fn z_value(level: f64) -> f64 {
+    inverse_normal_cdf((1.0 + level) / 2.0)
+}
+
+/// Quantile of the standard normal distribution at `p` in `[0.5, 1)` with Acklam's
+/// rational approximation, accurate to about `1e-9`.
+fn inverse_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    if p <= 1.0 - 0.024_25 {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        let q = (-2.0 * (1.0 - p).ln()).sqrt();
+        -(((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    }
+}
+
+#[test]
+fn errors_follow_the_confidence_level() {
+    assert!((z_value(0.95) - 1.959_964).abs() < 1e-6);
+    assert!((z_value(0.99) - 2.575_829).abs() < 1e-6);
+    assert!((z_value(0.999) - 3.290_527).abs() < 1e-6);
+
+    let record = |stats: Stats| {
+        stats.add_ping("2".to_string(), Duration::from_millis(10));
+        stats.add_ping("2".to_string(), Duration::from_millis(30));
+        stats.summarize_peer("2").unwrap().ping.unwrap().error
+    };
+    let default = record(Stats::new(100, "1".to_string()));
+    let wide = record(Stats::new(100, "1".to_string()).with_confidence(0.99));
+    let ratio = wide.as_secs_f64() / default.as_secs_f64();
+    assert!((ratio - 2.575_829 / 1.959_964).abs() < 1e-5);
+}
+
+#[test]
+fn reports_follow_the_confidence_level() {
+    use crate::Redaction;
+    use std::time::SystemTime;
+
+    let since = SystemTime::now();
+    let record = |stats: Stats| {
+        for (peer_id, rtt) in [("2", 10), ("2", 30), ("3", 20), ("3", 60)] {
+            stats.add_ping_over(peer_id.to_string(), Duration::from_millis(rtt), "tcp");
+        }
+        stats.set_capabilities("2".to_string(), ["relay"]);
+        stats.set_capabilities("3".to_string(), ["relay"]);
+        let snapshot = stats.snapshot();
+        let aggregate = snapshot.clone().redacted(&Redaction {
+            aggregates_only: true,
+            ..Redaction::default()
+        });
+        [
+            stats.summary_since("2", since).unwrap().error,
+            stats
+                .benchmark_report("tcp")
+                .get("tcp")
+                .unwrap()
+                .ping
+                .as_ref()
+                .unwrap()
+                .error,
+            snapshot
+                .capability_report()
+                .get("relay")
+                .unwrap()
+                .ping_with
+                .as_ref()
+                .unwrap()
+                .error,
+            aggregate.peers[0].ping.as_ref().unwrap().error,
+        ]
+    };
+    let default = record(Stats::new(100, "1".to_string()));
+    let wide = record(Stats::new(100, "1".to_string()).with_confidence(0.99));
+    for (default, wide) in default.iter().zip(&wide) {
+        let ratio = wide.as_secs_f64() / default.as_secs_f64();
+        assert!((ratio - 2.575_829 / 1.959_964).abs() < 1e-5);
+    }
+}
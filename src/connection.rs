@@ -0,0 +1,322 @@
+use crate::{ByteSize, PushLossy, Stats};
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::PoisonError,
+    time::{Duration, SystemTime},
+};
+
+/// Connection a sample was measured over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Connection {
+    /// Opened for this sample, so it includes the handshake
+    Fresh,
+    Established,
+}
+
+/// Why a connection to a peer was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisconnectReason {
+    /// Closed by us because it was unused
+    Idle,
+    Error,
+    /// Closed by us because of the peer's behavior
+    Banned,
+    RemoteClosed,
+    /// Replaced by a newer connection to the same peer
+    Superseded,
+}
+
+/// Number of closed connections for each `DisconnectReason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisconnectCounts {
+    pub idle: u64,
+    pub error: u64,
+    pub banned: u64,
+    pub remote_closed: u64,
+    pub superseded: u64,
+}
+
+impl DisconnectCounts {
+    pub const REASONS: [DisconnectReason; 5] = [
+        DisconnectReason::Idle,
+        DisconnectReason::Error,
+        DisconnectReason::Banned,
+        DisconnectReason::RemoteClosed,
+        DisconnectReason::Superseded,
+    ];
+
+    pub fn get(&self, reason: DisconnectReason) -> u64 {
+        match reason {
+            DisconnectReason::Idle => self.idle,
+            DisconnectReason::Error => self.error,
+            DisconnectReason::Banned => self.banned,
+            DisconnectReason::RemoteClosed => self.remote_closed,
+            DisconnectReason::Superseded => self.superseded,
+        }
+    }
+
+    /// Disconnects initiated by us rather than by the peer or the network.
+    pub fn evictions(&self) -> u64 {
+        self.idle + self.banned + self.superseded
+    }
+
+    pub fn total(&self) -> u64 {
+        Self::REASONS.iter().map(|reason| self.get(*reason)).sum()
+    }
+
+    fn add(&mut self, reason: DisconnectReason) {
+        match reason {
+            DisconnectReason::Idle => self.idle += 1,
+            DisconnectReason::Error => self.error += 1,
+            DisconnectReason::Banned => self.banned += 1,
+            DisconnectReason::RemoteClosed => self.remote_closed += 1,
+            DisconnectReason::Superseded => self.superseded += 1,
+        }
+    }
+}
+
+impl fmt::Display for DisconnectCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for reason in Self::REASONS.iter() {
+            let count = self.get(*reason);
+            if count > 0 {
+                write!(f, "{}{} {:?}", separator, count, reason)?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Connection session which is still open.
+#[derive(Debug, Clone)]
+pub(crate) struct OpenSession {
+    started: SystemTime,
+    bytes: ByteSize,
+    rtt_sum: Duration,
+    pings: u32,
+}
+
+impl OpenSession {
+    pub(crate) fn add_ping(&mut self, rtt: Duration) {
+        self.rtt_sum = self.rtt_sum.saturating_add(rtt);
+        self.pings += 1;
+    }
+
+    pub(crate) fn add_bytes(&mut self, bytes: ByteSize) {
+        self.bytes += bytes;
+    }
+
+    fn close(self, ended: SystemTime, reason: DisconnectReason) -> Session {
+        Session {
+            started: self.started,
+            duration: ended.duration_since(self.started).unwrap_or_default(),
+            bytes: self.bytes,
+            mean_rtt: self.rtt_sum.checked_div(self.pings),
+            reason,
+        }
+    }
+}
+
+/// Summary of a closed connection session, from `Stats::record_connected`
+/// to `Stats::record_disconnected`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    pub started: SystemTime,
+    pub duration: Duration,
+    /// Bytes of the transmissions during the session
+    pub bytes: ByteSize,
+    /// Mean of the pings during the session
+    pub mean_rtt: Option<Duration>,
+    pub reason: DisconnectReason,
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {}", self.duration, self.bytes)?;
+        if let Some(rtt) = self.mean_rtt {
+            write!(f, " rtt {:?}", rtt)?;
+        }
+        write!(f, " {:?}", self.reason)
+    }
+}
+
+impl Stats {
+    /// Number of closed sessions kept for each peer, the oldest are dropped first.
+    pub fn with_session_history(mut self, sessions: usize) -> Self {
+        self.session_history = sessions;
+        self
+    }
+
+    /// Starts a session with the peer, an open session is closed as `DisconnectReason::Superseded`.
+    pub fn record_connected(&self, peer_id: String) {
+        trace_span!("record_connected");
+        let _recording = self.recording(&peer_id);
+        let now = self.clock.now();
+        let history = self.session_history;
+        self.update_peer(&peer_id, |peer| {
+            if let Some(session) = peer.session.take() {
+                let reason = DisconnectReason::Superseded;
+                peer.disconnects.add(reason);
+                self.count_disconnect(reason);
+                push_session(&mut peer.sessions, session.close(now, reason), history);
+            }
+            peer.session = Some(OpenSession {
+                started: now,
+                bytes: ByteSize(0),
+                rtt_sum: Duration::from_secs(0),
+                pings: 0,
+            });
+        });
+    }
+
+    /// Counts the disconnect and closes the open session with the peer, if any,
+    /// adding it to the peer's history. The time since the peer was last seen
+    /// is summarized as `KeepAlive`.
+    pub fn record_disconnected(&self, peer_id: String, reason: DisconnectReason) {
+        trace_span!("record_disconnected");
+        let _recording = self.recording(&peer_id);
+        let now = self.clock.now();
+        let history = self.session_history;
+        let idle = self
+            .last_seen(&peer_id)
+            .map(|last_seen| now.duration_since(last_seen).unwrap_or_default());
+        self.count_disconnect(reason);
+        self.update_peer(&peer_id, |peer| {
+            peer.disconnects.add(reason);
+            if let Some(idle) = idle {
+                peer.idle_disconnects
+                    .add(idle, self.keep_alive_threshold, self.window_size);
+            }
+            if let Some(session) = peer.session.take() {
+                push_session(&mut peer.sessions, session.close(now, reason), history);
+            }
+        });
+    }
+
+    /// Disconnects from all peers.
+    pub fn disconnects(&self) -> DisconnectCounts {
+        *self
+            .disconnects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn count_disconnect(&self, reason: DisconnectReason) {
+        self.disconnects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .add(reason);
+    }
+
+    /// Records the ping like `add_ping` over an established connection,
+    /// pings over fresh connections are summarized as `PeerSummary::cold_ping` instead
+    /// to keep handshakes out of the steady-state round trip time.
+    pub fn add_ping_with_connection(&self, peer_id: String, rtt: Duration, connection: Connection) {
+        match connection {
+            Connection::Established => self.add_ping(peer_id, rtt),
+            Connection::Fresh => {
+                trace_span!("add_cold_ping");
+                let _recording = self.recording(&peer_id);
+                if self.quarantine_ping(&peer_id, rtt) {
+                    return;
+                }
+                let window_size = self.window_size;
+                self.update_peer(&peer_id, |peer| {
+                    peer.cold_pings.push_lossy(rtt, window_size)
+                });
+            }
+        }
+    }
+}
+
+fn push_session(sessions: &mut VecDeque<Session>, session: Session, history: usize) {
+    if history == 0 {
+        return;
+    }
+    if sessions.len() >= history {
+        sessions.pop_front();
+    }
+    sessions.push_back(session);
+}
+
+#[test]
+fn cold_pings_are_kept_apart() {
+    let stats = Stats::new(100, "1".to_string());
+    let millis = Duration::from_millis;
+    stats.add_ping_with_connection("2".to_string(), millis(300), Connection::Fresh);
+    stats.add_ping_with_connection("2".to_string(), millis(20), Connection::Established);
+    stats.add_ping_with_connection("2".to_string(), millis(40), Connection::Established);
+    stats.add_ping_with_connection("3".to_string(), millis(200), Connection::Fresh);
+    let peers = stats.snapshot().peers;
+    assert_eq!(peers[0].ping.as_ref().unwrap().mean, millis(30));
+    assert_eq!(peers[0].cold_ping.as_ref().unwrap().mean, millis(300));
+    assert_eq!(peers[1].ping, None);
+    assert_eq!(peers[1].cold_ping.as_ref().unwrap().samples, 1);
+}
+
+#[test]
+fn sessions_are_summarized_when_closed() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    stats.add_ping("2".to_string(), Duration::from_millis(500));
+    stats.record_connected("2".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("2".to_string(), Duration::from_millis(30));
+    stats.add_transmission("2".to_string(), Duration::from_millis(1), 1_000);
+    clock.advance(Duration::from_secs(60));
+    stats.record_disconnected("2".to_string(), DisconnectReason::RemoteClosed);
+    let session = stats.snapshot().peers.remove(0).sessions.remove(0);
+    assert_eq!(session.duration, Duration::from_secs(60));
+    assert_eq!(session.bytes, ByteSize(1_000));
+    assert_eq!(session.mean_rtt, Some(Duration::from_millis(20)));
+    assert_eq!(session.reason, DisconnectReason::RemoteClosed);
+}
+
+#[test]
+fn session_history_is_bounded() {
+    let stats = Stats::new(100, "1".to_string()).with_session_history(2);
+    stats.record_connected("2".to_string());
+    stats.record_connected("2".to_string());
+    stats.record_disconnected("2".to_string(), DisconnectReason::Idle);
+    stats.record_connected("2".to_string());
+    stats.record_disconnected("2".to_string(), DisconnectReason::Error);
+    stats.record_disconnected("2".to_string(), DisconnectReason::Banned);
+    let reasons: Vec<_> = stats.snapshot().peers[0]
+        .sessions
+        .iter()
+        .map(|session| session.reason)
+        .collect();
+    assert_eq!(
+        reasons,
+        vec![DisconnectReason::Idle, DisconnectReason::Error]
+    );
+}
+
+#[test]
+fn disconnects_are_counted_by_reason() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.record_connected("2".to_string());
+    stats.record_connected("2".to_string());
+    stats.record_disconnected("2".to_string(), DisconnectReason::RemoteClosed);
+    stats.record_disconnected("3".to_string(), DisconnectReason::Banned);
+    stats.record_disconnected("3".to_string(), DisconnectReason::Idle);
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.peers[0].disconnects.superseded, 1);
+    assert_eq!(snapshot.peers[0].disconnects.remote_closed, 1);
+    assert_eq!(snapshot.peers[1].disconnects.evictions(), 2);
+    assert_eq!(stats.disconnects().total(), 4);
+    assert_eq!(
+        snapshot.disconnects.to_string(),
+        "1 Idle, 1 Banned, 1 RemoteClosed, 1 Superseded"
+    );
+}
@@ -0,0 +1,83 @@
+use crate::{Rate, StatsSnapshot};
+use std::{fmt, time::SystemTime};
+
+/// Session of a peer which transferred its bytes faster than the peer's transmissions did,
+/// which can only happen when either is measured wrong.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Discrepancy {
+    pub peer_id: String,
+    pub session_started: SystemTime,
+    /// Bytes of the session over its duration
+    pub session_throughput: Rate,
+    /// Mean rate of the transmissions of the peer
+    pub transmission_rate: Rate,
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} session throughput {} above transmission rate {}",
+            self.peer_id, self.session_throughput, self.transmission_rate
+        )
+    }
+}
+
+impl StatsSnapshot {
+    /// Compares the byte counters and durations of the closed sessions with the
+    /// transmission rates of their peers. A session cannot average more than its transfers,
+    /// so sessions above the mean rate by more than the `tolerance` fraction,
+    /// allowing for the spread of the rates, are reported.
+    pub fn check_consistency(&self, tolerance: f64) -> Vec<Discrepancy> {
+        let mut discrepancies = Vec::new();
+        for peer in &self.peers {
+            let rate = match &peer.transmission_rate {
+                Some(rate) => rate,
+                None => continue,
+            };
+            let limit = (rate.mean + rate.std_dev) * (1.0 + tolerance);
+            for session in &peer.sessions {
+                if session.bytes.bytes() == 0 {
+                    continue;
+                }
+                let throughput = session.bytes / session.duration;
+                if throughput > limit {
+                    discrepancies.push(Discrepancy {
+                        peer_id: peer.peer_id.clone(),
+                        session_started: session.started,
+                        session_throughput: throughput,
+                        transmission_rate: rate.mean,
+                    });
+                }
+            }
+        }
+        discrepancies
+    }
+}
+
+#[test]
+fn sessions_faster_than_transmissions_are_flagged() {
+    use crate::{DisconnectReason, ManualClock, Stats};
+    use std::{sync::Arc, time::Duration};
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    stats.record_connected("2".to_string());
+    stats.record_connected("3".to_string());
+    stats.add_transmission("2".to_string(), Duration::from_secs(2), 2_000);
+    // Each transmission is timed as taking longer than the whole session
+    stats.add_transmission("3".to_string(), Duration::from_secs(10), 5_000);
+    stats.add_transmission("3".to_string(), Duration::from_secs(10), 5_000);
+    clock.advance(Duration::from_secs(2));
+    stats.record_disconnected("3".to_string(), DisconnectReason::Idle);
+    clock.advance(Duration::from_secs(8));
+    stats.record_disconnected("2".to_string(), DisconnectReason::Idle);
+    let discrepancies = stats.snapshot().check_consistency(0.1);
+    assert_eq!(discrepancies.len(), 1);
+    assert_eq!(discrepancies[0].peer_id, "3");
+    assert_eq!(
+        discrepancies[0].session_throughput,
+        Rate::from_bytes_per_sec(5_000.0)
+    );
+}
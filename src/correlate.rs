@@ -0,0 +1,236 @@
+use crate::{Metric, Stats};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Samples of a peer recorded over time which can be correlated with each other.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Series {
+    /// Round trip times in seconds
+    Ping,
+    /// Transmission rates in bytes per second
+    TransmissionRate,
+    /// Gauge recorded with `Stats::record_gauge`, by name
+    Gauge(String),
+}
+
+impl From<Metric> for Series {
+    fn from(metric: Metric) -> Self {
+        match metric {
+            Metric::Ping => Series::Ping,
+            Metric::TransmissionRate => Series::TransmissionRate,
+        }
+    }
+}
+
+impl From<&str> for Series {
+    fn from(gauge: &str) -> Self {
+        Series::Gauge(gauge.to_string())
+    }
+}
+
+impl fmt::Display for Series {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Series::Ping => f.write_str("ping"),
+            Series::TransmissionRate => f.write_str("transmission rate"),
+            Series::Gauge(name) => write!(f, "{:?}", name),
+        }
+    }
+}
+
+/// Pearson correlation of the bucket means of two series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Correlation {
+    /// From -1 for opposite to 1 for identical movements
+    pub coefficient: f64,
+    /// Time buckets with samples of both series
+    pub buckets: usize,
+}
+
+/// Correlation of two series for each peer with enough samples of both, ordered by peer id.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CorrelationReport {
+    pub a: Series,
+    pub b: Series,
+    pub bucket: Duration,
+    pub peers: Vec<(String, Correlation)>,
+}
+
+impl CorrelationReport {
+    pub fn get(&self, peer_id: &str) -> Option<&Correlation> {
+        self.peers
+            .iter()
+            .find(|(id, _)| id == peer_id)
+            .map(|(_, correlation)| correlation)
+    }
+}
+
+impl fmt::Display for CorrelationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Correlation of {} and {} in {:?} buckets by peer:",
+            self.a, self.b, self.bucket
+        )?;
+        for (peer_id, correlation) in &self.peers {
+            writeln!(
+                f,
+                "{:?} r {:.2} over {} buckets",
+                peer_id, correlation.coefficient, correlation.buckets
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Length of the time buckets of `correlate`, 10 seconds by default.
+    pub fn with_correlation_bucket(mut self, bucket: Duration) -> Self {
+        assert!(!bucket.is_zero(), "Correlation bucket must not be empty");
+        self.correlation_bucket = bucket;
+        self
+    }
+
+    /// Correlates the samples of two series of the peer in its current windows, e.g. the ping
+    /// with a `"queue_depth"` gauge. Samples are averaged in time buckets aligned to the
+    /// Unix epoch and the means of the buckets with samples of both series are correlated.
+    /// `None` with fewer than 3 such buckets or if a series does not vary.
+    pub fn correlate(
+        &self,
+        peer_id: &str,
+        a: impl Into<Series>,
+        b: impl Into<Series>,
+    ) -> Option<Correlation> {
+        self.correlate_series(peer_id, &a.into(), &b.into())
+    }
+
+    /// Correlation of two series for each peer, for root cause analysis over all peers.
+    pub fn correlation_report(
+        &self,
+        a: impl Into<Series>,
+        b: impl Into<Series>,
+    ) -> CorrelationReport {
+        trace_span!("correlation_report");
+        let (a, b) = (a.into(), b.into());
+        let peers = self
+            .peer_ids()
+            .into_iter()
+            .filter_map(|peer_id| {
+                let correlation = self.correlate_series(&peer_id, &a, &b)?;
+                Some((peer_id, correlation))
+            })
+            .collect();
+        CorrelationReport {
+            a,
+            b,
+            bucket: self.correlation_bucket,
+            peers,
+        }
+    }
+
+    fn correlate_series(&self, peer_id: &str, a: &Series, b: &Series) -> Option<Correlation> {
+        self.expire_samples(peer_id);
+        let a = self.bucket_means(peer_id, a)?;
+        let b = self.bucket_means(peer_id, b)?;
+        let pairs: Vec<(f64, f64)> = a
+            .iter()
+            .filter_map(|(bucket, a)| Some((*a, *b.get(bucket)?)))
+            .collect();
+        if pairs.len() < 3 {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let mean_a = pairs.iter().map(|(a, _)| a).sum::<f64>() / n;
+        let mean_b = pairs.iter().map(|(_, b)| b).sum::<f64>() / n;
+        let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+        for (a, b) in &pairs {
+            covariance += (a - mean_a) * (b - mean_b);
+            variance_a += (a - mean_a).powi(2);
+            variance_b += (b - mean_b).powi(2);
+        }
+        if variance_a == 0.0 || variance_b == 0.0 {
+            return None;
+        }
+        Some(Correlation {
+            coefficient: (covariance / (variance_a * variance_b).sqrt()).clamp(-1.0, 1.0),
+            buckets: pairs.len(),
+        })
+    }
+
+    /// Mean of the samples of the series in each bucket, by bucket index since the epoch.
+    fn bucket_means(&self, peer_id: &str, series: &Series) -> Option<BTreeMap<u128, f64>> {
+        let samples: Vec<(SystemTime, f64)> = match series {
+            Series::Ping => {
+                let pings = self.pings_to_peers.get(peer_id)?;
+                let (times, pings) = pings.between(UNIX_EPOCH, None);
+                let secs = pings.iter().map(Duration::as_secs_f64);
+                times.iter().copied().zip(secs).collect()
+            }
+            Series::TransmissionRate => {
+                let rates = self.transmissions_rates.get(peer_id)?;
+                let (times, rates) = rates.between(UNIX_EPOCH, None);
+                let rates = rates.iter().map(|rate| rate.bytes_per_sec());
+                times.iter().copied().zip(rates).collect()
+            }
+            Series::Gauge(name) => {
+                let peer = self.peers.get(peer_id)?;
+                let (times, values) = peer.gauges.get(name)?.between(UNIX_EPOCH, None);
+                times.iter().copied().zip(values.iter().copied()).collect()
+            }
+        };
+        let bucket = self.correlation_bucket.as_nanos();
+        let mut sums: BTreeMap<u128, (f64, usize)> = BTreeMap::new();
+        for (time, value) in samples {
+            let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+            let sum = sums.entry(since_epoch.as_nanos() / bucket).or_default();
+            sum.0 += value;
+            sum.1 += 1;
+        }
+        Some(
+            sums.into_iter()
+                .map(|(bucket, (sum, count))| (bucket, sum / count as f64))
+                .collect(),
+        )
+    }
+}
+
+#[test]
+fn series_are_correlated_over_buckets() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock.clone())
+        .with_correlation_bucket(Duration::from_secs(1));
+    for depth in [1, 4, 2, 8, 3] {
+        stats.record_gauge("2".to_string(), "queue_depth", depth as f64);
+        stats.add_ping("2".to_string(), Duration::from_millis(10 * depth));
+        stats.add_ping("2".to_string(), Duration::from_millis(10 * depth + 2));
+        stats.add_ping("3".to_string(), Duration::from_millis(100 - 10 * depth));
+        stats.add_transmission("3".to_string(), Duration::from_secs(1), depth * 1_000);
+        clock.advance(Duration::from_secs(1));
+    }
+    let correlation = stats.correlate("2", Metric::Ping, "queue_depth").unwrap();
+    assert_eq!(correlation.buckets, 5);
+    assert!((correlation.coefficient - 1.0).abs() < 1e-9);
+    let inverse = stats
+        .correlate("3", Metric::Ping, Metric::TransmissionRate)
+        .unwrap();
+    assert!((inverse.coefficient + 1.0).abs() < 1e-9);
+    assert_eq!(stats.correlate("3", Metric::Ping, "queue_depth"), None);
+
+    let report = stats.correlation_report(Metric::Ping, "queue_depth");
+    assert_eq!(report.peers.len(), 1);
+    assert_eq!(report.get("2"), Some(&correlation));
+    assert_eq!(
+        report.to_string(),
+        "Correlation of ping and \"queue_depth\" in 1s buckets by peer:\n\"2\" r 1.00 over 5 buckets\n"
+    );
+}
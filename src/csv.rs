@@ -0,0 +1,83 @@
+use crate::{Encoding, Rate, Stats, StatsSnapshot, Summary};
+use std::{
+    fmt::Display,
+    io::{self, prelude::*},
+    time::Duration,
+};
+
+const HEADER: &str = "peer_id,ping_samples,ping_mean_ms,ping_std_dev_ms,ping_error_ms,\
+transmission_rate_samples,transmission_rate_mean_bytes_per_sec,\
+transmission_rate_std_dev_bytes_per_sec,transmission_rate_error_bytes_per_sec";
+
+impl StatsSnapshot {
+    /// Writes a header and a row for each peer, cells of missing summaries are empty.
+    pub(crate) fn encode_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        for peer in &self.peers {
+            write!(writer, "{}", quoted(&peer.peer_id))?;
+            let millis = |duration: Duration| duration.as_secs_f64() * 1_000.0;
+            write_cells(&mut writer, peer.ping.as_ref(), millis)?;
+            write_cells(
+                &mut writer,
+                peer.transmission_rate.as_ref(),
+                Rate::bytes_per_sec,
+            )?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Saves a row for each peer with its ping and transmission rate summaries,
+    /// same as `save_snapshot` with `Encoding::Csv`.
+    pub fn save_as_csv(&self, filename: &str) -> io::Result<()> {
+        self.save_snapshot(filename, Encoding::Csv)
+    }
+}
+
+/// Writes the sample count, mean, standard deviation and error cells of `summary`.
+fn write_cells<W: Write, T: Copy, V: Display>(
+    writer: &mut W,
+    summary: Option<&Summary<T>>,
+    value: impl Fn(T) -> V,
+) -> io::Result<()> {
+    match summary {
+        Some(summary) => write!(
+            writer,
+            ",{},{},{},{}",
+            summary.samples,
+            value(summary.mean),
+            value(summary.std_dev),
+            value(summary.error)
+        ),
+        None => write!(writer, ",,,,"),
+    }
+}
+
+/// Peer id as a cell, quoted if it contains separators or quotes.
+fn quoted(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[test]
+fn csv_has_a_row_per_peer() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("2".to_string(), Duration::from_millis(30));
+    stats.add_transmission("a,\"b\"".to_string(), Duration::from_secs(2), 1_000);
+    let mut bytes = Vec::new();
+    stats.snapshot().encode(&mut bytes, Encoding::Csv).unwrap();
+    let csv = String::from_utf8(bytes).unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines[0], HEADER);
+    // The last digit of the error depends on the `fixed-point` feature
+    assert!(lines[1].starts_with("2,2,20,10,13.85929"));
+    assert!(lines[1].ends_with(",,,,"));
+    assert_eq!(lines[2], "\"a,\"\"b\"\"\",,,,,1,500,0,0");
+    assert_eq!(lines.len(), 3);
+}
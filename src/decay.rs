@@ -0,0 +1,100 @@
+use crate::Stats;
+use std::time::{Duration, SystemTime};
+
+/// Schedule by which samples of idle peers lose their weight.
+///
+/// After `grace` without new samples the weight of the window halves every `half_life`,
+/// which widens the confidence interval and moves estimates towards the prior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decay {
+    pub grace: Duration,
+    pub half_life: Duration,
+}
+
+impl Stats {
+    pub fn with_decay(mut self, decay: Decay) -> Self {
+        self.decay = Some(decay);
+        self
+    }
+
+    /// Weight from `0.0` to `1.0` of samples of a peer last seen at `last_seen`.
+    pub(crate) fn decay_weight(&self, last_seen: Option<SystemTime>) -> f64 {
+        let (decay, last_seen) = match (self.decay, last_seen) {
+            (Some(decay), Some(last_seen)) => (decay, last_seen),
+            _ => return 1.0,
+        };
+        let idle = self
+            .clock
+            .now()
+            .duration_since(last_seen)
+            .unwrap_or_default();
+        match idle.checked_sub(decay.grace) {
+            Some(excess) if !decay.half_life.is_zero() => {
+                0.5f64.powf(excess.as_secs_f64() / decay.half_life.as_secs_f64())
+            }
+            Some(excess) if !excess.is_zero() => 0.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Error of a window whose samples are only worth `weight` of their count.
+pub(crate) fn decayed_error(error: Duration, weight: f64) -> Duration {
+    if weight >= 1.0 {
+        return error;
+    }
+    Duration::try_from_secs_f64(error.as_secs_f64() / weight.sqrt()).unwrap_or(Duration::MAX)
+}
+
+#[cfg(test)]
+fn idle_stats(clock: std::sync::Arc<crate::ManualClock>) -> Stats {
+    use crate::{Metric, Prior};
+
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock)
+        .with_decay(Decay {
+            grace: Duration::from_secs(60),
+            half_life: Duration::from_secs(60),
+        })
+        .with_prior(
+            Metric::Ping,
+            Prior::Fixed {
+                mean: Duration::from_millis(80),
+                weight: 1,
+            },
+        );
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("2".to_string(), Duration::from_millis(30));
+    stats
+}
+
+#[test]
+fn idle_peers_widen_confidence_interval() {
+    let clock = std::sync::Arc::new(crate::ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = idle_stats(clock.clone());
+    let error = || {
+        stats.snapshot().peers[0]
+            .ping
+            .as_ref()
+            .unwrap()
+            .error
+            .as_secs_f64()
+    };
+    let fresh = error();
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(error(), fresh);
+    clock.advance(Duration::from_secs(120));
+    assert!((error() - fresh * 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn idle_peers_decay_to_prior() {
+    use crate::Metric;
+
+    let clock = std::sync::Arc::new(crate::ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = idle_stats(clock.clone());
+    let estimate = || stats.estimate("2", Metric::Ping).unwrap().as_secs_f64();
+    assert!((estimate() - 0.040).abs() < 1e-9);
+    clock.advance(Duration::from_secs(120));
+    assert!((estimate() - 0.050).abs() < 1e-9);
+}
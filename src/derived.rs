@@ -0,0 +1,51 @@
+use crate::{PeerSummary, Stats};
+use std::sync::{Arc, PoisonError};
+
+/// Metric computed from the summary of a peer, see `Stats::register_derived`.
+pub(crate) type Derive = Arc<dyn Fn(&PeerSummary) -> Option<f64> + Send + Sync>;
+
+impl Stats {
+    /// Adds the metric `name` to `PeerSummary::derived` of every summarized peer, computed by
+    /// `derive` from the rest of the summary before scoring, so it reaches all exports.
+    /// Peers for which `derive` returns `None` go without it, registering `name` again
+    /// replaces the metric.
+    pub fn register_derived<F>(&self, name: &str, derive: F)
+    where
+        F: Fn(&PeerSummary) -> Option<f64> + Send + Sync + 'static,
+    {
+        let mut derived = self.derived.lock().unwrap_or_else(PoisonError::into_inner);
+        derived.retain(|(registered, _)| registered != name);
+        derived.push((name.to_string(), Arc::new(derive)));
+    }
+
+    pub(crate) fn derive(&self, peer: &mut PeerSummary) {
+        let derived = self
+            .derived
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for (name, derive) in derived {
+            if let Some(value) = derive(peer) {
+                peer.derived.insert(name, value);
+            }
+        }
+    }
+}
+
+#[test]
+fn derived_metrics_are_summarized() {
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(20));
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    stats.add_ping("3".to_string(), Duration::from_millis(10));
+    stats.register_derived("bytes_per_rtt", |peer| {
+        let rate = peer.transmission_rate.as_ref()?.mean;
+        Some(rate.bytes_per_sec() * peer.ping.as_ref()?.mean.as_secs_f64())
+    });
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.peers[0].derived["bytes_per_rtt"], 20.0);
+    assert!(snapshot.peers[1].derived.is_empty());
+    assert!(snapshot.to_string().contains("\"2\" bytes_per_rtt 20"));
+}
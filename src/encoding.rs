@@ -0,0 +1,153 @@
+use crate::{Exporter, Stats, StatsSnapshot};
+use std::io::{self, prelude::*};
+
+/// Format used to encode a `StatsSnapshot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Human readable report, same as `Display` of `Stats`
+    Text,
+    /// Header and a row for each peer with its ping and transmission rate summaries,
+    /// which can not be decoded
+    Csv,
+    /// InfluxDB line protocol of the ping and transmission rate summaries, without the
+    /// percentiles of `Stats::render_influx`, which can not be decoded
+    Influx,
+    #[cfg(feature = "cbor")]
+    Cbor,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// `wire::StatsDigest` message
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+    /// Arrow IPC stream with a row for each peer, which can not be decoded
+    #[cfg(feature = "arrow")]
+    Arrow,
+    /// JSON object with a record for each peer, which can not be decoded
+    #[cfg(feature = "json")]
+    Json,
+}
+
+impl StatsSnapshot {
+    pub fn encode<W: Write>(&self, mut writer: W, encoding: Encoding) -> io::Result<()> {
+        match encoding {
+            Encoding::Text => writer.write_all(self.to_string().as_bytes()),
+            Encoding::Csv => self.encode_csv(writer),
+            Encoding::Influx => self.encode_influx(writer),
+            #[cfg(feature = "cbor")]
+            Encoding::Cbor => ciborium::ser::into_writer(self, writer).map_err(invalid_data),
+            #[cfg(feature = "msgpack")]
+            Encoding::MessagePack => {
+                rmp_serde::encode::write_named(&mut writer, self).map_err(invalid_data)
+            }
+            #[cfg(feature = "protobuf")]
+            Encoding::Protobuf => {
+                use prost::Message;
+                writer.write_all(&crate::wire::StatsDigest::from(self).encode_to_vec())
+            }
+            #[cfg(feature = "arrow")]
+            Encoding::Arrow => self.encode_arrow(writer),
+            #[cfg(feature = "json")]
+            Encoding::Json => serde_json::to_writer(writer, self).map_err(io::Error::from),
+        }
+    }
+
+    /// Text reports, CSV, line protocol, Arrow streams and JSON can not be decoded and result in `ErrorKind::InvalidInput`.
+    #[cfg_attr(
+        not(any(feature = "cbor", feature = "msgpack", feature = "protobuf")),
+        allow(unused_variables)
+    )]
+    pub fn decode<R: Read>(reader: R, encoding: Encoding) -> io::Result<Self> {
+        match encoding {
+            Encoding::Text => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "text report can not be decoded",
+            )),
+            Encoding::Csv => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "csv can not be decoded",
+            )),
+            Encoding::Influx => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line protocol can not be decoded",
+            )),
+            #[cfg(feature = "arrow")]
+            Encoding::Arrow => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "arrow stream can not be decoded",
+            )),
+            #[cfg(feature = "json")]
+            Encoding::Json => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "json can not be decoded",
+            )),
+            #[cfg(feature = "cbor")]
+            Encoding::Cbor => ciborium::de::from_reader(reader).map_err(invalid_data),
+            #[cfg(feature = "msgpack")]
+            Encoding::MessagePack => rmp_serde::decode::from_read(reader).map_err(invalid_data),
+            #[cfg(feature = "protobuf")]
+            Encoding::Protobuf => {
+                use prost::Message;
+                let mut reader = reader;
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes)?;
+                crate::wire::StatsDigest::decode(bytes.as_slice())
+                    .map(Into::into)
+                    .map_err(invalid_data)
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack", feature = "protobuf"))]
+fn invalid_data<E: std::fmt::Display>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+impl Stats {
+    /// Saves the snapshot without rounding, use `Exporter` for more options.
+    pub fn save_snapshot(&self, filename: &str, encoding: Encoding) -> io::Result<()> {
+        Exporter::new(encoding).save(self, filename)
+    }
+}
+
+/// Encodings which can be decoded back into a snapshot.
+#[cfg(all(test, any(feature = "cbor", feature = "msgpack", feature = "protobuf")))]
+#[allow(clippy::vec_init_then_push)]
+pub(crate) fn binary_encodings() -> Vec<Encoding> {
+    let mut encodings = Vec::new();
+    #[cfg(feature = "cbor")]
+    encodings.push(Encoding::Cbor);
+    #[cfg(feature = "msgpack")]
+    encodings.push(Encoding::MessagePack);
+    #[cfg(feature = "protobuf")]
+    encodings.push(Encoding::Protobuf);
+    encodings
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack", feature = "protobuf"))]
+#[test]
+fn binary_encodings_roundtrip() {
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_transmission("3".to_string(), Duration::from_millis(10), 100);
+    let snapshot = stats.snapshot();
+    for encoding in binary_encodings() {
+        let mut bytes = Vec::new();
+        snapshot.encode(&mut bytes, encoding).unwrap();
+        assert_eq!(
+            StatsSnapshot::decode(bytes.as_slice(), encoding).unwrap(),
+            snapshot
+        );
+    }
+}
+
+#[test]
+fn text_encoding_matches_display() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), std::time::Duration::from_millis(10));
+    let mut bytes = Vec::new();
+    stats.snapshot().encode(&mut bytes, Encoding::Text).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), stats.to_string());
+}
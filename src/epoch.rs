@@ -0,0 +1,131 @@
+use crate::{watchdog, Collector, Stats};
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Wall clock aligned periods which snapshots of a fleet describe, see `Stats::with_epochs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Epochs {
+    pub(crate) interval: Duration,
+    /// Longest delay of a node after each boundary, so that nodes do not push all at once
+    max_jitter: Duration,
+}
+
+impl Epochs {
+    /// Delay of the node with `peer_id` after each boundary.
+    fn jitter(&self, peer_id: &str) -> Duration {
+        match nanos(self.max_jitter) {
+            0 => Duration::from_secs(0),
+            max => Duration::from_nanos(fnv1a(peer_id.as_bytes()) % max),
+        }
+    }
+
+    /// Latest boundary at or before `time` shifted back by the `jitter`.
+    fn start(&self, time: SystemTime, jitter: Duration) -> SystemTime {
+        let since_epoch = watchdog::to_nanos(time).saturating_sub(nanos(jitter));
+        let interval = nanos(self.interval).max(1);
+        UNIX_EPOCH + Duration::from_nanos(since_epoch / interval * interval)
+    }
+}
+
+impl Stats {
+    /// Aligns snapshots to multiples of `interval` since the Unix epoch, e.g. every 30
+    /// minutes at :00 and :30, so that snapshots of many nodes describe the same periods.
+    /// Each node is delayed after the boundaries by a part of `max_jitter` derived from its
+    /// peer id, which spreads the pushes of the fleet but is stable across restarts. The
+    /// delay is derived from the peer id of the built stats, even if it is set later.
+    pub fn with_epochs(mut self, interval: Duration, max_jitter: Duration) -> Self {
+        self.epochs = Some(Epochs {
+            interval,
+            max_jitter: max_jitter.min(interval),
+        });
+        self
+    }
+
+    /// Boundary of the epoch the next snapshot belongs to, `None` without `with_epochs`.
+    pub fn epoch(&self) -> Option<SystemTime> {
+        let epochs = self.epochs?;
+        Some(epochs.start(self.clock.now(), epochs.jitter(&self.peer_id)))
+    }
+
+    /// Time to take and push the next snapshot, the next boundary delayed by the jitter
+    /// of this node.
+    pub fn next_epoch(&self) -> Option<SystemTime> {
+        let epochs = self.epochs?;
+        let jitter = epochs.jitter(&self.peer_id);
+        Some(epochs.start(self.clock.now(), jitter) + epochs.interval + jitter)
+    }
+}
+
+impl Collector {
+    /// Latest epoch of the ingested snapshots.
+    pub fn latest_epoch(&self) -> Option<SystemTime> {
+        self.snapshots().filter_map(|snapshot| snapshot.epoch).max()
+    }
+
+    /// Collector of only the snapshots of `epoch`, so that merged views compare
+    /// the same periods of all nodes.
+    pub fn of_epoch(&self, epoch: SystemTime) -> Collector {
+        let mut collector = Collector::new();
+        for snapshot in self.snapshots() {
+            if snapshot.epoch == Some(epoch) {
+                collector.ingest(snapshot.clone());
+            }
+        }
+        collector
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// FNV-1a hash, which unlike `DefaultHasher` is the same in every build.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[test]
+fn epochs_are_aligned_across_nodes() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let half_hour = Duration::from_secs(30 * 60);
+    let jitter = Duration::from_secs(60);
+    let start = UNIX_EPOCH + half_hour * 1_000;
+    let clock = Arc::new(ManualClock::new(start + Duration::from_secs(600)));
+    let mut collector = Collector::new();
+    let mut pushes = Vec::new();
+    for node in ["1", "2", "3"] {
+        let stats = Stats::new(100, node.to_string())
+            .with_clock(clock.clone())
+            .with_epochs(half_hour, jitter);
+        let next = stats.next_epoch().unwrap();
+        assert!(next >= start + half_hour && next < start + half_hour + jitter);
+        pushes.push(next);
+        stats.add_ping("4".to_string(), Duration::from_millis(10));
+        assert_eq!(stats.snapshot().epoch, Some(start));
+        collector.ingest(stats.snapshot());
+    }
+    assert_ne!(pushes[0], pushes[1]);
+    assert_eq!(Stats::new(100, "1".to_string()).epoch(), None);
+    let built = Stats::builder()
+        .clock(clock.clone())
+        .with(|stats| stats.with_epochs(half_hour, jitter))
+        .peer_id("2")
+        .build()
+        .unwrap();
+    assert_eq!(built.next_epoch(), Some(pushes[1]));
+
+    // A node pushing right after its delayed boundary belongs to the next epoch
+    let later = Stats::new(100, "1".to_string())
+        .with_clock(Arc::new(ManualClock::new(pushes[0])))
+        .with_epochs(half_hour, jitter);
+    assert_eq!(later.epoch(), Some(start + half_hour));
+    collector.ingest(later.snapshot());
+    assert_eq!(collector.latest_epoch(), Some(start + half_hour));
+    assert_eq!(collector.of_epoch(start).snapshots().count(), 2);
+}
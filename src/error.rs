@@ -0,0 +1,161 @@
+use crate::{ByteSize, Rtt, Stats};
+use std::{error, fmt, io, sync::atomic::Ordering, time::Duration};
+
+/// Why `Stats::try_add_ping` or `Stats::try_add_transmission` did not record a sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsError {
+    /// Zero or above `Rtt::MAX`, counted in `Stats::rejected_samples`
+    ImplausibleRtt(Duration),
+    /// Transmission which took no time and so has no rate, counted in `Stats::rejected_samples`
+    ZeroDuration,
+    /// Out of `Bounds` or an outlier of the `OutlierPolicy`, kept in the `Quarantine` of the peer
+    Quarantined,
+}
+
+impl fmt::Display for StatsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatsError::ImplausibleRtt(rtt) => write!(f, "implausible round trip time {:?}", rtt),
+            StatsError::ZeroDuration => f.write_str("transmission took no time"),
+            StatsError::Quarantined => f.write_str("sample quarantined"),
+        }
+    }
+}
+
+impl error::Error for StatsError {}
+
+impl From<StatsError> for io::Error {
+    fn from(error: StatsError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, error)
+    }
+}
+
+/// Problem with an option of a `StatsBuilder`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigProblem {
+    /// Windows of no samples, which would keep only the latest sample per metric
+    EmptyWindow,
+    EmptyPeerId,
+    /// Outside of (0, 1)
+    ConfidenceLevel(f64),
+    /// Outside of (0, 1]
+    EwmaAlpha(f64),
+    /// Time window of zero, which would expire every sample right away
+    ZeroTimeWindow,
+    /// Zero minimum or a maximum below the minimum
+    ProbeIntervalBounds {
+        min: Duration,
+        max: Duration,
+    },
+    /// Non-positive or NaN maximum distance from the mean
+    OutlierStdDevs(f64),
+    /// Decay half-life of zero, which would drop idle windows at the end of the grace
+    ZeroHalfLife,
+    /// Epochs of no time, which have no boundaries to align snapshots to
+    ZeroEpochInterval,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigProblem::EmptyWindow => f.write_str("window size must be at least 1"),
+            ConfigProblem::EmptyPeerId => f.write_str("peer id must not be empty"),
+            ConfigProblem::ConfidenceLevel(level) => {
+                write!(f, "confidence level {} out of (0, 1)", level)
+            }
+            ConfigProblem::EwmaAlpha(alpha) => write!(f, "EWMA alpha {} out of (0, 1]", alpha),
+            ConfigProblem::ZeroTimeWindow => f.write_str("time window must not be zero"),
+            ConfigProblem::ProbeIntervalBounds { min, max } => write!(
+                f,
+                "probe interval bounds {:?} to {:?} must be nonzero and increasing",
+                min, max
+            ),
+            ConfigProblem::OutlierStdDevs(std_devs) => {
+                write!(
+                    f,
+                    "outlier distance of {} standard deviations must be positive",
+                    std_devs
+                )
+            }
+            ConfigProblem::ZeroHalfLife => f.write_str("decay half-life must not be zero"),
+            ConfigProblem::ZeroEpochInterval => f.write_str("epoch interval must not be zero"),
+        }
+    }
+}
+
+/// Every problem found by `StatsBuilder::build` or `Stats::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub problems: Vec<ConfigProblem>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid stats configuration")?;
+        for (i, problem) in self.problems.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { "; " };
+            write!(f, "{}{}", separator, problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ConfigError {}
+
+impl From<ConfigError> for io::Error {
+    fn from(error: ConfigError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, error)
+    }
+}
+
+impl Stats {
+    /// Records the ping like `add_ping` if it `Rtt::is_plausible` and is not quarantined.
+    pub fn try_add_ping(&self, peer_id: String, rtt: Duration) -> Result<(), StatsError> {
+        if !Rtt::from(rtt).is_plausible() {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(StatsError::ImplausibleRtt(rtt));
+        }
+        self.record_ping(&peer_id, rtt)
+    }
+
+    /// Records the transmission like `add_transmission`, which drops the same samples
+    /// without telling why.
+    pub fn try_add_transmission(
+        &self,
+        peer_id: String,
+        time: Duration,
+        n_bytes: impl Into<ByteSize>,
+    ) -> Result<(), StatsError> {
+        self.record_transmission(&peer_id, time, n_bytes.into())
+    }
+}
+
+#[test]
+fn unrecorded_samples_are_reported() {
+    use crate::Bounds;
+
+    let stats = Stats::new(100, "1".to_string()).with_bounds(Bounds::default());
+    assert_eq!(
+        stats.try_add_ping("2".to_string(), Duration::from_millis(10)),
+        Ok(())
+    );
+    assert_eq!(
+        stats.try_add_ping("2".to_string(), Duration::from_secs(0)),
+        Err(StatsError::ImplausibleRtt(Duration::from_secs(0)))
+    );
+    assert_eq!(
+        stats.try_add_transmission("2".to_string(), Duration::from_secs(0), 1_000),
+        Err(StatsError::ZeroDuration)
+    );
+    stats.add_transmission("2".to_string(), Duration::from_secs(0), 1_000);
+    assert_eq!(stats.rejected_samples(), 3);
+    let error = stats
+        .try_add_transmission("2".to_string(), Duration::from_nanos(1), 1_000_000)
+        .unwrap_err();
+    assert_eq!(error, StatsError::Quarantined);
+    assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidInput);
+    let peer = stats.summarize_peer("2").unwrap();
+    assert_eq!(peer.ping.unwrap().samples, 1);
+    assert_eq!(peer.transmission_rate, None);
+    assert_eq!(peer.quarantine.unwrap().transmissions, 1);
+}
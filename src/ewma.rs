@@ -0,0 +1,103 @@
+use crate::{PeerState, Rate, Stats};
+use std::{fmt, time::Duration};
+
+/// Exponentially weighted moving averages of a peer, see `Stats::with_ewma`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ewma {
+    pub ping: Option<Duration>,
+    pub transmission_rate: Option<Rate>,
+}
+
+impl Ewma {
+    pub(crate) fn add_ping(&mut self, rtt: Duration, alpha: f64) {
+        self.ping = Some(match self.ping {
+            Some(average) => {
+                let secs =
+                    average.as_secs_f64() + alpha * (rtt.as_secs_f64() - average.as_secs_f64());
+                Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX)
+            }
+            None => rtt,
+        });
+    }
+
+    pub(crate) fn add_rate(&mut self, rate: Rate, alpha: f64) {
+        self.transmission_rate = Some(match self.transmission_rate {
+            Some(average) => average + (rate - average) * alpha,
+            None => rate,
+        });
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ping.is_none() && self.transmission_rate.is_none()
+    }
+}
+
+impl fmt::Display for Ewma {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        if let Some(ping) = self.ping {
+            write!(f, "ping {:?}", ping)?;
+            separator = " ";
+        }
+        if let Some(rate) = self.transmission_rate {
+            write!(f, "{}rate {}", separator, rate)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Also tracks exponentially weighted moving averages of the pings and transmission
+    /// rates of each peer, which react faster than the window means. Each sample moves
+    /// the average by `alpha` of its difference, e.g. `0.125` like TCP's smoothed RTT.
+    /// Factors out of (0, 1] are rejected by `StatsBuilder::build` and `Stats::validate`.
+    pub fn with_ewma(mut self, alpha: f64) -> Self {
+        self.ewma_alpha = Some(alpha);
+        self
+    }
+
+    /// Moving average of the pings of the peer, `None` without `with_ewma`.
+    pub fn ewma_ping(&self, peer_id: &str) -> Option<Duration> {
+        self.peers.get(peer_id)?.ewma.ping
+    }
+
+    /// Moving average of the transmission rates of the peer, `None` without `with_ewma`.
+    pub fn ewma_transmission_rate(&self, peer_id: &str) -> Option<Rate> {
+        self.peers.get(peer_id)?.ewma.transmission_rate
+    }
+
+    pub(crate) fn ewma_record_ping(&self, peer: &mut PeerState, rtt: Duration) {
+        if let Some(alpha) = self.ewma_alpha {
+            peer.ewma.add_ping(rtt, alpha);
+        }
+    }
+
+    pub(crate) fn ewma_record_rate(&self, peer: &mut PeerState, rate: Rate) {
+        if let Some(alpha) = self.ewma_alpha {
+            peer.ewma.add_rate(rate, alpha);
+        }
+    }
+}
+
+#[test]
+fn ewma_follows_latest_samples() {
+    let stats = Stats::new(100, "1".to_string()).with_ewma(0.5);
+    let millis = Duration::from_millis;
+    for rtt in [100, 100, 20, 20] {
+        stats.add_ping("2".to_string(), millis(rtt));
+    }
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 3_000);
+    assert_eq!(stats.ewma_ping("2"), Some(millis(40)));
+    assert_eq!(
+        stats.ewma_transmission_rate("2"),
+        Some(Rate::from_bytes_per_sec(2_000.0))
+    );
+    assert_eq!(Stats::new(100, "1".to_string()).ewma_ping("2"), None);
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.peers[0].ewma.unwrap().ping, Some(millis(40)));
+    assert!(snapshot
+        .to_string()
+        .contains("EWMA by peer:\n\"2\" ping 40ms rate 2.0 kB/s\n"));
+}
@@ -0,0 +1,74 @@
+use crate::{Sample, Stats, Window};
+use std::time::{Duration, SystemTime};
+
+impl Stats {
+    /// Keeps only the pings and transmission rates of the last `max_age` in the windows of
+    /// each peer, which still hold at most `window_size` samples. Expired samples are dropped
+    /// when the peer gets a new sample or is read.
+    pub fn with_max_sample_age(mut self, max_age: Duration) -> Self {
+        self.max_sample_age = Some(max_age);
+        self
+    }
+
+    /// Pushes a sample recorded at `time`, which is earlier than now for the samples
+    /// restored by `Stats::read_windows`.
+    pub(crate) fn push_sample<T: Sample>(
+        &self,
+        window: &mut Window<T>,
+        sample: T,
+        time: SystemTime,
+    ) {
+        let window_size = self.window_size_of(window);
+        window.push_timed(sample, time, window_size);
+        window.keep_latest(window_size);
+        if let Some(cutoff) = self.sample_cutoff() {
+            window.expire(cutoff);
+        }
+    }
+
+    /// Drops the expired pings and transmission rates of the peer before they are read.
+    pub(crate) fn expire_samples(&self, peer_id: &str) {
+        if let Some(cutoff) = self.sample_cutoff() {
+            if let Some(mut pings) = self.pings_to_peers.get_mut(peer_id) {
+                pings.expire(cutoff);
+            }
+            if let Some(mut rates) = self.transmissions_rates.get_mut(peer_id) {
+                rates.expire(cutoff);
+            }
+        }
+    }
+
+    pub(crate) fn sample_cutoff(&self) -> Option<SystemTime> {
+        let max_age = self.max_sample_age?;
+        Some(
+            self.clock
+                .now()
+                .checked_sub(max_age)
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        )
+    }
+}
+
+#[test]
+fn old_samples_expire() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock.clone())
+        .with_max_sample_age(Duration::from_secs(60));
+    let millis = Duration::from_millis;
+    stats.add_ping("2".to_string(), millis(100));
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    clock.advance(Duration::from_secs(45));
+    stats.add_ping("2".to_string(), millis(10));
+    clock.advance(Duration::from_secs(30));
+    stats.add_ping("2".to_string(), millis(30));
+    let peer = stats.snapshot().peers.remove(0);
+    assert_eq!(peer.ping.as_ref().unwrap().samples, 2);
+    assert_eq!(peer.ping.unwrap().mean, millis(20));
+    assert_eq!(peer.transmission_rate, None);
+    clock.advance(Duration::from_secs(120));
+    assert_eq!(stats.ping_percentile("2", 0.5), None);
+}
@@ -0,0 +1,231 @@
+//! Encoding, rounding and signing of snapshots.
+
+pub use crate::{Encoding, Noise, Record, RecordSink, Redaction, SignedDigest, Signer, Verifier};
+use crate::{Rate, Stats, StatsSnapshot, Summary};
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{self, prelude::*},
+    sync::PoisonError,
+    time::Duration,
+};
+
+/// Rounding of exported numbers, which shrinks the output and keeps diffs stable.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Precision {
+    /// Durations are rounded to the nearest multiple of it
+    pub durations: Option<Duration>,
+    /// Fractional values are rounded to this number of significant digits
+    pub significant_digits: Option<u32>,
+}
+
+impl Precision {
+    pub(crate) fn duration(&self, duration: Duration) -> Duration {
+        match self.durations.map(|unit| unit.as_nanos()) {
+            Some(unit) if unit > 0 => {
+                let nanos = (duration.as_nanos() + unit / 2) / unit * unit;
+                match u64::try_from(nanos / 1_000_000_000) {
+                    Ok(secs) => Duration::new(secs, (nanos % 1_000_000_000) as u32),
+                    Err(_) => duration,
+                }
+            }
+            _ => duration,
+        }
+    }
+
+    pub(crate) fn fraction(&self, value: f64) -> f64 {
+        match self.significant_digits {
+            Some(digits) if value != 0.0 && value.is_finite() => {
+                let scale = 10f64.powi(digits as i32 - 1 - value.abs().log10().floor() as i32);
+                (value * scale).round() / scale
+            }
+            _ => value,
+        }
+    }
+
+    fn summary(&self, summary: &mut Summary) {
+        summary.mean = self.duration(summary.mean);
+        summary.std_dev = self.duration(summary.std_dev);
+        summary.error = self.duration(summary.error);
+        self.score(summary);
+    }
+
+    fn rate_summary(&self, summary: &mut Summary<Rate>) {
+        let rate = |rate: Rate| Rate::from_bytes_per_sec(self.fraction(rate.bytes_per_sec()));
+        summary.mean = rate(summary.mean);
+        summary.std_dev = rate(summary.std_dev);
+        summary.error = rate(summary.error);
+        self.score(summary);
+    }
+
+    fn score<T>(&self, summary: &mut Summary<T>) {
+        if let Some(score) = summary.score.as_mut() {
+            score.z_score = self.fraction(score.z_score);
+            score.percentile_rank = self.fraction(score.percentile_rank);
+        }
+    }
+}
+
+impl StatsSnapshot {
+    pub fn rounded(mut self, precision: &Precision) -> Self {
+        for peer in self.peers.iter_mut() {
+            if let Some(requests) = peer.requests.as_mut() {
+                requests.success_rate = precision.fraction(requests.success_rate);
+            }
+            if let Some(keep_alive) = peer.keep_alive.as_mut() {
+                keep_alive.after_idle_rate = precision.fraction(keep_alive.after_idle_rate);
+            }
+            for summary in peer.summaries_mut() {
+                precision.summary(summary);
+            }
+            let view_rates = peer
+                .views
+                .iter_mut()
+                .filter_map(|view| view.transmission_rate.as_mut());
+            for summary in peer.transmission_rate.iter_mut().chain(view_rates) {
+                precision.rate_summary(summary);
+            }
+            for session in peer.sessions.iter_mut() {
+                session.duration = precision.duration(session.duration);
+                session.mean_rtt = session.mean_rtt.map(|rtt| precision.duration(rtt));
+            }
+            for ages in peer
+                .ping_ages
+                .iter_mut()
+                .chain(peer.transmission_rate_ages.iter_mut())
+            {
+                ages.oldest = precision.duration(ages.oldest);
+                ages.newest = precision.duration(ages.newest);
+                ages.median = precision.duration(ages.median);
+            }
+            if let Some(range) = peer.ping_min_max.as_mut() {
+                range.min = precision.duration(range.min);
+                range.max = precision.duration(range.max);
+            }
+            if let Some(range) = peer.transmission_rate_min_max.as_mut() {
+                let rate =
+                    |rate: Rate| Rate::from_bytes_per_sec(precision.fraction(rate.bytes_per_sec()));
+                range.min = rate(range.min);
+                range.max = rate(range.max);
+            }
+            if let Some(robust) = peer.ping_robust.as_mut() {
+                robust.median = precision.duration(robust.median);
+                robust.mad = precision.duration(robust.mad);
+            }
+            if let Some(ewma) = peer.ewma.as_mut() {
+                ewma.ping = ewma.ping.map(|ping| precision.duration(ping));
+                ewma.transmission_rate = ewma
+                    .transmission_rate
+                    .map(|rate| Rate::from_bytes_per_sec(precision.fraction(rate.bytes_per_sec())));
+            }
+            for value in peer.derived.values_mut() {
+                *value = precision.fraction(*value);
+            }
+            for gauge in peer.gauges.values_mut() {
+                gauge.mean = precision.fraction(gauge.mean);
+                gauge.std_dev = precision.fraction(gauge.std_dev);
+                gauge.error = precision.fraction(gauge.error);
+            }
+        }
+        self
+    }
+}
+
+/// Writes snapshots of `Stats` with its own settings, so that every destination
+/// can get the output it needs from the same `Stats`.
+#[derive(Debug, Clone)]
+pub struct Exporter {
+    encoding: Encoding,
+    precision: Precision,
+    redaction: Redaction,
+    noise: Option<Noise>,
+    max_size: Option<usize>,
+}
+
+impl Exporter {
+    pub fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            precision: Precision::default(),
+            redaction: Redaction::default(),
+            noise: None,
+            max_size: None,
+        }
+    }
+
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Adds `noise` to the summaries with `StatsSnapshot::noised`, after the redaction,
+    /// drawn from the rng of the stats, see `Stats::with_rng`.
+    pub fn noise(mut self, noise: Noise) -> Self {
+        self.noise = Some(noise);
+        self
+    }
+
+    /// Caps the output to `bytes` with `StatsSnapshot::truncated`.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    pub fn snapshot(&self, stats: &Stats) -> StatsSnapshot {
+        let mut snapshot = stats.snapshot().redacted(&self.redaction);
+        if let Some(noise) = &self.noise {
+            let mut rng = stats.rng.lock().unwrap_or_else(PoisonError::into_inner);
+            snapshot = snapshot.noised(noise, &mut **rng);
+        }
+        let snapshot = snapshot.rounded(&self.precision);
+        match self.max_size {
+            Some(max_size) => snapshot.truncated(max_size, self.encoding),
+            None => snapshot,
+        }
+    }
+
+    pub fn export<W: Write>(&self, stats: &Stats, writer: W) -> io::Result<()> {
+        self.snapshot(stats).encode(writer, self.encoding)
+    }
+
+    pub fn save(&self, stats: &Stats, filename: &str) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(File::create(filename)?);
+        self.export(stats, &mut writer)?;
+        writer.flush()
+    }
+}
+
+#[test]
+fn precision_rounds_numbers() {
+    let precision = Precision {
+        durations: Some(Duration::from_micros(1)),
+        significant_digits: Some(3),
+    };
+    assert_eq!(
+        precision.duration(Duration::from_nanos(1_234_567)),
+        Duration::from_micros(1235)
+    );
+    assert_eq!(precision.fraction(0.123_456), 0.123);
+    assert_eq!(precision.fraction(-12_345.0), -12_300.0);
+    assert_eq!(Precision::default().fraction(0.123_456), 0.123_456);
+}
+
+#[test]
+fn exporter_applies_precision_to_text() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_nanos(10_123_456));
+    let mut bytes = Vec::new();
+    Exporter::new(Encoding::Text)
+        .precision(Precision {
+            durations: Some(Duration::from_millis(1)),
+            significant_digits: None,
+        })
+        .export(&stats, &mut bytes)
+        .unwrap();
+    assert!(String::from_utf8(bytes).unwrap().contains("\"2\" 10ms±0ns"));
+}
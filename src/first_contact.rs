@@ -0,0 +1,212 @@
+use crate::{Rate, Rfc3339, Stats, Summary};
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+/// Thresholds of the early measurements of new peers, see `Stats::first_contact_report`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triage {
+    /// Samples of a metric, or requests, before it counts for the recommendation
+    pub min_samples: usize,
+    pub max_ping: Duration,
+    pub min_transmission_rate: Rate,
+    pub min_success_rate: f64,
+}
+
+impl Default for Triage {
+    /// Keeps peers with 3 pings of at most 500ms, any transmission rate and half of
+    /// the requests succeeding.
+    fn default() -> Self {
+        Self {
+            min_samples: 3,
+            max_ping: Duration::from_millis(500),
+            min_transmission_rate: Rate::from_bytes_per_sec(0.0),
+            min_success_rate: 0.5,
+        }
+    }
+}
+
+/// What to do with a new peer according to the `Triage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Recommendation {
+    /// Enough pings and every measured metric is within the thresholds
+    Keep,
+    /// A metric with enough samples is out of the thresholds
+    Drop,
+    /// Too few pings to decide either way
+    Undecided,
+}
+
+/// Early measurements of a peer first seen recently.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FirstContact {
+    pub peer_id: String,
+    /// Time of the first sample, capability or annotation of the peer
+    pub first_seen: SystemTime,
+    pub ping: Option<Summary>,
+    pub transmission_rate: Option<Summary<Rate>>,
+    pub success_rate: Option<f64>,
+    pub recommendation: Recommendation,
+}
+
+/// Peers first seen within a period, newest first.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FirstContactReport {
+    pub time: SystemTime,
+    pub peers: Vec<FirstContact>,
+}
+
+impl FirstContactReport {
+    pub fn get(&self, peer_id: &str) -> Option<&FirstContact> {
+        self.peers.iter().find(|peer| peer.peer_id == peer_id)
+    }
+
+    /// Ids of the peers with `recommendation`, newest first.
+    pub fn recommended(&self, recommendation: Recommendation) -> impl Iterator<Item = &str> {
+        self.peers
+            .iter()
+            .filter(move |peer| peer.recommendation == recommendation)
+            .map(|peer| peer.peer_id.as_str())
+    }
+}
+
+impl Triage {
+    fn recommend(
+        &self,
+        ping: Option<&Summary>,
+        rate: Option<&Summary<Rate>>,
+        requests: Option<(u64, f64)>,
+    ) -> Recommendation {
+        let min_samples = self.min_samples;
+        let slow =
+            ping.is_some_and(|ping| ping.samples >= min_samples && ping.mean > self.max_ping);
+        let narrow = rate.is_some_and(|rate| {
+            rate.samples >= min_samples
+                && rate.mean.bytes_per_sec() < self.min_transmission_rate.bytes_per_sec()
+        });
+        let failing = requests.is_some_and(|(requests, success_rate)| {
+            requests >= min_samples as u64 && success_rate < self.min_success_rate
+        });
+        if slow || narrow || failing {
+            Recommendation::Drop
+        } else if ping.is_some_and(|ping| ping.samples >= min_samples) {
+            Recommendation::Keep
+        } else {
+            Recommendation::Undecided
+        }
+    }
+}
+
+impl fmt::Display for FirstContactReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "First contacts at {}:", Rfc3339(self.time))?;
+        for peer in &self.peers {
+            write!(
+                f,
+                "{:?} {:?} since {}",
+                peer.peer_id,
+                peer.recommendation,
+                Rfc3339(peer.first_seen)
+            )?;
+            if let Some(ping) = &peer.ping {
+                write!(
+                    f,
+                    " ping {:?}±{:?} of {}",
+                    ping.mean, ping.error, ping.samples
+                )?;
+            }
+            if let Some(rate) = &peer.transmission_rate {
+                write!(f, " rate {}±{} of {}", rate.mean, rate.error, rate.samples)?;
+            }
+            if let Some(success_rate) = peer.success_rate {
+                write!(f, " success {:.1}%", success_rate * 100.0)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Time the peer was first seen, until it is removed.
+    pub fn first_seen(&self, peer_id: &str) -> Option<SystemTime> {
+        self.peers.get(peer_id).map(|peer| peer.first_seen)
+    }
+
+    /// Reports the peers first seen within `period` by the clock, with a recommendation
+    /// of the `triage` whether to keep them, e.g. to go through new peers after a bootstrap.
+    pub fn first_contact_report(&self, period: Duration, triage: &Triage) -> FirstContactReport {
+        trace_span!("first_contact_report");
+        let time = self.clock.now();
+        let snapshot = self.snapshot();
+        let mut peers: Vec<_> = snapshot
+            .peers
+            .into_iter()
+            .filter_map(|peer| {
+                let first_seen = self.first_seen(&peer.peer_id)?;
+                if time.duration_since(first_seen).unwrap_or_default() > period {
+                    return None;
+                }
+                let requests = peer
+                    .requests
+                    .as_ref()
+                    .map(|requests| (requests.succeeded + requests.failed, requests.success_rate));
+                Some(FirstContact {
+                    recommendation: triage.recommend(
+                        peer.ping.as_ref(),
+                        peer.transmission_rate.as_ref(),
+                        requests,
+                    ),
+                    success_rate: requests.map(|(_, success_rate)| success_rate),
+                    ping: peer.ping,
+                    transmission_rate: peer.transmission_rate,
+                    first_seen,
+                    peer_id: peer.peer_id,
+                })
+            })
+            .collect();
+        peers.sort_by_key(|peer| std::cmp::Reverse(peer.first_seen));
+        FirstContactReport { time, peers }
+    }
+}
+
+#[test]
+fn new_peers_are_triaged() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    let millis = Duration::from_millis;
+    stats.add_ping("old".to_string(), millis(10));
+    clock.advance(Duration::from_secs(600));
+    for _ in 0..3 {
+        stats.add_ping("fast".to_string(), millis(20));
+        stats.add_ping("slow".to_string(), millis(900));
+    }
+    clock.advance(Duration::from_secs(1));
+    stats.add_ping("unknown".to_string(), millis(20));
+    let report = stats.first_contact_report(Duration::from_secs(300), &Triage::default());
+    assert_eq!(report.peers.len(), 3);
+    assert_eq!(report.peers[0].peer_id, "unknown");
+    assert_eq!(report.get("old"), None);
+    assert_eq!(
+        report.get("fast").unwrap().recommendation,
+        Recommendation::Keep
+    );
+    assert_eq!(
+        report.recommended(Recommendation::Drop).collect::<Vec<_>>(),
+        vec!["slow"]
+    );
+    assert_eq!(
+        report.get("unknown").unwrap().recommendation,
+        Recommendation::Undecided
+    );
+    assert!(report
+        .to_string()
+        .contains("\"fast\" Keep since 1970-01-01T00:10:00Z ping 20ms±0ns of 3"));
+}
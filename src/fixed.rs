@@ -0,0 +1,146 @@
+//! Integer implementation of the duration statistics, selected by the `fixed-point` feature
+//! so that results do not depend on float rounding of the platform.
+//!
+//! Only the mean, standard deviation and 95% error of duration windows are computed here.
+//! Everything derived from them or from other samples stays `f64`: the scaling of errors
+//! to other confidence levels and by decay, moving averages, streaming summaries, gauges
+//! and all transmission rate summaries.
+
+use std::{convert::TryFrom, time::Duration};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+fn duration(nanos: u128) -> Duration {
+    match u64::try_from(nanos / NANOS_PER_SEC) {
+        Ok(secs) => Duration::new(secs, (nanos % NANOS_PER_SEC) as u32),
+        Err(_) => Duration::MAX,
+    }
+}
+
+/// Largest integer whose square is at most `n`.
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's method from an overestimate decreases monotonically to the root
+    let mut x = 1u128 << ((128 - n.leading_zeros()).div_ceil(2));
+    loop {
+        let next = (x + n / x) / 2;
+        if next >= x {
+            return x;
+        }
+        x = next;
+    }
+}
+
+fn mean_nanos(durations: &[Duration]) -> Option<u128> {
+    if durations.is_empty() {
+        return None;
+    }
+    let sum = durations
+        .iter()
+        .fold(0u128, |acc, x| acc.saturating_add(x.as_nanos()));
+    Some(sum / durations.len() as u128)
+}
+
+/// Variance in square nanoseconds.
+fn variance(durations: &[Duration]) -> Option<u128> {
+    let mean = mean_nanos(durations)?;
+    let squares = durations.iter().fold(0u128, |acc, x| {
+        let deviation = if x.as_nanos() > mean {
+            x.as_nanos() - mean
+        } else {
+            mean - x.as_nanos()
+        };
+        acc.saturating_add(deviation.saturating_mul(deviation))
+    });
+    Some(squares / durations.len() as u128)
+}
+
+pub(crate) fn durations_mean(durations: &[Duration]) -> Option<Duration> {
+    mean_nanos(durations).map(duration)
+}
+
+pub(crate) fn durations_std_dev(durations: &[Duration]) -> Option<Duration> {
+    variance(durations).map(|variance| duration(isqrt(variance)))
+}
+
+/// Same z-value of `1.96` as the float implementation, squared and scaled by `10^4`.
+pub(crate) fn durations_error_with_ci(durations: &[Duration]) -> Option<Duration> {
+    let variance = variance(durations)?;
+    let scaled = variance.saturating_mul(38_416) / (10_000 * durations.len() as u128);
+    Some(duration(isqrt(scaled)))
+}
+
+#[test]
+fn correct_isqrt() {
+    for n in (0..10_000u128).chain([u128::MAX, u128::MAX - 1, 1 << 100]) {
+        let root = isqrt(n);
+        assert!(root * root <= n);
+        assert!((root + 1)
+            .checked_mul(root + 1)
+            .is_none_or(|square| square > n));
+    }
+}
+
+#[test]
+fn fixed_point_matches_float() {
+    let windows: Vec<Vec<Duration>> = vec![
+        vec![
+            Duration::from_secs(1),
+            Duration::from_secs(3),
+            Duration::from_secs(5),
+        ],
+        (1..100)
+            .map(|i| Duration::from_micros(i * i * 37 % 10_007))
+            .collect(),
+        vec![Duration::from_millis(10); 30],
+        vec![Duration::from_nanos(1), Duration::from_secs(3600)],
+    ];
+    let close = |a: Duration, b: Duration| {
+        let (a, b) = (a.as_secs_f64(), b.as_secs_f64());
+        (a - b).abs() <= 1e-9 + 1e-9 * b.abs()
+    };
+    for window in &windows {
+        assert!(close(
+            durations_mean(window).unwrap(),
+            crate::float_durations_mean(window).unwrap()
+        ));
+        assert!(close(
+            durations_std_dev(window).unwrap(),
+            crate::float_durations_std_dev(window).unwrap()
+        ));
+        assert!(close(
+            durations_error_with_ci(window).unwrap(),
+            crate::float_durations_error_with_ci(window).unwrap()
+        ));
+    }
+    assert_eq!(durations_std_dev(&[]), None);
+}
+
+#[test]
+fn only_duration_windows_are_fixed_point() {
+    use crate::{confidence::scaled_error, ByteSize, Stats, Summary};
+
+    let pings = [10, 20, 40].map(Duration::from_millis);
+    let stats = Stats::new(100, "1".to_string()).with_confidence(0.99);
+    for ping in pings {
+        stats.add_ping("2".to_string(), ping);
+    }
+    for millis in [3, 7] {
+        stats.add_transmission("2".to_string(), Duration::from_millis(millis), 1_000u64);
+    }
+    let peer = stats.summarize_peer("2").unwrap();
+    let ping = peer.ping.unwrap();
+    if cfg!(feature = "fixed-point") {
+        assert_eq!(ping.mean, durations_mean(&pings).unwrap());
+        assert_eq!(ping.std_dev, durations_std_dev(&pings).unwrap());
+    }
+    let error = crate::durations_error_with_ci(&pings).unwrap();
+    assert_eq!(ping.error, scaled_error(error, stats.confidence_scale()));
+    let rates = [3, 7].map(|millis| ByteSize(1_000) / Duration::from_millis(millis));
+    assert_eq!(
+        peer.transmission_rate.unwrap().mean,
+        Summary::from_rates(&rates).unwrap().mean
+    );
+}
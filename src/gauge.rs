@@ -0,0 +1,44 @@
+use crate::{values_percentile_rank, Stats, Window};
+
+impl Stats {
+    /// Records a sample of a named numeric metric of the peer, e.g. queue depth or bytes in flight.
+    /// Gauges are windowed and summarized the same way as durations.
+    pub fn record_gauge(&self, peer_id: String, name: &str, value: f64) {
+        trace_span!("record_gauge");
+        let _recording = self.recording(&peer_id);
+        let window_size = self.window_size;
+        let now = self.clock.now();
+        self.update_peer(&peer_id, |peer| match peer.gauges.get_mut(name) {
+            Some(window) => window.push_timed(value, now, window_size),
+            None => {
+                let mut window = Window::new();
+                window.push_timed(value, now, window_size);
+                peer.gauges.insert(name.to_string(), window);
+            }
+        });
+    }
+
+    /// Where `value` would fall in the recent distribution of the gauge, like `percentile_rank`.
+    pub fn gauge_percentile_rank(&self, peer_id: &str, name: &str, value: f64) -> Option<f64> {
+        values_percentile_rank(self.peers.get(peer_id)?.gauges.get(name)?, value)
+    }
+}
+
+#[test]
+fn gauges_are_summarized_by_name() {
+    let stats = Stats::new(2, "1".to_string());
+    stats.record_gauge("2".to_string(), "queue_depth", 1.0);
+    stats.record_gauge("2".to_string(), "queue_depth", 3.0);
+    stats.record_gauge("2".to_string(), "queue_depth", 5.0);
+    stats.record_gauge("2".to_string(), "score", -1.0);
+    let gauges = stats.snapshot().peers.remove(0).gauges;
+    assert_eq!(gauges.len(), 2);
+    assert_eq!(gauges["queue_depth"].samples, 2);
+    assert_eq!(gauges["queue_depth"].mean, 4.0);
+    assert_eq!(gauges["score"].mean, -1.0);
+    assert_eq!(
+        stats.gauge_percentile_rank("2", "queue_depth", 4.0),
+        Some(0.5)
+    );
+    assert_eq!(stats.gauge_percentile_rank("2", "missing", 4.0), None);
+}
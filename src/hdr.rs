@@ -0,0 +1,126 @@
+use crate::{PeerState, Rate, Stats};
+use std::{convert::TryFrom, time::Duration};
+
+/// Sample counts in the log-linear buckets of a `hdrhistogram::Histogram` of a fixed relative
+/// precision, so that memory stays bounded however many samples are recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrHistogram(hdrhistogram::Histogram<u64>);
+
+impl HdrHistogram {
+    /// Buckets keeping `significant_digits` decimal digits of the values, from `1` to `5`.
+    pub fn new(significant_digits: u8) -> Self {
+        let histogram = hdrhistogram::Histogram::new(significant_digits.clamp(1, 5))
+            .expect("1 to 5 significant digits are valid");
+        Self(histogram)
+    }
+
+    /// Records the value, values beyond the range of the histogram count as its highest.
+    pub fn record(&mut self, value: u64) {
+        self.record_n(value, 1);
+    }
+
+    fn record_n(&mut self, value: u64, count: u64) {
+        // The histogram grows up to its range, only values beyond it saturate
+        if self.0.record_n(value, count).is_err() {
+            self.0.saturating_record_n(value, count);
+        }
+    }
+
+    /// Adds the samples of `other`, which may have another precision.
+    pub fn merge(&mut self, other: &HdrHistogram) {
+        for value in other.0.iter_recorded() {
+            let highest = other.0.highest_equivalent(value.value_iterated_to());
+            self.record_n(highest, value.count_at_value());
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.len()
+    }
+
+    /// Value at fraction `quantile` of the samples, as the highest value of its bucket.
+    pub fn value_at_quantile(&self, quantile: f64) -> Option<u64> {
+        if self.0.is_empty() {
+            return None;
+        }
+        Some(self.0.value_at_quantile(quantile.clamp(0.0, 1.0)))
+    }
+}
+
+impl Stats {
+    /// Also counts every ping and transmission rate of each peer in a `HdrHistogram` with
+    /// `significant_digits`, which the percentile queries use instead of the windows.
+    /// Summaries still use the windows, so a small `window_size` keeps the memory low.
+    pub fn with_hdr_histograms(mut self, significant_digits: u8) -> Self {
+        self.hdr_significant_digits = Some(significant_digits);
+        self
+    }
+
+    pub(crate) fn hdr_record_ping(&self, peer: &mut PeerState, rtt: Duration) {
+        if let Some(digits) = self.hdr_significant_digits {
+            peer.hdr_pings
+                .get_or_insert_with(|| HdrHistogram::new(digits))
+                .record(u64::try_from(rtt.as_nanos()).unwrap_or(u64::MAX));
+        }
+    }
+
+    pub(crate) fn hdr_record_rate(&self, peer: &mut PeerState, rate: Rate) {
+        if let Some(digits) = self.hdr_significant_digits {
+            peer.hdr_rates
+                .get_or_insert_with(|| HdrHistogram::new(digits))
+                .record(rate.bytes_per_sec().max(0.0).round() as u64);
+        }
+    }
+
+    /// Histograms of all peers merged, `select` picks the histogram of a peer.
+    pub(crate) fn hdr_merged(
+        &self,
+        significant_digits: u8,
+        select: impl Fn(&PeerState) -> Option<&HdrHistogram>,
+    ) -> HdrHistogram {
+        let mut merged = HdrHistogram::new(significant_digits);
+        for peer_id in self.peer_ids() {
+            if let Some(peer) = self.peers.get(&peer_id) {
+                if let Some(histogram) = select(&peer) {
+                    merged.merge(histogram);
+                }
+            }
+        }
+        merged
+    }
+}
+
+#[test]
+fn hdr_histogram_keeps_precision() {
+    let mut histogram = HdrHistogram::new(3);
+    for value in 1..=1_000_000 {
+        histogram.record(value * 1_000);
+    }
+    assert_eq!(histogram.total(), 1_000_000);
+    assert!(histogram.0.distinct_values() < 30_000);
+    for quantile in [0.01, 0.5, 0.9, 0.99] {
+        let exact = quantile * 1e9;
+        let value = histogram.value_at_quantile(quantile).unwrap() as f64;
+        assert!(
+            (value - exact).abs() / exact < 1e-3,
+            "{} {}",
+            quantile,
+            value
+        );
+    }
+    assert_eq!(HdrHistogram::new(2).value_at_quantile(0.5), None);
+    let mut merged = HdrHistogram::new(2);
+    merged.merge(&histogram);
+    merged.record(u64::MAX);
+    assert_eq!(merged.total(), 1_000_001);
+
+    let stats = Stats::new(2, "1".to_string()).with_hdr_histograms(3);
+    for millis in 1..=100 {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+    }
+    let p99 = stats.ping_percentile("2", 0.99).unwrap();
+    assert!(p99 >= Duration::from_millis(99) && p99 < Duration::from_micros(99_100));
+    stats.add_transmission("3".to_string(), Duration::from_secs(1), 5_000);
+    let rate = stats.aggregate_transmission_rate_percentile(0.5).unwrap();
+    assert!((rate.bytes_per_sec() - 5_000.0).abs() < 5.0);
+}
@@ -0,0 +1,85 @@
+use crate::Stats;
+use std::{collections::BTreeMap, convert::TryFrom, fmt, time::Duration};
+
+/// Sample counts in power of two buckets, where only the non-empty buckets are kept,
+/// so that the distribution can be exported without the samples.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Histogram {
+    /// Count by the inclusive upper bound of the bucket in microseconds,
+    /// each bucket starts above half of its bound
+    pub buckets: BTreeMap<u64, u64>,
+}
+
+impl Histogram {
+    pub(crate) fn from_durations(durations: &[Duration]) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        let mut histogram = Self::default();
+        for duration in durations {
+            *histogram
+                .buckets
+                .entry(Self::bucket_of(*duration))
+                .or_default() += 1;
+        }
+        Some(histogram)
+    }
+
+    /// Upper bound in microseconds of the bucket of `duration`.
+    pub fn bucket_of(duration: Duration) -> u64 {
+        let micros = duration.as_nanos().div_ceil(1_000).max(1);
+        u64::try_from(micros)
+            .ok()
+            .and_then(u64::checked_next_power_of_two)
+            .unwrap_or(u64::MAX)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.buckets.values().sum()
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for (bound, count) in &self.buckets {
+            write!(
+                f,
+                "{}≤{:?} {}",
+                separator,
+                Duration::from_micros(*bound),
+                count
+            )?;
+            separator = ", ";
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Adds `PeerSummary::ping_histogram` to the summaries.
+    pub fn with_ping_histograms(mut self) -> Self {
+        self.ping_histograms = true;
+        self
+    }
+}
+
+#[test]
+fn pings_are_counted_in_sparse_buckets() {
+    let stats = Stats::new(100, "1".to_string()).with_ping_histograms();
+    for micros in &[900, 1000, 1500, 3000, 3000] {
+        stats.add_ping("2".to_string(), Duration::from_micros(*micros));
+    }
+    let histogram = stats.snapshot().peers.remove(0).ping_histogram.unwrap();
+    let buckets: Vec<_> = histogram.buckets.into_iter().collect();
+    assert_eq!(buckets, vec![(1024, 2), (2048, 1), (4096, 2)]);
+    assert_eq!(Histogram::bucket_of(Duration::from_secs(0)), 1);
+    assert_eq!(Histogram::bucket_of(Duration::MAX), u64::MAX);
+    assert_eq!(
+        Histogram::from_durations(&[Duration::from_micros(3)])
+            .unwrap()
+            .to_string(),
+        "≤4µs 1"
+    );
+}
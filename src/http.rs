@@ -0,0 +1,205 @@
+//! Tiny HTTP server of the Prometheus exposition and the text report.
+
+use crate::Stats;
+use std::{
+    io::{self, prelude::*},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Longest request head read before answering `431`
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+/// Time for a connection to send its request and receive the response, however slowly
+/// it trickles the bytes
+const REQUEST_DEADLINE: Duration = Duration::from_secs(5);
+/// Connections served at once, further ones are closed right away
+const MAX_CONNECTIONS: usize = 16;
+
+/// Server thread of `Stats::serve_http`, which is stopped when the server is dropped.
+pub struct HttpServer {
+    local_addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    /// Address the server listens on, e.g. to find the port when binding to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops accepting connections and waits for the thread to finish.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        // Wakes up the blocking accept
+        let _ = TcpStream::connect_timeout(&self.local_addr, Duration::from_secs(1));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl Stats {
+    /// Serves `GET /metrics` with `render_prometheus` and `GET /report` with the text report,
+    /// e.g. for operators to `curl` the node. Each connection is served on its own thread,
+    /// up to `MAX_CONNECTIONS` at once, and is closed after `REQUEST_DEADLINE`.
+    pub fn serve_http(self: Arc<Self>, addr: impl ToSocketAddrs) -> io::Result<HttpServer> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let running = running.clone();
+            let connections = Arc::new(AtomicUsize::new(0));
+            thread::Builder::new()
+                .name("p2p-stats-http".to_string())
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if !running.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        let stream = match stream {
+                            Ok(stream) => stream,
+                            Err(_) => continue,
+                        };
+                        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                            connections.fetch_sub(1, Ordering::SeqCst);
+                            continue;
+                        }
+                        let stats = self.clone();
+                        let served = connections.clone();
+                        let spawned = thread::Builder::new()
+                            .name("p2p-stats-http-connection".to_string())
+                            .spawn(move || {
+                                let _ = stats.respond(stream);
+                                served.fetch_sub(1, Ordering::SeqCst);
+                            });
+                        if spawned.is_err() {
+                            connections.fetch_sub(1, Ordering::SeqCst);
+                        }
+                    }
+                })?
+        };
+        Ok(HttpServer {
+            local_addr,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        let deadline = Instant::now() + REQUEST_DEADLINE;
+        let request = read_request_line(&mut stream, deadline)?;
+        stream.set_write_timeout(Some(remaining(deadline)?))?;
+        let (status, content_type, body) = match &request {
+            None => ("431 Request Header Fields Too Large", TEXT, String::new()),
+            Some((method, path)) => match (method.as_str(), path.split('?').next()) {
+                ("GET" | "HEAD", Some("/metrics")) => {
+                    ("200 OK", PROMETHEUS, self.render_prometheus())
+                }
+                ("GET" | "HEAD", Some("/report")) => ("200 OK", TEXT, self.to_string()),
+                ("GET" | "HEAD", _) => ("404 Not Found", TEXT, "Not found\n".to_string()),
+                _ => (
+                    "405 Method Not Allowed",
+                    TEXT,
+                    "Method not allowed\n".to_string(),
+                ),
+            },
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            content_type,
+            body.len()
+        )?;
+        let head_only = matches!(&request, Some((method, _)) if method == "HEAD");
+        if !head_only {
+            stream.write_all(body.as_bytes())?;
+        }
+        stream.flush()?;
+        stream.shutdown(Shutdown::Write)
+    }
+}
+
+const TEXT: &str = "text/plain; charset=utf-8";
+const PROMETHEUS: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Method and target of the request, `None` if the head is longer than `MAX_REQUEST_HEAD`.
+/// Fails with `TimedOut` if the head has not arrived by the `deadline`.
+fn read_request_line(
+    stream: &mut TcpStream,
+    deadline: Instant,
+) -> io::Result<Option<(String, String)>> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD {
+            return Ok(None);
+        }
+        stream.set_read_timeout(Some(remaining(deadline)?))?;
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut parts = head.lines().next().unwrap_or_default().split(' ');
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+    Ok(Some((method, path)))
+}
+
+/// Nonzero time left until the `deadline`, or `TimedOut` once it passed.
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(remaining) if !remaining.is_zero() => Ok(remaining),
+        _ => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "request deadline passed",
+        )),
+    }
+}
+
+#[test]
+fn report_and_metrics_are_served() {
+    let stats = Arc::new(Stats::new(100, "1".to_string()));
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    let server = stats.clone().serve_http("127.0.0.1:0").unwrap();
+    let get = |request: &str| {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    // A client which never finishes its request does not hold up the others
+    let mut slow = TcpStream::connect(server.local_addr()).unwrap();
+    slow.write_all(b"GET /metrics HTTP/1.1\r\n").unwrap();
+    let metrics = get("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(metrics.contains("p2p_stats_pings_total{peer_id=\"2\"} 1\n"));
+    let report = get("GET /report HTTP/1.0\r\n\r\n");
+    assert!(report.contains("\r\n\r\n\"1\" at "));
+    let head = get("HEAD /report HTTP/1.1\r\n\r\n");
+    assert!(head.ends_with("Connection: close\r\n\r\n"));
+    assert!(get("GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404 "));
+    assert!(get("POST /metrics HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405 "));
+    drop(slow);
+    server.shutdown();
+}
@@ -0,0 +1,92 @@
+use crate::{durations_mean, durations_std_dev, Stats};
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+/// Number of incidents kept for each peer, the oldest are dropped first.
+const INCIDENTS: usize = 32;
+/// Samples needed in the window before outliers are detected.
+const MIN_SAMPLES: usize = 10;
+
+/// Extreme outlier ping kept for postmortems after it left the window.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Incident {
+    pub time: SystemTime,
+    pub rtt: Duration,
+    /// Mean of the window before the sample
+    pub window_mean: Duration,
+    pub window_std_dev: Duration,
+    /// Distance of the sample from the window mean in standard deviations
+    pub z_score: f64,
+}
+
+impl Stats {
+    /// Standard deviations from the window mean from which a ping is an incident, `6.0` by default.
+    pub fn with_incident_threshold(mut self, z_score: f64) -> Self {
+        self.incident_threshold = z_score;
+        self
+    }
+
+    /// Outlier pings of the peer, oldest first.
+    pub fn incidents(&self, peer_id: &str) -> Vec<Incident> {
+        match self.peers.get(peer_id) {
+            Some(peer) => peer.incidents.iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Incident of `rtt` if it is an extreme outlier of the `window` it is added to.
+    pub(crate) fn ping_incident(
+        &self,
+        window: &[Duration],
+        rtt: Duration,
+        now: SystemTime,
+    ) -> Option<Incident> {
+        if window.len() < MIN_SAMPLES.max(self.warm_up_samples) {
+            return None;
+        }
+        let window_mean = durations_mean(window)?;
+        let window_std_dev = durations_std_dev(window)?;
+        if window_std_dev.is_zero() {
+            return None;
+        }
+        let z_score =
+            (rtt.as_secs_f64() - window_mean.as_secs_f64()) / window_std_dev.as_secs_f64();
+        if z_score.abs() < self.incident_threshold {
+            return None;
+        }
+        Some(Incident {
+            time: now,
+            rtt,
+            window_mean,
+            window_std_dev,
+            z_score,
+        })
+    }
+}
+
+pub(crate) fn push_incident(incidents: &mut VecDeque<Incident>, incident: Incident) {
+    if incidents.len() >= INCIDENTS {
+        incidents.pop_front();
+    }
+    incidents.push_back(incident);
+}
+
+#[test]
+fn outliers_are_kept_after_leaving_window() {
+    let stats = Stats::new(20, "1".to_string());
+    for millis in (0..20).map(|i| 10 + i % 3) {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+    }
+    stats.add_ping("2".to_string(), Duration::from_secs(5));
+    for _ in 0..30 {
+        stats.add_ping("2".to_string(), Duration::from_millis(11));
+    }
+    let incidents = stats.incidents("2");
+    assert_eq!(incidents.len(), 1);
+    assert_eq!(incidents[0].rtt, Duration::from_secs(5));
+    assert!(incidents[0].z_score > 6.0);
+    assert!(stats.incidents("3").is_empty());
+}
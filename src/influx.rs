@@ -0,0 +1,173 @@
+//! InfluxDB line protocol of the ping and transmission rate summaries of all peers.
+
+use crate::{
+    snapshot::{PeerExtras, QUANTILES},
+    Encoding, Stats, StatsSnapshot, Summary,
+};
+use std::{
+    fmt::Write as _,
+    io::{self, prelude::*},
+    time::UNIX_EPOCH,
+};
+
+/// Names of the fields of `QUANTILES`
+const QUANTILE_FIELDS: [&str; 3] = ["p50", "p90", "p99"];
+
+impl StatsSnapshot {
+    /// Writes a `p2p_ping` and a `p2p_transmission_rate` line for each peer with a summary,
+    /// without percentiles since snapshots do not keep the samples.
+    pub(crate) fn encode_influx<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(lines(self, None).as_bytes())
+    }
+}
+
+impl Stats {
+    /// Renders the snapshot as InfluxDB line protocol, one line per peer and metric:
+    ///
+    /// `p2p_ping,node=<id>,peer=<id> samples=100i,mean=0.05,…,p99=0.09 <unix nanos>`
+    ///
+    /// Pings are in seconds and transmission rates in bytes per second, the fields are
+    /// `samples`, `mean`, `std_dev`, `error`, `p50`, `p90` and `p99`. The percentiles of a
+    /// peer are read at the same instant as its summary.
+    pub fn render_influx(&self) -> String {
+        let (snapshot, extras) = self.snapshot_with_extras();
+        lines(&snapshot, Some(&extras))
+    }
+
+    /// Writes `render_influx` to `writer`, e.g. a `TcpStream` to a Telegraf socket listener.
+    pub fn write_influx<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.render_influx().as_bytes())?;
+        writer.flush()
+    }
+
+    /// Saves the lines without percentiles, same as `save_snapshot` with `Encoding::Influx`.
+    pub fn save_as_influx(&self, filename: &str) -> io::Result<()> {
+        self.save_snapshot(filename, Encoding::Influx)
+    }
+}
+
+/// Lines of the snapshot, with the percentiles of `extras` of each peer if any.
+fn lines(snapshot: &StatsSnapshot, extras: Option<&[PeerExtras]>) -> String {
+    let mut output = String::new();
+    let timestamp = snapshot
+        .time
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| format!(" {}", since_epoch.as_nanos()))
+        .unwrap_or_default();
+    let node = escaped(&snapshot.peer_id);
+    for (index, peer) in snapshot.peers.iter().enumerate() {
+        let extras = extras.and_then(|extras| extras.get(index));
+        let tags = format!("node={},peer={}", node, escaped(&peer.peer_id));
+        if let Some(ping) = &peer.ping {
+            let mut fields = summary_fields(ping, |ping| ping.as_secs_f64());
+            if let Some(extras) = extras {
+                let percentiles = extras
+                    .ping_percentiles
+                    .map(|ping| Some(ping?.as_secs_f64()));
+                quantile_fields(&mut fields, percentiles);
+            }
+            let _ = writeln!(output, "p2p_ping,{} {}{}", tags, fields, timestamp);
+        }
+        if let Some(rate) = &peer.transmission_rate {
+            let mut fields = summary_fields(rate, crate::Rate::bytes_per_sec);
+            if let Some(extras) = extras {
+                let percentiles = extras
+                    .rate_percentiles
+                    .map(|rate| Some(rate?.bytes_per_sec()));
+                quantile_fields(&mut fields, percentiles);
+            }
+            let _ = writeln!(
+                output,
+                "p2p_transmission_rate,{} {}{}",
+                tags, fields, timestamp
+            );
+        }
+    }
+    output
+}
+
+fn summary_fields<T: Copy>(summary: &Summary<T>, value: impl Fn(T) -> f64) -> String {
+    let mut fields = format!("samples={}i", summary.samples);
+    let values = [
+        ("mean", value(summary.mean)),
+        ("std_dev", value(summary.std_dev)),
+        ("error", value(summary.error)),
+    ];
+    for (name, value) in values.iter() {
+        float_field(&mut fields, name, *value);
+    }
+    fields
+}
+
+fn quantile_fields(fields: &mut String, percentiles: [Option<f64>; QUANTILES.len()]) {
+    for (name, percentile) in QUANTILE_FIELDS.iter().zip(percentiles) {
+        if let Some(value) = percentile {
+            float_field(fields, name, value);
+        }
+    }
+}
+
+/// Line protocol has no representation of infinite or NaN floats, such fields are left out.
+fn float_field(fields: &mut String, name: &str, value: f64) {
+    if value.is_finite() {
+        let _ = write!(fields, ",{}={}", name, value);
+    }
+}
+
+/// Tag value with commas, equals signs, spaces and backslashes escaped. Line breaks, which
+/// cannot be escaped, become escaped spaces.
+fn escaped(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            ',' | '=' | ' ' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push_str("\\ "),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[test]
+fn influx_lines_are_tagged_by_peer() {
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string());
+    for millis in 1..=100 {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+    }
+    stats.add_transmission("a b,c".to_string(), Duration::from_secs(1), 1_000);
+    let text = stats.render_influx();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("p2p_ping,node=1,peer=2 samples=100i,mean=0.0505,std_dev="));
+    assert!(lines[0].contains(",p50=0.05,p90=0.09,p99=0.099 "));
+    assert!(lines[1].starts_with(
+        "p2p_transmission_rate,node=1,peer=a\\ b\\,c samples=1i,mean=1000,std_dev=0,error=0,p50=1000"
+    ));
+    let timestamp: u128 = lines[0].rsplit(' ').next().unwrap().parse().unwrap();
+    assert!(timestamp > 1_000_000_000_000_000_000);
+
+    let mut bytes = Vec::new();
+    stats
+        .snapshot()
+        .encode(&mut bytes, Encoding::Influx)
+        .unwrap();
+    let encoded = String::from_utf8(bytes).unwrap();
+    assert!(!encoded.contains("p99="));
+    assert!(encoded.contains(" samples=100i,mean=0.0505,"));
+}
+
+#[test]
+fn line_breaks_in_tags_stay_on_one_line() {
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("a\nb\rc".to_string(), Duration::from_millis(10));
+    let text = stats.render_influx();
+    assert_eq!(text.lines().count(), 1);
+    assert!(text.starts_with("p2p_ping,node=1,peer=a\\ b\\ c samples=1i,"));
+}
@@ -0,0 +1,38 @@
+//! JSON encoding of snapshots through their `serde::Serialize` implementations.
+
+use crate::{Stats, StatsSnapshot};
+use std::io;
+
+impl StatsSnapshot {
+    /// Snapshot as a JSON object with a record for each peer in `peers`.
+    /// Durations and times are objects of seconds and nanoseconds like in serde,
+    /// numbers which are not finite become `null`.
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string(self).map_err(io::Error::from)
+    }
+}
+
+impl Stats {
+    /// Saves the snapshot as JSON, same as `save_snapshot` with `Encoding::Json`.
+    pub fn save_as_json(&self, filename: &str) -> io::Result<()> {
+        self.save_snapshot(filename, crate::Encoding::Json)
+    }
+}
+
+#[test]
+fn snapshot_is_written_as_json() {
+    use std::{collections::BTreeMap, time::Duration};
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.annotate("line\n\"quoted\"", std::time::SystemTime::UNIX_EPOCH);
+    let json = stats.snapshot().to_json().unwrap();
+    assert!(json.starts_with(r#"{"peer_id":"1","time":{"secs_since_epoch":"#));
+    assert!(json.contains(r#""peers":[{"peer_id":"2","last_seen":"#));
+    assert!(json.contains(r#""mean":{"secs":0,"nanos":10000000}"#));
+    assert!(json.contains(r#"line\n\"quoted\""#));
+
+    let mut map = BTreeMap::new();
+    map.insert(1, (f64::NAN, Some('x')));
+    assert_eq!(serde_json::to_string(&map).unwrap(), r#"{"1":[null,"x"]}"#);
+}
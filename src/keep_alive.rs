@@ -0,0 +1,84 @@
+use crate::{compact::ShrinkToFit, PushLossy, Stats, Summary, Window};
+use std::time::Duration;
+
+/// Idle times of a peer before its disconnects.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IdleDisconnects {
+    idle: Window<Duration>,
+    disconnects: u64,
+    after_idle: u64,
+}
+
+impl IdleDisconnects {
+    pub(crate) fn add(&mut self, idle: Duration, threshold: Duration, window_size: usize) {
+        self.idle.push_lossy(idle, window_size);
+        self.disconnects += 1;
+        if idle >= threshold {
+            self.after_idle += 1;
+        }
+    }
+
+    pub(crate) fn summary(&self) -> Option<KeepAlive> {
+        if self.disconnects == 0 {
+            return None;
+        }
+        Some(KeepAlive {
+            idle_before_disconnect: Summary::from_durations(&self.idle),
+            disconnects: self.disconnects,
+            after_idle: self.after_idle,
+            after_idle_rate: self.after_idle as f64 / self.disconnects as f64,
+        })
+    }
+
+    pub(crate) fn shrink(&mut self) -> usize {
+        self.idle.shrink()
+    }
+}
+
+/// How connections to a peer end relative to their last activity,
+/// a high `after_idle_rate` suggests that keep-alives are sent too rarely.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeepAlive {
+    /// Time from the latest sample of any metric to the disconnect
+    pub idle_before_disconnect: Option<Summary>,
+    /// Disconnects of a previously seen peer
+    pub disconnects: u64,
+    /// Disconnects after being idle for at least the keep-alive threshold
+    pub after_idle: u64,
+    /// Fraction of disconnects after being idle
+    pub after_idle_rate: f64,
+}
+
+impl Stats {
+    /// Idle time from which disconnects count as `KeepAlive::after_idle`, 30 seconds by default.
+    pub fn with_keep_alive_threshold(mut self, threshold: Duration) -> Self {
+        self.keep_alive_threshold = threshold;
+        self
+    }
+}
+
+#[test]
+fn disconnects_after_idleness_are_counted() {
+    use crate::{DisconnectReason, ManualClock};
+    use std::{sync::Arc, time::SystemTime};
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock.clone())
+        .with_keep_alive_threshold(Duration::from_secs(10));
+    stats.record_disconnected("2".to_string(), DisconnectReason::Error);
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    clock.advance(Duration::from_secs(30));
+    stats.record_disconnected("2".to_string(), DisconnectReason::RemoteClosed);
+    clock.advance(Duration::from_secs(2));
+    stats.record_disconnected("2".to_string(), DisconnectReason::RemoteClosed);
+    let keep_alive = stats.snapshot().peers.remove(0).keep_alive.unwrap();
+    assert_eq!(keep_alive.disconnects, 2);
+    assert_eq!(keep_alive.after_idle, 1);
+    assert_eq!(keep_alive.after_idle_rate, 0.5);
+    assert_eq!(
+        keep_alive.idle_before_disconnect.unwrap().mean,
+        Duration::from_secs(16)
+    );
+}
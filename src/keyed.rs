@@ -0,0 +1,156 @@
+use crate::{ByteSize, Stats};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::Hash,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+/// Recorder of samples keyed by the peer type of the caller, e.g. `libp2p::PeerId` or
+/// `SocketAddr`. Each key is formatted into its peer id once, so recording a sample of a
+/// known peer neither formats nor allocates. Queries and exports use the formatted ids.
+///
+/// At most `capacity` keys are cached. A full cache first drops the keys of peers which
+/// the stats no longer know, e.g. after `compact`, and is cleared if that frees nothing,
+/// so that a stream of one-off keys costs formatting but not memory.
+pub struct KeyedRecorder<K> {
+    stats: Arc<Stats>,
+    peer_ids: RwLock<HashMap<K, String>>,
+    capacity: usize,
+}
+
+/// Keys cached by `KeyedRecorder::new`
+pub const DEFAULT_KEY_CAPACITY: usize = 10_000;
+
+impl<K: Hash + Eq + Clone + Display> KeyedRecorder<K> {
+    pub fn new(stats: Arc<Stats>) -> Self {
+        Self::with_capacity(stats, DEFAULT_KEY_CAPACITY)
+    }
+
+    /// Recorder which caches the peer ids of at most `capacity` keys.
+    pub fn with_capacity(stats: Arc<Stats>, capacity: usize) -> Self {
+        Self {
+            stats,
+            peer_ids: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    pub fn stats(&self) -> &Arc<Stats> {
+        &self.stats
+    }
+
+    /// Peer id of the key in the stats.
+    pub fn peer_id(&self, key: &K) -> String {
+        self.with_peer_id(key, str::to_string)
+    }
+
+    /// Drops the cached peer id of the key, e.g. after disconnecting from it for good.
+    pub fn forget(&self, key: &K) {
+        self.peer_ids
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key);
+    }
+
+    pub fn add_ping(&self, key: &K, rtt: Duration) {
+        self.with_peer_id(key, |peer_id| {
+            let _ = self.stats.record_ping(peer_id, rtt);
+        })
+    }
+
+    pub fn record_ping_result<E>(&self, key: &K, result: Result<Duration, E>) {
+        self.with_peer_id(key, |peer_id| match result {
+            Ok(rtt) => {
+                let _ = self.stats.record_ping(peer_id, rtt);
+            }
+            Err(_) => self.stats.record_ping_failure(peer_id),
+        })
+    }
+
+    pub fn add_transmission(&self, key: &K, time: Duration, n_bytes: impl Into<ByteSize>) {
+        let n_bytes = n_bytes.into();
+        self.with_peer_id(key, |peer_id| {
+            let _ = self.stats.record_transmission(peer_id, time, n_bytes);
+        })
+    }
+
+    fn with_peer_id<R>(&self, key: &K, f: impl FnOnce(&str) -> R) -> R {
+        {
+            let peer_ids = self.peer_ids.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(peer_id) = peer_ids.get(key) {
+                return f(peer_id);
+            }
+        }
+        let peer_id = key.to_string();
+        let result = f(&peer_id);
+        if self.capacity == 0 {
+            return result;
+        }
+        let mut peer_ids = self
+            .peer_ids
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if peer_ids.len() >= self.capacity && !peer_ids.contains_key(key) {
+            peer_ids.retain(|_, peer_id| self.stats.peers.contains_key(peer_id.as_str()));
+            if peer_ids.len() >= self.capacity {
+                peer_ids.clear();
+            }
+        }
+        peer_ids.insert(key.clone(), peer_id);
+        result
+    }
+}
+
+impl<K: Clone> Clone for KeyedRecorder<K> {
+    fn clone(&self) -> Self {
+        Self {
+            stats: self.stats.clone(),
+            peer_ids: RwLock::new(
+                self.peer_ids
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone(),
+            ),
+            capacity: self.capacity,
+        }
+    }
+}
+
+#[test]
+fn keys_are_formatted_once() {
+    use std::net::SocketAddr;
+
+    let stats = Arc::new(Stats::new(100, "1".to_string()));
+    let recorder = KeyedRecorder::new(stats.clone());
+    let addr: SocketAddr = "127.0.0.1:4001".parse().unwrap();
+    recorder.add_ping(&addr, Duration::from_millis(10));
+    recorder.add_ping(&addr, Duration::from_millis(20));
+    recorder.record_ping_result(&addr, Err(()));
+    recorder.add_transmission(&addr, Duration::from_secs(1), 1_000u64);
+    assert_eq!(recorder.peer_ids.read().unwrap().len(), 1);
+    assert_eq!(recorder.peer_id(&addr), "127.0.0.1:4001");
+    let peer = stats.summarize_peer("127.0.0.1:4001").unwrap();
+    assert_eq!(peer.ping.unwrap().samples, 2);
+    assert_eq!(peer.transmission_rate.unwrap().samples, 1);
+    assert_eq!(stats.failed_pings("127.0.0.1:4001"), 1);
+    recorder.forget(&addr);
+    assert!(recorder.peer_ids.read().unwrap().is_empty());
+
+    let clock = Arc::new(crate::ManualClock::new(std::time::UNIX_EPOCH));
+    let stats = Arc::new(Stats::new(100, "1".to_string()).with_clock(clock.clone()));
+    let bounded = KeyedRecorder::with_capacity(stats.clone(), 2);
+    let local = |port| SocketAddr::from(([127, 0, 0, 1], port));
+    for port in 1..=5 {
+        bounded.add_ping(&local(port), Duration::from_millis(1));
+        assert!(bounded.peer_ids.read().unwrap().len() <= 2);
+    }
+    clock.advance(Duration::from_secs(2));
+    bounded.add_ping(&local(4), Duration::from_millis(1));
+    stats.compact(Duration::from_secs(1));
+    bounded.add_ping(&local(6), Duration::from_millis(1));
+    let cached = bounded.peer_ids.read().unwrap();
+    assert_eq!(cached.len(), 2);
+    assert!(cached.contains_key(&local(4)) && cached.contains_key(&local(6)));
+}
@@ -1,57 +1,671 @@
-use chashmap::CHashMap;
+use map::ConcurrentMap;
 use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, VecDeque},
     fmt,
-    fs::File,
-    io::{self, prelude::*},
-    time::Duration,
+    hash::{Hash, Hasher},
+    io,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::{Duration, SystemTime},
 };
 
+/// Enters a `tracing` span until the end of the scope,
+/// compiled out unless the `tracing` feature is enabled.
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!($($args)*).entered();
+    };
+}
+
+mod adaptive;
+mod ages;
+mod annotate;
+#[cfg(feature = "arrow")]
+mod arrow;
+mod bandwidth;
+mod bench;
+#[cfg(feature = "cbor")]
+mod binary;
+mod blocklist;
+mod budget;
+mod builder;
+mod capability;
+mod checkpoint;
+mod clock;
+mod cohort;
+pub mod collect;
+mod compact;
+mod confidence;
+mod connection;
+mod consistency;
+mod correlate;
+mod csv;
+mod decay;
+mod derived;
+mod encoding;
+mod epoch;
+mod error;
+mod ewma;
+mod expiry;
+pub mod export;
+mod first_contact;
+mod fixed;
+mod gauge;
+#[cfg(feature = "hdr")]
+mod hdr;
+mod histogram;
+#[cfg(feature = "http")]
+mod http;
+mod incident;
+mod influx;
+#[cfg(feature = "json")]
+mod json;
+mod keep_alive;
+mod keyed;
+mod lite;
+mod loss;
+mod map;
+mod min_max;
+mod noise;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+mod percentile;
+mod pressure;
+mod prior;
+mod probe;
+mod profile;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod quarantine;
+mod query;
+mod range;
+mod records;
+mod redact;
+mod request;
+mod rfc3339;
+mod rng;
+mod robust;
+mod sampling;
+mod schedule;
+mod selection;
+mod self_benchmark;
+mod signing;
+#[cfg(any(test, feature = "sim"))]
+pub mod sim;
+mod sink;
+mod sla;
+mod snapshot;
+mod split;
+mod stage;
+mod streaming;
+#[cfg(any(test, feature = "stress"))]
+pub mod stress;
+mod subnet;
+mod units;
+mod upgrade;
+mod views;
+mod watchdog;
+mod widget;
+mod window;
+#[cfg(feature = "protobuf")]
+pub mod wire;
+
+pub use ages::SampleAges;
+pub use annotate::Annotation;
+use bandwidth::PendingBytes;
+#[cfg(feature = "libp2p")]
+pub use bandwidth::{CountingMuxer, CountingSubstream};
+use bench::TransportSamples;
+pub use bench::{BenchmarkReport, TransportBenchmark};
+pub use blocklist::{BlockEntry, Blocklist};
+pub use builder::StatsBuilder;
+pub use capability::{CapabilityComparison, CapabilityReport};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use cohort::{cohort, CohortReport, CohortSummary};
+pub use collect::Collector;
+pub use compact::Compaction;
+use connection::OpenSession;
+pub use connection::{Connection, DisconnectCounts, DisconnectReason, Session};
+pub use consistency::Discrepancy;
+pub use correlate::{Correlation, CorrelationReport, Series};
+pub use decay::Decay;
+use derived::Derive;
+pub use encoding::Encoding;
+use epoch::Epochs;
+pub use error::{ConfigError, ConfigProblem, StatsError};
+pub use ewma::Ewma;
+pub use export::{Exporter, Precision};
+pub use first_contact::{FirstContact, FirstContactReport, Recommendation, Triage};
+#[cfg(feature = "hdr")]
+pub use hdr::HdrHistogram;
+pub use histogram::Histogram;
+#[cfg(feature = "http")]
+pub use http::HttpServer;
+use incident::push_incident;
+pub use incident::Incident;
+use keep_alive::IdleDisconnects;
+pub use keep_alive::KeepAlive;
+pub use keyed::{KeyedRecorder, DEFAULT_KEY_CAPACITY};
+pub use min_max::MinMax;
+pub use noise::Noise;
+pub use pressure::{MemoryPressure, MemoryProbe, Shedding};
+pub use prior::Prior;
+pub use probe::{PingBySize, ProbeSize};
+pub use profile::{Column, Profile, RateUnit, ReportFormat, ReportOptions};
+pub use quarantine::{Bounds, OutlierPolicy, Quarantine};
+pub use query::{Page, PeerOrder};
+pub use records::{Record, RecordSink};
+pub use redact::Redaction;
+use request::Requests;
+pub use request::{ErrorCategory, ErrorCounts, RequestSummary};
+pub use rfc3339::Rfc3339;
+pub use rng::{RngSource, SeededRng};
+pub use robust::Robust;
+pub use sampling::PeerSampling;
+pub use selection::{SelectionInput, SelectionSnapshot};
+pub use self_benchmark::{SelfBenchmark, SelfBenchmarkReport};
+pub use signing::{SignedDigest, Signer, Verifier};
+pub use sink::{MockSink, NoopSink, SinkCall, StatsSink};
+pub use sla::{Projection, Sla, SlaWarning};
+use snapshot::{CachedSnapshot, PeerCapture};
+pub use snapshot::{PeerSummary, Score, SnapshotIter, StatsSnapshot, Summary};
+pub use split::{Querier, Recorder};
+pub use stage::{Stage, StageLatencies};
+use streaming::Welford;
+pub use subnet::{Subnet, SubnetReport, SubnetSummary};
+pub use units::{ByteSize, Rate, Rtt};
+pub use upgrade::{UpgradeLatencies, UpgradeStage};
+pub use views::WindowView;
+pub use watchdog::Starvation;
+pub use widget::{MetricCell, PeerLine, TimeUnit};
+use window::{Sample, Window};
+
+/// Recording samples into `Stats` and the values describing them.
+pub mod stats {
+    pub use crate::{
+        Bounds, ByteSize, Clock, ConfigError, ConfigProblem, Connection, Decay, DisconnectReason,
+        ErrorCategory, ManualClock, Metric, OutlierPolicy, PeerSampling, Prior, ProbeSize, Querier,
+        Rate, Recorder, Rtt, Sla, Stage, Stats, StatsBuilder, StatsError, StatsSink, SystemClock,
+        UpgradeStage,
+    };
+}
+
+/// Computed stats read from `Stats`.
+pub mod report {
+    #[cfg(feature = "hdr")]
+    pub use crate::HdrHistogram;
+    pub use crate::{
+        Annotation, BenchmarkReport, CapabilityComparison, CapabilityReport, CohortReport,
+        CohortSummary, Column, DisconnectCounts, Discrepancy, ErrorCounts, Ewma, Histogram,
+        KeepAlive, MetricCell, MinMax, Page, PeerLine, PeerOrder, PeerSummary, PingBySize, Profile,
+        Projection, Quarantine, RateUnit, ReportFormat, ReportOptions, RequestSummary, Rfc3339,
+        Robust, SampleAges, Score, Session, SlaWarning, SnapshotIter, StageLatencies,
+        StatsSnapshot, Summary, TimeUnit, TransportBenchmark, UpgradeLatencies, WindowView,
+    };
+}
+
+/// Types needed by most users, `use p2p_node_stats::prelude::*;`.
+pub mod prelude {
+    pub use crate::{
+        ByteSize, Collector, Encoding, Exporter, Metric, PeerSummary, Rate, Stats, StatsSnapshot,
+        Summary,
+    };
+}
+
+/// Metric recorded for each peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Metric {
+    Ping,
+    /// Elapsed time per byte in the `Duration` based queries, see `Rate::time_per_byte`
+    /// and the `Rate` based `transmission_rate_rank` and `estimate_transmission_rate`
+    TransmissionRate,
+}
+
+/// State of a peer besides its ping and transmission windows.
+#[derive(Debug, Clone)]
+struct PeerState {
+    first_seen: SystemTime,
+    last_seen: SystemTime,
+    requests: Requests,
+    /// Ping windows indexed by `ProbeSize`
+    pings_by_size: [Window<Duration>; 3],
+    gauges: BTreeMap<String, Window<f64>>,
+    /// Latency windows indexed by `Stage`
+    stages: [Window<Duration>; 5],
+    /// Connection upgrade windows indexed by `UpgradeStage`
+    upgrades: [Window<Duration>; 3],
+    /// Pings over fresh connections
+    cold_pings: Window<Duration>,
+    session: Option<OpenSession>,
+    /// Closed sessions, oldest first
+    sessions: VecDeque<Session>,
+    disconnects: DisconnectCounts,
+    idle_disconnects: IdleDisconnects,
+    /// Operator notes, oldest first
+    annotations: VecDeque<Annotation>,
+    /// Outlier pings, oldest first
+    incidents: VecDeque<Incident>,
+    /// Negotiated protocol capabilities
+    capabilities: BTreeSet<String>,
+    address: Option<IpAddr>,
+    quarantine: Quarantine,
+    /// Outliers of `OutlierPolicy` since the latest recorded ping and transmission
+    ping_outliers_in_row: usize,
+    rate_outliers_in_row: usize,
+    ewma: Ewma,
+    /// Pings and transmissions recorded since the start
+    total_pings: u64,
+    total_transmissions: u64,
+    /// Pings which got no response since the start
+    failed_pings: u64,
+    /// Bytes of `Stats::count_bytes` not flushed yet
+    pending_bytes: Option<PendingBytes>,
+    /// Ping and transmission rate summaries with `Stats::with_streaming`
+    streaming_pings: Welford,
+    streaming_rates: Welford,
+    #[cfg(feature = "hdr")]
+    hdr_pings: Option<HdrHistogram>,
+    #[cfg(feature = "hdr")]
+    hdr_rates: Option<HdrHistogram>,
+}
+
+impl PeerState {
+    fn new(last_seen: SystemTime) -> Self {
+        Self {
+            first_seen: last_seen,
+            last_seen,
+            requests: Requests::default(),
+            pings_by_size: Default::default(),
+            gauges: BTreeMap::new(),
+            stages: Default::default(),
+            upgrades: Default::default(),
+            cold_pings: Window::new(),
+            session: None,
+            sessions: VecDeque::new(),
+            disconnects: DisconnectCounts::default(),
+            idle_disconnects: IdleDisconnects::default(),
+            annotations: VecDeque::new(),
+            incidents: VecDeque::new(),
+            capabilities: BTreeSet::new(),
+            address: None,
+            quarantine: Quarantine::default(),
+            ping_outliers_in_row: 0,
+            rate_outliers_in_row: 0,
+            ewma: Ewma::default(),
+            total_pings: 0,
+            failed_pings: 0,
+            pending_bytes: None,
+            total_transmissions: 0,
+            streaming_pings: Welford::default(),
+            streaming_rates: Welford::default(),
+            #[cfg(feature = "hdr")]
+            hdr_pings: None,
+            #[cfg(feature = "hdr")]
+            hdr_rates: None,
+        }
+    }
+}
+
+/// Stripes of the recording guard of `Stats`
+const RECORDING_STRIPES: usize = 64;
+
 pub struct Stats {
-    pings_to_peers: CHashMap<String, Vec<Duration>>,
-    transmissions_rates: CHashMap<String, Vec<Duration>>,
+    pings_to_peers: ConcurrentMap<String, Window<Duration>>,
+    transmissions_rates: ConcurrentMap<String, Window<Rate>>,
+    peers: ConcurrentMap<String, PeerState>,
     window_size: usize,
     peer_id: String,
+    priors: HashMap<Metric, Prior>,
+    decay: Option<Decay>,
+    clock: Arc<dyn Clock>,
+    session_history: usize,
+    disconnects: Mutex<DisconnectCounts>,
+    keep_alive_threshold: Duration,
+    /// Samples by transport configuration label
+    transports: ConcurrentMap<String, TransportSamples>,
+    /// Events of the node, oldest first
+    annotations: Mutex<VecDeque<Annotation>>,
+    incident_threshold: f64,
+    /// Time of creation or of the clock replacement
+    started: SystemTime,
+    /// Nanoseconds since the epoch of the latest ping, `0` before the first one
+    last_ping: AtomicU64,
+    /// Nanoseconds since the epoch of the latest sample of any kind
+    last_ingest: AtomicU64,
+    no_pings_for: Option<Duration>,
+    /// Silence limit of watched peers and when they started to be watched
+    watched_peers: ConcurrentMap<String, (Duration, SystemTime)>,
+    snapshot_max_age: Option<Duration>,
+    cached_snapshot: Mutex<Option<CachedSnapshot>>,
+    probe_interval_bounds: (Duration, Duration),
+    /// Implausible samples which were not recorded
+    rejected: AtomicU64,
+    bounds: Option<Bounds>,
+    outlier_policy: Option<OutlierPolicy>,
+    warm_up_samples: usize,
+    ping_histograms: bool,
+    #[cfg(feature = "hdr")]
+    hdr_significant_digits: Option<u8>,
+    rng: Mutex<Box<dyn RngSource>>,
+    /// Metrics computed from peer summaries, in the order of registration
+    derived: Mutex<Vec<(String, Derive)>>,
+    /// Lengths of the extra summaries of the latest samples, shortest first
+    window_views: Vec<usize>,
+    max_sample_age: Option<Duration>,
+    ewma_alpha: Option<f64>,
+    streaming: bool,
+    lite: bool,
+    epochs: Option<Epochs>,
+    /// Time span and maximum of the ping and transmission rate windows
+    adaptive_windows: Option<(Duration, usize)>,
+    correlation_bucket: Duration,
+    confidence: f64,
+    /// `MemoryPressure` as set last
+    memory_pressure: AtomicU8,
+    memory_probe: Option<MemoryProbe>,
+    robust_summaries: bool,
+    /// Held shared while a sample is written to the windows and the state of its peer,
+    /// and exclusively while a peer is captured for its summary, picked by the hash of
+    /// the peer id so that capturing a peer only waits for the peers of its stripe
+    recording: Box<[RwLock<()>]>,
+    #[cfg(feature = "opentelemetry")]
+    instruments: Option<otel::Instruments>,
 }
 
 impl Stats {
     pub fn new(window_size: usize, peer_id: String) -> Self {
         Self {
-            pings_to_peers: CHashMap::new(),
-            transmissions_rates: CHashMap::new(),
+            pings_to_peers: ConcurrentMap::new(),
+            transmissions_rates: ConcurrentMap::new(),
+            peers: ConcurrentMap::new(),
             window_size,
             peer_id,
+            priors: HashMap::new(),
+            decay: None,
+            clock: Arc::new(SystemClock),
+            session_history: 16,
+            disconnects: Mutex::new(DisconnectCounts::default()),
+            keep_alive_threshold: Duration::from_secs(30),
+            transports: ConcurrentMap::new(),
+            annotations: Mutex::new(VecDeque::new()),
+            incident_threshold: 6.0,
+            started: SystemTime::now(),
+            last_ping: AtomicU64::new(0),
+            last_ingest: AtomicU64::new(0),
+            no_pings_for: None,
+            watched_peers: ConcurrentMap::new(),
+            snapshot_max_age: None,
+            cached_snapshot: Mutex::new(None),
+            probe_interval_bounds: (Duration::from_secs(1), Duration::from_secs(300)),
+            rejected: AtomicU64::new(0),
+            bounds: None,
+            outlier_policy: None,
+            warm_up_samples: 0,
+            ping_histograms: false,
+            #[cfg(feature = "hdr")]
+            hdr_significant_digits: None,
+            rng: Mutex::new(Box::new(SeededRng::new(watchdog::to_nanos(
+                SystemTime::now(),
+            )))),
+            derived: Mutex::new(Vec::new()),
+            window_views: Vec::new(),
+            max_sample_age: None,
+            ewma_alpha: None,
+            streaming: false,
+            lite: false,
+            epochs: None,
+            adaptive_windows: None,
+            correlation_bucket: Duration::from_secs(10),
+            confidence: confidence::DEFAULT_CONFIDENCE,
+            memory_pressure: AtomicU8::new(MemoryPressure::Normal as u8),
+            memory_probe: None,
+            robust_summaries: false,
+            recording: (0..RECORDING_STRIPES).map(|_| RwLock::new(())).collect(),
+            #[cfg(feature = "opentelemetry")]
+            instruments: None,
         }
     }
 
+    /// Replaces the system clock used to timestamp samples.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.started = clock.now();
+        self.clock = clock;
+        self
+    }
+
+    /// Saves the text report, kept for existing users.
+    #[deprecated(note = "use `save_snapshot` with `Encoding::Text` or an `Exporter`")]
     pub fn save_to_file(&self, filename: &str) -> io::Result<()> {
-        let mut file = File::create(filename)?;
-        file.write_all(self.to_string().as_bytes())?;
-        Ok(())
+        self.save_snapshot(filename, Encoding::Text)
+    }
+
+    /// Records the ping like `add_ping` if it `Rtt::is_plausible`, otherwise it is only
+    /// counted in `rejected_samples`.
+    pub fn add_rtt(&self, peer_id: String, rtt: Rtt) {
+        if rtt.is_plausible() {
+            self.add_ping(peer_id, rtt.duration())
+        } else {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Number of samples rejected as implausible.
+    pub fn rejected_samples(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
     }
 
     pub fn add_ping(&self, peer_id: String, rtt: Duration) {
-        if !self.pings_to_peers.contains_key(&peer_id) {
-            self.pings_to_peers.insert_new(peer_id.clone(), Vec::new())
+        let _ = self.record_ping(&peer_id, rtt);
+    }
+
+    /// Records a ping without owning the peer id, which is only copied for a new peer.
+    pub(crate) fn record_ping(&self, peer_id: &str, rtt: Duration) -> Result<(), StatsError> {
+        trace_span!("add_ping");
+        let _recording = self.recording(peer_id);
+        if self.quarantine_ping(peer_id, rtt) {
+            return Err(StatsError::Quarantined);
+        }
+        #[cfg(feature = "opentelemetry")]
+        self.otel_record_ping(peer_id, rtt);
+        self.write_ping(peer_id, rtt, self.clock.now());
+        Ok(())
+    }
+
+    /// Records a ping which passed the quarantine, taken at `now`, while the recording
+    /// guard of the peer is held.
+    fn write_ping(&self, peer_id: &str, rtt: Duration, now: SystemTime) {
+        let incident = if self.streaming || !self.keeps_samples() {
+            None
+        } else {
+            self.push_ping(peer_id, rtt, now)
+        };
+        self.last_ping
+            .store(watchdog::to_nanos(now), Ordering::Relaxed);
+        self.update_peer_at(peer_id, now, |peer| {
+            if let Some(session) = peer.session.as_mut() {
+                session.add_ping(rtt);
+            }
+            if let Some(incident) = incident {
+                push_incident(&mut peer.incidents, incident);
+            }
+            peer.total_pings += 1;
+            peer.ping_outliers_in_row = 0;
+            self.ewma_record_ping(peer, rtt);
+            if self.keeps_samples() {
+                self.streaming_record_ping(peer, rtt);
+                #[cfg(feature = "hdr")]
+                self.hdr_record_ping(peer, rtt);
+            }
+        });
+    }
+
+    /// Records a transfer of `n_bytes` which took `time`. Transfers which took no time
+    /// have no rate and are only counted in `rejected_samples`.
+    pub fn add_transmission(&self, peer_id: String, time: Duration, n_bytes: impl Into<ByteSize>) {
+        let _ = self.record_transmission(&peer_id, time, n_bytes.into());
+    }
+
+    /// Records a transmission without owning the peer id, like `record_ping`.
+    pub(crate) fn record_transmission(
+        &self,
+        peer_id: &str,
+        time: Duration,
+        n_bytes: ByteSize,
+    ) -> Result<(), StatsError> {
+        trace_span!("add_transmission");
+        let _recording = self.recording(peer_id);
+        self.write_transmission(peer_id, time, n_bytes)
+    }
+
+    /// Records a transmission while the recording guard of the peer is held.
+    fn write_transmission(
+        &self,
+        peer_id: &str,
+        time: Duration,
+        n_bytes: ByteSize,
+    ) -> Result<(), StatsError> {
+        if time.is_zero() {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(StatsError::ZeroDuration);
+        }
+        let rate = n_bytes / time;
+        if self.quarantine_rate(peer_id, rate) {
+            return Err(StatsError::Quarantined);
+        }
+        #[cfg(feature = "opentelemetry")]
+        self.otel_record_rate(peer_id, rate);
+        self.write_rate(peer_id, rate, Some(n_bytes), self.clock.now());
+        Ok(())
+    }
+
+    /// Records a transmission rate which passed the quarantine, measured at `now`, while
+    /// the recording guard of the peer is held. The bytes count towards the open session.
+    fn write_rate(&self, peer_id: &str, rate: Rate, n_bytes: Option<ByteSize>, now: SystemTime) {
+        self.update_peer_at(peer_id, now, |peer| {
+            if let (Some(session), Some(n_bytes)) = (peer.session.as_mut(), n_bytes) {
+                session.add_bytes(n_bytes);
+            }
+            peer.total_transmissions += 1;
+            peer.rate_outliers_in_row = 0;
+            self.ewma_record_rate(peer, rate);
+            if self.keeps_samples() {
+                self.streaming_record_rate(peer, rate);
+                #[cfg(feature = "hdr")]
+                self.hdr_record_rate(peer, rate);
+            }
+        });
+        if self.streaming || !self.keeps_samples() {
+            return;
+        }
+        update_window(&self.transmissions_rates, peer_id, |window| {
+            trace_span!("window_push");
+            self.push_sample(window, rate, now)
+        });
+    }
+
+    /// Pushes the ping to the window of the peer, returning it as an incident if it is one.
+    fn push_ping(&self, peer_id: &str, rtt: Duration, now: SystemTime) -> Option<Incident> {
+        let mut incident = None;
+        update_window(&self.pings_to_peers, peer_id, |window| {
+            trace_span!("window_push");
+            incident = self.ping_incident(window, rtt, now);
+            self.push_sample(window, rtt, now);
+        });
+        incident
+    }
+
+    /// Number of samples of `metric` recorded for the peer since the start,
+    /// including those no longer in its window.
+    pub fn total_samples(&self, peer_id: &str, metric: Metric) -> u64 {
+        self.peers.get(peer_id).map_or(0, |peer| match metric {
+            Metric::Ping => peer.total_pings,
+            Metric::TransmissionRate => peer.total_transmissions,
+        })
+    }
+
+    /// Where `value` would fall in the recent distribution of `metric` for the peer,
+    /// from `0.0` (below all samples) to `1.0` (above all samples).
+    pub fn percentile_rank(&self, peer_id: &str, metric: Metric, value: Duration) -> Option<f64> {
+        durations_percentile_rank(&self.window(metric, peer_id)?, value)
+    }
+
+    /// Where `rate` would fall in the recent transmission rates of the peer, from `0.0`
+    /// (slower than all samples) to `1.0` (faster than all samples).
+    pub fn transmission_rate_rank(&self, peer_id: &str, rate: Rate) -> Option<f64> {
+        self.expire_samples(peer_id);
+        let rates = self.transmissions_rates.get(peer_id)?;
+        let values: Vec<f64> = rates.iter().map(|rate| rate.bytes_per_sec()).collect();
+        values_percentile_rank(&values, rate.bytes_per_sec())
+    }
+
+    /// Keeps summaries from capturing the peer until the guard is dropped, so that they
+    /// see either none or all of the changes a sample makes to the windows and the state
+    /// of the peer. The guard must not be taken again while it is held.
+    fn recording(&self, peer_id: &str) -> RwLockReadGuard<'_, ()> {
+        self.recording_stripe(peer_id)
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits for the samples of the peer being written and keeps new ones from being
+    /// written until the guard is dropped.
+    fn capturing(&self, peer_id: &str) -> RwLockWriteGuard<'_, ()> {
+        self.recording_stripe(peer_id)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn recording_stripe(&self, peer_id: &str) -> &RwLock<()> {
+        let mut hasher = DefaultHasher::new();
+        peer_id.hash(&mut hasher);
+        &self.recording[hasher.finish() as usize % self.recording.len()]
+    }
+
+    /// Marks the peer as seen now and applies `update` to its state.
+    fn update_peer<F: FnOnce(&mut PeerState)>(&self, peer_id: &str, update: F) {
+        self.update_peer_at(peer_id, self.clock.now(), update)
+    }
+
+    /// Like `update_peer`, but the peer was seen at `last_seen`.
+    fn update_peer_at<F>(&self, peer_id: &str, last_seen: SystemTime, update: F)
+    where
+        F: FnOnce(&mut PeerState),
+    {
+        trace_span!("map_access");
+        self.last_ingest
+            .store(watchdog::to_nanos(last_seen), Ordering::Relaxed);
+        // Only a new peer needs an owned key
+        if let Some(mut peer) = self.peers.get_mut(peer_id) {
+            peer.last_seen = last_seen;
+            update(&mut peer);
+            return;
         }
-        self.pings_to_peers
-            .get_mut(&peer_id)
-            .expect("Failed to get peer entry")
-            .push_lossy(rtt, self.window_size)
+        self.peers.alter(peer_id.to_string(), |peer| {
+            let mut peer = peer.unwrap_or_else(|| PeerState::new(last_seen));
+            peer.last_seen = last_seen;
+            update(&mut peer);
+            Some(peer)
+        });
+    }
+
+    fn last_seen(&self, peer_id: &str) -> Option<SystemTime> {
+        self.peers.get(peer_id).map(|peer| peer.last_seen)
     }
 
-    pub fn add_transmission(&self, peer_id: String, time: Duration, n_bytes: u32) {
-        if !self.transmissions_rates.contains_key(&peer_id) {
-            self.transmissions_rates
-                .insert_new(peer_id.clone(), Vec::new())
+    /// Copy of the window of `metric` for the peer.
+    fn window(&self, metric: Metric, peer_id: &str) -> Option<Vec<Duration>> {
+        self.expire_samples(peer_id);
+        match metric {
+            Metric::Ping => self.pings_to_peers.get(peer_id).map(|pings| pings.to_vec()),
+            Metric::TransmissionRate => self
+                .transmissions_rates
+                .get(peer_id)
+                .map(|rates| rates.iter().map(|rate| rate.time_per_byte()).collect()),
         }
-        self.transmissions_rates
-            .get_mut(&peer_id)
-            .expect("Failed to get peer entry")
-            .push_lossy(
-                //put transmission rate which is elapsed time per byte
-                time / n_bytes,
-                self.window_size,
-            )
     }
 }
 
@@ -77,16 +691,79 @@ fn correctly_added_transmissions() {
     assert_eq!(peer_2_transmissions.len(), 2)
 }
 
-fn durations_mean(durations: &Vec<Duration>) -> Option<Duration> {
+#[test]
+fn correct_percentile_rank() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("2".to_string(), Duration::from_millis(20));
+    let rank = |millis| stats.percentile_rank("2", Metric::Ping, Duration::from_millis(millis));
+    assert_eq!(rank(500), Some(1.0));
+    assert_eq!(rank(15), Some(0.5));
+    assert_eq!(
+        stats.percentile_rank("2", Metric::TransmissionRate, Duration::from_secs(1)),
+        None
+    );
+}
+
+/// Applies `update` to the window of the peer, which is inserted first if there is none.
+/// Like `Stats::update_peer`, it holds the entry throughout, so that another thread
+/// removing the peer cannot leave it without a window.
+fn update_window<T>(
+    windows: &ConcurrentMap<String, Window<T>>,
+    peer_id: &str,
+    update: impl FnOnce(&mut Window<T>),
+) {
+    trace_span!("map_access");
+    // Only a new peer needs an owned key
+    if let Some(mut window) = windows.get_mut(peer_id) {
+        update(&mut window);
+        return;
+    }
+    windows.alter(peer_id.to_string(), |window| {
+        let mut window = window.unwrap_or_default();
+        update(&mut window);
+        Some(window)
+    });
+}
+
+fn durations_mean(durations: &[Duration]) -> Option<Duration> {
+    if cfg!(feature = "fixed-point") {
+        fixed::durations_mean(durations)
+    } else {
+        float_durations_mean(durations)
+    }
+}
+
+fn durations_std_dev(durations: &[Duration]) -> Option<Duration> {
+    if cfg!(feature = "fixed-point") {
+        fixed::durations_std_dev(durations)
+    } else {
+        float_durations_std_dev(durations)
+    }
+}
+
+/// Durations mean error with confidence interval of 95%, callers scale it by
+/// `Stats::confidence_scale` for other levels.
+/// For correct estimation `durations.len()` should be at least `30`.
+fn durations_error_with_ci(durations: &[Duration]) -> Option<Duration> {
+    if cfg!(feature = "fixed-point") {
+        fixed::durations_error_with_ci(durations)
+    } else {
+        float_durations_error_with_ci(durations)
+    }
+}
+
+fn float_durations_mean(durations: &[Duration]) -> Option<Duration> {
     if durations.is_empty() {
         None
     } else {
-        Some(
-            durations
-                .iter()
-                .fold(Duration::from_secs(0), |acc, x| acc + *x)
-                / durations.len() as u32,
-        )
+        // Nanoseconds of durations up to `Duration::MAX` add up in a u128 without overflow
+        let nanos: u128 = durations.iter().map(Duration::as_nanos).sum();
+        let mean = nanos / durations.len() as u128;
+        Some(Duration::new(
+            (mean / 1_000_000_000) as u64,
+            (mean % 1_000_000_000) as u32,
+        ))
     }
 }
 
@@ -100,15 +777,13 @@ fn correct_durations_mean() {
     assert_eq!(durations_mean(&durations).unwrap(), Duration::from_secs(3));
 }
 
-fn durations_std_dev(durations: &Vec<Duration>) -> Option<Duration> {
-    let mean = durations_mean(durations)?.as_secs_f64();
-    Some(Duration::from_secs_f64(
-        (durations
-            .iter()
-            .fold(0f64, |acc, x| acc + (x.as_secs_f64() - mean).powi(2))
-            / (durations.len() as f64))
-            .sqrt(),
-    ))
+fn float_durations_std_dev(durations: &[Duration]) -> Option<Duration> {
+    let mean = float_durations_mean(durations)?.as_secs_f64();
+    let variance = durations
+        .iter()
+        .fold(0f64, |acc, x| acc + (x.as_secs_f64() - mean).powi(2))
+        / (durations.len() as f64);
+    Some(Duration::try_from_secs_f64(variance.sqrt()).unwrap_or(Duration::MAX))
 }
 
 #[test]
@@ -123,57 +798,107 @@ fn correct_durations_std_dev() {
     assert!((std_dev - 1.63).abs() < epsilon);
 }
 
-/// Durations mean error with confidence interval of 95%
-/// For correct estimation `durations.len()` should be at least `30`.
-fn durations_error_with_ci(durations: &Vec<Duration>) -> Option<Duration> {
-    // Z-value for 95 percent confidence interval
+fn float_durations_error_with_ci(durations: &[Duration]) -> Option<Duration> {
+    // Z-value for 95 percent confidence interval, rescaled by the callers
     let z = 1.96;
-    let std_dev = durations_std_dev(durations)?;
-    Some(Duration::from_secs_f64(
-        z * std_dev.as_secs_f64() / (durations.len() as f64).sqrt(),
-    ))
+    let std_dev = float_durations_std_dev(durations)?;
+    let error = z * std_dev.as_secs_f64() / (durations.len() as f64).sqrt();
+    Some(Duration::try_from_secs_f64(error).unwrap_or(Duration::MAX))
+}
+
+/// Fraction of `durations` below `value`, values equal to it are counted as half.
+fn durations_percentile_rank(durations: &[Duration], value: Duration) -> Option<f64> {
+    if durations.is_empty() {
+        return None;
+    }
+    let (below, equal) = durations
+        .iter()
+        .fold((0, 0), |(below, equal), x| match x.cmp(&value) {
+            std::cmp::Ordering::Less => (below + 1, equal),
+            std::cmp::Ordering::Equal => (below, equal + 1),
+            std::cmp::Ordering::Greater => (below, equal),
+        });
+    Some((below as f64 + equal as f64 / 2.0) / durations.len() as f64)
+}
+
+/// Sample at fraction `quantile` of the sorted `durations` by the nearest rank method.
+fn durations_percentile(durations: &[Duration], quantile: f64) -> Option<Duration> {
+    if durations.is_empty() {
+        return None;
+    }
+    let mut sorted = durations.to_vec();
+    sorted.sort();
+    let rank = (quantile.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.saturating_sub(1)])
+}
+
+#[test]
+fn correct_durations_percentile() {
+    let durations: Vec<_> = (1..=10).map(Duration::from_secs).collect();
+    let percentile = |quantile| durations_percentile(&durations, quantile).unwrap();
+    assert_eq!(percentile(0.0), Duration::from_secs(1));
+    assert_eq!(percentile(0.5), Duration::from_secs(5));
+    assert_eq!(percentile(0.99), Duration::from_secs(10));
+    assert_eq!(durations_percentile(&[], 0.5), None);
+}
+
+#[test]
+fn correct_durations_percentile_rank() {
+    let durations = vec![
+        Duration::from_secs(1),
+        Duration::from_secs(3),
+        Duration::from_secs(5),
+        Duration::from_secs(7),
+    ];
+    let rank = |secs| durations_percentile_rank(&durations, Duration::from_secs(secs)).unwrap();
+    assert_eq!(rank(0), 0.0);
+    assert_eq!(rank(3), 0.375);
+    assert_eq!(rank(4), 0.5);
+    assert_eq!(rank(8), 1.0);
+}
+
+fn values_mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+fn values_std_dev(values: &[f64]) -> Option<f64> {
+    let mean = values_mean(values)?;
+    Some((values.iter().fold(0f64, |acc, x| acc + (x - mean).powi(2)) / values.len() as f64).sqrt())
+}
+
+/// Values mean error with confidence interval of 95%, callers scale it by
+/// `Stats::confidence_scale` for other levels.
+fn values_error_with_ci(values: &[f64]) -> Option<f64> {
+    // Z-value for 95 percent confidence interval, rescaled by the callers
+    let z = 1.96;
+    Some(z * values_std_dev(values)? / (values.len() as f64).sqrt())
+}
+
+#[test]
+fn correct_values_summary() {
+    let values = vec![1.0, 3.0, 5.0];
+    assert_eq!(values_mean(&values), Some(3.0));
+    assert!((values_std_dev(&values).unwrap() - 1.63).abs() < 0.01);
+    assert!((values_error_with_ci(&values).unwrap() - 1.84).abs() < 0.01);
+    assert_eq!(values_mean(&[]), None);
+}
+
+fn values_percentile_rank(values: &[f64], value: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let below = values.iter().filter(|x| **x < value).count();
+    let equal = values.iter().filter(|x| **x == value).count();
+    Some((below as f64 + equal as f64 / 2.0) / values.len() as f64)
 }
 
 impl fmt::Display for Stats {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let ping_by_peer: String = self
-            .pings_to_peers
-            .clone()
-            .into_iter()
-            .map(|(peer, durations)| {
-                match (
-                    durations_mean(&durations),
-                    durations_error_with_ci(&durations),
-                ) {
-                    (Some(duration), Some(error)) => {
-                        format!("{:?} {:?}±{:?}\n", peer, duration, error)
-                    }
-                    _ => format!("No ping data for peer {:?}", peer),
-                }
-            })
-            .collect();
-
-        let transmission_rate_by_peer: String = self
-            .transmissions_rates
-            .clone()
-            .into_iter()
-            .map(|(peer, durations)| {
-                match (
-                    durations_mean(&durations),
-                    durations_error_with_ci(&durations),
-                ) {
-                    (Some(duration), Some(error)) => {
-                        format!("{:?} {:?}±{:?} per byte\n", peer, duration, error)
-                    }
-                    _ => format!("No transmission data for peer {:?}", peer),
-                }
-            })
-            .collect();
-        write!(
-            f,
-            "{:?}\nPing mean for each peer:\n{}Transmission rate mean by peer:\n{}",
-            self.peer_id, ping_by_peer, transmission_rate_by_peer
-        )
+        write!(f, "{}", self.snapshot())
     }
 }
 
@@ -198,3 +923,95 @@ fn correct_push_lossy() {
     vector.push_lossy(3, 2);
     assert_eq!(vector, vec![2, 3]);
 }
+
+#[test]
+fn prelude_covers_recording_and_export() {
+    use crate::prelude::*;
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), ByteSize(1_000));
+    let snapshot: StatsSnapshot = Exporter::new(Encoding::Text).snapshot(&stats);
+    let peer: &PeerSummary = &snapshot.peers[0];
+    let ping: &crate::report::Summary = peer.ping.as_ref().unwrap();
+    assert_eq!(ping.samples, 1);
+    assert_eq!(
+        peer.transmission_rate.as_ref().unwrap().mean,
+        Rate::from_bytes_per_sec(1_000.0)
+    );
+}
+
+#[test]
+fn implausible_rtts_are_rejected() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_rtt("2".to_string(), Rtt::from_millis(20));
+    stats.add_rtt(
+        "2".to_string(),
+        Duration::from_secs(68 * 365 * 24 * 3600).into(),
+    );
+    stats.add_rtt("2".to_string(), Duration::from_secs(0).into());
+    assert_eq!(stats.rejected_samples(), 2);
+    let ping = stats.snapshot().peers.remove(0).ping.unwrap();
+    assert_eq!(ping.mean, Duration::from_millis(20));
+}
+
+#[test]
+#[allow(deprecated)]
+fn simple_api_keeps_working() {
+    let filename = std::env::temp_dir().join(format!("p2p_node_stats_{}.txt", std::process::id()));
+    let filename = filename.to_str().unwrap();
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_transmission("2".to_string(), Duration::from_millis(10), 100);
+    stats.save_to_file(filename).unwrap();
+    let report = std::fs::read_to_string(filename).unwrap();
+    std::fs::remove_file(filename).unwrap();
+    assert!(report.contains("\"2\" 10ms±0ns"));
+}
+
+#[test]
+fn multi_gigabyte_transfers_are_rated_in_bytes_per_sec() {
+    let stats = Stats::new(100, "1".to_string());
+    let n_bytes: u64 = 6_000_000_000;
+    stats.add_transmission("2".to_string(), Duration::from_secs(2), n_bytes);
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), n_bytes);
+    let rate = stats
+        .summarize_peer("2")
+        .unwrap()
+        .transmission_rate
+        .unwrap();
+    assert_eq!(rate.mean, Rate::from_bytes_per_sec(4.5e9));
+    assert_eq!(
+        stats.transmission_rate_rank("2", Rate::from_bytes_per_sec(6e9)),
+        Some(0.75)
+    );
+    assert_eq!(
+        stats.transmission_rate_rank("2", Rate::from_bytes_per_sec(1e9)),
+        Some(0.0)
+    );
+    assert_eq!(
+        stats.estimate_transmission_rate("2"),
+        Some(Rate::from_bytes_per_sec(4.5e9))
+    );
+}
+
+#[test]
+fn extreme_pings_do_not_panic_summaries() {
+    let stats = Stats::new(100, "1".to_string())
+        .with_confidence(0.99)
+        .with_ewma(0.5)
+        .with_robust_summaries()
+        .with_ping_histograms();
+    stats.add_ping("2".to_string(), Duration::MAX);
+    stats.add_ping("2".to_string(), Duration::MAX);
+    stats.add_ping("2".to_string(), Duration::from_nanos(1));
+    stats.add_ping("3".to_string(), Duration::from_millis(10));
+    let snapshot = stats.snapshot();
+    let ping = snapshot.peers[0].ping.as_ref().unwrap();
+    assert!(ping.mean > Duration::from_secs(u64::MAX / 2));
+    let _ = snapshot.to_string();
+    let _ = snapshot.cohort_report(2);
+    let _ = stats.estimate("2", Metric::Ping);
+    let sla = Sla::new(0.5, Duration::from_millis(1), Duration::from_secs(60));
+    assert_eq!(stats.check_sla(&sla).len(), 2);
+}
@@ -0,0 +1,54 @@
+use crate::{MemoryPressure, Stats};
+
+/// EWMA alpha of `Stats::with_lite` unless set with `Stats::with_ewma`
+const LITE_EWMA_ALPHA: f64 = 0.125;
+
+impl Stats {
+    /// Keeps only moving averages and counters of the pings and transmission rates of each
+    /// peer, without windows, streaming summaries or histograms, for relays with thousands
+    /// of peers and little memory. Enables `with_ewma` with an alpha of `0.125` unless set.
+    /// Queries keep working but find no samples of these metrics, so snapshots have EWMA but
+    /// no summaries for them. Samples recorded otherwise, like gauges, keep their windows.
+    pub fn with_lite(mut self) -> Self {
+        self.lite = true;
+        self.ewma_alpha.get_or_insert(LITE_EWMA_ALPHA);
+        self
+    }
+
+    /// Whether ping and transmission rate samples are kept rather than only averaged,
+    /// which memory pressure can pause.
+    pub(crate) fn keeps_samples(&self) -> bool {
+        !self.lite && self.memory_pressure() < MemoryPressure::Critical
+    }
+}
+
+#[test]
+fn lite_keeps_only_averages_and_counters() {
+    use crate::{Metric, Rate};
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string())
+        .with_streaming()
+        .with_lite();
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("2".to_string(), Duration::from_millis(18));
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    assert!(stats.pings_to_peers.is_empty());
+    assert!(stats.transmissions_rates.is_empty());
+    assert_eq!(stats.ewma_ping("2"), Some(Duration::from_millis(11)));
+    assert_eq!(
+        stats.ewma_transmission_rate("2"),
+        Some(Rate::from_bytes_per_sec(1_000.0))
+    );
+    assert_eq!(stats.total_samples("2", Metric::Ping), 2);
+    assert_eq!(stats.ping_percentile("2", 0.5), None);
+    let peer = stats.snapshot().peers.remove(0);
+    assert_eq!(peer.ping, None);
+    assert_eq!(peer.transmission_rate, None);
+    assert!(peer.ewma.is_some());
+
+    let stats = Stats::new(100, "1".to_string()).with_ewma(0.5).with_lite();
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("2".to_string(), Duration::from_millis(20));
+    assert_eq!(stats.ewma_ping("2"), Some(Duration::from_millis(15)));
+}
@@ -0,0 +1,94 @@
+use crate::Stats;
+use std::time::Duration;
+
+impl Stats {
+    /// Counts a ping of the peer which got no response, for `ping_loss`.
+    pub fn add_ping_failure(&self, peer_id: String) {
+        self.record_ping_failure(&peer_id)
+    }
+
+    pub(crate) fn record_ping_failure(&self, peer_id: &str) {
+        trace_span!("add_ping_failure");
+        let _recording = self.recording(peer_id);
+        self.update_peer(peer_id, |peer| peer.failed_pings += 1);
+    }
+
+    /// Records the outcome of a ping protocol round, a round trip time with `add_ping`
+    /// and an error with `add_ping_failure`, e.g. of a `libp2p::ping::Event` (or use
+    /// `record_ping_event` of the `libp2p` feature):
+    ///
+    /// ```ignore
+    /// stats.record_ping_result(event.peer.to_string(), event.result);
+    /// ```
+    pub fn record_ping_result<E>(&self, peer_id: String, result: Result<Duration, E>) {
+        match result {
+            Ok(rtt) => self.add_ping(peer_id, rtt),
+            Err(_) => self.add_ping_failure(peer_id),
+        }
+    }
+
+    /// Records a `libp2p::ping::Event` of the peer like `record_ping_result`. Peers which do
+    /// not support the ping protocol are not counted as a failure.
+    #[cfg(feature = "libp2p")]
+    pub fn record_ping_event(&self, event: &libp2p_ping::Event) {
+        let peer_id = event.peer.to_string();
+        match &event.result {
+            Ok(rtt) => self.add_ping(peer_id, *rtt),
+            Err(libp2p_ping::Failure::Unsupported) => {}
+            Err(_) => self.add_ping_failure(peer_id),
+        }
+    }
+
+    /// Pings of the peer which failed since the start.
+    pub fn failed_pings(&self, peer_id: &str) -> u64 {
+        self.peers.get(peer_id).map_or(0, |peer| peer.failed_pings)
+    }
+
+    /// Fraction of the pings of the peer since the start which failed, `None` before any.
+    pub fn ping_loss(&self, peer_id: &str) -> Option<f64> {
+        let peer = self.peers.get(peer_id)?;
+        let pings = peer.total_pings + peer.failed_pings;
+        if pings == 0 {
+            None
+        } else {
+            Some(peer.failed_pings as f64 / pings as f64)
+        }
+    }
+}
+
+#[test]
+fn failed_pings_count_as_loss() {
+    let stats = Stats::new(100, "1".to_string());
+    for result in [
+        Ok(Duration::from_millis(10)),
+        Err("timeout"),
+        Ok(Duration::from_millis(30)),
+    ] {
+        stats.record_ping_result("2".to_string(), result);
+    }
+    stats.add_ping_failure("2".to_string());
+    assert_eq!(stats.failed_pings("2"), 2);
+    assert_eq!(stats.ping_loss("2"), Some(0.5));
+    assert_eq!(stats.snapshot().peers[0].ping.as_ref().unwrap().samples, 2);
+    assert_eq!(stats.ping_loss("3"), None);
+
+    #[cfg(feature = "libp2p")]
+    {
+        use libp2p_ping::{Event, Failure};
+
+        let peer = libp2p_identity::PeerId::random();
+        for result in [
+            Ok(Duration::from_millis(10)),
+            Err(Failure::Timeout),
+            Err(Failure::Unsupported),
+        ] {
+            stats.record_ping_event(&Event {
+                peer,
+                connection: libp2p_swarm::ConnectionId::new_unchecked(0),
+                result,
+            });
+        }
+        assert_eq!(stats.failed_pings(&peer.to_string()), 1);
+        assert_eq!(stats.ping_loss(&peer.to_string()), Some(0.5));
+    }
+}
@@ -0,0 +1,216 @@
+//! Concurrent maps of the per-peer state.
+//!
+//! `chashmap::CHashMap` locks each bucket separately. Without the default `chashmap`
+//! feature a `HashMap` behind a single `RwLock` with the same methods is used instead,
+//! so that the crate has no third-party dependencies at all.
+
+#[cfg(feature = "chashmap")]
+pub(crate) use chashmap::CHashMap as ConcurrentMap;
+
+/// Keys of a map read without write locks, in no particular order. Keys inserted or
+/// removed meanwhile may or may not be included.
+pub(crate) trait Keys<K> {
+    fn keys(&self) -> Vec<K>;
+}
+
+// `CHashMap` has no borrowing iterator, but its clone only takes the read lock of
+// each bucket
+#[cfg(feature = "chashmap")]
+impl<K: Clone, V: Clone> Keys<K> for ConcurrentMap<K, V> {
+    fn keys(&self) -> Vec<K> {
+        self.clone().into_iter().map(|(key, _)| key).collect()
+    }
+}
+
+#[cfg(not(feature = "chashmap"))]
+pub(crate) use locked::ConcurrentMap;
+
+#[cfg(not(feature = "chashmap"))]
+mod locked {
+    use std::{
+        borrow::Borrow,
+        collections::HashMap,
+        hash::Hash,
+        ops::{Deref, DerefMut},
+        sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    };
+
+    /// Subset of the `CHashMap` interface over a locked `HashMap`. Guards hold the lock
+    /// of the whole map, so a guard must be dropped before the map is changed again.
+    pub(crate) struct ConcurrentMap<K, V> {
+        map: RwLock<HashMap<K, V>>,
+    }
+
+    /// Value of a key, holding the read lock of the map. The key is borrowed from the
+    /// caller, so that a lookup does not allocate.
+    pub(crate) struct ReadGuard<'a, K, Q: ?Sized, V> {
+        map: RwLockReadGuard<'a, HashMap<K, V>>,
+        key: &'a Q,
+    }
+
+    /// Value of a key, holding the write lock of the map.
+    pub(crate) struct WriteGuard<'a, K, Q: ?Sized, V> {
+        map: RwLockWriteGuard<'a, HashMap<K, V>>,
+        key: &'a Q,
+    }
+
+    impl<K: Hash + Eq + Borrow<Q>, Q: ?Sized + Hash + Eq, V> Deref for ReadGuard<'_, K, Q, V> {
+        type Target = V;
+
+        fn deref(&self) -> &V {
+            &self.map[self.key]
+        }
+    }
+
+    impl<K: Hash + Eq + Borrow<Q>, Q: ?Sized + Hash + Eq, V> Deref for WriteGuard<'_, K, Q, V> {
+        type Target = V;
+
+        fn deref(&self) -> &V {
+            &self.map[self.key]
+        }
+    }
+
+    impl<K: Hash + Eq + Borrow<Q>, Q: ?Sized + Hash + Eq, V> DerefMut for WriteGuard<'_, K, Q, V> {
+        fn deref_mut(&mut self) -> &mut V {
+            self.map
+                .get_mut(self.key)
+                .expect("Guarded key is in the map")
+        }
+    }
+
+    impl<K: Hash + Eq, V> ConcurrentMap<K, V> {
+        pub(crate) fn new() -> Self {
+            Self {
+                map: RwLock::new(HashMap::new()),
+            }
+        }
+
+        // Panics while a guard was held do not leave the maps inconsistent
+        fn read(&self) -> RwLockReadGuard<'_, HashMap<K, V>> {
+            self.map.read().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        fn write(&self) -> RwLockWriteGuard<'_, HashMap<K, V>> {
+            self.map.write().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        #[cfg(test)]
+        pub(crate) fn len(&self) -> usize {
+            self.read().len()
+        }
+
+        #[cfg(test)]
+        pub(crate) fn is_empty(&self) -> bool {
+            self.read().is_empty()
+        }
+
+        pub(crate) fn get<'a, Q>(&'a self, key: &'a Q) -> Option<ReadGuard<'a, K, Q, V>>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Hash + Eq,
+        {
+            let map = self.read();
+            if map.contains_key(key) {
+                Some(ReadGuard { map, key })
+            } else {
+                None
+            }
+        }
+
+        pub(crate) fn get_mut<'a, Q>(&'a self, key: &'a Q) -> Option<WriteGuard<'a, K, Q, V>>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Hash + Eq,
+        {
+            let map = self.write();
+            if map.contains_key(key) {
+                Some(WriteGuard { map, key })
+            } else {
+                None
+            }
+        }
+
+        pub(crate) fn contains_key<Q>(&self, key: &Q) -> bool
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Hash + Eq,
+        {
+            self.read().contains_key(key)
+        }
+
+        pub(crate) fn insert(&self, key: K, value: V) -> Option<V> {
+            self.write().insert(key, value)
+        }
+
+        #[cfg(test)]
+        pub(crate) fn upsert<F, G>(&self, key: K, insert: F, update: G)
+        where
+            F: FnOnce() -> V,
+            G: FnOnce(&mut V),
+        {
+            let mut map = self.write();
+            match map.get_mut(&key) {
+                Some(value) => update(value),
+                None => {
+                    map.insert(key, insert());
+                }
+            }
+        }
+
+        pub(crate) fn alter<F>(&self, key: K, f: F)
+        where
+            F: FnOnce(Option<V>) -> Option<V>,
+        {
+            let mut map = self.write();
+            let value = map.remove(&key);
+            if let Some(value) = f(value) {
+                map.insert(key, value);
+            }
+        }
+
+        pub(crate) fn remove<Q>(&self, key: &Q) -> Option<V>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Hash + Eq,
+        {
+            self.write().remove(key)
+        }
+
+        pub(crate) fn retain<F>(&self, predicate: F)
+        where
+            F: Fn(&K, &V) -> bool,
+        {
+            self.write().retain(|key, value| predicate(key, value));
+        }
+
+        pub(crate) fn shrink_to_fit(&self) {
+            self.write().shrink_to_fit();
+        }
+    }
+
+    impl<K: Hash + Eq + Clone, V> super::Keys<K> for ConcurrentMap<K, V> {
+        fn keys(&self) -> Vec<K> {
+            self.read().keys().cloned().collect()
+        }
+    }
+}
+
+#[test]
+fn map_guards_values() {
+    let map = ConcurrentMap::new();
+    map.upsert("1".to_string(), || 1, |value| *value += 1);
+    map.upsert("1".to_string(), || 1, |value| *value += 1);
+    map.alter("2".to_string(), |value| Some(value.unwrap_or(10) + 1));
+    assert_eq!(*map.get("1").unwrap(), 2);
+    if let Some(mut value) = map.get_mut("2") {
+        *value *= 2;
+    }
+    assert_eq!(map.insert("2".to_string(), 0), Some(22));
+    let mut keys = map.keys();
+    keys.sort();
+    assert_eq!(keys, vec!["1".to_string(), "2".to_string()]);
+    map.retain(|_, value| *value > 0);
+    assert!(!map.contains_key("2"));
+    assert_eq!(map.remove("1"), Some(2));
+    assert!(map.is_empty());
+}
@@ -0,0 +1,99 @@
+use crate::{Rate, Stats};
+use std::{fmt, time::Duration};
+
+/// Smallest and largest sample of a window. The minimum ping approximates the propagation
+/// delay to the peer, so the mean above it is mostly queueing delay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinMax<T = Duration> {
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: Copy + PartialOrd> MinMax<T> {
+    pub(crate) fn from_samples(samples: &[T]) -> Option<Self> {
+        let (first, rest) = samples.split_first()?;
+        Some(rest.iter().fold(
+            Self {
+                min: *first,
+                max: *first,
+            },
+            |range, sample| Self {
+                min: if *sample < range.min {
+                    *sample
+                } else {
+                    range.min
+                },
+                max: if *sample > range.max {
+                    *sample
+                } else {
+                    range.max
+                },
+            },
+        ))
+    }
+
+    /// Range of all samples of `ranges` together.
+    pub(crate) fn combined(ranges: impl Iterator<Item = Self>) -> Option<Self> {
+        let ranges: Vec<_> = ranges.collect();
+        let mins: Vec<T> = ranges.iter().map(|range| range.min).collect();
+        let maxs: Vec<T> = ranges.iter().map(|range| range.max).collect();
+        Some(Self {
+            min: Self::from_samples(&mins)?.min,
+            max: Self::from_samples(&maxs)?.max,
+        })
+    }
+}
+
+impl fmt::Display for MinMax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "min {:?} max {:?}", self.min, self.max)
+    }
+}
+
+impl fmt::Display for MinMax<Rate> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "min {} max {}", self.min, self.max)
+    }
+}
+
+impl Stats {
+    /// Fastest and slowest ping of the peer's window.
+    pub fn ping_min_max(&self, peer_id: &str) -> Option<MinMax> {
+        self.expire_samples(peer_id);
+        MinMax::from_samples(&self.pings_to_peers.get(peer_id)?)
+    }
+
+    /// Slowest and fastest transmission rate of the peer's window.
+    pub fn transmission_rate_min_max(&self, peer_id: &str) -> Option<MinMax<Rate>> {
+        self.expire_samples(peer_id);
+        MinMax::from_samples(&self.transmissions_rates.get(peer_id)?)
+    }
+}
+
+#[test]
+fn extremes_of_the_window_are_tracked() {
+    let stats = Stats::new(3, "1".to_string());
+    for millis in [5, 20, 12, 30] {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+    }
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    stats.add_transmission("2".to_string(), Duration::from_secs(2), 1_000);
+    let pings = stats.ping_min_max("2").unwrap();
+    assert_eq!(
+        (pings.min, pings.max),
+        (Duration::from_millis(12), Duration::from_millis(30))
+    );
+    assert_eq!(pings.to_string(), "min 12ms max 30ms");
+    let rates = stats.transmission_rate_min_max("2").unwrap();
+    assert_eq!(rates.min, Rate::from_bytes_per_sec(500.0));
+    assert_eq!(rates.max, Rate::from_bytes_per_sec(1_000.0));
+    let peer = stats.summarize_peer("2").unwrap();
+    assert_eq!(peer.ping_min_max, Some(pings));
+    assert_eq!(peer.transmission_rate_min_max, Some(rates));
+    assert!(stats
+        .snapshot()
+        .to_string()
+        .contains("Ping min/max by peer:\n\"2\" min 12ms max 30ms\n"));
+    assert_eq!(stats.ping_min_max("3"), None);
+}
@@ -0,0 +1,207 @@
+use crate::{Ewma, Rate, RngSource, StatsSnapshot, Summary};
+use std::time::Duration;
+
+/// Laplace noise added to exported means, standard deviations and errors, so that
+/// snapshots shared with public collectors do not reveal the exact behavior of single
+/// links. A smaller `epsilon` adds more noise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Noise {
+    pub epsilon: f64,
+    /// Largest change of a duration a single link may cause, 10ms by default
+    pub duration_sensitivity: Duration,
+    /// Largest change of a transmission rate a single link may cause, 100 kB/s by default
+    pub rate_sensitivity: Rate,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            epsilon: 1.0,
+            duration_sensitivity: Duration::from_millis(10),
+            rate_sensitivity: Rate::from_bytes_per_sec(100_000.0),
+        }
+    }
+}
+
+impl Noise {
+    /// Sample of the Laplace distribution with the scale `sensitivity / epsilon`.
+    fn sample(&self, sensitivity: f64, rng: &mut dyn RngSource) -> f64 {
+        let scale = sensitivity / self.epsilon;
+        let uniform = rng.next_f64() - 0.5;
+        -scale * uniform.signum() * (1.0 - 2.0 * uniform.abs()).max(f64::MIN_POSITIVE).ln()
+    }
+
+    fn duration(&self, duration: Duration, rng: &mut dyn RngSource) -> Duration {
+        let sensitivity = self.duration_sensitivity.as_secs_f64();
+        let secs = duration.as_secs_f64() + self.sample(sensitivity, rng);
+        Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX)
+    }
+
+    fn rate(&self, rate: Rate, rng: &mut dyn RngSource) -> Rate {
+        let sensitivity = self.rate_sensitivity.bytes_per_sec();
+        Rate::from_bytes_per_sec((rate.bytes_per_sec() + self.sample(sensitivity, rng)).max(0.0))
+    }
+
+    fn summary(&self, summary: &mut Summary, rng: &mut dyn RngSource) {
+        summary.mean = self.duration(summary.mean, rng);
+        summary.std_dev = self.duration(summary.std_dev, rng);
+        summary.error = self.duration(summary.error, rng);
+        summary.score = None;
+    }
+
+    fn rate_summary(&self, summary: &mut Summary<Rate>, rng: &mut dyn RngSource) {
+        summary.mean = self.rate(summary.mean, rng);
+        summary.std_dev = self.rate(summary.std_dev, rng);
+        summary.error = self.rate(summary.error, rng);
+        summary.score = None;
+    }
+
+    fn ewma(&self, ewma: &mut Ewma, rng: &mut dyn RngSource) {
+        ewma.ping = ewma.ping.map(|ping| self.duration(ping, rng));
+        ewma.transmission_rate = ewma.transmission_rate.map(|rate| self.rate(rate, rng));
+    }
+}
+
+impl StatsSnapshot {
+    /// Adds `noise` drawn from `rng` to the means, standard deviations and errors of the
+    /// duration and transmission rate summaries and to the moving averages. The other
+    /// values of single links are left out: scores, which rank the exact means, extremes,
+    /// histograms, robust summaries, gauges, derived metrics, sessions and quarantined
+    /// samples. Sample counts and times are kept. The snapshot is marked with
+    /// `noise_epsilon`.
+    pub fn noised(mut self, noise: &Noise, rng: &mut dyn RngSource) -> Self {
+        for peer in self.peers.iter_mut() {
+            peer.ping_histogram = None;
+            peer.ping_robust = None;
+            peer.ping_min_max = None;
+            peer.transmission_rate_min_max = None;
+            peer.gauges.clear();
+            peer.derived.clear();
+            peer.sessions.clear();
+            if let Some(quarantine) = peer.quarantine.as_mut() {
+                quarantine.latest_pings.clear();
+                quarantine.latest_rates.clear();
+            }
+            for summary in peer.summaries_mut() {
+                noise.summary(summary, rng);
+            }
+            let view_rates = peer
+                .views
+                .iter_mut()
+                .filter_map(|view| view.transmission_rate.as_mut());
+            for summary in peer.transmission_rate.iter_mut().chain(view_rates) {
+                noise.rate_summary(summary, rng);
+            }
+            if let Some(ewma) = peer.ewma.as_mut() {
+                noise.ewma(ewma, rng);
+            }
+        }
+        self.noise_epsilon = Some(noise.epsilon);
+        self
+    }
+}
+
+#[test]
+fn noise_is_calibrated_and_marked() {
+    use crate::{SeededRng, Stats};
+
+    let stats = Stats::new(100, "1".to_string());
+    for peer in 0..1_000 {
+        stats.add_ping(peer.to_string(), Duration::from_millis(100));
+    }
+    let noise = Noise {
+        epsilon: 0.5,
+        ..Noise::default()
+    };
+    let snapshot = stats.snapshot().noised(&noise, &mut SeededRng::new(7));
+    assert_eq!(snapshot.noise_epsilon, Some(0.5));
+    assert!(snapshot.to_string().contains("Noised with epsilon 0.5"));
+    let deviations: Vec<_> = snapshot
+        .peers
+        .iter()
+        .map(|peer| peer.ping.as_ref().unwrap().mean.as_secs_f64() - 0.1)
+        .collect();
+    // Laplace noise with the scale 20ms has a standard deviation of about 28ms
+    let mean = deviations.iter().sum::<f64>() / deviations.len() as f64;
+    let std_dev = (deviations.iter().map(|x| x * x).sum::<f64>() / deviations.len() as f64).sqrt();
+    assert!(mean.abs() < 0.005, "{}", mean);
+    assert!((std_dev - 0.028).abs() < 0.005, "{}", std_dev);
+    assert!(snapshot
+        .peers
+        .iter()
+        .all(|peer| peer.ping.as_ref().unwrap().score.is_none()));
+}
+
+#[test]
+fn no_exact_value_of_a_link_is_exported() {
+    use crate::{Bounds, DisconnectReason, PeerSummary, SeededRng, Stats};
+
+    let stats = Stats::new(100, "1".to_string())
+        .with_ping_histograms()
+        .with_robust_summaries()
+        .with_window_views(&[2])
+        .with_ewma(0.5)
+        .with_bounds(Bounds {
+            min_rtt: Duration::from_millis(1),
+            max_rtt: Duration::from_secs(1),
+            max_rate: Rate::from_bytes_per_sec(1e9),
+        });
+    stats.register_derived("double", |peer| {
+        Some(peer.ping.as_ref()?.mean.as_secs_f64() * 2.0)
+    });
+    stats.record_connected("2".to_string());
+    for millis in [10, 20, 40, 80] {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+        stats.add_transmission("2".to_string(), Duration::from_millis(millis), 1_000u64);
+    }
+    stats.add_ping("2".to_string(), Duration::from_secs(5));
+    stats.record_gauge("2".to_string(), "queue", 3.0);
+    stats.record_disconnected("2".to_string(), DisconnectReason::RemoteClosed);
+
+    let exact = stats.snapshot();
+    let noised = exact
+        .clone()
+        .noised(&Noise::default(), &mut SeededRng::new(1));
+    let (exact, noised): (&PeerSummary, &PeerSummary) = (&exact.peers[0], &noised.peers[0]);
+    let (ping, noised_ping) = (exact.ping.as_ref().unwrap(), noised.ping.as_ref().unwrap());
+    assert_eq!(noised_ping.samples, ping.samples);
+    assert_ne!(noised_ping.mean, ping.mean);
+    assert_ne!(noised_ping.std_dev, ping.std_dev);
+    assert_ne!(noised_ping.error, ping.error);
+    assert_eq!(noised_ping.score, None);
+    let (rate, noised_rate) = (
+        exact.transmission_rate.as_ref().unwrap(),
+        noised.transmission_rate.as_ref().unwrap(),
+    );
+    assert_ne!(noised_rate.mean, rate.mean);
+    assert_ne!(noised_rate.std_dev, rate.std_dev);
+    assert_ne!(noised_rate.error, rate.error);
+    let view = |peer: &PeerSummary| peer.views[0].ping.as_ref().unwrap().mean;
+    assert_ne!(view(noised), view(exact));
+    assert_ne!(noised.ewma.unwrap().ping, exact.ewma.unwrap().ping);
+    assert!(exact.ping_histogram.is_some() && noised.ping_histogram.is_none());
+    assert!(exact.ping_robust.is_some() && noised.ping_robust.is_none());
+    assert!(exact.ping_min_max.is_some() && noised.ping_min_max.is_none());
+    assert!(noised.transmission_rate_min_max.is_none());
+    assert!(!exact.gauges.is_empty() && noised.gauges.is_empty());
+    assert!(!exact.derived.is_empty() && noised.derived.is_empty());
+    assert!(!exact.sessions.is_empty() && noised.sessions.is_empty());
+    let quarantine = noised.quarantine.as_ref().unwrap();
+    assert_eq!(quarantine.pings, 1);
+    assert!(quarantine.latest_pings.is_empty());
+
+    // Exporters draw the noise from the rng of the stats
+    let exporter = crate::Exporter::new(crate::Encoding::Text).noise(Noise::default());
+    let means: Vec<_> = (0..2)
+        .map(|_| {
+            let stats = Stats::new(100, "1".to_string()).with_rng(SeededRng::new(3));
+            stats.add_ping("2".to_string(), Duration::from_millis(10));
+            exporter.snapshot(&stats).peers[0]
+                .ping
+                .as_ref()
+                .unwrap()
+                .mean
+        })
+        .collect();
+    assert_eq!(means[0], means[1]);
+}
@@ -0,0 +1,268 @@
+//! Recording of the samples into OpenTelemetry instruments or other metrics facades.
+//!
+//! `Stats::with_meter` takes the `opentelemetry::metrics::Meter` of the application, e.g.
+//! `opentelemetry::global::meter("p2p")`, and records into its `f64` histograms. With the
+//! `metrics` feature `MetricsFacade` mirrors the samples into the `metrics` crate facade
+//! instead, to whatever recorder the application installed.
+
+use crate::{Rate, Stats};
+use std::time::Duration;
+
+/// Name of the attribute with the id of the peer of a sample
+pub const PEER_ATTRIBUTE: &str = "peer.id";
+
+/// Histogram which records values with attributes, like `opentelemetry::metrics::Histogram<f64>`.
+pub trait HistogramInstrument: Send + Sync {
+    fn record(&self, value: f64, attributes: &[(&'static str, &str)]);
+}
+
+impl<F> HistogramInstrument for F
+where
+    F: Fn(f64, &[(&'static str, &str)]) + Send + Sync,
+{
+    fn record(&self, value: f64, attributes: &[(&'static str, &str)]) {
+        self(value, attributes)
+    }
+}
+
+/// Creates the instruments of `Stats::with_meter`, implemented by `opentelemetry::metrics::Meter`.
+pub trait Meter {
+    fn f64_histogram(
+        &self,
+        name: &'static str,
+        unit: &'static str,
+        description: &'static str,
+    ) -> Box<dyn HistogramInstrument>;
+}
+
+/// `Meter` of the `metrics` crate facade, records with `metrics::histogram!` and the
+/// attributes as labels.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsFacade;
+
+/// Records into `Histogram<f64>` instruments with the unit and description, and the
+/// attributes as `KeyValue`s.
+impl Meter for opentelemetry::metrics::Meter {
+    fn f64_histogram(
+        &self,
+        name: &'static str,
+        unit: &'static str,
+        description: &'static str,
+    ) -> Box<dyn HistogramInstrument> {
+        let histogram = opentelemetry::metrics::Meter::f64_histogram(self, name)
+            .with_unit(unit)
+            .with_description(description)
+            .build();
+        Box::new(move |value: f64, attributes: &[(&'static str, &str)]| {
+            let attributes: Vec<_> = attributes
+                .iter()
+                .map(|(key, value)| opentelemetry::KeyValue::new(*key, value.to_string()))
+                .collect();
+            histogram.record(value, &attributes);
+        })
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Meter for MetricsFacade {
+    fn f64_histogram(
+        &self,
+        name: &'static str,
+        _unit: &'static str,
+        description: &'static str,
+    ) -> Box<dyn HistogramInstrument> {
+        metrics::describe_histogram!(name, description);
+        Box::new(move |value: f64, attributes: &[(&'static str, &str)]| {
+            let labels: Vec<_> = attributes
+                .iter()
+                .map(|(key, value)| metrics::Label::new(*key, value.to_string()))
+                .collect();
+            metrics::histogram!(name, labels).record(value);
+        })
+    }
+}
+
+pub(crate) struct Instruments {
+    ping: Box<dyn HistogramInstrument>,
+    transmission_rate: Box<dyn HistogramInstrument>,
+}
+
+impl Stats {
+    /// Also records every ping into a `p2p.ping.duration` histogram in seconds and every
+    /// transmission rate into a `p2p.transmission.rate` histogram in bytes per second,
+    /// both with the `PEER_ATTRIBUTE` of the peer, e.g. into an `opentelemetry::metrics::Meter`.
+    /// Quarantined samples are not recorded.
+    pub fn with_meter(mut self, meter: &dyn Meter) -> Self {
+        self.instruments = Some(Instruments {
+            ping: meter.f64_histogram("p2p.ping.duration", "s", "Round trip times of pings"),
+            transmission_rate: meter.f64_histogram(
+                "p2p.transmission.rate",
+                "By/s",
+                "Transmission rates of transfers",
+            ),
+        });
+        self
+    }
+
+    pub(crate) fn otel_record_ping(&self, peer_id: &str, rtt: Duration) {
+        if let Some(instruments) = &self.instruments {
+            instruments
+                .ping
+                .record(rtt.as_secs_f64(), &[(PEER_ATTRIBUTE, peer_id)]);
+        }
+    }
+
+    pub(crate) fn otel_record_rate(&self, peer_id: &str, rate: Rate) {
+        if let Some(instruments) = &self.instruments {
+            instruments
+                .transmission_rate
+                .record(rate.bytes_per_sec(), &[(PEER_ATTRIBUTE, peer_id)]);
+        }
+    }
+}
+
+#[test]
+fn samples_are_recorded_into_instruments() {
+    use std::sync::{Arc, Mutex};
+
+    type Records = Arc<Mutex<Vec<(&'static str, f64, String)>>>;
+    struct TestMeter(Records);
+
+    impl Meter for TestMeter {
+        fn f64_histogram(
+            &self,
+            name: &'static str,
+            _unit: &'static str,
+            _description: &'static str,
+        ) -> Box<dyn HistogramInstrument> {
+            let records = self.0.clone();
+            Box::new(move |value: f64, attributes: &[(&'static str, &str)]| {
+                assert_eq!(attributes[0].0, PEER_ATTRIBUTE);
+                let peer_id = attributes[0].1.to_string();
+                records.lock().unwrap().push((name, value, peer_id));
+            })
+        }
+    }
+
+    let records = Records::default();
+    let stats = Stats::new(100, "1".to_string()).with_meter(&TestMeter(records.clone()));
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_transmission("3".to_string(), Duration::from_secs(2), 1_000);
+    assert_eq!(
+        *records.lock().unwrap(),
+        vec![
+            ("p2p.ping.duration", 0.01, "2".to_string()),
+            ("p2p.transmission.rate", 500.0, "3".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn samples_are_recorded_into_opentelemetry_histograms() {
+    use opentelemetry::{
+        metrics::{Histogram, HistogramBuilder, InstrumentProvider, SyncInstrument},
+        KeyValue,
+    };
+    use std::sync::{Arc, Mutex};
+
+    type Records = Arc<Mutex<Vec<String>>>;
+    struct TestHistogram(String, Records);
+
+    impl SyncInstrument<f64> for TestHistogram {
+        fn measure(&self, value: f64, attributes: &[KeyValue]) {
+            let attributes: Vec<_> = attributes
+                .iter()
+                .map(|attribute| format!("{}={}", attribute.key, attribute.value))
+                .collect();
+            let record = format!("{}{{{}}} {}", self.0, attributes.join(","), value);
+            self.1.lock().unwrap().push(record);
+        }
+    }
+
+    struct TestProvider(Records);
+
+    impl InstrumentProvider for TestProvider {
+        fn f64_histogram(&self, builder: HistogramBuilder<'_, Histogram<f64>>) -> Histogram<f64> {
+            let name = format!(
+                "{} [{}] {}",
+                builder.name,
+                builder.unit.unwrap_or_default(),
+                builder.description.unwrap_or_default()
+            );
+            Histogram::new(Arc::new(TestHistogram(name, self.0.clone())))
+        }
+    }
+
+    let records = Records::default();
+    let meter = opentelemetry::metrics::Meter::new(Arc::new(TestProvider(records.clone())));
+    let stats = Stats::new(100, "1".to_string()).with_meter(&meter);
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_transmission("3".to_string(), Duration::from_secs(2), 1_000);
+    assert_eq!(
+        *records.lock().unwrap(),
+        vec![
+            "p2p.ping.duration [s] Round trip times of pings{peer.id=2} 0.01",
+            "p2p.transmission.rate [By/s] Transmission rates of transfers{peer.id=3} 500",
+        ]
+    );
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn samples_are_recorded_through_the_metrics_facade() {
+    use metrics::{
+        Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString,
+        Unit,
+    };
+    use std::sync::{Arc, Mutex};
+
+    type Records = Arc<Mutex<Vec<(String, f64)>>>;
+    struct TestHistogram(Key, Records);
+
+    impl HistogramFn for TestHistogram {
+        fn record(&self, value: f64) {
+            let labels: Vec<_> = self
+                .0
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            let key = format!("{}{{{}}}", self.0.name(), labels.join(","));
+            self.1.lock().unwrap().push((key, value));
+        }
+    }
+
+    struct TestRecorder(Records);
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, _: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(Arc::new(TestHistogram(key.clone(), self.0.clone())))
+        }
+    }
+
+    let records = Records::default();
+    metrics::with_local_recorder(&TestRecorder(records.clone()), || {
+        let stats = Stats::new(100, "1".to_string()).with_meter(&MetricsFacade);
+        stats.add_ping("2".to_string(), Duration::from_millis(10));
+        stats.add_transmission("3".to_string(), Duration::from_secs(2), 1_000);
+    });
+    assert_eq!(
+        *records.lock().unwrap(),
+        vec![
+            ("p2p.ping.duration{peer.id=2}".to_string(), 0.01),
+            ("p2p.transmission.rate{peer.id=3}".to_string(), 500.0),
+        ]
+    );
+}
@@ -0,0 +1,150 @@
+use crate::{durations_percentile, PeerCapture, Rate, Stats};
+use std::time::Duration;
+
+impl Stats {
+    /// Ping at fraction `quantile` of the peer's window, e.g. `0.99` for the tail round trip time.
+    pub fn ping_percentile(&self, peer_id: &str, quantile: f64) -> Option<Duration> {
+        self.expire_samples(peer_id);
+        #[cfg(feature = "hdr")]
+        {
+            if self.hdr_significant_digits.is_some() {
+                let histogram = self.peers.get(peer_id)?.hdr_pings.clone()?;
+                return histogram
+                    .value_at_quantile(quantile)
+                    .map(Duration::from_nanos);
+            }
+        }
+        durations_percentile(&self.pings_to_peers.get(peer_id)?, quantile)
+    }
+
+    /// Ping at fraction `quantile` of the windows of all peers together.
+    pub fn aggregate_ping_percentile(&self, quantile: f64) -> Option<Duration> {
+        #[cfg(feature = "hdr")]
+        {
+            if let Some(digits) = self.hdr_significant_digits {
+                return self
+                    .hdr_merged(digits, |peer| peer.hdr_pings.as_ref())
+                    .value_at_quantile(quantile)
+                    .map(Duration::from_nanos);
+            }
+        }
+        let pings: Vec<_> = self
+            .peer_ids()
+            .iter()
+            .filter_map(|peer_id| {
+                self.expire_samples(peer_id);
+                self.pings_to_peers.get(peer_id)
+            })
+            .flat_map(|pings| pings.to_vec())
+            .collect();
+        durations_percentile(&pings, quantile)
+    }
+
+    /// Transmission rate at fraction `quantile` of the peer's window,
+    /// the slow tail is at low quantiles, e.g. `0.01`.
+    pub fn transmission_rate_percentile(&self, peer_id: &str, quantile: f64) -> Option<Rate> {
+        self.expire_samples(peer_id);
+        #[cfg(feature = "hdr")]
+        {
+            if self.hdr_significant_digits.is_some() {
+                let histogram = self.peers.get(peer_id)?.hdr_rates.clone()?;
+                return histogram
+                    .value_at_quantile(quantile)
+                    .map(|rate| Rate::from_bytes_per_sec(rate as f64));
+            }
+        }
+        rates_percentile(&self.transmissions_rates.get(peer_id)?, quantile)
+    }
+
+    /// Transmission rate at fraction `quantile` of the windows of all peers together.
+    pub fn aggregate_transmission_rate_percentile(&self, quantile: f64) -> Option<Rate> {
+        #[cfg(feature = "hdr")]
+        {
+            if let Some(digits) = self.hdr_significant_digits {
+                return self
+                    .hdr_merged(digits, |peer| peer.hdr_rates.as_ref())
+                    .value_at_quantile(quantile)
+                    .map(|rate| Rate::from_bytes_per_sec(rate as f64));
+            }
+        }
+        let rates: Vec<_> = self
+            .peer_ids()
+            .iter()
+            .filter_map(|peer_id| {
+                self.expire_samples(peer_id);
+                self.transmissions_rates.get(peer_id)
+            })
+            .flat_map(|rates| rates.to_vec())
+            .collect();
+        rates_percentile(&rates, quantile)
+    }
+}
+
+/// Like `durations_percentile`, NaN rates are sorted last.
+impl PeerCapture {
+    /// Ping at fraction `quantile` of the captured window like `Stats::ping_percentile`.
+    pub(crate) fn ping_percentile(&self, quantile: f64) -> Option<Duration> {
+        #[cfg(feature = "hdr")]
+        {
+            if let Some(histogram) = &self.state.hdr_pings {
+                return histogram
+                    .value_at_quantile(quantile)
+                    .map(Duration::from_nanos);
+            }
+        }
+        durations_percentile(self.pings.as_ref()?, quantile)
+    }
+
+    /// Transmission rate at fraction `quantile` of the captured window like
+    /// `Stats::transmission_rate_percentile`.
+    pub(crate) fn rate_percentile(&self, quantile: f64) -> Option<Rate> {
+        #[cfg(feature = "hdr")]
+        {
+            if let Some(histogram) = &self.state.hdr_rates {
+                return histogram
+                    .value_at_quantile(quantile)
+                    .map(|rate| Rate::from_bytes_per_sec(rate as f64));
+            }
+        }
+        rates_percentile(self.rates.as_ref()?, quantile)
+    }
+}
+
+fn rates_percentile(rates: &[Rate], quantile: f64) -> Option<Rate> {
+    if rates.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<_> = rates.iter().map(|rate| rate.bytes_per_sec()).collect();
+    sorted.sort_by(f64::total_cmp);
+    let rank = (quantile.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+    Some(Rate::from_bytes_per_sec(sorted[rank.saturating_sub(1)]))
+}
+
+#[test]
+fn percentiles_of_windows() {
+    let stats = Stats::new(100, "1".to_string());
+    for millis in 1..=100 {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+    }
+    stats.add_ping("3".to_string(), Duration::from_secs(1));
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 3_000);
+    stats.add_transmission("3".to_string(), Duration::from_secs(1), 2_000);
+    assert_eq!(
+        stats.ping_percentile("2", 0.99),
+        Some(Duration::from_millis(99))
+    );
+    assert_eq!(
+        stats.aggregate_ping_percentile(1.0),
+        Some(Duration::from_secs(1))
+    );
+    assert_eq!(stats.ping_percentile("4", 0.5), None);
+    assert_eq!(
+        stats.transmission_rate_percentile("2", 0.0),
+        Some(Rate::from_bytes_per_sec(1_000.0))
+    );
+    assert_eq!(
+        stats.aggregate_transmission_rate_percentile(0.5),
+        Some(Rate::from_bytes_per_sec(2_000.0))
+    );
+}
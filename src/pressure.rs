@@ -0,0 +1,172 @@
+use crate::{Compaction, Stats};
+use std::{fmt, sync::atomic::Ordering, sync::Arc, time::Duration};
+
+/// Peers not seen for this long are removed when memory pressure rises
+const COLD_AFTER: Duration = Duration::from_secs(300);
+
+/// Windows hold this fraction of `window_size` under memory pressure
+const PRESSURE_WINDOW_DIVISOR: usize = 4;
+
+/// Memory pressure of the node, signalled with `Stats::set_memory_pressure` or polled
+/// from `Stats::with_memory_probe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MemoryPressure {
+    Normal,
+    /// Windows shrink to a quarter of `window_size` and cold peers are removed
+    Moderate,
+    /// Like moderate, and new pings and transmission rates only update counters and
+    /// moving averages
+    Critical,
+}
+
+impl MemoryPressure {
+    fn from_u8(level: u8) -> Self {
+        match level {
+            0 => MemoryPressure::Normal,
+            1 => MemoryPressure::Moderate,
+            _ => MemoryPressure::Critical,
+        }
+    }
+}
+
+/// Callback of `Stats::with_memory_probe`, e.g. comparing allocated bytes to watermarks.
+pub type MemoryProbe = Arc<dyn Fn() -> MemoryPressure + Send + Sync>;
+
+/// What `Stats` shed when memory pressure rose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shedding {
+    pub pressure: MemoryPressure,
+    /// Samples kept per ping and transmission rate window from now on
+    pub window_size: usize,
+    /// Samples dropped from the windows to fit them
+    pub samples_dropped: usize,
+    /// Whether new samples only update counters and moving averages
+    pub retention_paused: bool,
+    /// Cold peers removed
+    pub compaction: Compaction,
+}
+
+impl fmt::Display for Shedding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} memory pressure: windows of {} samples, {} samples dropped, ",
+            self.pressure, self.window_size, self.samples_dropped
+        )?;
+        if self.retention_paused {
+            f.write_str("sample retention paused, ")?;
+        }
+        write!(f, "{}", self.compaction)
+    }
+}
+
+impl Stats {
+    /// Polls `probe` for the memory pressure on `poll_memory_pressure`.
+    pub fn with_memory_probe(
+        mut self,
+        probe: impl Fn() -> MemoryPressure + Send + Sync + 'static,
+    ) -> Self {
+        self.memory_probe = Some(Arc::new(probe));
+        self
+    }
+
+    /// Degrades gracefully at higher levels, so that the stats do not run the node out of
+    /// memory, and returns what was shed if the level rose. Windows are cut down and peers
+    /// not seen for 5 minutes are removed right away, and each rise is annotated on the
+    /// node. Windows grow back and samples are kept again once the pressure falls.
+    pub fn set_memory_pressure(&self, pressure: MemoryPressure) -> Option<Shedding> {
+        trace_span!("set_memory_pressure");
+        let previous = self.memory_pressure.swap(pressure as u8, Ordering::Relaxed);
+        if pressure <= MemoryPressure::from_u8(previous) {
+            return None;
+        }
+        let window_size = self.pressure_window_size(self.window_size);
+        let mut samples_dropped = 0;
+        for peer_id in self.peer_ids() {
+            if let Some(mut pings) = self.pings_to_peers.get_mut(&peer_id) {
+                let before = pings.len();
+                pings.keep_latest(window_size);
+                samples_dropped += before - pings.len();
+            }
+            if let Some(mut rates) = self.transmissions_rates.get_mut(&peer_id) {
+                let before = rates.len();
+                rates.keep_latest(window_size);
+                samples_dropped += before - rates.len();
+            }
+        }
+        let shedding = Shedding {
+            pressure,
+            window_size,
+            samples_dropped,
+            retention_paused: pressure == MemoryPressure::Critical,
+            compaction: self.compact(COLD_AFTER),
+        };
+        self.annotate(&shedding.to_string(), self.clock.now());
+        Some(shedding)
+    }
+
+    /// Sets the memory pressure reported by the probe of `with_memory_probe`, e.g. on a
+    /// timer. `None` without a probe or if the pressure did not rise.
+    pub fn poll_memory_pressure(&self) -> Option<Shedding> {
+        let pressure = (self.memory_probe.as_ref()?)();
+        self.set_memory_pressure(pressure)
+    }
+
+    pub fn memory_pressure(&self) -> MemoryPressure {
+        MemoryPressure::from_u8(self.memory_pressure.load(Ordering::Relaxed))
+    }
+
+    /// Size of a window of `window_size` samples at the current memory pressure.
+    pub(crate) fn pressure_window_size(&self, window_size: usize) -> usize {
+        match self.memory_pressure() {
+            MemoryPressure::Normal => window_size,
+            _ => (window_size / PRESSURE_WINDOW_DIVISOR).max(1),
+        }
+    }
+}
+
+#[test]
+fn pressure_sheds_samples_and_cold_peers() {
+    use crate::ManualClock;
+    use std::{sync::atomic::AtomicU8, time::SystemTime};
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let level = Arc::new(AtomicU8::new(0));
+    let probe = level.clone();
+    let stats = Stats::new(8, "1".to_string())
+        .with_clock(clock.clone())
+        .with_ewma(0.5)
+        .with_memory_probe(move || MemoryPressure::from_u8(probe.load(Ordering::Relaxed)));
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    clock.advance(Duration::from_secs(600));
+    for millis in 1..=8 {
+        stats.add_ping("3".to_string(), Duration::from_millis(millis));
+    }
+    assert_eq!(stats.poll_memory_pressure(), None);
+
+    level.store(1, Ordering::Relaxed);
+    let shedding = stats.poll_memory_pressure().unwrap();
+    assert_eq!(shedding.window_size, 2);
+    assert_eq!(shedding.samples_dropped, 6);
+    assert!(!shedding.retention_paused);
+    assert_eq!(shedding.compaction.peers_removed, 1);
+    assert_eq!(stats.peer_ids(), vec!["3".to_string()]);
+    stats.add_ping("3".to_string(), Duration::from_millis(9));
+    assert_eq!(stats.pings_to_peers.get("3").unwrap().len(), 2);
+    assert!(stats.annotations()[0]
+        .note
+        .starts_with("Moderate memory pressure"));
+
+    let shedding = stats.set_memory_pressure(MemoryPressure::Critical).unwrap();
+    assert!(shedding.retention_paused);
+    stats.add_ping("3".to_string(), Duration::from_millis(100));
+    assert_eq!(stats.pings_to_peers.get("3").unwrap().len(), 2);
+    assert_eq!(stats.total_samples("3", crate::Metric::Ping), 10);
+    assert_eq!(stats.set_memory_pressure(MemoryPressure::Moderate), None);
+
+    stats.set_memory_pressure(MemoryPressure::Normal);
+    for millis in 1..=8 {
+        stats.add_ping("3".to_string(), Duration::from_millis(millis));
+    }
+    assert_eq!(stats.pings_to_peers.get("3").unwrap().len(), 8);
+}
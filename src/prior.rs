@@ -0,0 +1,157 @@
+use crate::{durations_mean, values_mean, Metric, Rate, Stats};
+use std::time::Duration;
+
+/// Belief about a metric of a peer before enough samples are collected for it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prior {
+    Fixed {
+        mean: Duration,
+        /// Number of samples the prior is worth
+        weight: u32,
+    },
+    /// Mean of the metric over all other peers
+    Population {
+        /// Number of samples the prior is worth
+        weight: u32,
+    },
+}
+
+impl Stats {
+    /// Applies `prior` to `metric` estimates of every peer.
+    pub fn with_prior(mut self, metric: Metric, prior: Prior) -> Self {
+        self.priors.insert(metric, prior);
+        self
+    }
+
+    /// Mean of `metric` for the peer updated from its prior with the windowed samples.
+    ///
+    /// With `n` samples the prior contributes `weight / (weight + n)` to the estimate,
+    /// so it converges to the window mean as samples accumulate.
+    /// Samples of idle peers count less according to `Decay`.
+    /// Without a prior this is the window mean.
+    pub fn estimate(&self, peer_id: &str, metric: Metric) -> Option<Duration> {
+        let (samples, mean) = match self.window(metric, peer_id) {
+            Some(durations) => (
+                durations.len() as f64 * self.decay_weight(self.last_seen(peer_id)),
+                durations_mean(&durations),
+            ),
+            None => (0.0, None),
+        };
+        let (prior_mean, weight) = match self.priors.get(&metric) {
+            Some(Prior::Fixed { mean, weight }) => (Some(*mean), *weight),
+            Some(Prior::Population { weight }) => {
+                (durations_mean(&self.window_means(metric, peer_id)), *weight)
+            }
+            None => (None, 0),
+        };
+        let secs = |duration: Duration| duration.as_secs_f64();
+        blend(mean.map(secs), samples, prior_mean.map(secs), weight)
+            .map(|secs| Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX))
+    }
+
+    /// Like `estimate` of `Metric::TransmissionRate`, but averages the rates of the peer
+    /// in bytes per second, which stay exact for transfers faster than a byte per
+    /// nanosecond. A `Fixed` prior is taken as the time per byte.
+    pub fn estimate_transmission_rate(&self, peer_id: &str) -> Option<Rate> {
+        self.expire_samples(peer_id);
+        let (samples, mean) = match self.transmissions_rates.get(peer_id) {
+            Some(rates) => (
+                rates.len() as f64 * self.decay_weight(self.last_seen(peer_id)),
+                rates_mean(&rates),
+            ),
+            None => (0.0, None),
+        };
+        let (prior_mean, weight) = match self.priors.get(&Metric::TransmissionRate) {
+            Some(Prior::Fixed { mean, weight }) => (Some(Rate::from_time_per_byte(*mean)), *weight),
+            Some(Prior::Population { weight }) => {
+                let means: Vec<Rate> = self
+                    .peer_ids()
+                    .iter()
+                    .filter(|id| *id != peer_id)
+                    .filter_map(|id| rates_mean(&self.transmissions_rates.get(id)?))
+                    .collect();
+                (rates_mean(&means), *weight)
+            }
+            None => (None, 0),
+        };
+        let bytes_per_sec = |rate: Rate| rate.bytes_per_sec();
+        blend(
+            mean.map(bytes_per_sec),
+            samples,
+            prior_mean.map(bytes_per_sec),
+            weight,
+        )
+        .map(Rate::from_bytes_per_sec)
+    }
+
+    fn window_means(&self, metric: Metric, except_peer: &str) -> Vec<Duration> {
+        self.peer_ids()
+            .iter()
+            .filter(|peer_id| *peer_id != except_peer)
+            .filter_map(|peer_id| durations_mean(&self.window(metric, peer_id)?))
+            .collect()
+    }
+}
+
+/// Mean weighted `samples` against the prior mean weighted `weight`.
+fn blend(mean: Option<f64>, samples: f64, prior_mean: Option<f64>, weight: u32) -> Option<f64> {
+    match (mean, prior_mean) {
+        (Some(mean), Some(prior_mean)) if weight > 0 => {
+            let weight = f64::from(weight);
+            Some((prior_mean * weight + mean * samples) / (weight + samples))
+        }
+        (Some(mean), _) => Some(mean),
+        (None, prior_mean) => prior_mean,
+    }
+}
+
+fn rates_mean(rates: &[Rate]) -> Option<Rate> {
+    let values: Vec<f64> = rates.iter().map(|rate| rate.bytes_per_sec()).collect();
+    values_mean(&values).map(Rate::from_bytes_per_sec)
+}
+
+#[test]
+fn fixed_prior_converges_to_samples() {
+    let stats = Stats::new(100, "1".to_string()).with_prior(
+        Metric::Ping,
+        Prior::Fixed {
+            mean: Duration::from_millis(100),
+            weight: 10,
+        },
+    );
+    assert_eq!(
+        stats.estimate("2", Metric::Ping),
+        Some(Duration::from_millis(100))
+    );
+    for _ in 0..10 {
+        stats.add_ping("2".to_string(), Duration::from_millis(20));
+    }
+    assert_eq!(
+        stats.estimate("2", Metric::Ping),
+        Some(Duration::from_millis(60))
+    );
+    for _ in 0..80 {
+        stats.add_ping("2".to_string(), Duration::from_millis(20));
+    }
+    assert_eq!(
+        stats.estimate("2", Metric::Ping),
+        Some(Duration::from_millis(28))
+    );
+}
+
+#[test]
+fn population_prior_uses_other_peers() {
+    let stats =
+        Stats::new(100, "1".to_string()).with_prior(Metric::Ping, Prior::Population { weight: 1 });
+    assert_eq!(stats.estimate("2", Metric::Ping), None);
+    stats.add_ping("3".to_string(), Duration::from_millis(10));
+    stats.add_ping("4".to_string(), Duration::from_millis(30));
+    assert_eq!(
+        stats.estimate("2", Metric::Ping),
+        Some(Duration::from_millis(20))
+    );
+    assert_eq!(
+        stats.estimate("3", Metric::Ping),
+        Some(Duration::from_millis(20))
+    );
+}
@@ -0,0 +1,119 @@
+use crate::{PushLossy, Stats, Summary, Window};
+use std::{fmt, time::Duration};
+
+/// Bucket of the ping payload size, separating propagation delay from bandwidth effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProbeSize {
+    /// Less than 512 bytes
+    Small,
+    /// Less than 64 KiB
+    Medium,
+    Large,
+}
+
+impl ProbeSize {
+    pub const ALL: [ProbeSize; 3] = [ProbeSize::Small, ProbeSize::Medium, ProbeSize::Large];
+
+    pub fn of(n_bytes: u32) -> Self {
+        match n_bytes {
+            0..=511 => ProbeSize::Small,
+            512..=65535 => ProbeSize::Medium,
+            _ => ProbeSize::Large,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            ProbeSize::Small => 0,
+            ProbeSize::Medium => 1,
+            ProbeSize::Large => 2,
+        }
+    }
+}
+
+/// Ping summaries for each `ProbeSize`.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PingBySize {
+    pub small: Option<Summary>,
+    pub medium: Option<Summary>,
+    pub large: Option<Summary>,
+}
+
+impl PingBySize {
+    pub(crate) fn from_windows(windows: &[Window<Duration>; 3]) -> Option<Self> {
+        let by_size = Self {
+            small: Summary::from_durations(&windows[0]),
+            medium: Summary::from_durations(&windows[1]),
+            large: Summary::from_durations(&windows[2]),
+        };
+        if by_size == Self::default() {
+            None
+        } else {
+            Some(by_size)
+        }
+    }
+
+    pub fn get(&self, size: ProbeSize) -> Option<&Summary> {
+        match size {
+            ProbeSize::Small => self.small.as_ref(),
+            ProbeSize::Medium => self.medium.as_ref(),
+            ProbeSize::Large => self.large.as_ref(),
+        }
+    }
+
+    pub(crate) fn summaries_mut(&mut self) -> impl Iterator<Item = &mut Summary> {
+        self.small
+            .as_mut()
+            .into_iter()
+            .chain(self.medium.as_mut())
+            .chain(self.large.as_mut())
+    }
+}
+
+impl fmt::Display for PingBySize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for size in ProbeSize::ALL.iter() {
+            if let Some(summary) = self.get(*size) {
+                write!(
+                    f,
+                    "{}{:?} {:?}±{:?}",
+                    separator, size, summary.mean, summary.error
+                )?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Records the ping like `add_ping` and, unless it was quarantined, also by the size
+    /// of its payload.
+    pub fn add_ping_with_size(&self, peer_id: String, rtt: Duration, probe_bytes: u32) {
+        if self.record_ping(&peer_id, rtt).is_err() {
+            return;
+        }
+        let _recording = self.recording(&peer_id);
+        let window_size = self.window_size;
+        self.update_peer(&peer_id, |peer| {
+            peer.pings_by_size[ProbeSize::of(probe_bytes).index()].push_lossy(rtt, window_size)
+        });
+    }
+}
+
+#[test]
+fn pings_are_bucketed_by_size() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping_with_size("2".to_string(), Duration::from_millis(10), 64);
+    stats.add_ping_with_size("2".to_string(), Duration::from_millis(50), 1 << 20);
+    stats.add_ping_with_size("2".to_string(), Duration::from_millis(70), 1 << 20);
+    let peer = stats.snapshot().peers.remove(0);
+    assert_eq!(peer.ping.unwrap().samples, 3);
+    let by_size = peer.ping_by_size.unwrap();
+    assert_eq!(by_size.small.unwrap().mean, Duration::from_millis(10));
+    assert_eq!(by_size.medium, None);
+    assert_eq!(by_size.large.unwrap().mean, Duration::from_millis(60));
+}
@@ -0,0 +1,380 @@
+use crate::{MetricCell, PeerOrder, PeerSummary, Rate, Rfc3339, Stats, StatsSnapshot, TimeUnit};
+use std::{fmt::Write as _, time::Duration};
+
+/// Audience of a report, which picks its units, precision, columns and order of peers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Means in milliseconds and scaled rates, slowest peers last, for dashboards
+    Ops,
+    /// Means with errors, sample counts and exact rates by peer id, for analysis
+    Research,
+    /// Rounded pings only, for chat messages and small screens
+    Compact,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Ops => "ops",
+            Profile::Research => "research",
+            Profile::Compact => "compact",
+        }
+    }
+
+    /// Profile of a name like `"ops"`, e.g. from a command line flag.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Profile::Ops, Profile::Research, Profile::Compact]
+            .iter()
+            .copied()
+            .find(|profile| profile.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Plain text with padded columns
+    Table,
+    Markdown,
+    Html,
+}
+
+/// Units of the transmission rates of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateUnit {
+    /// Powers of 1000 like `kB/s`, as in the text report
+    Decimal,
+    /// Powers of 1024 like `KiB/s`
+    Binary,
+    /// Unscaled `B/s`
+    BytesPerSec,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    PeerId,
+    Ping,
+    /// Median and MAD of the pings, only with `Stats::with_robust_summaries`
+    PingMedian,
+    PingSamples,
+    TransmissionRate,
+    RateSamples,
+    SuccessRate,
+    LastSeen,
+}
+
+impl Column {
+    fn header(self) -> &'static str {
+        match self {
+            Column::PeerId => "peer",
+            Column::Ping => "ping",
+            Column::PingMedian => "median",
+            Column::PingSamples => "pings",
+            Column::TransmissionRate => "rate",
+            Column::RateSamples => "transmissions",
+            Column::SuccessRate => "success",
+            Column::LastSeen => "last seen",
+        }
+    }
+}
+
+/// Layout of `StatsSnapshot::report`, starting from a `Profile` whose choices can be
+/// changed field by field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportOptions {
+    pub format: ReportFormat,
+    pub time_unit: TimeUnit,
+    pub rate_unit: RateUnit,
+    /// Decimals of pings and rates
+    pub precision: usize,
+    /// Whether means are followed by `±error`
+    pub errors: bool,
+    pub columns: Vec<Column>,
+    pub order: PeerOrder,
+}
+
+impl ReportOptions {
+    pub fn profile(profile: Profile) -> Self {
+        match profile {
+            Profile::Ops => Self {
+                format: ReportFormat::Table,
+                time_unit: TimeUnit::Millis,
+                rate_unit: RateUnit::Decimal,
+                precision: 1,
+                errors: false,
+                columns: vec![
+                    Column::PeerId,
+                    Column::Ping,
+                    Column::TransmissionRate,
+                    Column::SuccessRate,
+                    Column::LastSeen,
+                ],
+                order: PeerOrder::Ping,
+            },
+            Profile::Research => Self {
+                format: ReportFormat::Table,
+                time_unit: TimeUnit::Micros,
+                rate_unit: RateUnit::BytesPerSec,
+                precision: 3,
+                errors: true,
+                columns: vec![
+                    Column::PeerId,
+                    Column::Ping,
+                    Column::PingMedian,
+                    Column::PingSamples,
+                    Column::TransmissionRate,
+                    Column::RateSamples,
+                    Column::SuccessRate,
+                ],
+                order: PeerOrder::PeerId,
+            },
+            Profile::Compact => Self {
+                format: ReportFormat::Table,
+                time_unit: TimeUnit::Millis,
+                rate_unit: RateUnit::Decimal,
+                precision: 0,
+                errors: false,
+                columns: vec![Column::PeerId, Column::Ping],
+                order: PeerOrder::Ping,
+            },
+        }
+    }
+
+    pub fn format(mut self, format: ReportFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    fn cell(&self, peer: &PeerSummary, column: Column) -> String {
+        let precision = self.precision;
+        let missing = || "-".to_string();
+        match column {
+            Column::PeerId => peer.peer_id.clone(),
+            Column::Ping => peer.ping.as_ref().map_or_else(missing, |ping| {
+                let cell = MetricCell::new(ping).unit(self.time_unit);
+                if self.errors {
+                    format!("{:.*}", precision, cell)
+                } else {
+                    format!("{:.*}", precision, cell.without_error())
+                }
+            }),
+            Column::PingMedian => peer.ping_robust.as_ref().map_or_else(missing, |robust| {
+                let mut text = self.duration(robust.median);
+                if self.errors {
+                    text.push('±');
+                    text.push_str(&self.duration(robust.mad));
+                }
+                text
+            }),
+            Column::PingSamples => peer
+                .ping
+                .as_ref()
+                .map_or_else(missing, |ping| ping.samples.to_string()),
+            Column::TransmissionRate => {
+                peer.transmission_rate
+                    .as_ref()
+                    .map_or_else(missing, |rate| {
+                        let mut text = self.rate(rate.mean);
+                        if self.errors {
+                            text.push('±');
+                            text.push_str(&self.rate(rate.error));
+                        }
+                        text
+                    })
+            }
+            Column::RateSamples => peer
+                .transmission_rate
+                .as_ref()
+                .map_or_else(missing, |rate| rate.samples.to_string()),
+            Column::SuccessRate => peer.requests.as_ref().map_or_else(missing, |requests| {
+                format!("{:.*}%", precision, requests.success_rate * 100.0)
+            }),
+            Column::LastSeen => peer
+                .last_seen
+                .map_or_else(missing, |time| Rfc3339(time).to_string()),
+        }
+    }
+
+    fn duration(&self, duration: Duration) -> String {
+        let unit = self.time_unit;
+        format!(
+            "{:.*}{}",
+            self.precision,
+            unit.scale(duration),
+            unit.symbol()
+        )
+    }
+
+    fn rate(&self, rate: Rate) -> String {
+        match self.rate_unit {
+            RateUnit::Decimal => format!("{:.*}", self.precision, rate),
+            RateUnit::Binary => {
+                const PREFIXES: [&str; 5] = ["", "Ki", "Mi", "Gi", "Ti"];
+                let mut scaled = rate.bytes_per_sec();
+                let mut prefix = 0;
+                while scaled.is_finite() && scaled.abs() >= 1024.0 && prefix + 1 < PREFIXES.len() {
+                    scaled /= 1024.0;
+                    prefix += 1;
+                }
+                format!("{:.*} {}B/s", self.precision, scaled, PREFIXES[prefix])
+            }
+            RateUnit::BytesPerSec => format!("{:.*} B/s", self.precision, rate.bytes_per_sec()),
+        }
+    }
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self::profile(Profile::Ops)
+    }
+}
+
+impl StatsSnapshot {
+    /// Table of the peers in the layout of `options`, one row per peer.
+    pub fn report(&self, options: &ReportOptions) -> String {
+        let mut peers: Vec<_> = self.peers.iter().collect();
+        peers.sort_by(|a, b| options.order.compare(a, b));
+        let headers: Vec<String> = options
+            .columns
+            .iter()
+            .map(|column| column.header().to_string())
+            .collect();
+        let rows: Vec<Vec<String>> = peers
+            .iter()
+            .map(|peer| {
+                options
+                    .columns
+                    .iter()
+                    .map(|column| options.cell(peer, *column))
+                    .collect()
+            })
+            .collect();
+        match options.format {
+            ReportFormat::Table => table(&headers, &rows),
+            ReportFormat::Markdown => markdown(&headers, &rows),
+            ReportFormat::Html => html(&headers, &rows),
+        }
+    }
+}
+
+impl Stats {
+    pub fn report(&self, options: &ReportOptions) -> String {
+        self.snapshot().report(options)
+    }
+}
+
+fn table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|column| {
+            rows.iter()
+                .chain(Some(&headers.to_vec()))
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut text = String::new();
+    let mut line = |cells: &[String]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<1$}", cell, width))
+            .collect();
+        text.push_str(cells.join("  ").trim_end());
+        text.push('\n');
+    };
+    line(headers);
+    line(
+        &widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>(),
+    );
+    for row in rows {
+        line(row);
+    }
+    text
+}
+
+fn markdown(headers: &[String], rows: &[Vec<String>]) -> String {
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let mut text = line(headers);
+    text.push_str(&line(&vec!["---".to_string(); headers.len()]));
+    for row in rows {
+        text.push_str(&line(row));
+    }
+    text
+}
+
+fn html(headers: &[String], rows: &[Vec<String>]) -> String {
+    let escape = |cell: &str| {
+        cell.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    let mut text = String::from("<table>\n<thead>\n<tr>");
+    for header in headers {
+        let _ = write!(text, "<th>{}</th>", escape(header));
+    }
+    text.push_str("</tr>\n</thead>\n<tbody>\n");
+    for row in rows {
+        text.push_str("<tr>");
+        for cell in row {
+            let _ = write!(text, "<td>{}</td>", escape(cell));
+        }
+        text.push_str("</tr>\n");
+    }
+    text.push_str("</tbody>\n</table>\n");
+    text
+}
+
+#[test]
+fn profiles_shape_the_report() {
+    let stats = Stats::new(100, "1".to_string()).with_robust_summaries();
+    stats.add_ping("2".to_string(), Duration::from_micros(30_250));
+    stats.add_ping("3".to_string(), Duration::from_micros(12_340));
+    stats.add_transmission("3".to_string(), Duration::from_secs(1), 2_048);
+    let snapshot = stats.snapshot();
+
+    let compact = ReportOptions::profile(Profile::Compact);
+    assert_eq!(
+        snapshot.report(&compact),
+        "peer  ping\n----  ----\n3     12ms\n2     30ms\n"
+    );
+    let research = ReportOptions {
+        columns: vec![
+            Column::PeerId,
+            Column::PingSamples,
+            Column::TransmissionRate,
+        ],
+        ..ReportOptions::profile(Profile::Research)
+    };
+    assert_eq!(
+        snapshot.report(&research.format(ReportFormat::Markdown)),
+        "| peer | pings | rate |\n| --- | --- | --- |\n| 2 | 1 | - |\n| 3 | 1 | 2048.000 B/s±0.000 B/s |\n"
+    );
+    let binary = ReportOptions {
+        rate_unit: RateUnit::Binary,
+        columns: vec![Column::PeerId, Column::TransmissionRate],
+        ..ReportOptions::default()
+    };
+    assert_eq!(
+        snapshot.report(&binary.format(ReportFormat::Html)),
+        "<table>\n<thead>\n<tr><th>peer</th><th>rate</th></tr>\n</thead>\n<tbody>\n\
+         <tr><td>3</td><td>2.0 KiB/s</td></tr>\n<tr><td>2</td><td>-</td></tr>\n</tbody>\n</table>\n"
+    );
+    let median = ReportOptions {
+        columns: vec![Column::PeerId, Column::PingMedian],
+        ..ReportOptions::profile(Profile::Research)
+    };
+    assert_eq!(
+        snapshot.report(&median),
+        "peer  median\n----  -------------------\n\
+         2     30250.000µs±0.000µs\n3     12340.000µs±0.000µs\n"
+    );
+    assert_eq!(Profile::from_name("research"), Some(Profile::Research));
+    assert_eq!(Profile::from_name("verbose"), None);
+}
@@ -0,0 +1,261 @@
+//! Prometheus text exposition of the stats of all peers.
+
+use crate::{snapshot::QUANTILES, DisconnectCounts, Stats};
+use std::{fmt::Write as _, time::UNIX_EPOCH};
+
+impl Stats {
+    /// Renders the snapshot in the Prometheus text exposition format, labeled by `peer_id`.
+    ///
+    /// Means, standard deviations, errors and percentiles of pings and transmission rates
+    /// are gauges, the samples recorded since the start are counters. The time of the latest
+    /// sample is `p2p_stats_last_ingest_timestamp` for alerts on a stalled pipeline.
+    /// The summaries, percentiles and counters of a peer are read at the same instant.
+    pub fn render_prometheus(&self) -> String {
+        let (snapshot, extras) = self.snapshot_with_extras();
+        let mut output = Exposition::default();
+        let peers: Vec<_> = snapshot.peers.iter().zip(&extras).collect();
+
+        let pings: Vec<_> = peers
+            .iter()
+            .filter_map(|(peer, extras)| Some((&peer.peer_id, peer.ping.as_ref()?, *extras)))
+            .collect();
+        let secs = |duration: std::time::Duration| duration.as_secs_f64();
+        output.gauges(
+            "p2p_stats_ping_mean_seconds",
+            "Mean round trip time of the ping window",
+            pings
+                .iter()
+                .map(|(peer_id, ping, _)| (*peer_id, secs(ping.mean))),
+        );
+        output.gauges(
+            "p2p_stats_ping_std_dev_seconds",
+            "Standard deviation of the ping window",
+            pings
+                .iter()
+                .map(|(peer_id, ping, _)| (*peer_id, secs(ping.std_dev))),
+        );
+        output.gauges(
+            "p2p_stats_ping_error_seconds",
+            "Error of the ping mean at the configured confidence level",
+            pings
+                .iter()
+                .map(|(peer_id, ping, _)| (*peer_id, secs(ping.error))),
+        );
+        output.quantiles(
+            "p2p_stats_ping_seconds",
+            "Percentiles of the ping window",
+            pings.iter().map(|(peer_id, _, extras)| {
+                let percentiles = extras.ping_percentiles.map(|ping| ping.map(secs));
+                (*peer_id, percentiles)
+            }),
+        );
+
+        let rates: Vec<_> = peers
+            .iter()
+            .filter_map(|(peer, extras)| {
+                Some((&peer.peer_id, peer.transmission_rate.as_ref()?, *extras))
+            })
+            .collect();
+        output.gauges(
+            "p2p_stats_transmission_rate_mean_bytes_per_second",
+            "Mean of the transmission rate window",
+            rates
+                .iter()
+                .map(|(peer_id, rate, _)| (*peer_id, rate.mean.bytes_per_sec())),
+        );
+        output.gauges(
+            "p2p_stats_transmission_rate_error_bytes_per_second",
+            "Error of the transmission rate mean at the configured confidence level",
+            rates
+                .iter()
+                .map(|(peer_id, rate, _)| (*peer_id, rate.error.bytes_per_sec())),
+        );
+        output.quantiles(
+            "p2p_stats_transmission_rate_bytes_per_second",
+            "Percentiles of the transmission rate window",
+            rates.iter().map(|(peer_id, _, extras)| {
+                let percentiles = extras
+                    .rate_percentiles
+                    .map(|rate| rate.map(|rate| rate.bytes_per_sec()));
+                (*peer_id, percentiles)
+            }),
+        );
+
+        output.counters(
+            "p2p_stats_pings_total",
+            "Pings recorded since the start",
+            peers
+                .iter()
+                .map(|(peer, extras)| (&peer.peer_id, extras.total_pings)),
+        );
+        output.counters(
+            "p2p_stats_failed_pings_total",
+            "Pings which got no response since the start",
+            peers
+                .iter()
+                .map(|(peer, extras)| (&peer.peer_id, extras.failed_pings)),
+        );
+        output.counters(
+            "p2p_stats_transmissions_total",
+            "Transmissions recorded since the start",
+            peers
+                .iter()
+                .map(|(peer, extras)| (&peer.peer_id, extras.total_transmissions)),
+        );
+        output.disconnects(&snapshot.disconnects);
+        output.header(
+            "p2p_stats_rejected_samples_total",
+            "Implausible samples which were not recorded",
+            "counter",
+        );
+        let _ = writeln!(
+            output.text,
+            "p2p_stats_rejected_samples_total {}",
+            self.rejected_samples()
+        );
+        if let Some(time) = self.last_ingest_time() {
+            output.header(
+                "p2p_stats_last_ingest_timestamp",
+                "Unix time in seconds of the latest sample of any kind",
+                "gauge",
+            );
+            let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+            let _ = writeln!(
+                output.text,
+                "p2p_stats_last_ingest_timestamp {}",
+                value(secs.as_secs_f64())
+            );
+        }
+        output.text
+    }
+}
+
+#[derive(Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    fn header(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.text, "# HELP {} {}", name, help);
+        let _ = writeln!(self.text, "# TYPE {} {}", name, kind);
+    }
+
+    fn gauges<'a>(
+        &mut self,
+        name: &str,
+        help: &str,
+        values: impl Iterator<Item = (&'a String, f64)>,
+    ) {
+        self.header(name, help, "gauge");
+        for (peer_id, sample) in values {
+            let _ = writeln!(
+                self.text,
+                "{}{{peer_id=\"{}\"}} {}",
+                name,
+                label(peer_id),
+                value(sample)
+            );
+        }
+    }
+
+    fn counters<'a>(
+        &mut self,
+        name: &str,
+        help: &str,
+        values: impl Iterator<Item = (&'a String, u64)>,
+    ) {
+        self.header(name, help, "counter");
+        for (peer_id, count) in values {
+            let _ = writeln!(
+                self.text,
+                "{}{{peer_id=\"{}\"}} {}",
+                name,
+                label(peer_id),
+                count
+            );
+        }
+    }
+
+    /// Gauges labeled by `quantile`, without the sum and count of a Prometheus summary
+    /// which windows do not have.
+    fn quantiles<'a>(
+        &mut self,
+        name: &str,
+        help: &str,
+        values: impl Iterator<Item = (&'a String, [Option<f64>; 3])>,
+    ) {
+        self.header(name, help, "gauge");
+        for (peer_id, percentiles) in values {
+            for (quantile, percentile) in QUANTILES.iter().zip(percentiles) {
+                if let Some(percentile) = percentile {
+                    let _ = writeln!(
+                        self.text,
+                        "{}{{peer_id=\"{}\",quantile=\"{}\"}} {}",
+                        name,
+                        label(peer_id),
+                        quantile,
+                        value(percentile)
+                    );
+                }
+            }
+        }
+    }
+
+    fn disconnects(&mut self, disconnects: &DisconnectCounts) {
+        let name = "p2p_stats_disconnects_total";
+        self.header(name, "Disconnects from all peers by reason", "counter");
+        for reason in DisconnectCounts::REASONS.iter() {
+            let _ = writeln!(
+                self.text,
+                "{}{{reason=\"{:?}\"}} {}",
+                name,
+                reason,
+                disconnects.get(*reason)
+            );
+        }
+    }
+}
+
+/// Label value with backslashes, quotes and line feeds escaped.
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn value(value: f64) -> String {
+    if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[test]
+fn prometheus_exposition_is_labeled_by_peer() {
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string());
+    for millis in 1..=100 {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+    }
+    stats.add_transmission("a\"b".to_string(), Duration::from_secs(1), 1_000);
+    let text = stats.render_prometheus();
+    assert!(text.contains("# TYPE p2p_stats_ping_mean_seconds gauge\n"));
+    assert!(text.contains("p2p_stats_ping_mean_seconds{peer_id=\"2\"} 0.0505\n"));
+    assert!(text.contains("p2p_stats_ping_seconds{peer_id=\"2\",quantile=\"0.9\"} 0.09\n"));
+    assert!(text.contains("p2p_stats_pings_total{peer_id=\"2\"} 100\n"));
+    assert!(text.contains("p2p_stats_pings_total{peer_id=\"a\\\"b\"} 0\n"));
+    assert!(text.contains("p2p_stats_failed_pings_total{peer_id=\"2\"} 0\n"));
+    assert!(text
+        .contains("p2p_stats_transmission_rate_mean_bytes_per_second{peer_id=\"a\\\"b\"} 1000\n"));
+    assert!(text.contains("p2p_stats_disconnects_total{reason=\"Idle\"} 0\n"));
+    assert!(text.contains("p2p_stats_last_ingest_timestamp "));
+    // Every sample line follows the headers of its metric
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let name = line.split(['{', ' ']).next().unwrap();
+        assert!(text.contains(&format!("# TYPE {} ", name)), "{}", line);
+    }
+}
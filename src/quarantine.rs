@@ -0,0 +1,249 @@
+use crate::{PushLossy, Rate, Rtt, Sample, Stats, Window};
+use std::{fmt, time::Duration};
+
+/// Latest quarantined samples kept for each peer
+const QUARANTINED: usize = 16;
+
+/// Validity bounds of samples, samples outside of them are quarantined
+/// instead of entering the windows, see `Stats::with_bounds`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_rtt: Duration,
+    pub max_rtt: Duration,
+    pub max_rate: Rate,
+}
+
+impl Default for Bounds {
+    /// Rejects zero and above 5 minute round trips and rates above 1 TB/s.
+    fn default() -> Self {
+        Self {
+            min_rtt: Duration::from_nanos(1),
+            max_rtt: Rtt::MAX,
+            max_rate: Rate::from_bytes_per_sec(1e12),
+        }
+    }
+}
+
+/// Relative bound of samples, samples further from the mean of their window are
+/// quarantined like samples out of `Bounds`, see `Stats::with_outlier_policy`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierPolicy {
+    /// Distance from the window mean in standard deviations beyond which samples are outliers
+    pub max_std_devs: f64,
+    /// Samples in the window needed before outliers are detected. As many outliers in a
+    /// row are taken as a shift of the level, from which samples are recorded again.
+    pub min_samples: usize,
+    /// Distance from the window mean as a fraction of the mean below which samples are
+    /// never outliers, so that a window of nearly equal samples, whose standard deviation
+    /// is about zero, still records ordinary jitter but quarantines spikes.
+    pub min_relative_deviation: f64,
+}
+
+impl OutlierPolicy {
+    /// Samples more than `max_std_devs` and 10% from the mean of a window of at least 10
+    /// samples are outliers.
+    pub fn std_devs(max_std_devs: f64) -> Self {
+        Self {
+            max_std_devs,
+            min_samples: 10,
+            min_relative_deviation: 0.1,
+        }
+    }
+
+    fn is_outlier<T: Sample>(&self, window: &Window<T>, sample: T) -> bool {
+        if window.len() < self.min_samples.max(2) {
+            return false;
+        }
+        match window.mean_and_std_dev() {
+            Some((mean, std_dev)) => {
+                let deviation = (sample.value() - mean).abs();
+                deviation > self.max_std_devs * std_dev
+                    && deviation > self.min_relative_deviation * mean.abs()
+            }
+            None => false,
+        }
+    }
+}
+
+/// Samples of a peer which were out of `Bounds` or outliers of the `OutlierPolicy`.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quarantine {
+    pub pings: u64,
+    pub transmissions: u64,
+    /// Pings and transmissions of `pings` and `transmissions` which were outliers
+    pub outliers: u64,
+    /// Latest quarantined round trip times, oldest first
+    pub latest_pings: Vec<Duration>,
+    /// Latest quarantined transmission rates, oldest first
+    pub latest_rates: Vec<Rate>,
+}
+
+impl fmt::Display for Quarantine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} pings", self.pings)?;
+        if let Some(rtt) = self.latest_pings.last() {
+            write!(f, " latest {:?}", rtt)?;
+        }
+        write!(f, ", {} transmissions", self.transmissions)?;
+        if let Some(rate) = self.latest_rates.last() {
+            write!(f, " latest {}", rate)?;
+        }
+        if self.outliers > 0 {
+            write!(f, ", {} outliers", self.outliers)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Quarantines samples outside of `bounds`, by default all samples are recorded.
+    pub fn with_bounds(mut self, bounds: Bounds) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Also quarantines samples which are outliers of `policy`, e.g. spurious timer glitches
+    /// which would dominate a small window. Combine it with `with_bounds` for an absolute
+    /// cap, e.g. a `max_rtt` of 30 seconds.
+    pub fn with_outlier_policy(mut self, policy: OutlierPolicy) -> Self {
+        self.outlier_policy = Some(policy);
+        self
+    }
+
+    fn rtt_in_bounds(&self, rtt: Duration) -> bool {
+        self.bounds
+            .is_none_or(|bounds| rtt >= bounds.min_rtt && rtt <= bounds.max_rtt)
+    }
+
+    fn rate_in_bounds(&self, rate: Rate) -> bool {
+        // Also false for NaN, e.g. zero bytes in zero time
+        self.bounds
+            .is_none_or(|bounds| rate.bytes_per_sec() <= bounds.max_rate.bytes_per_sec())
+    }
+
+    /// Quarantines the ping if it is out of bounds, returns whether it was.
+    pub(crate) fn quarantine_ping(&self, peer_id: &str, rtt: Duration) -> bool {
+        if !self.rtt_in_bounds(rtt) {
+            self.update_peer(peer_id, |peer| {
+                peer.quarantine.pings += 1;
+                peer.quarantine.latest_pings.push_lossy(rtt, QUARANTINED);
+            });
+            return true;
+        }
+        let policy = match self.outlier_policy {
+            Some(policy) => policy,
+            None => return false,
+        };
+        let outlier = self
+            .pings_to_peers
+            .get(peer_id)
+            .is_some_and(|pings| policy.is_outlier(&pings, rtt));
+        if !outlier {
+            return false;
+        }
+        let mut quarantined = false;
+        self.update_peer(peer_id, |peer| {
+            peer.ping_outliers_in_row += 1;
+            if peer.ping_outliers_in_row < policy.min_samples {
+                quarantined = true;
+                peer.quarantine.pings += 1;
+                peer.quarantine.outliers += 1;
+                peer.quarantine.latest_pings.push_lossy(rtt, QUARANTINED);
+            }
+        });
+        quarantined
+    }
+
+    /// Quarantines the transmission rate if it is out of bounds, returns whether it was.
+    pub(crate) fn quarantine_rate(&self, peer_id: &str, rate: Rate) -> bool {
+        if !self.rate_in_bounds(rate) {
+            self.update_peer(peer_id, |peer| {
+                peer.quarantine.transmissions += 1;
+                peer.quarantine.latest_rates.push_lossy(rate, QUARANTINED);
+            });
+            return true;
+        }
+        let policy = match self.outlier_policy {
+            Some(policy) => policy,
+            None => return false,
+        };
+        let outlier = self
+            .transmissions_rates
+            .get(peer_id)
+            .is_some_and(|rates| policy.is_outlier(&rates, rate));
+        if !outlier {
+            return false;
+        }
+        let mut quarantined = false;
+        self.update_peer(peer_id, |peer| {
+            peer.rate_outliers_in_row += 1;
+            if peer.rate_outliers_in_row < policy.min_samples {
+                quarantined = true;
+                peer.quarantine.transmissions += 1;
+                peer.quarantine.outliers += 1;
+                peer.quarantine.latest_rates.push_lossy(rate, QUARANTINED);
+            }
+        });
+        quarantined
+    }
+}
+
+#[test]
+fn out_of_bounds_samples_are_quarantined() {
+    let stats = Stats::new(100, "1".to_string()).with_bounds(Bounds::default());
+    let corrupted = Duration::from_secs(68 * 365 * 24 * 3600);
+    stats.add_ping("2".to_string(), Duration::from_millis(20));
+    stats.add_ping("2".to_string(), corrupted);
+    stats.add_ping_with_size("2".to_string(), corrupted, 64);
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    stats.add_transmission("2".to_string(), Duration::from_nanos(1), 1_000_000);
+    let snapshot = stats.snapshot();
+    let peer = &snapshot.peers[0];
+    assert_eq!(peer.ping.as_ref().unwrap().mean, Duration::from_millis(20));
+    assert_eq!(peer.ping_by_size, None);
+    assert_eq!(peer.transmission_rate.as_ref().unwrap().samples, 1);
+    let quarantine = peer.quarantine.as_ref().unwrap();
+    assert_eq!(quarantine.pings, 2);
+    assert_eq!(quarantine.latest_pings, vec![corrupted, corrupted]);
+    assert_eq!(quarantine.transmissions, 1);
+    assert!(snapshot
+        .to_string()
+        .contains("\"2\" 2 pings latest 2144448000s, 1 transmissions latest 1000.0 TB/s"));
+}
+
+#[test]
+fn outliers_of_the_window_are_quarantined() {
+    let stats = Stats::new(100, "1".to_string()).with_outlier_policy(OutlierPolicy::std_devs(4.0));
+    let millis = Duration::from_millis;
+    for i in 0..10 {
+        stats.add_ping("2".to_string(), millis(20 + i % 3));
+    }
+    stats.add_ping("2".to_string(), Duration::from_secs(3));
+    stats.add_ping("2".to_string(), millis(22));
+    assert_eq!(stats.summarize_peer("2").unwrap().ping.unwrap().samples, 11);
+    let quarantine = stats.summarize_peer("2").unwrap().quarantine.unwrap();
+    assert_eq!((quarantine.pings, quarantine.outliers), (1, 1));
+    assert!(quarantine.to_string().ends_with(", 1 outliers"));
+
+    // A shift of the level is recorded after as many outliers in a row as the window needs
+    for _ in 0..10 {
+        stats.add_ping("2".to_string(), millis(200));
+    }
+    let peer = stats.summarize_peer("2").unwrap();
+    assert_eq!(peer.quarantine.unwrap().outliers, 10);
+    assert_eq!(peer.ping.unwrap().samples, 12);
+    stats.add_ping("2".to_string(), millis(200));
+    assert_eq!(stats.summarize_peer("2").unwrap().ping.unwrap().samples, 13);
+
+    // A window of equal samples has no deviation, but still only spikes are outliers
+    let stats = Stats::new(100, "1".to_string()).with_outlier_policy(OutlierPolicy::std_devs(4.0));
+    for _ in 0..10 {
+        stats.add_ping("2".to_string(), millis(20));
+    }
+    stats.add_ping("2".to_string(), millis(21));
+    stats.add_ping("2".to_string(), Duration::from_secs(3));
+    let peer = stats.summarize_peer("2").unwrap();
+    assert_eq!(peer.ping.unwrap().samples, 11);
+    assert_eq!(peer.quarantine.unwrap().outliers, 1);
+}
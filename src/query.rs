@@ -0,0 +1,100 @@
+use crate::{PeerSummary, Stats, Summary};
+use std::cmp::Ordering;
+
+/// Ordering of peers in paginated queries, ties are broken by peer id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerOrder {
+    PeerId,
+    /// Lowest mean ping first, peers without pings last
+    Ping,
+    /// Highest mean rate first, peers without transmissions last
+    TransmissionRate,
+    /// Most recently seen first
+    LastSeen,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub peers: Vec<PeerSummary>,
+    /// Number of peers across all pages
+    pub total: usize,
+}
+
+/// Orders by `key` of the means, missing summaries last.
+fn by_mean<T, K: PartialOrd>(
+    a: &Option<Summary<T>>,
+    b: &Option<Summary<T>>,
+    key: impl Fn(&T) -> K,
+) -> Ordering {
+    let mean = |summary: &Option<Summary<T>>| summary.as_ref().map(|summary| key(&summary.mean));
+    match (mean(a), mean(b)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+impl PeerOrder {
+    pub(crate) fn compare(self, a: &PeerSummary, b: &PeerSummary) -> Ordering {
+        match self {
+            PeerOrder::PeerId => Ordering::Equal,
+            PeerOrder::Ping => by_mean(&a.ping, &b.ping, |mean| *mean),
+            PeerOrder::TransmissionRate => {
+                by_mean(&a.transmission_rate, &b.transmission_rate, |mean| {
+                    -mean.bytes_per_sec()
+                })
+            }
+            PeerOrder::LastSeen => b.last_seen.cmp(&a.last_seen),
+        }
+        .then_with(|| a.peer_id.cmp(&b.peer_id))
+    }
+}
+
+impl Stats {
+    /// Summaries of at most `limit` peers starting at `offset` in the given order.
+    pub fn peers_page(&self, offset: usize, limit: usize, order: PeerOrder) -> Page {
+        let peer_ids = self.peer_ids();
+        let total = peer_ids.len();
+        if order == PeerOrder::PeerId {
+            // Peer ids are already sorted, so only the requested page is summarized
+            let peers = peer_ids
+                .iter()
+                .skip(offset)
+                .filter_map(|peer_id| self.summarize_peer(peer_id))
+                .take(limit)
+                .collect();
+            return Page { peers, total };
+        }
+        let mut peers: Vec<_> = self.snapshot_iter().collect();
+        peers.sort_by(|a, b| order.compare(a, b));
+        Page {
+            peers: peers.into_iter().skip(offset).take(limit).collect(),
+            total,
+        }
+    }
+}
+
+#[test]
+fn pages_are_ordered_and_bounded() {
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string());
+    for (peer, millis) in [("2", 30), ("3", 10), ("4", 20), ("5", 10)].iter() {
+        stats.add_ping(peer.to_string(), Duration::from_millis(*millis));
+    }
+    stats.add_transmission("6".to_string(), Duration::from_secs(1), 1);
+    let ids =
+        |page: Page| -> Vec<String> { page.peers.into_iter().map(|peer| peer.peer_id).collect() };
+
+    let page = stats.peers_page(0, 3, PeerOrder::Ping);
+    assert_eq!(page.total, 5);
+    assert_eq!(ids(page), vec!["3", "5", "4"]);
+    assert_eq!(ids(stats.peers_page(3, 3, PeerOrder::Ping)), vec!["2", "6"]);
+    assert_eq!(
+        ids(stats.peers_page(1, 2, PeerOrder::PeerId)),
+        vec!["3", "4"]
+    );
+    assert!(stats
+        .peers_page(10, 2, PeerOrder::LastSeen)
+        .peers
+        .is_empty());
+}
@@ -0,0 +1,79 @@
+use crate::{confidence::scaled, Rate, Stats, Summary};
+use std::time::{Duration, SystemTime};
+
+impl Stats {
+    /// Pings of the peer's window recorded from `from` until before `to`, with their times.
+    pub fn pings_between(
+        &self,
+        peer_id: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Vec<(SystemTime, Duration)> {
+        self.expire_samples(peer_id);
+        match self.pings_to_peers.get(peer_id) {
+            Some(window) => {
+                let (times, pings) = window.between(from, Some(to));
+                times.iter().copied().zip(pings.iter().copied()).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Transmission rates of the peer's window recorded from `from` until before `to`,
+    /// with their times.
+    pub fn transmission_rates_between(
+        &self,
+        peer_id: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Vec<(SystemTime, Rate)> {
+        self.expire_samples(peer_id);
+        match self.transmissions_rates.get(peer_id) {
+            Some(window) => {
+                let (times, rates) = window.between(from, Some(to));
+                times.iter().copied().zip(rates.iter().copied()).collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Summary of the pings of the peer's window recorded since `since`, e.g. over
+    /// the last 5 minutes, `None` without such pings.
+    pub fn summary_since(&self, peer_id: &str, since: SystemTime) -> Option<Summary> {
+        self.expire_samples(peer_id);
+        let window = self.pings_to_peers.get(peer_id)?;
+        scaled(
+            Summary::from_durations(window.between(since, None).1),
+            self.confidence_scale(),
+        )
+    }
+}
+
+#[test]
+fn samples_are_queried_by_time() {
+    use crate::{Clock, ManualClock};
+    use std::sync::Arc;
+
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let clock = Arc::new(ManualClock::new(start));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    let millis = Duration::from_millis;
+    stats.add_ping("2".to_string(), millis(100));
+    clock.advance(Duration::from_secs(60));
+    stats.add_ping("2".to_string(), millis(10));
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    clock.advance(Duration::from_secs(60));
+    stats.add_ping("2".to_string(), millis(30));
+    let minute = start + Duration::from_secs(60);
+    assert_eq!(
+        stats.pings_between("2", minute, minute + Duration::from_secs(1)),
+        vec![(minute, millis(10))]
+    );
+    assert_eq!(
+        stats.transmission_rates_between("2", start, clock.now()),
+        vec![(minute, Rate::from_bytes_per_sec(1_000.0))]
+    );
+    assert_eq!(stats.summary_since("2", minute).unwrap().mean, millis(20));
+    assert_eq!(stats.summary_since("2", clock.now() + millis(1)), None);
+    assert!(stats.pings_between("3", start, clock.now()).is_empty());
+}
@@ -0,0 +1,185 @@
+use crate::{
+    Annotation, DisconnectCounts, Exporter, Rate, RequestSummary, Stats, StatsSnapshot, Summary,
+};
+use std::{io, time::SystemTime};
+
+/// Structured part of a snapshot, for embedders which own the transport of their telemetry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Record<'a> {
+    /// First record of every snapshot
+    Node {
+        peer_id: &'a str,
+        time: Option<SystemTime>,
+        peers: usize,
+        /// Disconnects from all peers
+        disconnects: &'a DisconnectCounts,
+    },
+    Ping {
+        peer_id: &'a str,
+        summary: &'a Summary,
+    },
+    TransmissionRate {
+        peer_id: &'a str,
+        summary: &'a Summary<Rate>,
+    },
+    Requests {
+        peer_id: &'a str,
+        summary: &'a RequestSummary,
+    },
+    Gauge {
+        peer_id: &'a str,
+        name: &'a str,
+        summary: &'a Summary<f64>,
+    },
+    Derived {
+        peer_id: &'a str,
+        name: &'a str,
+        value: f64,
+    },
+    /// Annotation of the node if `peer_id` is `None`
+    Annotation {
+        peer_id: Option<&'a str>,
+        annotation: &'a Annotation,
+    },
+}
+
+/// Receiver of the records of `Exporter::export_records`, e.g. a custom UDP or Kafka producer.
+pub trait RecordSink {
+    fn record(&mut self, record: Record<'_>) -> io::Result<()>;
+
+    /// Called after the last record of a snapshot, e.g. to send a batch.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(Record<'_>) -> io::Result<()>> RecordSink for F {
+    fn record(&mut self, record: Record<'_>) -> io::Result<()> {
+        self(record)
+    }
+}
+
+impl StatsSnapshot {
+    /// Records of the node and of each peer in order, peers with their summaries first.
+    pub fn records(&self) -> Vec<Record<'_>> {
+        let mut records = vec![Record::Node {
+            peer_id: &self.peer_id,
+            time: self.time,
+            peers: self.peers.len(),
+            disconnects: &self.disconnects,
+        }];
+        for peer in &self.peers {
+            let peer_id = peer.peer_id.as_str();
+            if let Some(summary) = &peer.ping {
+                records.push(Record::Ping { peer_id, summary });
+            }
+            if let Some(summary) = &peer.transmission_rate {
+                records.push(Record::TransmissionRate { peer_id, summary });
+            }
+            if let Some(summary) = &peer.requests {
+                records.push(Record::Requests { peer_id, summary });
+            }
+            for (name, summary) in &peer.gauges {
+                records.push(Record::Gauge {
+                    peer_id,
+                    name,
+                    summary,
+                });
+            }
+            for (name, value) in &peer.derived {
+                records.push(Record::Derived {
+                    peer_id,
+                    name,
+                    value: *value,
+                });
+            }
+            for annotation in &peer.annotations {
+                records.push(Record::Annotation {
+                    peer_id: Some(peer_id),
+                    annotation,
+                });
+            }
+        }
+        for annotation in &self.annotations {
+            records.push(Record::Annotation {
+                peer_id: None,
+                annotation,
+            });
+        }
+        records
+    }
+
+    /// Passes the `records` to `sink` and finishes it.
+    pub fn write_records(&self, sink: &mut dyn RecordSink) -> io::Result<()> {
+        for record in self.records() {
+            sink.record(record)?;
+        }
+        sink.finish()
+    }
+}
+
+impl Exporter {
+    /// Passes the records of the snapshot of the exporter to `sink` instead of encoding it,
+    /// with the same redaction, noise and rounding.
+    pub fn export_records(&self, stats: &Stats, sink: &mut dyn RecordSink) -> io::Result<()> {
+        self.snapshot(stats).write_records(sink)
+    }
+}
+
+#[test]
+fn records_reach_the_sink() {
+    use crate::{Encoding, Precision};
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_nanos(10_123_456));
+    stats.record_gauge("2".to_string(), "queue_depth", 3.0);
+    stats.add_transmission("3".to_string(), Duration::from_secs(1), 1_000);
+    let mut lines = Vec::new();
+    let mut finished = false;
+    struct Lines<'a>(&'a mut Vec<String>, &'a mut bool);
+    impl RecordSink for Lines<'_> {
+        fn record(&mut self, record: Record<'_>) -> io::Result<()> {
+            self.0.push(match record {
+                Record::Node { peer_id, peers, .. } => format!("node {} {}", peer_id, peers),
+                Record::Ping { peer_id, summary } => format!("ping {} {:?}", peer_id, summary.mean),
+                Record::Gauge { peer_id, name, .. } => format!("gauge {} {}", peer_id, name),
+                Record::TransmissionRate { peer_id, summary } => {
+                    format!("rate {} {}", peer_id, summary.mean)
+                }
+                _ => "other".to_string(),
+            });
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            *self.1 = true;
+            Ok(())
+        }
+    }
+    Exporter::new(Encoding::Text)
+        .precision(Precision {
+            durations: Some(Duration::from_millis(1)),
+            significant_digits: None,
+        })
+        .export_records(&stats, &mut Lines(&mut lines, &mut finished))
+        .unwrap();
+    assert_eq!(
+        lines,
+        vec![
+            "node 1 2",
+            "ping 2 10ms",
+            "gauge 2 queue_depth",
+            "rate 3 1.0 kB/s"
+        ]
+    );
+    assert!(finished);
+
+    let mut count = 0;
+    let mut counter = |_: Record<'_>| -> io::Result<()> {
+        count += 1;
+        Ok(())
+    };
+    stats.snapshot().write_records(&mut counter).unwrap();
+    assert_eq!(count, 4);
+}
@@ -0,0 +1,203 @@
+use crate::{DisconnectCounts, MinMax, PeerSummary, Rate, StatsSnapshot, Summary};
+use std::{collections::BTreeMap, time::Duration};
+
+/// Privacy level of exported snapshots, so that detailed and public exports
+/// can be written from the same `Stats`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Redaction {
+    /// Peers whose id matches any of the patterns are left out, `*` matches any characters
+    pub drop_peers: Vec<String>,
+    /// Leaves out the id of the node, annotations, capabilities, addresses, sessions
+    /// and last seen times
+    pub strip_metadata: bool,
+    /// Replaces the peers with a single summary of all of them, with the id `"*"`
+    pub aggregates_only: bool,
+}
+
+impl Redaction {
+    pub fn drops(&self, peer_id: &str) -> bool {
+        self.drop_peers
+            .iter()
+            .any(|pattern| matches_pattern(pattern, peer_id))
+    }
+}
+
+impl StatsSnapshot {
+    pub fn redacted(mut self, redaction: &Redaction) -> Self {
+        self.peers.retain(|peer| !redaction.drops(&peer.peer_id));
+        if redaction.strip_metadata {
+            self.peer_id.clear();
+            self.annotations.clear();
+            for peer in self.peers.iter_mut() {
+                peer.last_seen = None;
+                peer.annotations.clear();
+                peer.capabilities.clear();
+                peer.address = None;
+                peer.sessions.clear();
+            }
+        }
+        if redaction.aggregates_only && !self.peers.is_empty() {
+            self.peers = vec![aggregate_peers(&self.peers, self.confidence_scale())];
+        }
+        self
+    }
+}
+
+fn aggregate_peers(peers: &[PeerSummary], confidence_scale: f64) -> PeerSummary {
+    let mut disconnects = DisconnectCounts::default();
+    for peer in peers {
+        disconnects.idle += peer.disconnects.idle;
+        disconnects.error += peer.disconnects.error;
+        disconnects.banned += peer.disconnects.banned;
+        disconnects.remote_closed += peer.disconnects.remote_closed;
+        disconnects.superseded += peer.disconnects.superseded;
+    }
+    PeerSummary {
+        peer_id: "*".to_string(),
+        last_seen: peers.iter().filter_map(|peer| peer.last_seen).max(),
+        ping: pooled(
+            peers.iter().filter_map(|peer| peer.ping.as_ref()),
+            |duration| duration.as_secs_f64(),
+            Duration::from_secs_f64,
+            confidence_scale,
+        ),
+        ping_histogram: None,
+        ping_robust: None,
+        ping_min_max: MinMax::combined(peers.iter().filter_map(|peer| peer.ping_min_max)),
+        ping_ages: None,
+        cold_ping: pooled(
+            peers.iter().filter_map(|peer| peer.cold_ping.as_ref()),
+            |duration| duration.as_secs_f64(),
+            Duration::from_secs_f64,
+            confidence_scale,
+        ),
+        ping_by_size: None,
+        transmission_rate: pooled(
+            peers
+                .iter()
+                .filter_map(|peer| peer.transmission_rate.as_ref()),
+            Rate::bytes_per_sec,
+            Rate::from_bytes_per_sec,
+            confidence_scale,
+        ),
+        transmission_rate_min_max: MinMax::combined(
+            peers
+                .iter()
+                .filter_map(|peer| peer.transmission_rate_min_max),
+        ),
+        transmission_rate_ages: None,
+        requests: None,
+        gauges: BTreeMap::new(),
+        stages: None,
+        upgrades: None,
+        sessions: Vec::new(),
+        disconnects,
+        keep_alive: None,
+        annotations: Vec::new(),
+        capabilities: Default::default(),
+        address: None,
+        quarantine: None,
+        derived: BTreeMap::new(),
+        views: Vec::new(),
+        ewma: None,
+    }
+}
+
+/// Summary of all samples of `summaries` together, as if they were one window, with the
+/// error scaled by `confidence_scale` like those of the `summaries`.
+pub(crate) fn pooled<'a, T: Copy + 'a>(
+    summaries: impl Iterator<Item = &'a Summary<T>>,
+    value: impl Fn(T) -> f64,
+    from_value: impl Fn(f64) -> T,
+    confidence_scale: f64,
+) -> Option<Summary<T>> {
+    let parts: Vec<_> = summaries
+        .map(|summary| {
+            (
+                summary.samples as f64,
+                value(summary.mean),
+                value(summary.std_dev),
+            )
+        })
+        .collect();
+    let samples: f64 = parts.iter().map(|(samples, _, _)| samples).sum();
+    if samples == 0.0 {
+        return None;
+    }
+    let mean = parts.iter().map(|(n, mean, _)| n * mean).sum::<f64>() / samples;
+    let variance = parts
+        .iter()
+        .map(|(n, part_mean, std_dev)| n * (std_dev.powi(2) + (part_mean - mean).powi(2)))
+        .sum::<f64>()
+        / samples;
+    // Z-value for 95 percent confidence interval, as for windows
+    let error = confidence_scale * 1.96 * variance.sqrt() / samples.sqrt();
+    Some(Summary {
+        samples: samples as usize,
+        mean: from_value(mean),
+        std_dev: from_value(variance.sqrt()),
+        error: from_value(error),
+        score: None,
+        warming_up: false,
+    })
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let middle: Vec<_> = parts.collect();
+    let last = match middle.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(index) => rest = &rest[index + part.len()..],
+                    None => return false,
+                }
+            }
+            last
+        }
+        // No `*` in the pattern
+        None => return rest.is_empty(),
+    };
+    rest.ends_with(last)
+}
+
+#[test]
+fn redaction_drops_and_aggregates_peers() {
+    use crate::Stats;
+
+    assert!(matches_pattern("relay-*", "relay-1"));
+    assert!(matches_pattern("*-eu-*", "node-eu-2"));
+    assert!(!matches_pattern("relay", "relay-1"));
+    let stats = Stats::new(100, "1".to_string());
+    let millis = Duration::from_millis;
+    stats.add_ping("2".to_string(), millis(10));
+    stats.add_ping("2".to_string(), millis(30));
+    stats.add_ping("3".to_string(), millis(50));
+    stats.add_ping("relay-1".to_string(), millis(900));
+    stats.set_capabilities("2".to_string(), vec!["quic"]);
+    let redaction = Redaction {
+        drop_peers: vec!["relay-*".to_string()],
+        strip_metadata: true,
+        aggregates_only: false,
+    };
+    let snapshot = stats.snapshot().redacted(&redaction);
+    assert_eq!(snapshot.peer_id, "");
+    assert_eq!(snapshot.peers.len(), 2);
+    assert!(snapshot.peers[0].capabilities.is_empty());
+    assert_eq!(snapshot.peers[0].last_seen, None);
+    let snapshot = snapshot.redacted(&Redaction {
+        aggregates_only: true,
+        ..Redaction::default()
+    });
+    let ping = snapshot.peers[0].ping.as_ref().unwrap();
+    assert_eq!(snapshot.peers[0].peer_id, "*");
+    assert_eq!(ping.samples, 3);
+    assert_eq!(ping.mean, millis(30));
+    assert!((ping.std_dev.as_secs_f64() - 0.01633).abs() < 1e-5);
+}
@@ -0,0 +1,190 @@
+use crate::{compact::ShrinkToFit, PushLossy, Stats, Summary, Window};
+use std::{fmt, time::Duration};
+
+/// Reason of a failed request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorCategory {
+    Timeout,
+    /// Connection was reset
+    Reset,
+    /// Peer violated the protocol
+    Protocol,
+    /// Peer refused the request, e.g. because of an unsupported protocol version
+    Rejected,
+    Other,
+}
+
+/// Number of failed requests in each `ErrorCategory`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorCounts {
+    pub timeout: u64,
+    pub reset: u64,
+    pub protocol: u64,
+    pub rejected: u64,
+    pub other: u64,
+}
+
+impl ErrorCounts {
+    pub const CATEGORIES: [ErrorCategory; 5] = [
+        ErrorCategory::Timeout,
+        ErrorCategory::Reset,
+        ErrorCategory::Protocol,
+        ErrorCategory::Rejected,
+        ErrorCategory::Other,
+    ];
+
+    pub fn get(&self, category: ErrorCategory) -> u64 {
+        match category {
+            ErrorCategory::Timeout => self.timeout,
+            ErrorCategory::Reset => self.reset,
+            ErrorCategory::Protocol => self.protocol,
+            ErrorCategory::Rejected => self.rejected,
+            ErrorCategory::Other => self.other,
+        }
+    }
+
+    fn add(&mut self, category: ErrorCategory) {
+        match category {
+            ErrorCategory::Timeout => self.timeout += 1,
+            ErrorCategory::Reset => self.reset += 1,
+            ErrorCategory::Protocol => self.protocol += 1,
+            ErrorCategory::Rejected => self.rejected += 1,
+            ErrorCategory::Other => self.other += 1,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for category in Self::CATEGORIES.iter() {
+            let count = self.get(*category);
+            if count > 0 {
+                write!(f, "{}{} {:?}", separator, count, category)?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcomes of requests to a peer, latencies of successes and failures are kept apart
+/// because failed requests often return fast.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Requests {
+    succeeded: u64,
+    failed: u64,
+    errors: ErrorCounts,
+    success_latencies: Window<Duration>,
+    failure_latencies: Window<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestSummary {
+    pub succeeded: u64,
+    pub failed: u64,
+    pub errors: ErrorCounts,
+    /// Fraction of all requests which succeeded
+    pub success_rate: f64,
+    pub success_latency: Option<Summary>,
+    pub failure_latency: Option<Summary>,
+}
+
+impl Requests {
+    pub(crate) fn shrink(&mut self) -> usize {
+        self.success_latencies.shrink() + self.failure_latencies.shrink()
+    }
+
+    pub(crate) fn summary(&self) -> Option<RequestSummary> {
+        let total = self.succeeded + self.failed;
+        if total == 0 {
+            return None;
+        }
+        Some(RequestSummary {
+            succeeded: self.succeeded,
+            failed: self.failed,
+            errors: self.errors,
+            success_rate: self.succeeded as f64 / total as f64,
+            success_latency: Summary::from_durations(&self.success_latencies),
+            failure_latency: Summary::from_durations(&self.failure_latencies),
+        })
+    }
+}
+
+impl Stats {
+    /// Failed requests are counted as `ErrorCategory::Other`,
+    /// use `record_request_failure` to distinguish them.
+    pub fn record_request_outcome(&self, peer_id: String, ok: bool, latency: Duration) {
+        if ok {
+            trace_span!("record_request_outcome");
+            let _recording = self.recording(&peer_id);
+            let window_size = self.window_size;
+            self.update_peer(&peer_id, |peer| {
+                peer.requests.succeeded += 1;
+                peer.requests
+                    .success_latencies
+                    .push_lossy(latency, window_size);
+            });
+        } else {
+            self.record_request_failure(peer_id, ErrorCategory::Other, latency)
+        }
+    }
+
+    pub fn record_request_failure(
+        &self,
+        peer_id: String,
+        category: ErrorCategory,
+        latency: Duration,
+    ) {
+        trace_span!("record_request_failure");
+        let _recording = self.recording(&peer_id);
+        let window_size = self.window_size;
+        self.update_peer(&peer_id, |peer| {
+            let requests = &mut peer.requests;
+            requests.failed += 1;
+            requests.errors.add(category);
+            requests.failure_latencies.push_lossy(latency, window_size);
+        });
+    }
+}
+
+#[test]
+fn failures_do_not_affect_success_latency() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.record_request_outcome("2".to_string(), true, Duration::from_millis(100));
+    stats.record_request_outcome("2".to_string(), true, Duration::from_millis(300));
+    stats.record_request_outcome("2".to_string(), false, Duration::from_millis(1));
+    stats.record_request_outcome("2".to_string(), true, Duration::from_millis(200));
+    let requests = stats.snapshot().peers[0].requests.clone().unwrap();
+    assert_eq!(requests.succeeded, 3);
+    assert_eq!(requests.failed, 1);
+    assert_eq!(requests.success_rate, 0.75);
+    assert_eq!(
+        requests.success_latency.unwrap().mean,
+        Duration::from_millis(200)
+    );
+    assert_eq!(
+        requests.failure_latency.unwrap().mean,
+        Duration::from_millis(1)
+    );
+}
+
+#[test]
+fn failures_are_counted_by_category() {
+    let stats = Stats::new(100, "1".to_string());
+    let latency = Duration::from_millis(10);
+    stats.record_request_failure("2".to_string(), ErrorCategory::Rejected, latency);
+    stats.record_request_failure("2".to_string(), ErrorCategory::Rejected, latency);
+    stats.record_request_failure("2".to_string(), ErrorCategory::Timeout, latency);
+    stats.record_request_outcome("2".to_string(), false, latency);
+    let requests = stats.snapshot().peers[0].requests.clone().unwrap();
+    assert_eq!(requests.failed, 4);
+    assert_eq!(requests.errors.get(ErrorCategory::Rejected), 2);
+    assert_eq!(
+        requests.errors.to_string(),
+        "1 Timeout, 2 Rejected, 1 Other"
+    );
+}
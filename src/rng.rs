@@ -0,0 +1,52 @@
+/// Source of random numbers for randomized components, injectable so that runs are reproducible.
+pub trait RngSource: Send {
+    fn next_u64(&mut self) -> u64;
+
+    /// Uniformly distributed number from `0.0` up to but not including `1.0`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Whether an event with `probability` from `0.0` to `1.0` happens.
+    fn happens(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.next_f64() < probability
+    }
+
+    /// Uniformly distributed number from `0` to `n - 1`, `n` must not be zero.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Deterministic xorshift64* generator, the same seed always gives the same numbers.
+#[derive(Debug, Clone)]
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        // State must never be zero
+        Self(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+impl RngSource for SeededRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+#[test]
+fn same_seed_gives_same_numbers() {
+    let numbers = |seed| {
+        let mut rng = SeededRng::new(seed);
+        (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>()
+    };
+    assert_eq!(numbers(7), numbers(7));
+    assert_ne!(numbers(7), numbers(8));
+    let mut rng = SeededRng::new(0);
+    assert!((0..100).all(|_| rng.below(3) < 3));
+    assert!(!rng.happens(0.0));
+}
@@ -0,0 +1,116 @@
+use crate::{Rate, Stats};
+use std::{fmt, time::Duration};
+
+/// Median and median absolute deviation of a window, which a single outlier barely moves
+/// unlike the mean and the standard deviation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Robust<T = Duration> {
+    pub median: T,
+    /// Median of the absolute deviations from the median
+    pub mad: T,
+}
+
+/// Median of sorted values, the mean of the middle two for an even number.
+fn median(sorted: &[f64]) -> f64 {
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+impl Robust<f64> {
+    pub(crate) fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let median = median(&sorted);
+        let mut deviations: Vec<f64> = sorted.iter().map(|value| (value - median).abs()).collect();
+        deviations.sort_by(f64::total_cmp);
+        Some(Self {
+            median,
+            mad: self::median(&deviations),
+        })
+    }
+}
+
+impl Robust {
+    pub(crate) fn from_durations(durations: &[Duration]) -> Option<Self> {
+        // Nanoseconds are exact in a float for windows of up to 104 days
+        let nanos: Vec<f64> = durations.iter().map(|d| d.as_nanos() as f64).collect();
+        let robust = Robust::from_values(&nanos)?;
+        let duration = |nanos: f64| Duration::from_nanos(nanos.round() as u64);
+        Some(Self {
+            median: duration(robust.median),
+            mad: duration(robust.mad),
+        })
+    }
+}
+
+impl Robust<Rate> {
+    pub(crate) fn from_rates(rates: &[Rate]) -> Option<Self> {
+        let values: Vec<f64> = rates.iter().map(|rate| rate.bytes_per_sec()).collect();
+        let robust = Robust::from_values(&values)?;
+        Some(Self {
+            median: Rate::from_bytes_per_sec(robust.median),
+            mad: Rate::from_bytes_per_sec(robust.mad),
+        })
+    }
+}
+
+impl fmt::Display for Robust {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "median {:?} MAD {:?}", self.median, self.mad)
+    }
+}
+
+impl Stats {
+    /// Also summarizes the ping window of each peer with its median and MAD in
+    /// `PeerSummary::ping_robust`, which sorts the window on every summary.
+    pub fn with_robust_summaries(mut self) -> Self {
+        self.robust_summaries = true;
+        self
+    }
+
+    /// Median and MAD of the ping window of the peer.
+    pub fn ping_robust(&self, peer_id: &str) -> Option<Robust> {
+        self.expire_samples(peer_id);
+        Robust::from_durations(&self.pings_to_peers.get(peer_id)?)
+    }
+
+    /// Median and MAD of the transmission rate window of the peer.
+    pub fn transmission_rate_robust(&self, peer_id: &str) -> Option<Robust<Rate>> {
+        self.expire_samples(peer_id);
+        Robust::from_rates(&self.transmissions_rates.get(peer_id)?)
+    }
+}
+
+#[test]
+fn outliers_barely_move_the_median() {
+    let stats = Stats::new(100, "1".to_string()).with_robust_summaries();
+    for millis in [20, 21, 19, 20, 22, 10_000] {
+        stats.add_ping("2".to_string(), Duration::from_millis(millis));
+    }
+    for bytes in [1_000, 3_000, 2_000] {
+        stats.add_transmission("2".to_string(), Duration::from_secs(1), bytes);
+    }
+    let robust = stats.ping_robust("2").unwrap();
+    assert_eq!(robust.median, Duration::from_micros(20_500));
+    assert_eq!(robust.mad, Duration::from_millis(1));
+    assert_eq!(robust.to_string(), "median 20.5ms MAD 1ms");
+    let peer = stats.summarize_peer("2").unwrap();
+    assert_eq!(peer.ping_robust, Some(robust));
+    assert!(peer.ping.unwrap().mean > Duration::from_secs(1));
+    assert_eq!(
+        stats.transmission_rate_robust("2"),
+        Some(Robust {
+            median: Rate::from_bytes_per_sec(2_000.0),
+            mad: Rate::from_bytes_per_sec(1_000.0),
+        })
+    );
+    assert_eq!(Stats::new(100, "1".to_string()).ping_robust("2"), None);
+}
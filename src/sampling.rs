@@ -0,0 +1,122 @@
+use crate::{RngSource, Stats};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    sync::PoisonError,
+};
+
+/// How `Stats::sample_peers` picks peers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PeerSampling {
+    /// Every peer is equally likely
+    Uniform,
+    /// Peers are grouped by their capabilities and the groups take turns,
+    /// so that rare kinds of peers are represented
+    StratifiedByCapabilities,
+    /// Peers are picked with a probability inversely proportional to their number of pings,
+    /// so that rarely measured peers catch up
+    InverseActivity,
+}
+
+impl Stats {
+    /// Replaces the generator of randomized components, by default seeded from the clock.
+    pub fn with_rng(self, rng: impl RngSource + 'static) -> Self {
+        *self.rng.lock().unwrap_or_else(PoisonError::into_inner) = Box::new(rng);
+        self
+    }
+
+    /// Picks up to `n` distinct peers among all tracked peers, e.g. for the next probing round.
+    pub fn sample_peers(&self, n: usize, sampling: PeerSampling) -> Vec<String> {
+        trace_span!("sample_peers");
+        let mut rng = self.rng.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut peer_ids = self.peer_ids();
+        match sampling {
+            PeerSampling::Uniform => {
+                shuffle(&mut peer_ids, &mut **rng);
+                peer_ids.truncate(n);
+                peer_ids
+            }
+            PeerSampling::StratifiedByCapabilities => {
+                let strata = RefCell::new(BTreeMap::<BTreeSet<String>, Vec<String>>::new());
+                self.peers.retain(|peer_id, peer| {
+                    strata
+                        .borrow_mut()
+                        .entry(peer.capabilities.clone())
+                        .or_default()
+                        .push(peer_id.clone());
+                    true
+                });
+                let mut strata: Vec<_> = strata.into_inner().into_values().collect();
+                for stratum in strata.iter_mut() {
+                    shuffle(stratum, &mut **rng);
+                    stratum.reverse();
+                }
+                let mut sample = Vec::new();
+                while sample.len() < n && strata.iter().any(|stratum| !stratum.is_empty()) {
+                    for stratum in strata.iter_mut() {
+                        if sample.len() < n {
+                            sample.extend(stratum.pop());
+                        }
+                    }
+                }
+                sample
+            }
+            PeerSampling::InverseActivity => {
+                let mut weighted: Vec<_> = peer_ids
+                    .into_iter()
+                    .map(|peer_id| {
+                        let pings = self.pings_to_peers.get(&peer_id).map_or(0, |p| p.len());
+                        (peer_id, 1.0 / (pings + 1) as f64)
+                    })
+                    .collect();
+                let mut sample = Vec::new();
+                while sample.len() < n && !weighted.is_empty() {
+                    let total: f64 = weighted.iter().map(|(_, weight)| weight).sum();
+                    let mut point = rng.next_f64() * total;
+                    let index = weighted
+                        .iter()
+                        .position(|(_, weight)| {
+                            point -= weight;
+                            point < 0.0
+                        })
+                        .unwrap_or(weighted.len() - 1);
+                    sample.push(weighted.swap_remove(index).0);
+                }
+                sample
+            }
+        }
+    }
+}
+
+/// Fisher-Yates shuffle.
+fn shuffle<T>(items: &mut [T], rng: &mut dyn RngSource) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, rng.below(i + 1));
+    }
+}
+
+#[test]
+fn peers_are_sampled_by_strategy() {
+    use crate::SeededRng;
+    use std::time::Duration;
+
+    let stats = Stats::new(100, "1".to_string()).with_rng(SeededRng::new(1));
+    for peer in 2..12 {
+        stats.add_ping(peer.to_string(), Duration::from_millis(10));
+    }
+    for _ in 0..1_000 {
+        stats.add_ping("chatty".to_string(), Duration::from_millis(10));
+    }
+    stats.set_capabilities("11".to_string(), vec!["relay"]);
+    let uniform = stats.sample_peers(4, PeerSampling::Uniform);
+    assert_eq!(uniform.len(), 4);
+    assert_eq!(uniform.iter().collect::<BTreeSet<_>>().len(), 4);
+    assert_eq!(stats.sample_peers(20, PeerSampling::Uniform).len(), 11);
+    let stratified = stats.sample_peers(2, PeerSampling::StratifiedByCapabilities);
+    assert!(stratified.contains(&"11".to_string()));
+    // With uniform sampling it would be picked about 18 times
+    let chatty = (0..200)
+        .filter(|_| stats.sample_peers(1, PeerSampling::InverseActivity) == vec!["chatty"])
+        .count();
+    assert!(chatty < 5);
+}
@@ -0,0 +1,86 @@
+use crate::{durations_mean, durations_std_dev, Stats};
+use std::time::Duration;
+
+impl Stats {
+    /// Bounds of `suggested_probe_interval`, 1 second to 5 minutes by default.
+    pub fn with_probe_interval_bounds(mut self, min: Duration, max: Duration) -> Self {
+        self.probe_interval_bounds = (min, max);
+        self
+    }
+
+    /// Interval until the next ping of the peer, so that the prober spends its probes
+    /// on the peers whose estimates need them.
+    ///
+    /// The interval shrinks from the maximum towards the minimum with the coefficient of
+    /// variation of the ping window, reaching the minimum at a standard deviation as large
+    /// as the mean. Peers with fewer than two pings, or not seen for longer than the interval,
+    /// get the minimum.
+    pub fn suggested_probe_interval(&self, peer_id: &str) -> Duration {
+        let (min, max) = self.probe_interval_bounds;
+        let max = max.max(min);
+        self.expire_samples(peer_id);
+        let pings = match self.pings_to_peers.get(peer_id) {
+            Some(pings) if pings.len() >= 2 => pings.clone(),
+            _ => return min,
+        };
+        let variation = match (durations_mean(&pings), durations_std_dev(&pings)) {
+            (Some(mean), Some(std_dev)) if !mean.is_zero() => {
+                (std_dev.as_secs_f64() / mean.as_secs_f64()).min(1.0)
+            }
+            _ => 1.0,
+        };
+        let interval = min + (max - min).mul_f64(1.0 - variation);
+        let stale = self
+            .last_seen(peer_id)
+            .and_then(|last_seen| self.clock.now().duration_since(last_seen).ok())
+            .is_some_and(|elapsed| elapsed > interval);
+        if stale {
+            min
+        } else {
+            interval
+        }
+    }
+}
+
+#[test]
+fn volatile_peers_are_probed_more_often() {
+    use crate::ManualClock;
+    use std::{sync::Arc, time::SystemTime};
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock.clone())
+        .with_probe_interval_bounds(Duration::from_secs(1), Duration::from_secs(101));
+    for millis in &[50, 50, 50, 50] {
+        stats.add_ping("stable".to_string(), Duration::from_millis(*millis));
+    }
+    for millis in &[10, 90, 10, 90] {
+        stats.add_ping("volatile".to_string(), Duration::from_millis(*millis));
+    }
+    stats.add_ping("new".to_string(), Duration::from_millis(50));
+    assert_eq!(
+        stats.suggested_probe_interval("stable"),
+        Duration::from_secs(101)
+    );
+    assert_eq!(
+        stats.suggested_probe_interval("volatile"),
+        Duration::from_secs(21)
+    );
+    assert_eq!(
+        stats.suggested_probe_interval("new"),
+        Duration::from_secs(1)
+    );
+    assert_eq!(
+        stats.suggested_probe_interval("unknown"),
+        Duration::from_secs(1)
+    );
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(
+        stats.suggested_probe_interval("volatile"),
+        Duration::from_secs(1)
+    );
+    assert_eq!(
+        stats.suggested_probe_interval("stable"),
+        Duration::from_secs(101)
+    );
+}
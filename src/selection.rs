@@ -0,0 +1,129 @@
+use crate::{Metric, Rate, Rfc3339, Score, Stats};
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+/// Inputs of peer selection for one peer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelectionInput {
+    pub peer_id: String,
+    pub last_seen: Option<SystemTime>,
+    pub ping: Option<Duration>,
+    pub ping_error: Option<Duration>,
+    pub ping_score: Option<Score>,
+    /// Ping mean adjusted by the prior, see `Stats::estimate`
+    pub ping_estimate: Option<Duration>,
+    pub transmission_rate: Option<Rate>,
+    pub transmission_rate_score: Option<Score>,
+    pub success_rate: Option<f64>,
+}
+
+/// Everything a peer selection decision can be based on at one instant, small enough
+/// to be logged with the decision.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelectionSnapshot {
+    pub time: SystemTime,
+    /// Ordered by peer id
+    pub peers: Vec<SelectionInput>,
+}
+
+impl SelectionSnapshot {
+    pub fn get(&self, peer_id: &str) -> Option<&SelectionInput> {
+        self.peers.iter().find(|peer| peer.peer_id == peer_id)
+    }
+}
+
+impl fmt::Display for SelectionInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.peer_id)?;
+        if let (Some(ping), Some(error)) = (self.ping, self.ping_error) {
+            write!(f, " ping {:?}±{:?}", ping, error)?;
+        }
+        if let Some(score) = self.ping_score {
+            write!(f, " z {:.2}", score.z_score)?;
+        }
+        if let Some(estimate) = self.ping_estimate {
+            write!(f, " estimate {:?}", estimate)?;
+        }
+        if let Some(rate) = self.transmission_rate {
+            write!(f, " rate {}", rate)?;
+        }
+        if let Some(score) = self.transmission_rate_score {
+            write!(f, " z {:.2}", score.z_score)?;
+        }
+        if let Some(success_rate) = self.success_rate {
+            write!(f, " success {:.1}%", success_rate * 100.0)?;
+        }
+        if let Some(last_seen) = self.last_seen {
+            write!(f, " seen {}", Rfc3339(last_seen))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SelectionSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "selection at {}", Rfc3339(self.time))?;
+        for peer in &self.peers {
+            write!(f, "; {}", peer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Captures the scores, summaries and timestamps of all peers for a selection decision.
+    pub fn selection_snapshot(&self) -> SelectionSnapshot {
+        trace_span!("selection_snapshot");
+        let snapshot = self.snapshot();
+        let peers = snapshot
+            .peers
+            .into_iter()
+            .map(|peer| {
+                let ping = peer.ping.as_ref();
+                let rate = peer.transmission_rate.as_ref();
+                SelectionInput {
+                    ping_estimate: self.estimate(&peer.peer_id, Metric::Ping),
+                    last_seen: peer.last_seen,
+                    ping: ping.map(|ping| ping.mean),
+                    ping_error: ping.map(|ping| ping.error),
+                    ping_score: ping.and_then(|ping| ping.score),
+                    transmission_rate: rate.map(|rate| rate.mean),
+                    transmission_rate_score: rate.and_then(|rate| rate.score),
+                    success_rate: peer.requests.as_ref().map(|requests| requests.success_rate),
+                    peer_id: peer.peer_id,
+                }
+            })
+            .collect();
+        SelectionSnapshot {
+            time: snapshot.time.unwrap_or_else(|| self.clock.now()),
+            peers,
+        }
+    }
+}
+
+#[test]
+fn selection_snapshot_captures_scores() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock);
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("3".to_string(), Duration::from_millis(30));
+    stats.record_request_outcome("3".to_string(), true, Duration::from_millis(5));
+    let selection = stats.selection_snapshot();
+    let peer = selection.get("3").unwrap();
+    assert_eq!(peer.ping, Some(Duration::from_millis(30)));
+    assert_eq!(peer.ping_score.unwrap().percentile_rank, 0.75);
+    assert_eq!(peer.success_rate, Some(1.0));
+    assert_eq!(
+        selection.to_string(),
+        "selection at 1970-01-01T00:00:00Z; \
+         \"2\" ping 10ms±0ns z -1.00 estimate 10ms seen 1970-01-01T00:00:00Z; \
+         \"3\" ping 30ms±0ns z 1.00 estimate 30ms success 100.0% seen 1970-01-01T00:00:00Z"
+    );
+}
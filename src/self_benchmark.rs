@@ -0,0 +1,145 @@
+use crate::Stats;
+use std::{
+    fmt,
+    sync::PoisonError,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Load of `Stats::self_benchmark`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfBenchmark {
+    /// Threads recording at the same time
+    pub threads: usize,
+    pub peers: usize,
+    /// Pings recorded by each thread
+    pub samples: usize,
+    /// Snapshots taken to measure query latency
+    pub snapshots: usize,
+}
+
+impl Default for SelfBenchmark {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            peers: 1_000,
+            samples: 100_000,
+            snapshots: 10,
+        }
+    }
+}
+
+/// Throughput and latencies measured by `Stats::self_benchmark` on this machine.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfBenchmarkReport {
+    /// Pings recorded per second by all threads together
+    pub samples_per_sec: f64,
+    /// Mean time of `Stats::snapshot` with all peers recorded
+    pub snapshot_latency: Duration,
+    /// Mean time of `Stats::peer_summary` of a single peer
+    pub peer_summary_latency: Duration,
+}
+
+impl fmt::Display for SelfBenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.0} samples/s, snapshot {:?}, peer summary {:?}",
+            self.samples_per_sec, self.snapshot_latency, self.peer_summary_latency
+        )
+    }
+}
+
+impl Stats {
+    /// Measures the load this configuration can take on this machine with synthetic pings.
+    /// They are recorded into an empty `Stats` with the same settings, not into this one.
+    pub fn self_benchmark(&self, benchmark: SelfBenchmark) -> SelfBenchmarkReport {
+        let stats = self.with_settings_of();
+        let peers = benchmark.peers.max(1);
+        let started = Instant::now();
+        thread::scope(|scope| {
+            for thread in 0..benchmark.threads {
+                let stats = &stats;
+                scope.spawn(move || {
+                    for sample in 0..benchmark.samples {
+                        let peer_id = ((thread + sample) % peers).to_string();
+                        let rtt = Duration::from_micros(1_000 + (sample % 997) as u64);
+                        stats.add_ping(peer_id, rtt);
+                    }
+                });
+            }
+        });
+        let recording = started.elapsed().as_secs_f64();
+        let samples = (benchmark.threads * benchmark.samples) as f64;
+        let snapshots = benchmark.snapshots.max(1);
+        let started = Instant::now();
+        for _ in 0..snapshots {
+            stats.snapshot();
+        }
+        let snapshot_latency = started.elapsed() / snapshots as u32;
+        let started = Instant::now();
+        for peer in 0..peers {
+            stats.peer_summary(&peer.to_string());
+        }
+        SelfBenchmarkReport {
+            samples_per_sec: if recording > 0.0 {
+                samples / recording
+            } else {
+                f64::INFINITY
+            },
+            snapshot_latency,
+            peer_summary_latency: started.elapsed() / peers as u32,
+        }
+    }
+
+    /// Empty `Stats` with the same settings and clock.
+    fn with_settings_of(&self) -> Stats {
+        let mut stats = Stats::new(self.window_size, self.peer_id.clone())
+            .with_clock(self.clock.clone())
+            .with_session_history(self.session_history)
+            .with_keep_alive_threshold(self.keep_alive_threshold)
+            .with_incident_threshold(self.incident_threshold)
+            .with_warm_up(self.warm_up_samples);
+        stats.priors = self.priors.clone();
+        stats.decay = self.decay;
+        stats.no_pings_for = self.no_pings_for;
+        stats.probe_interval_bounds = self.probe_interval_bounds;
+        stats.bounds = self.bounds;
+        stats.outlier_policy = self.outlier_policy;
+        stats.ping_histograms = self.ping_histograms;
+        stats.window_views = self.window_views.clone();
+        stats.max_sample_age = self.max_sample_age;
+        stats.ewma_alpha = self.ewma_alpha;
+        stats.streaming = self.streaming;
+        stats.lite = self.lite;
+        stats.epochs = self.epochs;
+        stats.adaptive_windows = self.adaptive_windows;
+        stats.correlation_bucket = self.correlation_bucket;
+        stats.confidence = self.confidence;
+        stats.memory_probe = self.memory_probe.clone();
+        stats.robust_summaries = self.robust_summaries;
+        let derived = self.derived.lock().unwrap_or_else(PoisonError::into_inner);
+        stats.derived = derived.clone().into();
+        #[cfg(feature = "hdr")]
+        {
+            stats.hdr_significant_digits = self.hdr_significant_digits;
+        }
+        // Caching would hide the cost of the snapshots
+        stats.snapshot_max_age = None;
+        stats
+    }
+}
+
+#[test]
+fn self_benchmark_leaves_stats_untouched() {
+    let stats = Stats::new(10, "1".to_string());
+    let report = stats.self_benchmark(SelfBenchmark {
+        threads: 2,
+        peers: 10,
+        samples: 1_000,
+        snapshots: 2,
+    });
+    assert!(report.samples_per_sec > 0.0);
+    assert!(report.snapshot_latency > Duration::from_secs(0));
+    assert_eq!(stats.snapshot().peers.len(), 0);
+}
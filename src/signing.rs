@@ -0,0 +1,123 @@
+use crate::{Encoding, Stats, StatsSnapshot};
+use std::io;
+
+/// Signs digests with the keypair of the node.
+pub trait Signer {
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+/// Checks that a signature was produced by the node `peer_id`.
+pub trait Verifier {
+    fn verify(&self, peer_id: &str, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// Encoded snapshot together with the signature of the node which produced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedDigest {
+    pub peer_id: String,
+    pub encoding: Encoding,
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SignedDigest {
+    pub fn new<S: Signer>(
+        snapshot: &StatsSnapshot,
+        encoding: Encoding,
+        signer: &S,
+    ) -> io::Result<Self> {
+        let mut payload = Vec::new();
+        snapshot.encode(&mut payload, encoding)?;
+        Ok(Self {
+            peer_id: snapshot.peer_id.clone(),
+            encoding,
+            signature: signer.sign(&payload),
+            payload,
+        })
+    }
+
+    /// Returns the payload only if it was signed by `peer_id`.
+    pub fn verified_payload<V: Verifier>(&self, verifier: &V) -> Option<&[u8]> {
+        if verifier.verify(&self.peer_id, &self.payload, &self.signature) {
+            Some(&self.payload)
+        } else {
+            None
+        }
+    }
+
+    /// Verifies the signature and decodes the snapshot,
+    /// which also has to be produced by the signing node.
+    pub fn verify<V: Verifier>(&self, verifier: &V) -> io::Result<StatsSnapshot> {
+        let payload = self.verified_payload(verifier).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "invalid digest signature")
+        })?;
+        let snapshot = StatsSnapshot::decode(payload, self.encoding)?;
+        if snapshot.peer_id != self.peer_id {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "digest was signed by another node",
+            ));
+        }
+        Ok(snapshot)
+    }
+}
+
+impl Stats {
+    pub fn signed_snapshot<S: Signer>(
+        &self,
+        encoding: Encoding,
+        signer: &S,
+    ) -> io::Result<SignedDigest> {
+        SignedDigest::new(&self.snapshot(), encoding, signer)
+    }
+}
+
+/// Signature is the sum of the message bytes and the key, which is the node id.
+#[cfg(test)]
+struct ChecksumKey(u8);
+
+#[cfg(test)]
+impl Signer for ChecksumKey {
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        vec![message.iter().fold(self.0, |acc, x| acc.wrapping_add(*x))]
+    }
+}
+
+#[cfg(test)]
+struct ChecksumKeys;
+
+#[cfg(test)]
+impl Verifier for ChecksumKeys {
+    fn verify(&self, peer_id: &str, message: &[u8], signature: &[u8]) -> bool {
+        match peer_id.parse() {
+            Ok(key) => ChecksumKey(key).sign(message) == signature,
+            Err(_) => false,
+        }
+    }
+}
+
+#[test]
+fn tampered_digest_is_rejected() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), std::time::Duration::from_millis(10));
+    let mut digest = stats
+        .signed_snapshot(Encoding::Text, &ChecksumKey(1))
+        .unwrap();
+    assert!(digest.verified_payload(&ChecksumKeys).is_some());
+    digest.payload[0] ^= 1;
+    assert!(digest.verified_payload(&ChecksumKeys).is_none());
+}
+
+#[cfg(any(feature = "cbor", feature = "msgpack", feature = "protobuf"))]
+#[test]
+fn digest_signed_by_another_node_is_rejected() {
+    let encoding = crate::encoding::binary_encodings()[0];
+    let clock = std::sync::Arc::new(crate::ManualClock::new(std::time::SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock);
+    let digest = stats.signed_snapshot(encoding, &ChecksumKey(1)).unwrap();
+    assert_eq!(digest.verify(&ChecksumKeys).unwrap(), stats.snapshot());
+
+    let mut forged = stats.signed_snapshot(encoding, &ChecksumKey(2)).unwrap();
+    forged.peer_id = "2".to_string();
+    assert!(forged.verify(&ChecksumKeys).is_err());
+}
@@ -0,0 +1,186 @@
+//! In-process network of virtual nodes for end-to-end testing.
+//!
+//! Every node records pings to all other nodes according to a latency matrix
+//! and pushes its snapshot to a shared `Collector`, all driven by one `ManualClock`.
+//! `Faults` inject misbehavior into any of these steps.
+
+use crate::{Clock, Collector, ManualClock, RngSource, SeededRng, Stats};
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// Probabilities from `0.0` to `1.0` of faults happening, all disabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    /// A pushed snapshot is lost
+    pub drop_export: f64,
+    /// The clock jumps forwards or backwards by `clock_jump_by` at a step
+    pub clock_jump: f64,
+    pub clock_jump_by: Duration,
+    /// A ping is recorded under the id of another random node
+    pub peer_id_collision: f64,
+    /// A ping is recorded as zero or as 68 years
+    pub extreme_value: f64,
+    /// Seed of the `SeededRng` deciding when faults happen, replaced by `Simulation::with_rng`
+    pub seed: u64,
+}
+
+pub struct Simulation {
+    pub clock: Arc<ManualClock>,
+    pub nodes: Vec<Stats>,
+    pub collector: Collector,
+    /// Snapshots lost because of `Faults::drop_export`
+    pub dropped_exports: usize,
+    rtts: Vec<Vec<Duration>>,
+    faults: Faults,
+    rng: Box<dyn RngSource>,
+}
+
+impl Simulation {
+    /// Creates a node for each row of `rtts`, where `rtts[i][j]` is the round trip time
+    /// from node `i` to node `j`. Node ids are their indices.
+    pub fn new(rtts: Vec<Vec<Duration>>, window_size: usize) -> Self {
+        let clock = Arc::new(ManualClock::new(UNIX_EPOCH));
+        let nodes = (0..rtts.len())
+            .map(|node| Stats::new(window_size, node.to_string()).with_clock(clock.clone()))
+            .collect();
+        Self {
+            clock,
+            nodes,
+            collector: Collector::new(),
+            dropped_exports: 0,
+            rtts,
+            faults: Faults::default(),
+            rng: Box::new(SeededRng::new(0)),
+        }
+    }
+
+    pub fn with_faults(mut self, faults: Faults) -> Self {
+        self.rng = Box::new(SeededRng::new(faults.seed));
+        self.faults = faults;
+        self
+    }
+
+    /// Replaces the generator deciding when faults happen.
+    pub fn with_rng(mut self, rng: impl RngSource + 'static) -> Self {
+        self.rng = Box::new(rng);
+        self
+    }
+
+    /// Advances the clock by `interval` and pings every pair of nodes once.
+    pub fn step(&mut self, interval: Duration) {
+        self.clock.advance(interval);
+        if self.rng.happens(self.faults.clock_jump) {
+            if self.rng.happens(0.5) {
+                self.clock.advance(self.faults.clock_jump_by);
+            } else {
+                let now = self.clock.now();
+                self.clock
+                    .set(now.checked_sub(self.faults.clock_jump_by).unwrap_or(now));
+            }
+        }
+        for from in 0..self.nodes.len() {
+            for to in 0..self.nodes.len() {
+                if from == to {
+                    continue;
+                }
+                let mut peer = to;
+                if self.rng.happens(self.faults.peer_id_collision) {
+                    peer = self.rng.below(self.nodes.len());
+                }
+                let mut rtt = self.rtts[from][to];
+                if self.rng.happens(self.faults.extreme_value) {
+                    rtt = if self.rng.happens(0.5) {
+                        Duration::from_secs(0)
+                    } else {
+                        Duration::from_secs(i32::MAX as u64)
+                    };
+                }
+                self.nodes[from].add_ping(peer.to_string(), rtt);
+            }
+        }
+    }
+
+    /// Pushes snapshots of all nodes to the collector.
+    pub fn push_snapshots(&mut self) {
+        for stats in &self.nodes {
+            if self.rng.happens(self.faults.drop_export) {
+                self.dropped_exports += 1;
+            } else {
+                self.collector.ingest(stats.snapshot());
+            }
+        }
+    }
+
+    pub fn run(&mut self, steps: usize, interval: Duration) {
+        for _ in 0..steps {
+            self.step(interval);
+        }
+        self.push_snapshots();
+    }
+
+    /// Report of the collector.
+    pub fn report(&self) -> String {
+        self.collector.to_string()
+    }
+}
+
+#[cfg(test)]
+fn triangle() -> Vec<Vec<Duration>> {
+    let millis = Duration::from_millis;
+    vec![
+        vec![millis(0), millis(10), millis(20)],
+        vec![millis(10), millis(0), millis(30)],
+        vec![millis(20), millis(30), millis(0)],
+    ]
+}
+
+#[test]
+fn collector_sees_link_latencies() {
+    let mut sim = Simulation::new(triangle(), 100);
+    sim.run(5, Duration::from_secs(1));
+    let link = sim.collector.link("1", "2").unwrap();
+    assert_eq!(link.ping.as_ref().unwrap().mean, Duration::from_millis(30));
+    assert_eq!(link.ping.as_ref().unwrap().samples, 5);
+    assert_eq!(sim.collector.peer_view("0").count(), 2);
+    assert!(sim.report().contains("\"2\" 20ms"));
+}
+
+#[test]
+fn injected_faults_are_survived() {
+    let mut sim = Simulation::new(triangle(), 100).with_faults(Faults {
+        drop_export: 0.5,
+        clock_jump: 0.5,
+        clock_jump_by: Duration::from_secs(3600),
+        peer_id_collision: 0.2,
+        extreme_value: 0.2,
+        seed: 7,
+    });
+    for _ in 0..10 {
+        sim.run(10, Duration::from_secs(1));
+    }
+    assert!(sim.dropped_exports > 0);
+    assert_eq!(sim.collector.snapshots().count(), 3);
+    let extreme = Duration::from_secs(i32::MAX as u64);
+    assert!(sim
+        .collector
+        .snapshots()
+        .flat_map(|snapshot| snapshot.peers.iter())
+        .any(|peer| peer.ping.as_ref().unwrap().mean > extreme / 100));
+}
+
+#[test]
+fn faults_are_reproducible() {
+    let run = || {
+        let mut sim = Simulation::new(triangle(), 100).with_faults(Faults {
+            drop_export: 0.5,
+            seed: 3,
+            ..Faults::default()
+        });
+        sim.run(1, Duration::from_secs(1));
+        sim.run(1, Duration::from_secs(1));
+        sim.dropped_exports
+    };
+    assert_eq!(run(), run());
+}
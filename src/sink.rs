@@ -0,0 +1,284 @@
+use crate::{ByteSize, DisconnectReason, Recorder, Stats};
+use std::{
+    mem,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+/// Recording surface of `Stats`, for instrumentation which takes `&dyn StatsSink` so that
+/// its tests can pass a `MockSink` instead of real stats.
+pub trait StatsSink: Send + Sync {
+    fn add_ping(&self, peer_id: String, rtt: Duration);
+
+    fn add_ping_failure(&self, peer_id: String);
+
+    fn add_transmission(&self, peer_id: String, time: Duration, n_bytes: ByteSize);
+
+    fn record_gauge(&self, peer_id: String, name: &str, value: f64);
+
+    fn record_request_outcome(&self, peer_id: String, ok: bool, latency: Duration);
+
+    fn record_connected(&self, peer_id: String);
+
+    fn record_disconnected(&self, peer_id: String, reason: DisconnectReason);
+}
+
+impl StatsSink for Stats {
+    fn add_ping(&self, peer_id: String, rtt: Duration) {
+        Stats::add_ping(self, peer_id, rtt)
+    }
+
+    fn add_ping_failure(&self, peer_id: String) {
+        Stats::add_ping_failure(self, peer_id)
+    }
+
+    fn add_transmission(&self, peer_id: String, time: Duration, n_bytes: ByteSize) {
+        Stats::add_transmission(self, peer_id, time, n_bytes)
+    }
+
+    fn record_gauge(&self, peer_id: String, name: &str, value: f64) {
+        Stats::record_gauge(self, peer_id, name, value)
+    }
+
+    fn record_request_outcome(&self, peer_id: String, ok: bool, latency: Duration) {
+        Stats::record_request_outcome(self, peer_id, ok, latency)
+    }
+
+    fn record_connected(&self, peer_id: String) {
+        Stats::record_connected(self, peer_id)
+    }
+
+    fn record_disconnected(&self, peer_id: String, reason: DisconnectReason) {
+        Stats::record_disconnected(self, peer_id, reason)
+    }
+}
+
+impl StatsSink for Recorder {
+    fn add_ping(&self, peer_id: String, rtt: Duration) {
+        Recorder::add_ping(self, peer_id, rtt)
+    }
+
+    fn add_ping_failure(&self, peer_id: String) {
+        Recorder::add_ping_failure(self, peer_id)
+    }
+
+    fn add_transmission(&self, peer_id: String, time: Duration, n_bytes: ByteSize) {
+        Recorder::add_transmission(self, peer_id, time, n_bytes)
+    }
+
+    fn record_gauge(&self, peer_id: String, name: &str, value: f64) {
+        Recorder::record_gauge(self, peer_id, name, value)
+    }
+
+    fn record_request_outcome(&self, peer_id: String, ok: bool, latency: Duration) {
+        Recorder::record_request_outcome(self, peer_id, ok, latency)
+    }
+
+    fn record_connected(&self, peer_id: String) {
+        Recorder::record_connected(self, peer_id)
+    }
+
+    fn record_disconnected(&self, peer_id: String, reason: DisconnectReason) {
+        Recorder::record_disconnected(self, peer_id, reason)
+    }
+}
+
+impl<S: StatsSink + ?Sized> StatsSink for Arc<S> {
+    fn add_ping(&self, peer_id: String, rtt: Duration) {
+        (**self).add_ping(peer_id, rtt)
+    }
+
+    fn add_ping_failure(&self, peer_id: String) {
+        (**self).add_ping_failure(peer_id)
+    }
+
+    fn add_transmission(&self, peer_id: String, time: Duration, n_bytes: ByteSize) {
+        (**self).add_transmission(peer_id, time, n_bytes)
+    }
+
+    fn record_gauge(&self, peer_id: String, name: &str, value: f64) {
+        (**self).record_gauge(peer_id, name, value)
+    }
+
+    fn record_request_outcome(&self, peer_id: String, ok: bool, latency: Duration) {
+        (**self).record_request_outcome(peer_id, ok, latency)
+    }
+
+    fn record_connected(&self, peer_id: String) {
+        (**self).record_connected(peer_id)
+    }
+
+    fn record_disconnected(&self, peer_id: String, reason: DisconnectReason) {
+        (**self).record_disconnected(peer_id, reason)
+    }
+}
+
+/// Sink which drops every sample, e.g. to turn off instrumentation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopSink;
+
+impl StatsSink for NoopSink {
+    fn add_ping(&self, _: String, _: Duration) {}
+
+    fn add_ping_failure(&self, _: String) {}
+
+    fn add_transmission(&self, _: String, _: Duration, _: ByteSize) {}
+
+    fn record_gauge(&self, _: String, _: &str, _: f64) {}
+
+    fn record_request_outcome(&self, _: String, _: bool, _: Duration) {}
+
+    fn record_connected(&self, _: String) {}
+
+    fn record_disconnected(&self, _: String, _: DisconnectReason) {}
+}
+
+/// Call of a `StatsSink` method seen by a `MockSink`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SinkCall {
+    Ping {
+        peer_id: String,
+        rtt: Duration,
+    },
+    PingFailure {
+        peer_id: String,
+    },
+    Transmission {
+        peer_id: String,
+        time: Duration,
+        n_bytes: ByteSize,
+    },
+    Gauge {
+        peer_id: String,
+        name: String,
+        value: f64,
+    },
+    RequestOutcome {
+        peer_id: String,
+        ok: bool,
+        latency: Duration,
+    },
+    Connected {
+        peer_id: String,
+    },
+    Disconnected {
+        peer_id: String,
+        reason: DisconnectReason,
+    },
+}
+
+/// Sink which keeps its calls in order, for assertions in tests of instrumentation.
+#[derive(Debug, Default)]
+pub struct MockSink {
+    calls: Mutex<Vec<SinkCall>>,
+}
+
+impl MockSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls so far, oldest first.
+    pub fn calls(&self) -> Vec<SinkCall> {
+        self.lock().clone()
+    }
+
+    /// Removes and returns the calls so far, e.g. between the steps of a test.
+    pub fn take(&self) -> Vec<SinkCall> {
+        mem::take(&mut *self.lock())
+    }
+
+    /// Round trip times recorded for the peer, oldest first.
+    pub fn pings(&self, peer_id: &str) -> Vec<Duration> {
+        self.lock()
+            .iter()
+            .filter_map(|call| match call {
+                SinkCall::Ping { peer_id: id, rtt } if id == peer_id => Some(*rtt),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn push(&self, call: SinkCall) {
+        self.lock().push(call);
+    }
+
+    // A panicking test thread does not leave the calls inconsistent
+    fn lock(&self) -> MutexGuard<'_, Vec<SinkCall>> {
+        self.calls.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl StatsSink for MockSink {
+    fn add_ping(&self, peer_id: String, rtt: Duration) {
+        self.push(SinkCall::Ping { peer_id, rtt })
+    }
+
+    fn add_ping_failure(&self, peer_id: String) {
+        self.push(SinkCall::PingFailure { peer_id })
+    }
+
+    fn add_transmission(&self, peer_id: String, time: Duration, n_bytes: ByteSize) {
+        self.push(SinkCall::Transmission {
+            peer_id,
+            time,
+            n_bytes,
+        })
+    }
+
+    fn record_gauge(&self, peer_id: String, name: &str, value: f64) {
+        self.push(SinkCall::Gauge {
+            peer_id,
+            name: name.to_string(),
+            value,
+        })
+    }
+
+    fn record_request_outcome(&self, peer_id: String, ok: bool, latency: Duration) {
+        self.push(SinkCall::RequestOutcome {
+            peer_id,
+            ok,
+            latency,
+        })
+    }
+
+    fn record_connected(&self, peer_id: String) {
+        self.push(SinkCall::Connected { peer_id })
+    }
+
+    fn record_disconnected(&self, peer_id: String, reason: DisconnectReason) {
+        self.push(SinkCall::Disconnected { peer_id, reason })
+    }
+}
+
+#[test]
+fn sinks_receive_the_same_calls() {
+    fn instrument(sink: &dyn StatsSink) {
+        sink.record_connected("2".to_string());
+        sink.add_ping("2".to_string(), Duration::from_millis(10));
+        sink.add_transmission("2".to_string(), Duration::from_secs(1), ByteSize(1_000));
+        sink.record_disconnected("2".to_string(), DisconnectReason::Idle);
+    }
+
+    let mock = MockSink::new();
+    instrument(&mock);
+    assert_eq!(mock.pings("2"), vec![Duration::from_millis(10)]);
+    let calls = mock.take();
+    assert_eq!(calls.len(), 4);
+    assert_eq!(
+        calls[2],
+        SinkCall::Transmission {
+            peer_id: "2".to_string(),
+            time: Duration::from_secs(1),
+            n_bytes: ByteSize(1_000),
+        }
+    );
+    assert!(mock.calls().is_empty());
+
+    let stats = Arc::new(Stats::new(100, "1".to_string()));
+    instrument(&stats);
+    instrument(&NoopSink);
+    let peer = stats.summarize_peer("2").unwrap();
+    assert_eq!(peer.ping.unwrap().samples, 1);
+    assert_eq!(peer.sessions.len(), 1);
+}
@@ -0,0 +1,199 @@
+use crate::{durations_percentile, Stats};
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+/// Latency objective of `Stats::check_sla`: the ping at `quantile` stays below `threshold`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sla {
+    /// E.g. `0.99` for the tail round trip time
+    pub quantile: f64,
+    pub threshold: Duration,
+    /// How far ahead breaches are projected
+    pub horizon: Duration,
+}
+
+impl Sla {
+    pub fn new(quantile: f64, threshold: Duration, horizon: Duration) -> Self {
+        Self {
+            quantile,
+            threshold,
+            horizon,
+        }
+    }
+}
+
+/// Ping percentile of a window extrapolated along the trend of the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    /// Ping at the quantile of the SLA
+    pub current: Duration,
+    /// Least squares slope of the pings over their recording times, seconds per second
+    pub trend: f64,
+    /// Percentile at the end of the horizon if the trend holds
+    pub projected: Duration,
+    /// Time until the percentile crosses the threshold, zero if it already did and
+    /// `None` if it does not within the horizon
+    pub breach_in: Option<Duration>,
+}
+
+impl Projection {
+    fn new(times: &[SystemTime], pings: &[Duration], sla: &Sla) -> Option<Self> {
+        let current = durations_percentile(pings, sla.quantile)?;
+        let trend = trend(times, pings);
+        let projected = (current.as_secs_f64() + trend * sla.horizon.as_secs_f64()).max(0.0);
+        let breach_in = if current >= sla.threshold {
+            Some(Duration::from_secs(0))
+        } else if trend > 0.0 {
+            let breach_in = (sla.threshold - current).as_secs_f64() / trend;
+            Duration::try_from_secs_f64(breach_in)
+                .ok()
+                .filter(|breach_in| *breach_in <= sla.horizon)
+        } else {
+            None
+        };
+        Some(Self {
+            current,
+            trend,
+            projected: Duration::try_from_secs_f64(projected).unwrap_or(Duration::MAX),
+            breach_in,
+        })
+    }
+}
+
+/// Early warning of `Stats::check_sla` for a peer projected to breach the SLA.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlaWarning {
+    pub peer_id: String,
+    pub projection: Projection,
+}
+
+impl fmt::Display for SlaWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let projection = &self.projection;
+        write!(
+            f,
+            "{:?} at {:?} projected to {:?}, breach in {:?}",
+            self.peer_id,
+            projection.current,
+            projection.projected,
+            projection.breach_in.unwrap_or_default()
+        )
+    }
+}
+
+/// Least squares slope of `pings` over `times`, zero without a time span.
+fn trend(times: &[SystemTime], pings: &[Duration]) -> f64 {
+    let first = match times.iter().min() {
+        Some(first) => *first,
+        None => return 0.0,
+    };
+    let points: Vec<(f64, f64)> = times
+        .iter()
+        .zip(pings)
+        .map(|(time, ping)| {
+            let since = time.duration_since(first).unwrap_or_default();
+            (since.as_secs_f64(), ping.as_secs_f64())
+        })
+        .collect();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance
+    }
+}
+
+impl Stats {
+    /// Projection of the ping percentile of the peer's window over the horizon of `sla`.
+    pub fn project_ping(&self, peer_id: &str, sla: &Sla) -> Option<Projection> {
+        self.expire_samples(peer_id);
+        let pings = self.pings_to_peers.get(peer_id)?;
+        let (times, pings) = pings.between(SystemTime::UNIX_EPOCH, None);
+        Projection::new(times, pings, sla)
+    }
+
+    /// Projection of the windows of all peers in `cohort` among `n_buckets` together,
+    /// e.g. of the peers of a staged rollout.
+    pub fn project_cohort_ping(
+        &self,
+        cohort: u32,
+        n_buckets: u32,
+        sla: &Sla,
+    ) -> Option<Projection> {
+        let mut times = Vec::new();
+        let mut pings = Vec::new();
+        for peer_id in self.peer_ids() {
+            if crate::cohort(&peer_id, n_buckets) != cohort {
+                continue;
+            }
+            self.expire_samples(&peer_id);
+            if let Some(window) = self.pings_to_peers.get(&peer_id) {
+                let (window_times, window_pings) = window.between(SystemTime::UNIX_EPOCH, None);
+                times.extend_from_slice(window_times);
+                pings.extend_from_slice(window_pings);
+            }
+        }
+        Projection::new(&times, &pings, sla)
+    }
+
+    /// Warnings for the peers whose ping percentile breaches `sla` or is projected to
+    /// within its horizon, soonest first, e.g. checked on a timer to act before hard
+    /// alerts on the percentile fire.
+    pub fn check_sla(&self, sla: &Sla) -> Vec<SlaWarning> {
+        let mut warnings: Vec<_> = self
+            .peer_ids()
+            .into_iter()
+            .filter_map(|peer_id| {
+                let projection = self.project_ping(&peer_id, sla)?;
+                projection.breach_in?;
+                Some(SlaWarning {
+                    peer_id,
+                    projection,
+                })
+            })
+            .collect();
+        warnings.sort_by_key(|warning| warning.projection.breach_in);
+        warnings
+    }
+}
+
+#[test]
+fn rising_pings_warn_before_the_breach() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    for i in 0..10 {
+        stats.add_ping("2".to_string(), Duration::from_millis(10 + i));
+        stats.add_ping("3".to_string(), Duration::from_millis(10));
+        clock.advance(Duration::from_secs(1));
+    }
+    let sla = Sla::new(0.9, Duration::from_millis(25), Duration::from_secs(60));
+    let projection = stats.project_ping("2", &sla).unwrap();
+    assert_eq!(projection.current, Duration::from_millis(18));
+    assert!((projection.trend - 0.001).abs() < 1e-9);
+    assert_eq!(projection.projected, Duration::from_millis(78));
+    assert_eq!(projection.breach_in, Some(Duration::from_secs(7)));
+    let warnings = stats.check_sla(&sla);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].peer_id, "2");
+    assert_eq!(
+        warnings[0].to_string(),
+        "\"2\" at 18ms projected to 78ms, breach in 7s"
+    );
+    assert_eq!(stats.project_ping("3", &sla).unwrap().breach_in, None);
+    let short = Sla::new(0.9, Duration::from_millis(25), Duration::from_secs(5));
+    assert!(stats.check_sla(&short).is_empty());
+    let all = stats.project_cohort_ping(0, 1, &sla).unwrap();
+    assert!(all.trend > 0.0 && all.trend < projection.trend);
+}
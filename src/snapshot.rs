@@ -0,0 +1,931 @@
+use crate::{
+    confidence::scaled_error, decay::decayed_error, durations_error_with_ci, durations_mean,
+    durations_std_dev, map::Keys, values_error_with_ci, values_mean, values_percentile_rank,
+    values_std_dev, Annotation, DisconnectCounts, Ewma, Histogram, KeepAlive, MetricCell, MinMax,
+    PeerState, PingBySize, Quarantine, Rate, RequestSummary, Rfc3339, Robust, SampleAges, Session,
+    StageLatencies, Stats, UpgradeLatencies, Window, WindowView,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    net::IpAddr,
+    sync::PoisonError,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+    vec,
+};
+
+/// Summary of a window of samples, durations unless stated otherwise.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Summary<T = Duration> {
+    pub samples: usize,
+    pub mean: T,
+    pub std_dev: T,
+    /// Error of the mean at the confidence level of `Stats::with_confidence`, 95% by default
+    pub error: T,
+    /// Position of the mean among the means of all peers, only set in `Stats::snapshot`
+    pub score: Option<Score>,
+    /// Fewer samples than `Stats::with_warm_up` requires, such summaries are not scored
+    pub warming_up: bool,
+}
+
+/// Mean of a peer relative to the means of all peers of the snapshot.
+///
+/// Higher values mean slower peers, both for pings and transmission rates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Score {
+    /// Distance from the population mean in standard deviations
+    pub z_score: f64,
+    /// Fraction of the scored peers which are faster, counting peers with the same mean,
+    /// the peer itself included, as half, e.g. 0.5 for every peer if all are equally fast
+    pub percentile_rank: f64,
+}
+
+impl Summary {
+    pub(crate) fn from_durations(durations: &[Duration]) -> Option<Self> {
+        Some(Self {
+            samples: durations.len(),
+            mean: durations_mean(durations)?,
+            std_dev: durations_std_dev(durations)?,
+            error: durations_error_with_ci(durations)?,
+            score: None,
+            warming_up: false,
+        })
+    }
+}
+
+impl Summary<f64> {
+    pub(crate) fn from_values(values: &[f64]) -> Option<Self> {
+        Some(Self {
+            samples: values.len(),
+            mean: values_mean(values)?,
+            std_dev: values_std_dev(values)?,
+            error: values_error_with_ci(values)?,
+            score: None,
+            warming_up: false,
+        })
+    }
+}
+
+/// Scores each summary against the means of all of them,
+/// `slowness` maps a mean to a value which is higher for slower peers.
+fn normalize<'a, T: 'a>(
+    summaries: impl Iterator<Item = &'a mut Summary<T>>,
+    slowness: impl Fn(&T) -> f64,
+) {
+    let mut summaries: Vec<_> = summaries.filter(|summary| !summary.warming_up).collect();
+    let means: Vec<_> = summaries
+        .iter()
+        .map(|summary| slowness(&summary.mean))
+        .collect();
+    let (mean, std_dev) = match (values_mean(&means), values_std_dev(&means)) {
+        (Some(mean), Some(std_dev)) => (mean, std_dev),
+        _ => return,
+    };
+    for (summary, value) in summaries.iter_mut().zip(&means) {
+        let z_score = if std_dev > 0.0 {
+            (value - mean) / std_dev
+        } else {
+            0.0
+        };
+        summary.score = values_percentile_rank(&means, *value).map(|percentile_rank| Score {
+            z_score,
+            percentile_rank,
+        });
+    }
+}
+
+/// Computed stats of a single peer.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct PeerSummary {
+    pub peer_id: String,
+    /// Time of the latest sample of any metric
+    pub last_seen: Option<SystemTime>,
+    pub ping: Option<Summary>,
+    /// Distribution of the ping window, only with `Stats::with_ping_histograms`
+    pub ping_histogram: Option<Histogram>,
+    /// Median and MAD of the ping window, only with `Stats::with_robust_summaries`
+    pub ping_robust: Option<Robust>,
+    /// Fastest and slowest ping of the window
+    pub ping_min_max: Option<MinMax>,
+    /// Ages of the pings of the window at the time of the summary
+    pub ping_ages: Option<SampleAges>,
+    /// Pings over fresh connections, which are not part of `ping`
+    pub cold_ping: Option<Summary>,
+    /// Pings recorded with their payload size
+    pub ping_by_size: Option<PingBySize>,
+    pub transmission_rate: Option<Summary<Rate>>,
+    /// Slowest and fastest transmission rate of the window
+    pub transmission_rate_min_max: Option<MinMax<Rate>>,
+    pub transmission_rate_ages: Option<SampleAges>,
+    pub requests: Option<RequestSummary>,
+    /// Numeric metrics recorded with `Stats::record_gauge`
+    pub gauges: BTreeMap<String, Summary<f64>>,
+    /// Latencies of interactions recorded with `Stats::record_stages`
+    pub stages: Option<StageLatencies>,
+    /// Latencies of connection upgrades recorded with `Stats::record_upgrade`
+    pub upgrades: Option<UpgradeLatencies>,
+    /// Latest closed connection sessions, oldest first
+    pub sessions: Vec<Session>,
+    pub disconnects: DisconnectCounts,
+    pub keep_alive: Option<KeepAlive>,
+    /// Notes of operators, oldest first
+    pub annotations: Vec<Annotation>,
+    /// Negotiated protocol capabilities set with `Stats::set_capabilities`
+    pub capabilities: BTreeSet<String>,
+    /// Network address set with `Stats::set_address`
+    pub address: Option<IpAddr>,
+    /// Samples out of `Stats::with_bounds`, which are not part of any summary
+    pub quarantine: Option<Quarantine>,
+    /// Metrics registered with `Stats::register_derived`
+    pub derived: BTreeMap<String, f64>,
+    /// Summaries of the latest samples, only with `Stats::with_window_views`
+    pub views: Vec<WindowView>,
+    /// Moving averages, only with `Stats::with_ewma`
+    pub ewma: Option<Ewma>,
+}
+
+impl PeerSummary {
+    /// All duration summaries of the peer, transmission rates are not durations.
+    pub fn summaries_mut(&mut self) -> impl Iterator<Item = &mut Summary> {
+        let (success_latency, failure_latency) = match self.requests.as_mut() {
+            Some(requests) => (
+                requests.success_latency.as_mut(),
+                requests.failure_latency.as_mut(),
+            ),
+            None => (None, None),
+        };
+        self.ping
+            .as_mut()
+            .into_iter()
+            .chain(self.cold_ping.as_mut())
+            .chain(
+                self.ping_by_size
+                    .iter_mut()
+                    .flat_map(PingBySize::summaries_mut),
+            )
+            .chain(success_latency)
+            .chain(failure_latency)
+            .chain(
+                self.keep_alive
+                    .as_mut()
+                    .and_then(|keep_alive| keep_alive.idle_before_disconnect.as_mut()),
+            )
+            .chain(
+                self.stages
+                    .iter_mut()
+                    .flat_map(StageLatencies::summaries_mut),
+            )
+            .chain(
+                self.upgrades
+                    .iter_mut()
+                    .flat_map(UpgradeLatencies::summaries_mut),
+            )
+            .chain(self.views.iter_mut().filter_map(|view| view.ping.as_mut()))
+    }
+}
+
+/// Computed stats of all peers known to a node.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct StatsSnapshot {
+    pub peer_id: String,
+    /// Time the snapshot was taken by the clock of the node
+    pub time: Option<SystemTime>,
+    /// Start of the aligned period the snapshot describes, only with `Stats::with_epochs`
+    pub epoch: Option<SystemTime>,
+    pub peers: Vec<PeerSummary>,
+    /// Disconnects from all peers
+    pub disconnects: DisconnectCounts,
+    /// Events of the node, oldest first
+    pub annotations: Vec<Annotation>,
+    /// Epsilon of the `Noise` added to the summaries, `None` if they are exact
+    pub noise_epsilon: Option<f64>,
+    /// Confidence level of the errors, only if set to other than 95% with
+    /// `Stats::with_confidence`
+    pub confidence: Option<f64>,
+}
+
+impl fmt::Display for StatsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.time {
+            Some(time) => writeln!(f, "{:?} at {}", self.peer_id, Rfc3339(time))?,
+            None => writeln!(f, "{:?}", self.peer_id)?,
+        }
+        if let Some(epoch) = self.epoch {
+            writeln!(f, "Epoch {}", Rfc3339(epoch))?;
+        }
+        if let Some(epsilon) = self.noise_epsilon {
+            writeln!(f, "Noised with epsilon {}", epsilon)?;
+        }
+        if let Some(level) = self.confidence {
+            writeln!(f, "Errors at confidence level {}", level)?;
+        }
+        if !self.annotations.is_empty() {
+            writeln!(f, "Annotations:")?;
+            for annotation in &self.annotations {
+                writeln!(f, "{}", annotation)?;
+            }
+        }
+        if self.peers.iter().any(|peer| peer.last_seen.is_some()) {
+            writeln!(f, "Last seen by peer:")?;
+            for peer in &self.peers {
+                if let Some(last_seen) = peer.last_seen {
+                    writeln!(f, "{:?} {}", peer.peer_id, Rfc3339(last_seen))?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.ping.is_some()) {
+            writeln!(f, "Ping mean for each peer:")?;
+            for peer in &self.peers {
+                if let Some(ping) = &peer.ping {
+                    writeln!(f, "{:?} {}", peer.peer_id, MetricCell::new(ping))?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.ping_min_max.is_some()) {
+            writeln!(f, "Ping min/max by peer:")?;
+            for peer in &self.peers {
+                if let Some(range) = &peer.ping_min_max {
+                    writeln!(f, "{:?} {}", peer.peer_id, range)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.ping_histogram.is_some()) {
+            writeln!(f, "Ping histogram for each peer:")?;
+            for peer in &self.peers {
+                if let Some(histogram) = &peer.ping_histogram {
+                    writeln!(f, "{:?} {}", peer.peer_id, histogram)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.cold_ping.is_some()) {
+            writeln!(f, "Cold ping mean for each peer:")?;
+            for peer in &self.peers {
+                if let Some(ping) = &peer.cold_ping {
+                    writeln!(f, "{:?} {:?}±{:?}", peer.peer_id, ping.mean, ping.error)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.ping_by_size.is_some()) {
+            writeln!(f, "Ping mean by probe size:")?;
+            for peer in &self.peers {
+                if let Some(by_size) = &peer.ping_by_size {
+                    writeln!(f, "{:?} {}", peer.peer_id, by_size)?;
+                }
+            }
+        }
+        if self
+            .peers
+            .iter()
+            .any(|peer| peer.transmission_rate.is_some())
+        {
+            writeln!(f, "Transmission rate mean by peer:")?;
+            for peer in &self.peers {
+                if let Some(rate) = &peer.transmission_rate {
+                    writeln!(f, "{:?} {}", peer.peer_id, MetricCell::new(rate))?;
+                }
+            }
+        }
+        if self
+            .peers
+            .iter()
+            .any(|peer| peer.transmission_rate_min_max.is_some())
+        {
+            writeln!(f, "Transmission rate min/max by peer:")?;
+            for peer in &self.peers {
+                if let Some(range) = &peer.transmission_rate_min_max {
+                    writeln!(f, "{:?} {}", peer.peer_id, range)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| !peer.gauges.is_empty()) {
+            writeln!(f, "Gauge mean by peer:")?;
+            for peer in &self.peers {
+                for (name, gauge) in &peer.gauges {
+                    writeln!(
+                        f,
+                        "{:?} {} {}±{}",
+                        peer.peer_id, name, gauge.mean, gauge.error
+                    )?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| !peer.views.is_empty()) {
+            writeln!(f, "Window views by peer:")?;
+            for peer in &self.peers {
+                for view in &peer.views {
+                    writeln!(f, "{:?} {}", peer.peer_id, view)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.address.is_some()) {
+            writeln!(f, "Addresses by peer:")?;
+            for peer in &self.peers {
+                if let Some(address) = peer.address {
+                    writeln!(f, "{:?} {}", peer.peer_id, address)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.ewma.is_some()) {
+            writeln!(f, "EWMA by peer:")?;
+            for peer in &self.peers {
+                if let Some(ewma) = &peer.ewma {
+                    writeln!(f, "{:?} {}", peer.peer_id, ewma)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.ping_robust.is_some()) {
+            writeln!(f, "Robust pings by peer:")?;
+            for peer in &self.peers {
+                if let Some(robust) = &peer.ping_robust {
+                    writeln!(f, "{:?} {}", peer.peer_id, robust)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| !peer.derived.is_empty()) {
+            writeln!(f, "Derived metrics by peer:")?;
+            for peer in &self.peers {
+                for (name, value) in &peer.derived {
+                    writeln!(f, "{:?} {} {}", peer.peer_id, name, value)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.stages.is_some()) {
+            writeln!(f, "Latency by stage:")?;
+            for peer in &self.peers {
+                if let Some(stages) = &peer.stages {
+                    writeln!(f, "{:?} {}", peer.peer_id, stages)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.upgrades.is_some()) {
+            writeln!(f, "Connection upgrade latency by stage:")?;
+            for peer in &self.peers {
+                if let Some(upgrades) = &peer.upgrades {
+                    writeln!(f, "{:?} {}", peer.peer_id, upgrades)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| !peer.sessions.is_empty()) {
+            writeln!(f, "Sessions by peer:")?;
+            for peer in &self.peers {
+                for session in &peer.sessions {
+                    writeln!(f, "{:?} {}", peer.peer_id, session)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.disconnects.total() > 0) {
+            writeln!(f, "Disconnects by peer:")?;
+            for peer in &self.peers {
+                if peer.disconnects.total() > 0 {
+                    writeln!(f, "{:?} {}", peer.peer_id, peer.disconnects)?;
+                }
+            }
+        }
+        writeln!(f, "Disconnects: {}", self.disconnects)?;
+        if self.peers.iter().any(|peer| peer.keep_alive.is_some()) {
+            writeln!(f, "Idle time before disconnect by peer:")?;
+            for peer in &self.peers {
+                if let Some(keep_alive) = &peer.keep_alive {
+                    write!(f, "{:?}", peer.peer_id)?;
+                    if let Some(idle) = &keep_alive.idle_before_disconnect {
+                        write!(f, " {:?}±{:?}", idle.mean, idle.error)?;
+                    }
+                    writeln!(
+                        f,
+                        ", {:.1}% of {} disconnects after idleness",
+                        keep_alive.after_idle_rate * 100.0,
+                        keep_alive.disconnects
+                    )?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| !peer.annotations.is_empty()) {
+            writeln!(f, "Annotations by peer:")?;
+            for peer in &self.peers {
+                for annotation in &peer.annotations {
+                    writeln!(f, "{:?} {}", peer.peer_id, annotation)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| !peer.capabilities.is_empty()) {
+            writeln!(f, "Capabilities by peer:")?;
+            for peer in &self.peers {
+                if !peer.capabilities.is_empty() {
+                    let capabilities: Vec<_> =
+                        peer.capabilities.iter().map(String::as_str).collect();
+                    writeln!(f, "{:?} {}", peer.peer_id, capabilities.join(", "))?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.quarantine.is_some()) {
+            writeln!(f, "Quarantined samples by peer:")?;
+            for peer in &self.peers {
+                if let Some(quarantine) = &peer.quarantine {
+                    writeln!(f, "{:?} {}", peer.peer_id, quarantine)?;
+                }
+            }
+        }
+        if self.peers.iter().any(|peer| peer.requests.is_some()) {
+            writeln!(f, "Request success rate by peer:")?;
+            for peer in &self.peers {
+                if let Some(requests) = &peer.requests {
+                    write!(
+                        f,
+                        "{:?} {:.1}% of {} requests",
+                        peer.peer_id,
+                        requests.success_rate * 100.0,
+                        requests.succeeded + requests.failed
+                    )?;
+                    if requests.failed > 0 {
+                        write!(f, ", failed: {}", requests.errors)?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copy of the state and the windows of a peer, see `Stats::capture_peer`.
+pub(crate) struct PeerCapture {
+    pub(crate) state: PeerState,
+    pub(crate) pings: Option<Window<Duration>>,
+    pub(crate) rates: Option<Window<Rate>>,
+}
+
+/// Quantiles of `PeerExtras`, as rendered by the Prometheus and line protocol expositions
+pub(crate) const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+/// Percentiles and counters of a peer taken from the capture of its summary, for the
+/// expositions which render more than a snapshot holds.
+#[derive(Debug, Clone)]
+pub(crate) struct PeerExtras {
+    pub(crate) ping_percentiles: [Option<Duration>; 3],
+    pub(crate) rate_percentiles: [Option<Rate>; 3],
+    #[cfg(feature = "prometheus")]
+    pub(crate) total_pings: u64,
+    #[cfg(feature = "prometheus")]
+    pub(crate) failed_pings: u64,
+    #[cfg(feature = "prometheus")]
+    pub(crate) total_transmissions: u64,
+}
+
+impl PeerExtras {
+    fn of(capture: &PeerCapture) -> Self {
+        Self {
+            ping_percentiles: QUANTILES.map(|quantile| capture.ping_percentile(quantile)),
+            rate_percentiles: QUANTILES.map(|quantile| capture.rate_percentile(quantile)),
+            #[cfg(feature = "prometheus")]
+            total_pings: capture.state.total_pings,
+            #[cfg(feature = "prometheus")]
+            failed_pings: capture.state.failed_pings,
+            #[cfg(feature = "prometheus")]
+            total_transmissions: capture.state.total_transmissions,
+        }
+    }
+}
+
+/// Snapshot of `Stats::with_snapshot_cache`, with the extras of its peers once an
+/// exposition needed them.
+pub(crate) struct CachedSnapshot {
+    snapshot: StatsSnapshot,
+    extras: Option<Vec<PeerExtras>>,
+}
+
+/// Iterator yielding a `PeerSummary` for each peer, ordered by peer id.
+///
+/// Only peer ids are collected up front, each summary is computed when it is requested.
+pub struct SnapshotIter<'a> {
+    stats: &'a Stats,
+    peers: vec::IntoIter<String>,
+}
+
+impl<'a> Iterator for SnapshotIter<'a> {
+    type Item = PeerSummary;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Peers removed after the iterator was created are skipped
+        let stats = self.stats;
+        self.peers
+            .find_map(|peer_id| stats.summarize_peer(&peer_id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.peers.len()))
+    }
+}
+
+impl Stats {
+    /// Summary of a single peer, which unlike those of `snapshot` is not scored
+    /// against the other peers.
+    pub fn peer_summary(&self, peer_id: &str) -> Option<PeerSummary> {
+        self.summarize_peer(peer_id)
+    }
+
+    /// Streams per-peer summaries without materializing the whole snapshot.
+    pub fn snapshot_iter(&self) -> SnapshotIter<'_> {
+        SnapshotIter {
+            stats: self,
+            peers: self.peer_ids().into_iter(),
+        }
+    }
+
+    /// Reuses a snapshot for `max_age` instead of summarizing all peers again,
+    /// so that frequent scrapers share one aggregation at the cost of staleness.
+    pub fn with_snapshot_cache(mut self, max_age: Duration) -> Self {
+        self.snapshot_max_age = Some(max_age);
+        self
+    }
+
+    /// Summaries with fewer than `min_samples` are flagged as `Summary::warming_up`,
+    /// left out of scoring and do not raise incidents, `0` by default.
+    pub fn with_warm_up(mut self, min_samples: usize) -> Self {
+        self.warm_up_samples = min_samples;
+        self
+    }
+
+    /// Collects summaries of all peers and scores them against each other,
+    /// see `with_snapshot_cache`. The pings, transmissions and counters of each peer are
+    /// read at the same instant, but the peers are read one after the other, so samples
+    /// recorded meanwhile may be in the summaries of some peers only.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        self.cached_aggregate(false).0
+    }
+
+    /// Snapshot with the extras of each of its peers, in the order of `peers`, taken from
+    /// the same captures as the summaries.
+    pub(crate) fn snapshot_with_extras(&self) -> (StatsSnapshot, Vec<PeerExtras>) {
+        let (snapshot, extras) = self.cached_aggregate(true);
+        (snapshot, extras.unwrap_or_default())
+    }
+
+    fn cached_aggregate(&self, with_extras: bool) -> (StatsSnapshot, Option<Vec<PeerExtras>>) {
+        let max_age = match self.snapshot_max_age {
+            Some(max_age) => max_age,
+            None => return self.aggregate(with_extras),
+        };
+        // Held while aggregating, so concurrent callers wait for the result
+        let mut cached = self
+            .cached_snapshot
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let now = self.clock.now();
+        let fresh = |cached: &CachedSnapshot| {
+            let age = cached
+                .snapshot
+                .time
+                .and_then(|time| now.duration_since(time).ok());
+            age.is_some_and(|age| age < max_age) && (cached.extras.is_some() || !with_extras)
+        };
+        match cached.as_ref() {
+            Some(cached) if fresh(cached) => (cached.snapshot.clone(), cached.extras.clone()),
+            _ => {
+                let (snapshot, extras) = self.aggregate(with_extras);
+                *cached = Some(CachedSnapshot {
+                    snapshot: snapshot.clone(),
+                    extras: extras.clone(),
+                });
+                (snapshot, extras)
+            }
+        }
+    }
+
+    fn aggregate(&self, with_extras: bool) -> (StatsSnapshot, Option<Vec<PeerExtras>>) {
+        trace_span!("snapshot");
+        let mut peers = Vec::new();
+        let mut extras = Vec::new();
+        for peer_id in self.peer_ids() {
+            // Peers removed meanwhile are skipped
+            if let Some(capture) = self.capture_peer(&peer_id) {
+                if with_extras {
+                    extras.push(PeerExtras::of(&capture));
+                }
+                peers.push(self.summarize_capture(&peer_id, &capture));
+            }
+        }
+        normalize(
+            peers.iter_mut().filter_map(|peer| peer.ping.as_mut()),
+            Duration::as_secs_f64,
+        );
+        normalize(
+            peers
+                .iter_mut()
+                .filter_map(|peer| peer.transmission_rate.as_mut()),
+            |rate| -rate.bytes_per_sec(),
+        );
+        let snapshot = StatsSnapshot {
+            peer_id: self.peer_id.clone(),
+            time: Some(self.clock.now()),
+            epoch: self.epoch(),
+            peers,
+            disconnects: self.disconnects(),
+            annotations: self.annotations(),
+            noise_epsilon: None,
+            confidence: self.snapshot_confidence(),
+        };
+        (snapshot, Some(extras).filter(|_| with_extras))
+    }
+
+    pub(crate) fn peer_ids(&self) -> Vec<String> {
+        trace_span!("map_access");
+        // Every recorded peer has a state
+        let mut peer_ids = self.peers.keys();
+        peer_ids.sort();
+        peer_ids
+    }
+
+    /// First phase of a summary, copying the state and the windows of the peer at one
+    /// instant. Recording a sample writes them one after the other, so without the
+    /// exclusive guard a summary could count a ping in its window but not in its state,
+    /// or see a new peer in one map but not in the other. The summary is computed from
+    /// the copy in the second phase, while samples are recorded again.
+    pub(crate) fn capture_peer(&self, peer_id: &str) -> Option<PeerCapture> {
+        let _capturing = self.capturing(peer_id);
+        self.expire_samples(peer_id);
+        Some(PeerCapture {
+            state: self.peers.get(peer_id)?.clone(),
+            pings: self.pings_to_peers.get(peer_id).map(|pings| pings.clone()),
+            rates: self
+                .transmissions_rates
+                .get(peer_id)
+                .map(|rates| rates.clone()),
+        })
+    }
+
+    /// Summary of the peer which is consistent in itself, though the summaries of a
+    /// snapshot are captured one peer after the other.
+    pub(crate) fn summarize_peer(&self, peer_id: &str) -> Option<PeerSummary> {
+        let capture = self.capture_peer(peer_id)?;
+        Some(self.summarize_capture(peer_id, &capture))
+    }
+
+    /// Second phase of a summary, computed from the capture of `capture_peer`.
+    fn summarize_capture(&self, peer_id: &str, capture: &PeerCapture) -> PeerSummary {
+        trace_span!("summarize_peer");
+        let mut peer = {
+            let peer = &capture.state;
+            PeerSummary {
+                peer_id: peer_id.to_string(),
+                last_seen: Some(peer.last_seen),
+                ping: peer.streaming_pings.ping_summary(),
+                ping_histogram: None,
+                ping_robust: None,
+                ping_min_max: None,
+                ping_ages: None,
+                cold_ping: Summary::from_durations(&peer.cold_pings),
+                ping_by_size: PingBySize::from_windows(&peer.pings_by_size),
+                transmission_rate: peer.streaming_rates.rate_summary(),
+                transmission_rate_min_max: None,
+                transmission_rate_ages: None,
+                requests: peer.requests.summary(),
+                gauges: peer
+                    .gauges
+                    .iter()
+                    .filter_map(|(name, values)| {
+                        Some((name.clone(), Summary::from_values(values)?))
+                    })
+                    .collect(),
+                stages: StageLatencies::from_windows(&peer.stages),
+                upgrades: UpgradeLatencies::from_windows(&peer.upgrades),
+                sessions: peer.sessions.iter().cloned().collect(),
+                disconnects: peer.disconnects,
+                keep_alive: peer.idle_disconnects.summary(),
+                annotations: peer.annotations.iter().cloned().collect(),
+                capabilities: peer.capabilities.clone(),
+                address: peer.address,
+                quarantine: Some(peer.quarantine.clone())
+                    .filter(|quarantine| quarantine.pings + quarantine.transmissions > 0),
+                derived: BTreeMap::new(),
+                views: Vec::new(),
+                ewma: Some(peer.ewma).filter(|ewma| !ewma.is_empty()),
+            }
+        };
+        let now = self.clock.now();
+        if let Some(pings) = &capture.pings {
+            peer.ping = Summary::from_durations(pings);
+            peer.ping_min_max = MinMax::from_samples(pings);
+            peer.ping_ages = SampleAges::from_times(pings.between(UNIX_EPOCH, None).0, now);
+            if self.ping_histograms {
+                peer.ping_histogram = Histogram::from_durations(pings);
+            }
+            if self.robust_summaries {
+                peer.ping_robust = Robust::from_durations(pings);
+            }
+        }
+        if let Some(rates) = &capture.rates {
+            peer.transmission_rate = Summary::from_rates(rates);
+            peer.transmission_rate_min_max = MinMax::from_samples(rates);
+            peer.transmission_rate_ages =
+                SampleAges::from_times(rates.between(UNIX_EPOCH, None).0, now);
+        }
+        if !self.window_views.is_empty() {
+            peer.views = self.window_views(
+                capture.pings.as_ref().map_or(&[], |pings| &pings[..]),
+                capture.rates.as_ref().map_or(&[], |rates| &rates[..]),
+            );
+        }
+        let weight = self.decay_weight(peer.last_seen);
+        let min_samples = self.warm_up_samples;
+        let confidence_scale = self.confidence_scale();
+        for summary in peer.summaries_mut() {
+            summary.error = scaled_error(decayed_error(summary.error, weight), confidence_scale);
+            summary.warming_up = summary.samples < min_samples;
+        }
+        for gauge in peer.gauges.values_mut() {
+            gauge.error *= confidence_scale;
+            gauge.warming_up = gauge.samples < min_samples;
+        }
+        let view_rates = peer
+            .views
+            .iter_mut()
+            .filter_map(|view| view.transmission_rate.as_mut());
+        for rate in peer.transmission_rate.iter_mut().chain(view_rates) {
+            rate.warming_up = rate.samples < min_samples;
+            if weight < 1.0 {
+                rate.error = rate.error / weight.sqrt();
+            }
+            rate.error = rate.error * confidence_scale;
+        }
+        self.derive(&mut peer);
+        peer
+    }
+}
+
+#[test]
+fn peer_summary_is_queried_directly() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("2".to_string(), Duration::from_millis(30));
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    let peer = stats.peer_summary("2").unwrap();
+    let ping = peer.ping.unwrap();
+    assert_eq!(
+        (ping.mean, ping.std_dev, ping.samples),
+        (Duration::from_millis(20), Duration::from_millis(10), 2)
+    );
+    assert_eq!(
+        peer.transmission_rate.unwrap().mean,
+        Rate::from_bytes_per_sec(1_000.0)
+    );
+    assert_eq!(stats.peer_summary("3"), None);
+}
+
+#[test]
+fn snapshot_iter_yields_each_peer_once() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("3".to_string(), Duration::from_secs(1));
+    stats.add_ping("2".to_string(), Duration::from_secs(1));
+    stats.add_transmission("2".to_string(), Duration::from_secs(2), 1);
+    stats.add_transmission("4".to_string(), Duration::from_secs(2), 1);
+    let peers: Vec<_> = stats.snapshot_iter().map(|peer| peer.peer_id).collect();
+    assert_eq!(peers, vec!["2", "3", "4"]);
+}
+
+#[test]
+fn snapshot_contains_peer_summaries() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_secs(1));
+    stats.add_ping("2".to_string(), Duration::from_secs(3));
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.peer_id, "1");
+    assert_eq!(snapshot.peers.len(), 1);
+    let ping = snapshot.peers[0].ping.as_ref().unwrap();
+    assert_eq!(ping.samples, 2);
+    assert_eq!(ping.mean, Duration::from_secs(2));
+    assert_eq!(snapshot.peers[0].transmission_rate, None);
+}
+
+#[test]
+fn snapshot_scores_peers_against_each_other() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_ping("3".to_string(), Duration::from_millis(20));
+    stats.add_ping("4".to_string(), Duration::from_millis(30));
+    let snapshot = stats.snapshot();
+    let scores: Vec<_> = snapshot
+        .peers
+        .iter()
+        .map(|peer| peer.ping.as_ref().unwrap().score.unwrap())
+        .collect();
+    assert!(scores[0].z_score < 0.0);
+    assert!(scores[1].z_score.abs() < 1e-9);
+    assert!(scores[2].z_score > 0.0);
+    assert_eq!(scores[2].percentile_rank, 5.0 / 6.0);
+}
+
+#[test]
+fn snapshots_are_cached_for_max_age() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock.clone())
+        .with_snapshot_cache(Duration::from_secs(5));
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    let first = stats.snapshot();
+    stats.add_ping("3".to_string(), Duration::from_millis(10));
+    clock.advance(Duration::from_secs(4));
+    assert_eq!(stats.snapshot(), first);
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(stats.snapshot().peers.len(), 2);
+}
+
+#[test]
+fn warming_up_peers_are_flagged_and_not_scored() {
+    let stats = Stats::new(100, "1".to_string()).with_warm_up(3);
+    for _ in 0..3 {
+        stats.add_ping("2".to_string(), Duration::from_millis(10));
+        stats.add_ping("3".to_string(), Duration::from_millis(30));
+    }
+    stats.add_ping("4".to_string(), Duration::from_millis(500));
+    let snapshot = stats.snapshot();
+    let ping = |peer: usize| snapshot.peers[peer].ping.as_ref().unwrap();
+    assert!(!ping(0).warming_up);
+    assert!(ping(2).warming_up);
+    assert_eq!(ping(2).score, None);
+    assert_eq!(ping(1).score.unwrap().percentile_rank, 0.75);
+    assert!(snapshot
+        .to_string()
+        .contains("\"4\" 500ms±0ns (warming up)"));
+}
+
+#[test]
+fn report_is_dated() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_614_834_367),
+    ));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    clock.advance(Duration::from_secs(60));
+    let report = stats.to_string();
+    assert!(report.starts_with("\"1\" at 2021-03-04T05:07:07Z\n"));
+    assert!(report.contains("\"2\" 2021-03-04T05:06:07Z\n"));
+}
+
+#[test]
+fn report_leaves_out_empty_sections() {
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    let report = stats.snapshot().to_string();
+    let headers: Vec<_> = report.lines().filter(|line| line.ends_with(':')).collect();
+    assert_eq!(
+        headers,
+        vec![
+            "Last seen by peer:",
+            "Ping mean for each peer:",
+            "Ping min/max by peer:"
+        ]
+    );
+}
+
+#[test]
+fn summaries_are_consistent_while_recording() {
+    use std::{sync::Arc, thread};
+
+    // The moving average of alpha 1 is the latest sample, which is the largest one
+    let stats = Arc::new(Stats::new(10_000, "1".to_string()).with_ewma(1.0));
+    let recorder = {
+        let stats = stats.clone();
+        thread::spawn(move || {
+            for millis in 1..=2_000 {
+                stats.add_ping("2".to_string(), Duration::from_millis(millis));
+                stats.add_transmission("2".to_string(), Duration::from_secs(1), millis);
+            }
+        })
+    };
+    while !recorder.is_finished() {
+        if let Some(peer) = stats.summarize_peer("2") {
+            let ewma = peer.ewma.unwrap();
+            assert_eq!(peer.ping_min_max.map(|range| range.max), ewma.ping);
+            let rates = peer.transmission_rate_min_max;
+            assert_eq!(rates.map(|range| range.max), ewma.transmission_rate);
+        }
+        let (snapshot, extras) = stats.snapshot_with_extras();
+        if let (Some(peer), Some(extras)) = (snapshot.peers.first(), extras.first()) {
+            // The pings are 1 ms to `samples` ms, so their percentiles follow from the count
+            let samples = peer.ping.as_ref().unwrap().samples as f64;
+            for (quantile, percentile) in QUANTILES.iter().zip(extras.ping_percentiles) {
+                let millis = (quantile * samples).ceil() as u64;
+                assert_eq!(percentile, Some(Duration::from_millis(millis)));
+            }
+        }
+    }
+    recorder.join().unwrap();
+}
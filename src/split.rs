@@ -0,0 +1,395 @@
+use crate::{
+    Annotation, BenchmarkReport, ByteSize, Connection, Correlation, CorrelationReport,
+    DisconnectCounts, DisconnectReason, ErrorCategory, Exporter, FirstContactReport, Incident,
+    KeyedRecorder, Metric, MinMax, Page, PeerOrder, PeerSampling, PeerSummary, Projection, Rate,
+    ReportOptions, Robust, Rtt, SelectionSnapshot, Series, Sla, SlaWarning, SnapshotIter, Stage,
+    Starvation, Stats, StatsSnapshot, Summary, Triage, UpgradeStage,
+};
+use std::{
+    fmt::Display,
+    hash::Hash,
+    io::{self, Write},
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// Write half of `Stats` for components on the packet path, which can only record samples.
+#[derive(Clone)]
+pub struct Recorder {
+    stats: Arc<Stats>,
+}
+
+/// Read half of `Stats`, with the queries and exports which summarize peers.
+#[derive(Clone)]
+pub struct Querier {
+    stats: Arc<Stats>,
+}
+
+impl Stats {
+    /// Splits into halves sharing the same samples,
+    /// settings are expected to be applied before.
+    pub fn split(self) -> (Recorder, Querier) {
+        let stats = Arc::new(self);
+        (
+            Recorder {
+                stats: stats.clone(),
+            },
+            Querier { stats },
+        )
+    }
+}
+
+impl Recorder {
+    /// Recorder of the same samples keyed by the peer type of the caller.
+    pub fn keyed<K: Hash + Eq + Clone + Display>(&self) -> KeyedRecorder<K> {
+        KeyedRecorder::new(self.stats.clone())
+    }
+
+    pub fn add_ping(&self, peer_id: String, rtt: Duration) {
+        self.stats.add_ping(peer_id, rtt)
+    }
+
+    pub fn add_ping_failure(&self, peer_id: String) {
+        self.stats.add_ping_failure(peer_id)
+    }
+
+    pub fn record_ping_result<E>(&self, peer_id: String, result: Result<Duration, E>) {
+        self.stats.record_ping_result(peer_id, result)
+    }
+
+    pub fn add_rtt(&self, peer_id: String, rtt: Rtt) {
+        self.stats.add_rtt(peer_id, rtt)
+    }
+
+    pub fn add_ping_with_connection(&self, peer_id: String, rtt: Duration, connection: Connection) {
+        self.stats
+            .add_ping_with_connection(peer_id, rtt, connection)
+    }
+
+    pub fn add_ping_with_size(&self, peer_id: String, rtt: Duration, probe_bytes: u32) {
+        self.stats.add_ping_with_size(peer_id, rtt, probe_bytes)
+    }
+
+    pub fn add_ping_over(&self, peer_id: String, rtt: Duration, label: &str) {
+        self.stats.add_ping_over(peer_id, rtt, label)
+    }
+
+    pub fn add_transmission(&self, peer_id: String, time: Duration, n_bytes: impl Into<ByteSize>) {
+        self.stats.add_transmission(peer_id, time, n_bytes)
+    }
+
+    pub fn count_bytes(&self, peer_id: String, n_bytes: impl Into<ByteSize>) {
+        self.stats.count_bytes(peer_id, n_bytes)
+    }
+
+    pub fn flush_bytes(&self) -> usize {
+        self.stats.flush_bytes()
+    }
+
+    pub fn add_transmission_over(
+        &self,
+        peer_id: String,
+        time: Duration,
+        n_bytes: impl Into<ByteSize>,
+        label: &str,
+    ) {
+        self.stats
+            .add_transmission_over(peer_id, time, n_bytes, label)
+    }
+
+    pub fn record_gauge(&self, peer_id: String, name: &str, value: f64) {
+        self.stats.record_gauge(peer_id, name, value)
+    }
+
+    pub fn record_stages(&self, peer_id: String, timings: &[(Stage, Duration)]) {
+        self.stats.record_stages(peer_id, timings)
+    }
+
+    pub fn record_upgrade(&self, peer_id: String, timings: &[(UpgradeStage, Duration)]) {
+        self.stats.record_upgrade(peer_id, timings)
+    }
+
+    pub fn set_capabilities<I, S>(&self, peer_id: String, capabilities: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stats.set_capabilities(peer_id, capabilities)
+    }
+
+    pub fn set_address(&self, peer_id: String, address: IpAddr) {
+        self.stats.set_address(peer_id, address)
+    }
+
+    pub fn record_request_outcome(&self, peer_id: String, ok: bool, latency: Duration) {
+        self.stats.record_request_outcome(peer_id, ok, latency)
+    }
+
+    pub fn record_request_failure(
+        &self,
+        peer_id: String,
+        category: ErrorCategory,
+        latency: Duration,
+    ) {
+        self.stats
+            .record_request_failure(peer_id, category, latency)
+    }
+
+    pub fn record_connected(&self, peer_id: String) {
+        self.stats.record_connected(peer_id)
+    }
+
+    pub fn record_disconnected(&self, peer_id: String, reason: DisconnectReason) {
+        self.stats.record_disconnected(peer_id, reason)
+    }
+
+    pub fn annotate(&self, note: &str, time: SystemTime) {
+        self.stats.annotate(note, time)
+    }
+
+    pub fn annotate_peer(&self, peer_id: String, note: &str, time: SystemTime) {
+        self.stats.annotate_peer(peer_id, note, time)
+    }
+}
+
+impl Querier {
+    pub fn snapshot(&self) -> StatsSnapshot {
+        self.stats.snapshot()
+    }
+
+    pub fn peer_summary(&self, peer_id: &str) -> Option<PeerSummary> {
+        self.stats.peer_summary(peer_id)
+    }
+
+    pub fn snapshot_iter(&self) -> SnapshotIter<'_> {
+        self.stats.snapshot_iter()
+    }
+
+    pub fn export<W: Write>(&self, exporter: &Exporter, writer: W) -> io::Result<()> {
+        exporter.export(&self.stats, writer)
+    }
+
+    pub fn save(&self, exporter: &Exporter, filename: &str) -> io::Result<()> {
+        exporter.save(&self.stats, filename)
+    }
+
+    pub fn percentile_rank(&self, peer_id: &str, metric: Metric, value: Duration) -> Option<f64> {
+        self.stats.percentile_rank(peer_id, metric, value)
+    }
+
+    pub fn transmission_rate_rank(&self, peer_id: &str, rate: Rate) -> Option<f64> {
+        self.stats.transmission_rate_rank(peer_id, rate)
+    }
+
+    pub fn ping_percentile(&self, peer_id: &str, quantile: f64) -> Option<Duration> {
+        self.stats.ping_percentile(peer_id, quantile)
+    }
+
+    pub fn project_ping(&self, peer_id: &str, sla: &Sla) -> Option<Projection> {
+        self.stats.project_ping(peer_id, sla)
+    }
+
+    pub fn project_cohort_ping(
+        &self,
+        cohort: u32,
+        n_buckets: u32,
+        sla: &Sla,
+    ) -> Option<Projection> {
+        self.stats.project_cohort_ping(cohort, n_buckets, sla)
+    }
+
+    pub fn check_sla(&self, sla: &Sla) -> Vec<SlaWarning> {
+        self.stats.check_sla(sla)
+    }
+
+    pub fn ping_min_max(&self, peer_id: &str) -> Option<MinMax> {
+        self.stats.ping_min_max(peer_id)
+    }
+
+    pub fn transmission_rate_min_max(&self, peer_id: &str) -> Option<MinMax<Rate>> {
+        self.stats.transmission_rate_min_max(peer_id)
+    }
+
+    pub fn ping_robust(&self, peer_id: &str) -> Option<Robust> {
+        self.stats.ping_robust(peer_id)
+    }
+
+    pub fn transmission_rate_robust(&self, peer_id: &str) -> Option<Robust<Rate>> {
+        self.stats.transmission_rate_robust(peer_id)
+    }
+
+    pub fn aggregate_ping_percentile(&self, quantile: f64) -> Option<Duration> {
+        self.stats.aggregate_ping_percentile(quantile)
+    }
+
+    pub fn transmission_rate_percentile(&self, peer_id: &str, quantile: f64) -> Option<Rate> {
+        self.stats.transmission_rate_percentile(peer_id, quantile)
+    }
+
+    pub fn aggregate_transmission_rate_percentile(&self, quantile: f64) -> Option<Rate> {
+        self.stats.aggregate_transmission_rate_percentile(quantile)
+    }
+
+    pub fn epoch(&self) -> Option<SystemTime> {
+        self.stats.epoch()
+    }
+
+    pub fn next_epoch(&self) -> Option<SystemTime> {
+        self.stats.next_epoch()
+    }
+
+    pub fn ewma_ping(&self, peer_id: &str) -> Option<Duration> {
+        self.stats.ewma_ping(peer_id)
+    }
+
+    pub fn ewma_transmission_rate(&self, peer_id: &str) -> Option<Rate> {
+        self.stats.ewma_transmission_rate(peer_id)
+    }
+
+    pub fn pings_between(
+        &self,
+        peer_id: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Vec<(SystemTime, Duration)> {
+        self.stats.pings_between(peer_id, from, to)
+    }
+
+    pub fn transmission_rates_between(
+        &self,
+        peer_id: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Vec<(SystemTime, Rate)> {
+        self.stats.transmission_rates_between(peer_id, from, to)
+    }
+
+    pub fn summary_since(&self, peer_id: &str, since: SystemTime) -> Option<Summary> {
+        self.stats.summary_since(peer_id, since)
+    }
+
+    pub fn gauge_percentile_rank(&self, peer_id: &str, name: &str, value: f64) -> Option<f64> {
+        self.stats.gauge_percentile_rank(peer_id, name, value)
+    }
+
+    pub fn estimate(&self, peer_id: &str, metric: Metric) -> Option<Duration> {
+        self.stats.estimate(peer_id, metric)
+    }
+
+    pub fn estimate_transmission_rate(&self, peer_id: &str) -> Option<Rate> {
+        self.stats.estimate_transmission_rate(peer_id)
+    }
+
+    pub fn peers_page(&self, offset: usize, limit: usize, order: PeerOrder) -> Page {
+        self.stats.peers_page(offset, limit, order)
+    }
+
+    pub fn suggested_probe_interval(&self, peer_id: &str) -> Duration {
+        self.stats.suggested_probe_interval(peer_id)
+    }
+
+    pub fn sample_peers(&self, n: usize, sampling: PeerSampling) -> Vec<String> {
+        self.stats.sample_peers(n, sampling)
+    }
+
+    pub fn selection_snapshot(&self) -> SelectionSnapshot {
+        self.stats.selection_snapshot()
+    }
+
+    pub fn correlate(
+        &self,
+        peer_id: &str,
+        a: impl Into<Series>,
+        b: impl Into<Series>,
+    ) -> Option<Correlation> {
+        self.stats.correlate(peer_id, a, b)
+    }
+
+    pub fn correlation_report(
+        &self,
+        a: impl Into<Series>,
+        b: impl Into<Series>,
+    ) -> CorrelationReport {
+        self.stats.correlation_report(a, b)
+    }
+
+    pub fn first_seen(&self, peer_id: &str) -> Option<SystemTime> {
+        self.stats.first_seen(peer_id)
+    }
+
+    pub fn first_contact_report(&self, period: Duration, triage: &Triage) -> FirstContactReport {
+        self.stats.first_contact_report(period, triage)
+    }
+
+    pub fn benchmark_report(&self, baseline: &str) -> BenchmarkReport {
+        self.stats.benchmark_report(baseline)
+    }
+
+    pub fn incidents(&self, peer_id: &str) -> Vec<Incident> {
+        self.stats.incidents(peer_id)
+    }
+
+    pub fn disconnects(&self) -> DisconnectCounts {
+        self.stats.disconnects()
+    }
+
+    pub fn annotations(&self) -> Vec<Annotation> {
+        self.stats.annotations()
+    }
+
+    pub fn last_ping_time(&self) -> Option<SystemTime> {
+        self.stats.last_ping_time()
+    }
+
+    pub fn last_ingest_time(&self) -> Option<SystemTime> {
+        self.stats.last_ingest_time()
+    }
+
+    pub fn check_starvation(&self) -> Vec<Starvation> {
+        self.stats.check_starvation()
+    }
+
+    pub fn total_samples(&self, peer_id: &str, metric: Metric) -> u64 {
+        self.stats.total_samples(peer_id, metric)
+    }
+
+    pub fn failed_pings(&self, peer_id: &str) -> u64 {
+        self.stats.failed_pings(peer_id)
+    }
+
+    pub fn ping_loss(&self, peer_id: &str) -> Option<f64> {
+        self.stats.ping_loss(peer_id)
+    }
+
+    pub fn render_influx(&self) -> String {
+        self.stats.render_influx()
+    }
+
+    pub fn report(&self, options: &ReportOptions) -> String {
+        self.stats.report(options)
+    }
+
+    #[cfg(feature = "prometheus")]
+    pub fn render_prometheus(&self) -> String {
+        self.stats.render_prometheus()
+    }
+
+    #[cfg(feature = "http")]
+    pub fn serve_http(&self, addr: impl std::net::ToSocketAddrs) -> io::Result<crate::HttpServer> {
+        self.stats.clone().serve_http(addr)
+    }
+}
+
+#[test]
+fn halves_share_samples() {
+    let (recorder, querier) = Stats::new(100, "1".to_string()).split();
+    let packet_path = recorder.clone();
+    std::thread::spawn(move || packet_path.add_ping("2".to_string(), Duration::from_millis(10)))
+        .join()
+        .unwrap();
+    recorder.add_ping("2".to_string(), Duration::from_millis(30));
+    let ping = querier.snapshot().peers.remove(0).ping.unwrap();
+    assert_eq!(ping.mean, Duration::from_millis(20));
+    assert!(querier.last_ping_time().is_some());
+}
@@ -0,0 +1,152 @@
+use crate::{PushLossy, Stats, Summary, Window};
+use std::{fmt, time::Duration};
+
+/// Part of a single interaction with a peer, in the order they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stage {
+    /// Waiting in local queues before anything is sent
+    Queue,
+    Connect,
+    /// Sending the request
+    Request,
+    /// Waiting for the first byte of the response
+    FirstByte,
+    /// Receiving the rest of the response
+    Transfer,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Queue,
+        Stage::Connect,
+        Stage::Request,
+        Stage::FirstByte,
+        Stage::Transfer,
+    ];
+
+    fn index(self) -> usize {
+        match self {
+            Stage::Queue => 0,
+            Stage::Connect => 1,
+            Stage::Request => 2,
+            Stage::FirstByte => 3,
+            Stage::Transfer => 4,
+        }
+    }
+}
+
+/// Latency summaries for each `Stage`.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StageLatencies {
+    pub queue: Option<Summary>,
+    pub connect: Option<Summary>,
+    pub request: Option<Summary>,
+    pub first_byte: Option<Summary>,
+    pub transfer: Option<Summary>,
+}
+
+impl StageLatencies {
+    pub(crate) fn from_windows(windows: &[Window<Duration>; 5]) -> Option<Self> {
+        let stages = Self {
+            queue: Summary::from_durations(&windows[0]),
+            connect: Summary::from_durations(&windows[1]),
+            request: Summary::from_durations(&windows[2]),
+            first_byte: Summary::from_durations(&windows[3]),
+            transfer: Summary::from_durations(&windows[4]),
+        };
+        if stages == Self::default() {
+            None
+        } else {
+            Some(stages)
+        }
+    }
+
+    pub fn get(&self, stage: Stage) -> Option<&Summary> {
+        match stage {
+            Stage::Queue => self.queue.as_ref(),
+            Stage::Connect => self.connect.as_ref(),
+            Stage::Request => self.request.as_ref(),
+            Stage::FirstByte => self.first_byte.as_ref(),
+            Stage::Transfer => self.transfer.as_ref(),
+        }
+    }
+
+    /// Stage with the highest mean latency.
+    pub fn dominant(&self) -> Option<Stage> {
+        Stage::ALL
+            .iter()
+            .filter_map(|stage| Some((*stage, self.get(*stage)?.mean)))
+            .max_by_key(|(_, mean)| *mean)
+            .map(|(stage, _)| stage)
+    }
+
+    pub(crate) fn summaries_mut(&mut self) -> impl Iterator<Item = &mut Summary> {
+        self.queue
+            .as_mut()
+            .into_iter()
+            .chain(self.connect.as_mut())
+            .chain(self.request.as_mut())
+            .chain(self.first_byte.as_mut())
+            .chain(self.transfer.as_mut())
+    }
+}
+
+impl fmt::Display for StageLatencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for stage in Stage::ALL.iter() {
+            if let Some(summary) = self.get(*stage) {
+                write!(
+                    f,
+                    "{}{:?} {:?}±{:?}",
+                    separator, stage, summary.mean, summary.error
+                )?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Records the timings of the stages of a single interaction with the peer,
+    /// stages which did not happen, e.g. `Stage::Connect` over an open connection, are left out.
+    pub fn record_stages(&self, peer_id: String, timings: &[(Stage, Duration)]) {
+        trace_span!("record_stages");
+        let _recording = self.recording(&peer_id);
+        let window_size = self.window_size;
+        self.update_peer(&peer_id, |peer| {
+            for (stage, time) in timings {
+                peer.stages[stage.index()].push_lossy(*time, window_size);
+            }
+        });
+    }
+}
+
+#[test]
+fn stages_are_summarized_separately() {
+    let stats = Stats::new(100, "1".to_string());
+    let millis = Duration::from_millis;
+    stats.record_stages(
+        "2".to_string(),
+        &[
+            (Stage::Connect, millis(80)),
+            (Stage::FirstByte, millis(20)),
+            (Stage::Transfer, millis(10)),
+        ],
+    );
+    stats.record_stages(
+        "2".to_string(),
+        &[
+            (Stage::FirstByte, millis(40)),
+            (Stage::Transfer, millis(10)),
+        ],
+    );
+    let stages = stats.snapshot().peers.remove(0).stages.unwrap();
+    assert_eq!(stages.connect.as_ref().unwrap().samples, 1);
+    assert_eq!(stages.first_byte.as_ref().unwrap().mean, millis(30));
+    assert_eq!(stages.queue, None);
+    assert_eq!(stages.dominant(), Some(Stage::Connect));
+}
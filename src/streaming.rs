@@ -0,0 +1,107 @@
+use crate::{PeerState, Rate, Stats, Summary};
+use std::{convert::TryFrom, time::Duration};
+
+/// Count, mean and sum of squared deviations updated with Welford's algorithm,
+/// which summarize all samples in constant memory.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Welford {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Welford {
+    pub(crate) fn add(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Population standard deviation and 95% confidence error like the window summaries,
+    /// which the peer summaries scale to the configured confidence level.
+    pub(crate) fn summary(&self) -> Option<Summary<f64>> {
+        if self.count == 0 {
+            return None;
+        }
+        let std_dev = (self.m2 / self.count as f64).sqrt();
+        Some(Summary {
+            samples: usize::try_from(self.count).unwrap_or(usize::MAX),
+            mean: self.mean,
+            std_dev,
+            error: 1.96 * std_dev / (self.count as f64).sqrt(),
+            score: None,
+            warming_up: false,
+        })
+    }
+
+    pub(crate) fn ping_summary(&self) -> Option<Summary> {
+        let secs = |secs: f64| Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX);
+        let summary = self.summary()?;
+        Some(Summary {
+            samples: summary.samples,
+            mean: secs(summary.mean),
+            std_dev: secs(summary.std_dev),
+            error: secs(summary.error),
+            score: None,
+            warming_up: false,
+        })
+    }
+
+    pub(crate) fn rate_summary(&self) -> Option<Summary<Rate>> {
+        let summary = self.summary()?;
+        Some(Summary {
+            samples: summary.samples,
+            mean: Rate::from_bytes_per_sec(summary.mean),
+            std_dev: Rate::from_bytes_per_sec(summary.std_dev),
+            error: Rate::from_bytes_per_sec(summary.error),
+            score: None,
+            warming_up: false,
+        })
+    }
+}
+
+impl Stats {
+    /// Summarizes pings and transmission rates of each peer over all their samples with
+    /// constant memory instead of keeping windows, for nodes with many long-lived peers.
+    /// Queries which need the samples, like percentiles, window views, histograms and
+    /// time ranges, find none for these metrics and pings are not checked for incidents.
+    pub fn with_streaming(mut self) -> Self {
+        self.streaming = true;
+        self
+    }
+
+    pub(crate) fn streaming_record_ping(&self, peer: &mut PeerState, rtt: Duration) {
+        if self.streaming {
+            peer.streaming_pings.add(rtt.as_secs_f64());
+        }
+    }
+
+    pub(crate) fn streaming_record_rate(&self, peer: &mut PeerState, rate: Rate) {
+        if self.streaming {
+            peer.streaming_rates.add(rate.bytes_per_sec());
+        }
+    }
+}
+
+#[test]
+fn streaming_keeps_no_samples() {
+    let stats = Stats::new(2, "1".to_string()).with_streaming();
+    for rtt in [10, 20, 30, 40] {
+        stats.add_ping("2".to_string(), Duration::from_millis(rtt));
+    }
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 3_000);
+    assert!(stats.pings_to_peers.is_empty());
+    assert!(stats.transmissions_rates.is_empty());
+    let peer = stats.snapshot().peers.remove(0);
+    let ping = peer.ping.unwrap();
+    let windowed = Summary::from_durations(&[10, 20, 30, 40].map(Duration::from_millis)).unwrap();
+    assert_eq!(ping.samples, 4);
+    assert_eq!(ping.mean, windowed.mean);
+    assert!(ping.std_dev.abs_diff(windowed.std_dev) < Duration::from_nanos(10));
+    assert!(ping.error.abs_diff(windowed.error) < Duration::from_nanos(10));
+    let rate = peer.transmission_rate.unwrap();
+    assert_eq!(rate.mean, Rate::from_bytes_per_sec(2_000.0));
+    assert_eq!(rate.std_dev, Rate::from_bytes_per_sec(1_000.0));
+}
@@ -0,0 +1,163 @@
+//! Concurrent stress harness for soak tests of code embedding `Stats`.
+//!
+//! Threads record a seeded mix of operations against a shared `Stats`,
+//! taking snapshots in between, and the invariants of the snapshots are asserted,
+//! so a violation panics the calling test.
+
+use crate::{DisconnectReason, RngSource, SeededRng, Stats, StatsSnapshot};
+use std::{
+    collections::BTreeSet,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// Shape of the load, every thread runs `operations` operations.
+#[derive(Debug, Clone)]
+pub struct Stress {
+    pub threads: usize,
+    pub operations: usize,
+    /// Number of peers active at the same time
+    pub peers: usize,
+    /// Operations after which the active peers are replaced by new ones, `0` disables churn
+    pub churn_every: usize,
+    /// Fraction of operations taking a snapshot instead of recording
+    pub snapshots: f64,
+    pub seed: u64,
+}
+
+impl Default for Stress {
+    fn default() -> Self {
+        Self {
+            threads: 4,
+            operations: 1_000,
+            peers: 16,
+            churn_every: 100,
+            snapshots: 0.01,
+            seed: 0,
+        }
+    }
+}
+
+/// Counts of the operations done by `Stress::run`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StressReport {
+    pub pings: usize,
+    pub transmissions: usize,
+    pub gauges: usize,
+    pub connects: usize,
+    pub disconnects: usize,
+    pub snapshots: usize,
+}
+
+impl Stress {
+    /// Runs the load against `stats` and asserts the invariants of every snapshot taken.
+    /// Disconnects of `stats` are expected to start at zero.
+    pub fn run(&self, stats: &Stats) -> StressReport {
+        let pings = AtomicUsize::new(0);
+        let transmissions = AtomicUsize::new(0);
+        let gauges = AtomicUsize::new(0);
+        let connects = AtomicUsize::new(0);
+        let disconnects = AtomicUsize::new(0);
+        let snapshots = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for thread in 0..self.threads {
+                let (pings, transmissions, gauges) = (&pings, &transmissions, &gauges);
+                let (connects, disconnects, snapshots) = (&connects, &disconnects, &snapshots);
+                scope.spawn(move || {
+                    let mut rng = SeededRng::new(self.seed.wrapping_add(thread as u64));
+                    for operation in 0..self.operations {
+                        if rng.happens(self.snapshots) {
+                            check_invariants(stats, &stats.snapshot());
+                            snapshots.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        let generation = operation.checked_div(self.churn_every).unwrap_or(0);
+                        let peer_id =
+                            format!("{}", generation * self.peers + rng.below(self.peers));
+                        let value = Duration::from_micros(1 + rng.below(1_000_000) as u64);
+                        match rng.below(5) {
+                            0 | 1 => {
+                                stats.add_ping(peer_id, value);
+                                pings.fetch_add(1, Ordering::Relaxed);
+                            }
+                            2 => {
+                                stats.add_transmission(peer_id, value, rng.below(1 << 20) as u64);
+                                transmissions.fetch_add(1, Ordering::Relaxed);
+                            }
+                            3 => {
+                                stats.record_gauge(peer_id, "load", value.as_secs_f64());
+                                gauges.fetch_add(1, Ordering::Relaxed);
+                            }
+                            _ => {
+                                if rng.happens(0.5) {
+                                    stats.record_connected(peer_id);
+                                    connects.fetch_add(1, Ordering::Relaxed);
+                                } else {
+                                    stats.record_disconnected(peer_id, DisconnectReason::Idle);
+                                    disconnects.fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        let report = StressReport {
+            pings: pings.into_inner(),
+            transmissions: transmissions.into_inner(),
+            gauges: gauges.into_inner(),
+            connects: connects.into_inner(),
+            disconnects: disconnects.into_inner(),
+            snapshots: snapshots.into_inner(),
+        };
+        let snapshot = stats.snapshot();
+        check_invariants(stats, &snapshot);
+        // Reconnects are counted as superseded disconnects, which are not recorded directly
+        assert_eq!(snapshot.disconnects.idle as usize, report.disconnects);
+        assert!(snapshot.disconnects.superseded as usize <= report.connects);
+        report
+    }
+}
+
+fn check_invariants(stats: &Stats, snapshot: &StatsSnapshot) {
+    let mut peer_ids = BTreeSet::new();
+    for peer in &snapshot.peers {
+        assert!(
+            peer_ids.insert(&peer.peer_id),
+            "Duplicate peer {}",
+            peer.peer_id
+        );
+        if let Some(ping) = &peer.ping {
+            assert!(ping.samples > 0 && ping.samples <= stats.window_size);
+            assert!(ping.error <= ping.std_dev * 2);
+        }
+        if let Some(rate) = &peer.transmission_rate {
+            assert!(rate.samples > 0 && rate.samples <= stats.window_size);
+            assert!(rate.mean.bytes_per_sec() >= 0.0);
+        }
+        for gauge in peer.gauges.values() {
+            assert!(gauge.samples > 0 && gauge.samples <= stats.window_size);
+        }
+        assert!(peer.sessions.len() <= stats.session_history);
+        assert!(peer.disconnects.total() <= snapshot.disconnects.total());
+    }
+}
+
+#[test]
+fn stats_survive_concurrent_load() {
+    let stats = Stats::new(20, "0".to_string());
+    let report = Stress {
+        threads: 4,
+        operations: 500,
+        ..Stress::default()
+    }
+    .run(&stats);
+    let recorded = report.pings + report.transmissions + report.gauges;
+    assert_eq!(
+        recorded + report.connects + report.disconnects + report.snapshots,
+        4 * 500
+    );
+    assert!(report.snapshots > 0);
+    assert!(stats.snapshot().peers.len() > 16);
+}
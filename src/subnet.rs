@@ -0,0 +1,172 @@
+use crate::{redact::pooled, PeerState, PeerSummary, Rate, Stats, StatsSnapshot, Summary};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+/// Network of peer addresses, a /24 of IPv4 or a /48 of IPv6 addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subnet {
+    /// Address with the bits after the prefix cleared
+    pub network: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl Subnet {
+    /// IPv4 addresses mapped to IPv6 are grouped with the IPv4 addresses.
+    pub fn of(address: IpAddr) -> Self {
+        match address {
+            IpAddr::V6(address) => match address.to_ipv4_mapped() {
+                Some(address) => Self::v4(address),
+                None => {
+                    let mut segments = address.segments();
+                    segments[3..].iter_mut().for_each(|segment| *segment = 0);
+                    Self {
+                        network: Ipv6Addr::from(segments).into(),
+                        prefix_len: 48,
+                    }
+                }
+            },
+            IpAddr::V4(address) => Self::v4(address),
+        }
+    }
+
+    fn v4(address: Ipv4Addr) -> Self {
+        let [a, b, c, _] = address.octets();
+        Self {
+            network: Ipv4Addr::new(a, b, c, 0).into(),
+            prefix_len: 24,
+        }
+    }
+}
+
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// Stats of all samples of the peers in a subnet, as if they were one window.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubnetSummary {
+    pub subnet: Subnet,
+    /// Ids of the peers in the subnet, ordered
+    pub peers: Vec<String>,
+    pub ping: Option<Summary>,
+    pub transmission_rate: Option<Summary<Rate>>,
+}
+
+/// Summaries of every subnet of the peers of a snapshot with a known address, ordered by subnet.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubnetReport {
+    pub subnets: Vec<SubnetSummary>,
+}
+
+impl SubnetReport {
+    pub fn get(&self, subnet: Subnet) -> Option<&SubnetSummary> {
+        self.subnets.iter().find(|summary| summary.subnet == subnet)
+    }
+}
+
+impl SubnetSummary {
+    fn new(subnet: Subnet, peers: &[&PeerSummary], confidence_scale: f64) -> Self {
+        Self {
+            subnet,
+            peers: peers.iter().map(|peer| peer.peer_id.clone()).collect(),
+            ping: pooled(
+                peers.iter().filter_map(|peer| peer.ping.as_ref()),
+                |duration| duration.as_secs_f64(),
+                Duration::from_secs_f64,
+                confidence_scale,
+            ),
+            transmission_rate: pooled(
+                peers
+                    .iter()
+                    .filter_map(|peer| peer.transmission_rate.as_ref()),
+                Rate::bytes_per_sec,
+                Rate::from_bytes_per_sec,
+                confidence_scale,
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SubnetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Peers by subnet:")?;
+        for summary in &self.subnets {
+            write!(f, "{} {} peers", summary.subnet, summary.peers.len())?;
+            if let Some(ping) = &summary.ping {
+                write!(f, " ping {:?}±{:?}", ping.mean, ping.error)?;
+            }
+            if let Some(rate) = &summary.transmission_rate {
+                write!(f, " rate {}±{}", rate.mean, rate.error)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl StatsSnapshot {
+    /// Groups the peers with an address set by `Stats::set_address` by their `Subnet`,
+    /// to see whether performance correlates with networks or hosting providers.
+    pub fn subnet_report(&self) -> SubnetReport {
+        let mut subnets: BTreeMap<Subnet, Vec<&PeerSummary>> = BTreeMap::new();
+        for peer in &self.peers {
+            if let Some(address) = peer.address {
+                subnets.entry(Subnet::of(address)).or_default().push(peer);
+            }
+        }
+        SubnetReport {
+            subnets: subnets
+                .into_iter()
+                .map(|(subnet, peers)| SubnetSummary::new(subnet, &peers, self.confidence_scale()))
+                .collect(),
+        }
+    }
+}
+
+impl Stats {
+    /// Replaces the network address of the peer, which does not count as a sample
+    /// for `PeerSummary::last_seen`.
+    pub fn set_address(&self, peer_id: String, address: IpAddr) {
+        trace_span!("set_address");
+        let _recording = self.recording(&peer_id);
+        let now = self.clock.now();
+        self.peers.alter(peer_id, |peer| {
+            let mut peer = peer.unwrap_or_else(|| PeerState::new(now));
+            peer.address = Some(address);
+            Some(peer)
+        });
+    }
+}
+
+#[test]
+fn peers_are_grouped_by_subnet() {
+    let stats = Stats::new(100, "1".to_string());
+    let millis = Duration::from_millis;
+    let address = |text: &str| text.parse::<IpAddr>().unwrap();
+    stats.set_address("2".to_string(), address("192.0.2.10"));
+    stats.set_address("3".to_string(), address("::ffff:192.0.2.200"));
+    stats.set_address("4".to_string(), address("2001:db8:1:2::1"));
+    stats.add_ping("2".to_string(), millis(10));
+    stats.add_ping("3".to_string(), millis(30));
+    stats.add_ping("4".to_string(), millis(80));
+    stats.add_ping("5".to_string(), millis(80));
+    let report = stats.snapshot().subnet_report();
+    assert_eq!(report.subnets.len(), 2);
+    let v4 = report.get(Subnet::of(address("192.0.2.1"))).unwrap();
+    assert_eq!(v4.peers, vec!["2".to_string(), "3".to_string()]);
+    assert_eq!(v4.ping.as_ref().unwrap().mean, millis(20));
+    assert_eq!(v4.ping.as_ref().unwrap().samples, 2);
+    assert_eq!(report.subnets[1].subnet.to_string(), "2001:db8:1::/48");
+    assert!(report
+        .to_string()
+        .contains("192.0.2.0/24 2 peers ping 20ms"));
+}
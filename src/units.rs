@@ -0,0 +1,278 @@
+use crate::Summary;
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, Sub},
+    time::{Duration, SystemTime},
+};
+
+/// Amount of transferred data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct ByteSize(pub u64);
+
+/// Throughput in bytes per second.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Rate(f64);
+
+/// Round trip time constructed with an explicit unit, see `Stats::add_rtt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rtt(Duration);
+
+impl Rtt {
+    /// Longest plausible round trip time, longer ones are most likely unit mistakes.
+    pub const MAX: Duration = Duration::from_secs(5 * 60);
+
+    pub fn from_secs_f64(secs: f64) -> Self {
+        Self::checked(Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX))
+    }
+
+    pub fn from_millis(millis: u64) -> Self {
+        Self::checked(Duration::from_millis(millis))
+    }
+
+    pub fn from_micros(micros: u64) -> Self {
+        Self::checked(Duration::from_micros(micros))
+    }
+
+    pub fn from_nanos(nanos: u64) -> Self {
+        Self::checked(Duration::from_nanos(nanos))
+    }
+
+    /// Time from sending the probe to receiving the reply,
+    /// `None` if the clock went backwards in between.
+    pub fn between(sent: SystemTime, received: SystemTime) -> Option<Self> {
+        received.duration_since(sent).ok().map(Self::checked)
+    }
+
+    /// Neither zero, which clock math produces when it saturates, nor above `Rtt::MAX`.
+    pub fn is_plausible(self) -> bool {
+        !self.0.is_zero() && self.0 <= Self::MAX
+    }
+
+    pub fn duration(self) -> Duration {
+        self.0
+    }
+
+    fn checked(rtt: Duration) -> Self {
+        let rtt = Self(rtt);
+        debug_assert!(
+            rtt.is_plausible(),
+            "Implausible rtt {:?}, check its unit",
+            rtt.0
+        );
+        rtt
+    }
+}
+
+impl From<Duration> for Rtt {
+    fn from(rtt: Duration) -> Self {
+        Self(rtt)
+    }
+}
+
+impl ByteSize {
+    pub fn bytes(self) -> u64 {
+        self.0
+    }
+}
+
+impl Rate {
+    pub fn from_bytes_per_sec(bytes_per_sec: f64) -> Self {
+        Rate(bytes_per_sec)
+    }
+
+    pub fn bytes_per_sec(self) -> f64 {
+        self.0
+    }
+
+    /// Time it takes to transfer a single byte at this rate.
+    pub fn from_time_per_byte(time: Duration) -> Self {
+        Rate(1.0 / time.as_secs_f64())
+    }
+
+    /// Time it takes to transfer a single byte at this rate, the inverse of
+    /// `from_time_per_byte`.
+    pub fn time_per_byte(self) -> Duration {
+        Duration::try_from_secs_f64(1.0 / self.0).unwrap_or(Duration::MAX)
+    }
+}
+
+// A single integer impl keeps literals inferring, so `u32` counts widen with `u64::from`
+impl From<u64> for ByteSize {
+    fn from(bytes: u64) -> Self {
+        ByteSize(bytes)
+    }
+}
+
+impl Add for ByteSize {
+    type Output = ByteSize;
+
+    fn add(self, other: ByteSize) -> ByteSize {
+        ByteSize(self.0.saturating_add(other.0))
+    }
+}
+
+impl AddAssign for ByteSize {
+    fn add_assign(&mut self, other: ByteSize) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for ByteSize {
+    type Output = ByteSize;
+
+    fn sub(self, other: ByteSize) -> ByteSize {
+        ByteSize(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Sum for ByteSize {
+    fn sum<I: Iterator<Item = ByteSize>>(iter: I) -> ByteSize {
+        iter.fold(ByteSize(0), Add::add)
+    }
+}
+
+impl Div<Duration> for ByteSize {
+    type Output = Rate;
+
+    fn div(self, time: Duration) -> Rate {
+        Rate(self.0 as f64 / time.as_secs_f64())
+    }
+}
+
+impl Add for Rate {
+    type Output = Rate;
+
+    fn add(self, other: Rate) -> Rate {
+        Rate(self.0 + other.0)
+    }
+}
+
+impl Sub for Rate {
+    type Output = Rate;
+
+    fn sub(self, other: Rate) -> Rate {
+        Rate(self.0 - other.0)
+    }
+}
+
+impl Mul<f64> for Rate {
+    type Output = Rate;
+
+    fn mul(self, factor: f64) -> Rate {
+        Rate(self.0 * factor)
+    }
+}
+
+impl Div<f64> for Rate {
+    type Output = Rate;
+
+    fn div(self, divisor: f64) -> Rate {
+        Rate(self.0 / divisor)
+    }
+}
+
+impl Mul<Duration> for Rate {
+    type Output = ByteSize;
+
+    fn mul(self, time: Duration) -> ByteSize {
+        ByteSize((self.0 * time.as_secs_f64()).max(0.0) as u64)
+    }
+}
+
+/// Writes `value` with the largest decimal unit prefix keeping it at least `1`.
+fn write_scaled(f: &mut fmt::Formatter<'_>, value: f64, unit: &str) -> fmt::Result {
+    const PREFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+    let mut scaled = value;
+    let mut prefix = 0;
+    while scaled.is_finite() && scaled.abs() >= 1000.0 && prefix + 1 < PREFIXES.len() {
+        scaled /= 1000.0;
+        prefix += 1;
+    }
+    let precision = f.precision().unwrap_or(1);
+    write!(f, "{:.*} {}{}", precision, scaled, PREFIXES[prefix], unit)
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 1000 {
+            write!(f, "{} B", self.0)
+        } else {
+            write_scaled(f, self.0 as f64, "B")
+        }
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_scaled(f, self.0, "B/s")
+    }
+}
+
+impl Summary<Rate> {
+    pub(crate) fn from_rates(rates: &[Rate]) -> Option<Self> {
+        let values: Vec<_> = rates.iter().map(|rate| rate.0).collect();
+        let summary = Summary::from_values(&values)?;
+        Some(Self {
+            samples: summary.samples,
+            mean: Rate(summary.mean),
+            std_dev: Rate(summary.std_dev),
+            error: Rate(summary.error),
+            score: None,
+            warming_up: false,
+        })
+    }
+}
+
+#[test]
+fn units_are_scaled_in_display() {
+    assert_eq!(ByteSize(512).to_string(), "512 B");
+    assert_eq!(ByteSize(1_500).to_string(), "1.5 kB");
+    assert_eq!(format!("{:.2}", ByteSize(3_250_000_000)), "3.25 GB");
+    assert_eq!(Rate(12.0).to_string(), "12.0 B/s");
+    assert_eq!(Rate(2_500_000.0).to_string(), "2.5 MB/s");
+}
+
+#[test]
+fn rtt_units_are_explicit() {
+    assert_eq!(Rtt::from_millis(20), Rtt::from_micros(20_000));
+    assert_eq!(
+        Rtt::from_secs_f64(0.5).duration(),
+        Duration::from_millis(500)
+    );
+    let sent = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+    assert_eq!(Rtt::between(sent + Duration::from_millis(1), sent), None);
+    assert!(!Rtt::from(Duration::from_secs(0)).is_plausible());
+    assert!(!Rtt::from(Duration::from_secs(301)).is_plausible());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Implausible rtt")]
+fn rtt_in_wrong_unit_is_caught() {
+    // Microseconds passed as milliseconds
+    Rtt::from_millis(20_000_000);
+}
+
+#[test]
+fn units_arithmetic() {
+    let rate = ByteSize(1_000) / Duration::from_millis(500);
+    assert_eq!(rate, Rate(2_000.0));
+    assert_eq!(rate * Duration::from_secs(3), ByteSize(6_000));
+    assert_eq!(rate.time_per_byte(), Duration::from_micros(500));
+    assert_eq!(ByteSize(5) - ByteSize(7), ByteSize(0));
+    assert_eq!(
+        vec![ByteSize(1), ByteSize(2)].into_iter().sum::<ByteSize>(),
+        ByteSize(3)
+    );
+}
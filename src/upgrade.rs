@@ -0,0 +1,137 @@
+use crate::{PushLossy, Stats, Summary, Window};
+use std::{fmt, time::Duration};
+
+/// Layer of the connection upgrade pipeline, in the order they are negotiated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UpgradeStage {
+    /// Dialing the transport, e.g. the TCP handshake
+    Dial,
+    /// Security handshake, e.g. Noise or TLS
+    Security,
+    /// Stream multiplexer negotiation, e.g. yamux
+    Muxer,
+}
+
+impl UpgradeStage {
+    pub const ALL: [UpgradeStage; 3] = [
+        UpgradeStage::Dial,
+        UpgradeStage::Security,
+        UpgradeStage::Muxer,
+    ];
+
+    fn index(self) -> usize {
+        match self {
+            UpgradeStage::Dial => 0,
+            UpgradeStage::Security => 1,
+            UpgradeStage::Muxer => 2,
+        }
+    }
+}
+
+/// Latency summaries for each `UpgradeStage`.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UpgradeLatencies {
+    pub dial: Option<Summary>,
+    pub security: Option<Summary>,
+    pub muxer: Option<Summary>,
+}
+
+impl UpgradeLatencies {
+    pub(crate) fn from_windows(windows: &[Window<Duration>; 3]) -> Option<Self> {
+        let upgrades = Self {
+            dial: Summary::from_durations(&windows[0]),
+            security: Summary::from_durations(&windows[1]),
+            muxer: Summary::from_durations(&windows[2]),
+        };
+        if upgrades == Self::default() {
+            None
+        } else {
+            Some(upgrades)
+        }
+    }
+
+    pub fn get(&self, stage: UpgradeStage) -> Option<&Summary> {
+        match stage {
+            UpgradeStage::Dial => self.dial.as_ref(),
+            UpgradeStage::Security => self.security.as_ref(),
+            UpgradeStage::Muxer => self.muxer.as_ref(),
+        }
+    }
+
+    /// Stage with the highest mean latency.
+    pub fn dominant(&self) -> Option<UpgradeStage> {
+        UpgradeStage::ALL
+            .iter()
+            .filter_map(|stage| Some((*stage, self.get(*stage)?.mean)))
+            .max_by_key(|(_, mean)| *mean)
+            .map(|(stage, _)| stage)
+    }
+
+    pub(crate) fn summaries_mut(&mut self) -> impl Iterator<Item = &mut Summary> {
+        self.dial
+            .as_mut()
+            .into_iter()
+            .chain(self.security.as_mut())
+            .chain(self.muxer.as_mut())
+    }
+}
+
+impl fmt::Display for UpgradeLatencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for stage in UpgradeStage::ALL.iter() {
+            if let Some(summary) = self.get(*stage) {
+                write!(
+                    f,
+                    "{}{:?} {:?}±{:?}",
+                    separator, stage, summary.mean, summary.error
+                )?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Records the timings of the layers of a single connection upgrade to the peer,
+    /// so that a regression of the total connect time can be attributed to one of them.
+    pub fn record_upgrade(&self, peer_id: String, timings: &[(UpgradeStage, Duration)]) {
+        trace_span!("record_upgrade");
+        let _recording = self.recording(&peer_id);
+        let window_size = self.window_size;
+        self.update_peer(&peer_id, |peer| {
+            for (stage, time) in timings {
+                peer.upgrades[stage.index()].push_lossy(*time, window_size);
+            }
+        });
+    }
+}
+
+#[test]
+fn upgrade_stages_are_summarized_separately() {
+    let stats = Stats::new(100, "1".to_string());
+    let millis = Duration::from_millis;
+    stats.record_upgrade(
+        "2".to_string(),
+        &[
+            (UpgradeStage::Dial, millis(20)),
+            (UpgradeStage::Security, millis(60)),
+            (UpgradeStage::Muxer, millis(5)),
+        ],
+    );
+    stats.record_upgrade(
+        "2".to_string(),
+        &[
+            (UpgradeStage::Dial, millis(40)),
+            (UpgradeStage::Security, millis(80)),
+        ],
+    );
+    let upgrades = stats.snapshot().peers.remove(0).upgrades.unwrap();
+    assert_eq!(upgrades.dial.as_ref().unwrap().mean, millis(30));
+    assert_eq!(upgrades.muxer.as_ref().unwrap().samples, 1);
+    assert_eq!(upgrades.dominant(), Some(UpgradeStage::Security));
+    assert!(upgrades.to_string().starts_with("Dial 30ms±"));
+}
@@ -0,0 +1,70 @@
+use crate::{Rate, Stats, Summary};
+use std::{fmt, time::Duration};
+
+/// Summaries of only the latest samples of the windows, see `Stats::with_window_views`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowView {
+    /// Number of the latest samples summarized
+    pub window: usize,
+    pub ping: Option<Summary>,
+    pub transmission_rate: Option<Summary<Rate>>,
+}
+
+impl fmt::Display for WindowView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "last {}", self.window)?;
+        if let Some(ping) = &self.ping {
+            write!(f, " ping {:?}±{:?}", ping.mean, ping.error)?;
+        }
+        if let Some(rate) = &self.transmission_rate {
+            write!(f, " rate {}±{}", rate.mean, rate.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stats {
+    /// Also summarizes the pings and transmission rates of each peer over the latest
+    /// `windows` samples, like load averages over several periods, as `PeerSummary::views`.
+    /// Views share the samples of the window, so they are at most `window_size` long.
+    pub fn with_window_views(mut self, windows: &[usize]) -> Self {
+        let mut windows = windows.to_vec();
+        windows.sort_unstable();
+        windows.dedup();
+        self.window_views = windows;
+        self
+    }
+
+    pub(crate) fn window_views(&self, pings: &[Duration], rates: &[Rate]) -> Vec<WindowView> {
+        self.window_views
+            .iter()
+            .map(|window| WindowView {
+                window: *window,
+                ping: Summary::from_durations(latest(pings, *window)),
+                transmission_rate: Summary::from_rates(latest(rates, *window)),
+            })
+            .filter(|view| view.ping.is_some() || view.transmission_rate.is_some())
+            .collect()
+    }
+}
+
+fn latest<T>(samples: &[T], window: usize) -> &[T] {
+    &samples[samples.len().saturating_sub(window)..]
+}
+
+#[test]
+fn views_summarize_latest_samples() {
+    let stats = Stats::new(100, "1".to_string()).with_window_views(&[10, 2]);
+    let millis = Duration::from_millis;
+    for rtt in [100, 100, 100, 10, 30] {
+        stats.add_ping("2".to_string(), millis(rtt));
+    }
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_000);
+    let views = stats.snapshot().peers.remove(0).views;
+    assert_eq!(views.len(), 2);
+    assert_eq!(views[0].window, 2);
+    assert_eq!(views[0].ping.as_ref().unwrap().mean, millis(20));
+    assert_eq!(views[1].ping.as_ref().unwrap().samples, 5);
+    assert_eq!(views[1].transmission_rate.as_ref().unwrap().samples, 1);
+}
@@ -0,0 +1,183 @@
+use crate::{Rfc3339, Stats};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    convert::TryFrom,
+    fmt,
+    sync::atomic::Ordering,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Sign that the measurement pipeline itself stopped producing samples.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Starvation {
+    /// No ping to any peer for longer than the watchdog allows
+    NoPings { last_ping: Option<SystemTime> },
+    /// A watched peer got no samples for longer than it allows
+    PeerSilent {
+        peer_id: String,
+        last_seen: Option<SystemTime>,
+    },
+}
+
+impl fmt::Display for Starvation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Starvation::NoPings {
+                last_ping: Some(time),
+            } => write!(f, "no pings since {}", Rfc3339(*time)),
+            Starvation::NoPings { last_ping: None } => write!(f, "no pings recorded"),
+            Starvation::PeerSilent {
+                peer_id,
+                last_seen: Some(time),
+            } => write!(f, "{:?} silent since {}", peer_id, Rfc3339(*time)),
+            Starvation::PeerSilent {
+                peer_id,
+                last_seen: None,
+            } => write!(f, "{:?} never seen", peer_id),
+        }
+    }
+}
+
+/// Nanoseconds since the epoch of `time`, `0` stands for no time.
+pub(crate) fn to_nanos(time: SystemTime) -> u64 {
+    let nanos = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    u64::try_from(nanos).unwrap_or(u64::MAX).max(1)
+}
+
+pub(crate) fn from_nanos(nanos: u64) -> Option<SystemTime> {
+    if nanos == 0 {
+        None
+    } else {
+        Some(UNIX_EPOCH + Duration::from_nanos(nanos))
+    }
+}
+
+impl Stats {
+    /// Reports `Starvation::NoPings` from `check_starvation` after `no_pings_for` without pings.
+    pub fn with_watchdog(mut self, no_pings_for: Duration) -> Self {
+        self.no_pings_for = Some(no_pings_for);
+        self
+    }
+
+    /// Reports `Starvation::PeerSilent` from `check_starvation` once the peer
+    /// got no samples for `silent_for`.
+    pub fn watch_peer(&self, peer_id: String, silent_for: Duration) {
+        let now = self.clock.now();
+        self.watched_peers.insert(peer_id, (silent_for, now));
+    }
+
+    pub fn unwatch_peer(&self, peer_id: &str) {
+        self.watched_peers.remove(peer_id);
+    }
+
+    /// Time of the latest ping to any peer.
+    pub fn last_ping_time(&self) -> Option<SystemTime> {
+        from_nanos(self.last_ping.load(Ordering::Relaxed))
+    }
+
+    /// Time of the latest sample of any kind for any peer, for liveness checks
+    /// of the measurement pipeline.
+    pub fn last_ingest_time(&self) -> Option<SystemTime> {
+        from_nanos(self.last_ingest.load(Ordering::Relaxed))
+    }
+
+    /// Checks the watchdog limits, meant to be polled periodically.
+    /// Each starvation is also logged as a `tracing` warning if the feature is enabled.
+    pub fn check_starvation(&self) -> Vec<Starvation> {
+        trace_span!("check_starvation");
+        let now = self.clock.now();
+        let silent_since = |since: SystemTime, limit: Duration| {
+            now.duration_since(since).unwrap_or_default() > limit
+        };
+        let mut starvations = Vec::new();
+        if let Some(limit) = self.no_pings_for {
+            let last_ping = self.last_ping_time();
+            if silent_since(last_ping.unwrap_or(self.started), limit) {
+                starvations.push(Starvation::NoPings { last_ping });
+            }
+        }
+        let watched = self.watched_peers();
+        for (peer_id, limit, watched_since) in watched {
+            let last_seen = self.last_seen(&peer_id);
+            if silent_since(last_seen.unwrap_or(watched_since), limit) {
+                starvations.push(Starvation::PeerSilent { peer_id, last_seen });
+            }
+        }
+        #[cfg(feature = "tracing")]
+        for starvation in &starvations {
+            tracing::warn!(%starvation, "measurement starvation");
+        }
+        starvations
+    }
+
+    /// Watched peers ordered by peer id with their silence limit and watch start.
+    fn watched_peers(&self) -> Vec<(String, Duration, SystemTime)> {
+        let watched = RefCell::new(BTreeMap::new());
+        self.watched_peers.retain(|peer_id, (limit, since)| {
+            watched
+                .borrow_mut()
+                .insert(peer_id.clone(), (*limit, *since));
+            true
+        });
+        watched
+            .into_inner()
+            .into_iter()
+            .map(|(peer_id, (limit, since))| (peer_id, limit, since))
+            .collect()
+    }
+}
+
+#[test]
+fn starvation_is_detected() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(UNIX_EPOCH));
+    let stats = Stats::new(100, "1".to_string())
+        .with_clock(clock.clone())
+        .with_watchdog(Duration::from_secs(10));
+    stats.watch_peer("2".to_string(), Duration::from_secs(30));
+    assert!(stats.check_starvation().is_empty());
+    clock.advance(Duration::from_secs(11));
+    assert_eq!(
+        stats.check_starvation(),
+        vec![Starvation::NoPings { last_ping: None }]
+    );
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    assert!(stats.check_starvation().is_empty());
+    clock.advance(Duration::from_secs(31));
+    let starvations = stats.check_starvation();
+    assert_eq!(starvations.len(), 2);
+    assert_eq!(
+        starvations[1].to_string(),
+        "\"2\" silent since 1970-01-01T00:00:11Z"
+    );
+    stats.unwatch_peer("2");
+    assert_eq!(stats.check_starvation().len(), 1);
+}
+
+#[test]
+fn last_ingest_time_follows_any_sample() {
+    use crate::ManualClock;
+    use std::sync::Arc;
+
+    let clock = Arc::new(ManualClock::new(UNIX_EPOCH + Duration::from_secs(1)));
+    let stats = Stats::new(100, "1".to_string()).with_clock(clock.clone());
+    assert_eq!(stats.last_ingest_time(), None);
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    clock.advance(Duration::from_secs(1));
+    stats.record_gauge("2".to_string(), "queue_depth", 1.0);
+    assert_eq!(
+        stats.last_ingest_time(),
+        Some(UNIX_EPOCH + Duration::from_secs(2))
+    );
+    assert_eq!(
+        stats.last_ping_time(),
+        Some(UNIX_EPOCH + Duration::from_secs(1))
+    );
+}
@@ -0,0 +1,208 @@
+use crate::{PeerSummary, Rate, Summary};
+use std::{
+    fmt::{self, Alignment, Write as _},
+    time::Duration,
+};
+
+/// Unit of the durations of a `MetricCell`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Secs,
+    Millis,
+    Micros,
+}
+
+impl TimeUnit {
+    pub(crate) fn scale(self, duration: Duration) -> f64 {
+        match self {
+            TimeUnit::Secs => duration.as_secs_f64(),
+            TimeUnit::Millis => duration.as_secs_f64() * 1e3,
+            TimeUnit::Micros => duration.as_secs_f64() * 1e6,
+        }
+    }
+
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            TimeUnit::Secs => "s",
+            TimeUnit::Millis => "ms",
+            TimeUnit::Micros => "µs",
+        }
+    }
+}
+
+/// Mean and error of a summary formatted like in the report of `Stats`, for embedding
+/// into other `Display` implementations.
+///
+/// Width, fill and alignment of the formatter apply to the whole cell, left aligned by
+/// default. Precision sets the decimals of rates and of durations with a `TimeUnit`.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricCell<'a, T = Duration> {
+    summary: &'a Summary<T>,
+    unit: Option<TimeUnit>,
+    error: bool,
+}
+
+impl<'a, T> MetricCell<'a, T> {
+    pub fn new(summary: &'a Summary<T>) -> Self {
+        Self {
+            summary,
+            unit: None,
+            error: true,
+        }
+    }
+
+    /// Leaves out the `±error` part.
+    pub fn without_error(mut self) -> Self {
+        self.error = false;
+        self
+    }
+
+    fn warm_up_note(&self) -> &'static str {
+        if self.summary.warming_up {
+            " (warming up)"
+        } else {
+            ""
+        }
+    }
+}
+
+impl MetricCell<'_> {
+    /// Writes durations as decimals of `unit` instead of their `Debug` format.
+    pub fn unit(mut self, unit: TimeUnit) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    fn duration(&self, text: &mut String, duration: Duration, precision: usize) -> fmt::Result {
+        match self.unit {
+            Some(unit) => write!(
+                text,
+                "{:.*}{}",
+                precision,
+                unit.scale(duration),
+                unit.symbol()
+            ),
+            None => write!(text, "{:?}", duration),
+        }
+    }
+}
+
+impl fmt::Display for MetricCell<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(1);
+        let mut text = String::new();
+        self.duration(&mut text, self.summary.mean, precision)?;
+        if self.error {
+            text.push('±');
+            self.duration(&mut text, self.summary.error, precision)?;
+        }
+        text.push_str(self.warm_up_note());
+        pad(f, &text)
+    }
+}
+
+impl fmt::Display for MetricCell<'_, Rate> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(1);
+        let mut text = format!("{:.*}", precision, self.summary.mean);
+        if self.error {
+            write!(text, "±{:.*}", precision, self.summary.error)?;
+        }
+        text.push_str(self.warm_up_note());
+        pad(f, &text)
+    }
+}
+
+/// Ping and transmission rate of a peer on a single line, for status screens.
+///
+/// Width of the formatter pads the peer id, so that the cells of many lines line up.
+#[derive(Debug, Clone, Copy)]
+pub struct PeerLine<'a> {
+    peer: &'a PeerSummary,
+    unit: Option<TimeUnit>,
+}
+
+impl<'a> PeerLine<'a> {
+    pub fn new(peer: &'a PeerSummary) -> Self {
+        Self { peer, unit: None }
+    }
+
+    /// Unit of the ping cell, see `MetricCell::unit`.
+    pub fn unit(mut self, unit: TimeUnit) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+}
+
+impl fmt::Display for PeerLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(1);
+        pad(f, &format!("{:?}", self.peer.peer_id))?;
+        if let Some(ping) = &self.peer.ping {
+            let cell = MetricCell::new(ping);
+            match self.unit {
+                Some(unit) => write!(f, " ping {:.*}", precision, cell.unit(unit))?,
+                None => write!(f, " ping {:.*}", precision, cell)?,
+            }
+        }
+        if let Some(rate) = &self.peer.transmission_rate {
+            write!(f, " rate {:.*}", precision, MetricCell::new(rate))?;
+        }
+        Ok(())
+    }
+}
+
+/// Pads `text` to the width of `f` like `Formatter::pad`, which would also cut it
+/// to the precision.
+fn pad(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    let padding = f.width().unwrap_or(0).saturating_sub(text.chars().count());
+    let (before, after) = match f.align() {
+        Some(Alignment::Right) => (padding, 0),
+        Some(Alignment::Center) => (padding / 2, padding - padding / 2),
+        _ => (0, padding),
+    };
+    let fill = f.fill();
+    for _ in 0..before {
+        f.write_char(fill)?;
+    }
+    f.write_str(text)?;
+    for _ in 0..after {
+        f.write_char(fill)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn cells_are_aligned_with_units() {
+    use crate::Stats;
+
+    let stats = Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_micros(12_340));
+    stats.add_transmission("2".to_string(), Duration::from_secs(1), 1_500);
+    let peer = stats.snapshot().peers.remove(0);
+    let mut ping = peer.ping.clone().unwrap();
+    assert_eq!(MetricCell::new(&ping).to_string(), "12.34ms±0ns");
+    assert_eq!(
+        format!(
+            "[{:>10.2}]",
+            MetricCell::new(&ping)
+                .unit(TimeUnit::Millis)
+                .without_error()
+        ),
+        "[   12.34ms]"
+    );
+    ping.warming_up = true;
+    assert_eq!(
+        format!("{:.3}", MetricCell::new(&ping).unit(TimeUnit::Secs)),
+        "0.012s±0.000s (warming up)"
+    );
+    let rate = peer.transmission_rate.as_ref().unwrap();
+    assert_eq!(
+        format!("[{:*^12}]", MetricCell::new(rate).without_error()),
+        "[**1.5 kB/s**]"
+    );
+    assert_eq!(
+        format!("{:<5}|", PeerLine::new(&peer).unit(TimeUnit::Micros)),
+        "\"2\"   ping 12340.0µs±0.0µs rate 1.5 kB/s±0.0 B/s|"
+    );
+}
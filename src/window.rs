@@ -0,0 +1,262 @@
+use crate::{compact::ShrinkToFit, PushLossy, Rate};
+use std::{
+    mem,
+    ops::Deref,
+    time::{Duration, SystemTime},
+};
+
+/// Samples whose windows keep running moments, as the value the moments are taken of.
+pub(crate) trait Sample: Copy {
+    fn value(self) -> f64;
+}
+
+impl Sample for Duration {
+    fn value(self) -> f64 {
+        self.as_secs_f64()
+    }
+}
+
+impl Sample for Rate {
+    fn value(self) -> f64 {
+        self.bytes_per_sec()
+    }
+}
+
+impl Sample for f64 {
+    fn value(self) -> f64 {
+        self
+    }
+}
+
+impl Sample for u64 {
+    fn value(self) -> f64 {
+        self as f64
+    }
+}
+
+/// Sum and sum of squares of the kept samples, taken from `shift` so that the variance of
+/// samples far from zero does not cancel out. Dropped samples are subtracted until the
+/// window compacts, which sums the kept samples again so that errors do not build up.
+#[derive(Debug, Clone, Copy, Default)]
+struct Moments {
+    shift: f64,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl Moments {
+    fn of<T: Sample>(samples: &[T]) -> Self {
+        let mut moments = Self {
+            shift: samples.first().map_or(0.0, |sample| sample.value()),
+            ..Self::default()
+        };
+        for sample in samples {
+            moments.add(*sample);
+        }
+        moments
+    }
+
+    fn add<T: Sample>(&mut self, sample: T) {
+        let value = sample.value() - self.shift;
+        self.sum += value;
+        self.sum_squares += value * value;
+    }
+
+    fn remove<T: Sample>(&mut self, sample: T) {
+        let value = sample.value() - self.shift;
+        self.sum -= value;
+        self.sum_squares -= value * value;
+    }
+}
+
+/// Latest samples of a metric, which unlike a `Vec` drops the oldest sample in O(1).
+/// Dropped samples stay in the buffer until they are as many as the kept ones
+/// and are then removed at once, so that the window is always a contiguous slice.
+#[derive(Debug, Clone)]
+pub(crate) struct Window<T> {
+    buffer: Vec<T>,
+    /// Index of the oldest kept sample
+    start: usize,
+    /// Times of the samples in `buffer`, only for windows filled by `push_timed`
+    times: Vec<SystemTime>,
+    /// Moments of the kept samples
+    moments: Moments,
+}
+
+impl<T> Window<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            start: 0,
+            times: Vec::new(),
+            moments: Moments::default(),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Samples recorded from `from` until before `to`, if any, with their times,
+    /// times are assumed to only grow.
+    pub(crate) fn between(
+        &self,
+        from: SystemTime,
+        to: Option<SystemTime>,
+    ) -> (&[SystemTime], &[T]) {
+        if self.times.len() != self.buffer.len() {
+            return (&[], &[]);
+        }
+        let times = &self.times[self.start..];
+        let first = times.partition_point(|time| *time < from);
+        let end = match to {
+            Some(to) => times.partition_point(|time| *time < to).max(first),
+            None => times.len(),
+        };
+        (&times[first..end], &self[first..end])
+    }
+}
+
+impl<T: Sample> Window<T> {
+    /// Pushes the sample like `push_lossy` along with the time it was recorded at.
+    pub(crate) fn push_timed(&mut self, element: T, time: SystemTime, window_size: usize) {
+        // Samples pushed without a time get the time of the first one pushed with it
+        self.times.resize(self.buffer.len(), time);
+        self.push_lossy(element, window_size);
+        self.times.push(time);
+    }
+
+    /// Mean and population standard deviation of the samples from the running moments,
+    /// in O(1) whatever the size of the window.
+    pub(crate) fn mean_and_std_dev(&self) -> Option<(f64, f64)> {
+        if self.is_empty() {
+            return None;
+        }
+        let len = self.len() as f64;
+        let mean = self.moments.sum / len;
+        let variance = (self.moments.sum_squares / len - mean * mean).max(0.0);
+        Some((self.moments.shift + mean, variance.sqrt()))
+    }
+
+    /// Drops the samples older than `cutoff`, times are assumed to only grow.
+    pub(crate) fn expire(&mut self, cutoff: SystemTime) {
+        if self.times.len() != self.buffer.len() {
+            return;
+        }
+        let expired = self.times[self.start..].partition_point(|time| *time < cutoff);
+        self.drop_oldest(expired);
+    }
+
+    /// Drops the oldest samples beyond the latest `window_size`.
+    pub(crate) fn keep_latest(&mut self, window_size: usize) {
+        self.drop_oldest(self.len().saturating_sub(window_size));
+    }
+
+    fn drop_oldest(&mut self, count: usize) {
+        for sample in &self.buffer[self.start..self.start + count] {
+            self.moments.remove(*sample);
+        }
+        self.start += count;
+        if self.start >= self.len() {
+            self.compact();
+        }
+    }
+
+    fn compact(&mut self) {
+        if self.times.len() == self.buffer.len() {
+            self.times.drain(..self.start);
+        }
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        self.moments = Moments::of(&self.buffer);
+    }
+}
+
+impl<T: PartialEq> PartialEq for Window<T> {
+    /// Windows of the same samples, whatever the rounding of their moments.
+    fn eq(&self, other: &Self) -> bool {
+        self.buffer == other.buffer && self.start == other.start && self.times == other.times
+    }
+}
+
+impl<T> Default for Window<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Sample> From<Vec<T>> for Window<T> {
+    fn from(buffer: Vec<T>) -> Self {
+        Self {
+            moments: Moments::of(&buffer),
+            buffer,
+            start: 0,
+            times: Vec::new(),
+        }
+    }
+}
+
+impl<T> Deref for Window<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.buffer[self.start..]
+    }
+}
+
+impl<T: Sample> PushLossy<T> for Window<T> {
+    fn push_lossy(&mut self, element: T, window_size: usize) {
+        if !self.is_empty() && self.len() >= window_size {
+            self.drop_oldest(1);
+        }
+        if self.is_empty() {
+            self.moments.shift = element.value();
+        }
+        self.moments.add(element);
+        self.buffer.push(element);
+    }
+}
+
+impl<T: Sample> ShrinkToFit for Window<T> {
+    fn shrink(&mut self) -> usize {
+        let unused = self.capacity() - self.len();
+        self.compact();
+        self.buffer.shrink_to_fit();
+        self.times.shrink_to_fit();
+        (unused - (self.capacity() - self.len())) * mem::size_of::<T>()
+    }
+}
+
+#[test]
+fn window_keeps_latest_samples() {
+    let mut window = Window::new();
+    for sample in 0..1_000u64 {
+        window.push_lossy(sample, 3);
+        assert!(window.capacity() <= 16);
+    }
+    assert_eq!(&*window, &[997, 998, 999]);
+    let mut vector = Vec::new();
+    let mut window = Window::new();
+    for sample in 0..10u64 {
+        vector.push_lossy(sample, 4);
+        window.push_lossy(sample, 4);
+        assert_eq!(&*window, vector.as_slice());
+        let mean = vector.iter().sum::<u64>() as f64 / vector.len() as f64;
+        assert_eq!(window.mean_and_std_dev().unwrap().0, mean);
+    }
+}
+
+#[test]
+fn times_stay_with_samples() {
+    use std::time::Duration;
+
+    let mut window = Window::new();
+    let time = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+    for secs in 0..10u64 {
+        window.push_timed(secs, time(secs), 3);
+    }
+    assert_eq!(
+        window.between(time(0), None),
+        (&[time(7), time(8), time(9)][..], &[7, 8, 9][..])
+    );
+}
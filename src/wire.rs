@@ -0,0 +1,803 @@
+//! Protobuf wire types matching `proto/stats.proto`.
+//!
+//! They can be embedded into existing protobuf based p2p messages,
+//! durations are transferred as nanoseconds.
+
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    time::{Duration, UNIX_EPOCH},
+};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Summary {
+    #[prost(uint64, tag = "1")]
+    pub samples: u64,
+    #[prost(uint64, tag = "2")]
+    pub mean_nanos: u64,
+    #[prost(uint64, tag = "3")]
+    pub std_dev_nanos: u64,
+    #[prost(uint64, tag = "4")]
+    pub error_nanos: u64,
+    #[prost(message, optional, tag = "5")]
+    pub score: Option<Score>,
+    #[prost(bool, tag = "6")]
+    pub warming_up: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct Score {
+    #[prost(double, tag = "1")]
+    pub z_score: f64,
+    #[prost(double, tag = "2")]
+    pub percentile_rank: f64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PeerSummary {
+    #[prost(string, tag = "1")]
+    pub peer_id: String,
+    #[prost(message, optional, tag = "2")]
+    pub ping: Option<Summary>,
+    #[prost(uint64, optional, tag = "4")]
+    pub last_seen_unix_nanos: Option<u64>,
+    #[prost(message, optional, tag = "5")]
+    pub requests: Option<RequestSummary>,
+    #[prost(message, optional, tag = "6")]
+    pub ping_by_size: Option<PingBySize>,
+    #[prost(btree_map = "string, message", tag = "7")]
+    pub gauges: BTreeMap<String, GaugeSummary>,
+    #[prost(message, optional, tag = "8")]
+    pub transmission_rate: Option<RateSummary>,
+    #[prost(message, optional, tag = "9")]
+    pub stages: Option<StageLatencies>,
+    #[prost(message, optional, tag = "10")]
+    pub cold_ping: Option<Summary>,
+    #[prost(message, repeated, tag = "11")]
+    pub sessions: Vec<Session>,
+    #[prost(message, optional, tag = "12")]
+    pub disconnects: Option<DisconnectCounts>,
+    #[prost(message, optional, tag = "13")]
+    pub keep_alive: Option<KeepAlive>,
+    #[prost(message, repeated, tag = "14")]
+    pub annotations: Vec<Annotation>,
+    #[prost(string, repeated, tag = "15")]
+    pub capabilities: Vec<String>,
+    #[prost(message, optional, tag = "16")]
+    pub upgrades: Option<UpgradeLatencies>,
+    #[prost(message, optional, tag = "17")]
+    pub quarantine: Option<Quarantine>,
+    #[prost(message, optional, tag = "18")]
+    pub ping_histogram: Option<Histogram>,
+    #[prost(btree_map = "string, double", tag = "19")]
+    pub derived: BTreeMap<String, f64>,
+    #[prost(message, repeated, tag = "20")]
+    pub views: Vec<WindowView>,
+    #[prost(message, optional, tag = "21")]
+    pub ewma: Option<Ewma>,
+    #[prost(string, optional, tag = "22")]
+    pub address: Option<String>,
+    #[prost(message, optional, tag = "23")]
+    pub ping_robust: Option<Robust>,
+    #[prost(message, optional, tag = "24")]
+    pub ping_min_max: Option<PingMinMax>,
+    #[prost(message, optional, tag = "25")]
+    pub transmission_rate_min_max: Option<RateMinMax>,
+    #[prost(message, optional, tag = "26")]
+    pub ping_ages: Option<SampleAges>,
+    #[prost(message, optional, tag = "27")]
+    pub transmission_rate_ages: Option<SampleAges>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SampleAges {
+    #[prost(uint64, tag = "1")]
+    pub oldest_nanos: u64,
+    #[prost(uint64, tag = "2")]
+    pub newest_nanos: u64,
+    #[prost(uint64, tag = "3")]
+    pub median_nanos: u64,
+}
+
+impl From<crate::SampleAges> for SampleAges {
+    fn from(ages: crate::SampleAges) -> Self {
+        Self {
+            oldest_nanos: nanos(ages.oldest),
+            newest_nanos: nanos(ages.newest),
+            median_nanos: nanos(ages.median),
+        }
+    }
+}
+
+impl From<SampleAges> for crate::SampleAges {
+    fn from(ages: SampleAges) -> Self {
+        Self {
+            oldest: Duration::from_nanos(ages.oldest_nanos),
+            newest: Duration::from_nanos(ages.newest_nanos),
+            median: Duration::from_nanos(ages.median_nanos),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PingMinMax {
+    #[prost(uint64, tag = "1")]
+    pub min_nanos: u64,
+    #[prost(uint64, tag = "2")]
+    pub max_nanos: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RateMinMax {
+    #[prost(double, tag = "1")]
+    pub min_bytes_per_sec: f64,
+    #[prost(double, tag = "2")]
+    pub max_bytes_per_sec: f64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Robust {
+    #[prost(uint64, tag = "1")]
+    pub median_nanos: u64,
+    #[prost(uint64, tag = "2")]
+    pub mad_nanos: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Ewma {
+    #[prost(uint64, optional, tag = "1")]
+    pub ping_nanos: Option<u64>,
+    #[prost(double, optional, tag = "2")]
+    pub transmission_rate_bytes_per_sec: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WindowView {
+    #[prost(uint64, tag = "1")]
+    pub window: u64,
+    #[prost(message, optional, tag = "2")]
+    pub ping: Option<Summary>,
+    #[prost(message, optional, tag = "3")]
+    pub transmission_rate: Option<RateSummary>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Histogram {
+    #[prost(btree_map = "uint64, uint64", tag = "1")]
+    pub buckets: BTreeMap<u64, u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Quarantine {
+    #[prost(uint64, tag = "1")]
+    pub pings: u64,
+    #[prost(uint64, tag = "2")]
+    pub transmissions: u64,
+    #[prost(uint64, repeated, tag = "3")]
+    pub latest_ping_nanos: Vec<u64>,
+    #[prost(double, repeated, tag = "4")]
+    pub latest_rates_bytes_per_sec: Vec<f64>,
+    #[prost(uint64, tag = "5")]
+    pub outliers: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Annotation {
+    #[prost(uint64, tag = "1")]
+    pub time_unix_nanos: u64,
+    #[prost(string, tag = "2")]
+    pub note: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct KeepAlive {
+    #[prost(message, optional, tag = "1")]
+    pub idle_before_disconnect: Option<Summary>,
+    #[prost(uint64, tag = "2")]
+    pub disconnects: u64,
+    #[prost(uint64, tag = "3")]
+    pub after_idle: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct DisconnectCounts {
+    #[prost(uint64, tag = "1")]
+    pub idle: u64,
+    #[prost(uint64, tag = "2")]
+    pub error: u64,
+    #[prost(uint64, tag = "3")]
+    pub banned: u64,
+    #[prost(uint64, tag = "4")]
+    pub remote_closed: u64,
+    #[prost(uint64, tag = "5")]
+    pub superseded: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Session {
+    #[prost(uint64, tag = "1")]
+    pub started_unix_nanos: u64,
+    #[prost(uint64, tag = "2")]
+    pub duration_nanos: u64,
+    #[prost(uint64, tag = "3")]
+    pub bytes: u64,
+    #[prost(uint64, optional, tag = "4")]
+    pub mean_rtt_nanos: Option<u64>,
+    #[prost(enumeration = "DisconnectReason", tag = "5")]
+    pub reason: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum DisconnectReason {
+    Idle = 0,
+    Error = 1,
+    Banned = 2,
+    RemoteClosed = 3,
+    Superseded = 4,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StageLatencies {
+    #[prost(message, optional, tag = "1")]
+    pub queue: Option<Summary>,
+    #[prost(message, optional, tag = "2")]
+    pub connect: Option<Summary>,
+    #[prost(message, optional, tag = "3")]
+    pub request: Option<Summary>,
+    #[prost(message, optional, tag = "4")]
+    pub first_byte: Option<Summary>,
+    #[prost(message, optional, tag = "5")]
+    pub transfer: Option<Summary>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UpgradeLatencies {
+    #[prost(message, optional, tag = "1")]
+    pub dial: Option<Summary>,
+    #[prost(message, optional, tag = "2")]
+    pub security: Option<Summary>,
+    #[prost(message, optional, tag = "3")]
+    pub muxer: Option<Summary>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RateSummary {
+    #[prost(uint64, tag = "1")]
+    pub samples: u64,
+    #[prost(double, tag = "2")]
+    pub mean_bytes_per_sec: f64,
+    #[prost(double, tag = "3")]
+    pub std_dev_bytes_per_sec: f64,
+    #[prost(double, tag = "4")]
+    pub error_bytes_per_sec: f64,
+    #[prost(message, optional, tag = "5")]
+    pub score: Option<Score>,
+    #[prost(bool, tag = "6")]
+    pub warming_up: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GaugeSummary {
+    #[prost(uint64, tag = "1")]
+    pub samples: u64,
+    #[prost(double, tag = "2")]
+    pub mean: f64,
+    #[prost(double, tag = "3")]
+    pub std_dev: f64,
+    #[prost(double, tag = "4")]
+    pub error: f64,
+    #[prost(bool, tag = "5")]
+    pub warming_up: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PingBySize {
+    #[prost(message, optional, tag = "1")]
+    pub small: Option<Summary>,
+    #[prost(message, optional, tag = "2")]
+    pub medium: Option<Summary>,
+    #[prost(message, optional, tag = "3")]
+    pub large: Option<Summary>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RequestSummary {
+    #[prost(uint64, tag = "1")]
+    pub succeeded: u64,
+    #[prost(uint64, tag = "2")]
+    pub failed: u64,
+    #[prost(message, optional, tag = "3")]
+    pub success_latency: Option<Summary>,
+    #[prost(message, optional, tag = "4")]
+    pub failure_latency: Option<Summary>,
+    #[prost(message, optional, tag = "5")]
+    pub errors: Option<ErrorCounts>,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct ErrorCounts {
+    #[prost(uint64, tag = "1")]
+    pub timeout: u64,
+    #[prost(uint64, tag = "2")]
+    pub reset: u64,
+    #[prost(uint64, tag = "3")]
+    pub protocol: u64,
+    #[prost(uint64, tag = "4")]
+    pub rejected: u64,
+    #[prost(uint64, tag = "5")]
+    pub other: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StatsDigest {
+    #[prost(string, tag = "1")]
+    pub peer_id: String,
+    #[prost(message, repeated, tag = "2")]
+    pub peers: Vec<PeerSummary>,
+    #[prost(message, optional, tag = "3")]
+    pub disconnects: Option<DisconnectCounts>,
+    #[prost(uint64, optional, tag = "4")]
+    pub time_unix_nanos: Option<u64>,
+    #[prost(message, repeated, tag = "5")]
+    pub annotations: Vec<Annotation>,
+    #[prost(double, optional, tag = "6")]
+    pub noise_epsilon: Option<f64>,
+    #[prost(uint64, optional, tag = "7")]
+    pub epoch_unix_nanos: Option<u64>,
+    #[prost(double, optional, tag = "8")]
+    pub confidence: Option<f64>,
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+impl From<&crate::Summary> for Summary {
+    fn from(summary: &crate::Summary) -> Self {
+        Self {
+            samples: summary.samples as u64,
+            mean_nanos: nanos(summary.mean),
+            std_dev_nanos: nanos(summary.std_dev),
+            error_nanos: nanos(summary.error),
+            score: summary.score.map(|score| Score {
+                z_score: score.z_score,
+                percentile_rank: score.percentile_rank,
+            }),
+            warming_up: summary.warming_up,
+        }
+    }
+}
+
+impl From<Summary> for crate::Summary {
+    fn from(summary: Summary) -> Self {
+        Self {
+            samples: summary.samples as usize,
+            mean: Duration::from_nanos(summary.mean_nanos),
+            std_dev: Duration::from_nanos(summary.std_dev_nanos),
+            error: Duration::from_nanos(summary.error_nanos),
+            score: summary.score.map(|score| crate::Score {
+                z_score: score.z_score,
+                percentile_rank: score.percentile_rank,
+            }),
+            warming_up: summary.warming_up,
+        }
+    }
+}
+
+impl From<&crate::Summary<crate::Rate>> for RateSummary {
+    fn from(summary: &crate::Summary<crate::Rate>) -> Self {
+        Self {
+            samples: summary.samples as u64,
+            mean_bytes_per_sec: summary.mean.bytes_per_sec(),
+            std_dev_bytes_per_sec: summary.std_dev.bytes_per_sec(),
+            error_bytes_per_sec: summary.error.bytes_per_sec(),
+            score: summary.score.map(|score| Score {
+                z_score: score.z_score,
+                percentile_rank: score.percentile_rank,
+            }),
+            warming_up: summary.warming_up,
+        }
+    }
+}
+
+impl From<RateSummary> for crate::Summary<crate::Rate> {
+    fn from(summary: RateSummary) -> Self {
+        Self {
+            samples: summary.samples as usize,
+            mean: crate::Rate::from_bytes_per_sec(summary.mean_bytes_per_sec),
+            std_dev: crate::Rate::from_bytes_per_sec(summary.std_dev_bytes_per_sec),
+            error: crate::Rate::from_bytes_per_sec(summary.error_bytes_per_sec),
+            score: summary.score.map(|score| crate::Score {
+                z_score: score.z_score,
+                percentile_rank: score.percentile_rank,
+            }),
+            warming_up: summary.warming_up,
+        }
+    }
+}
+
+impl From<crate::DisconnectCounts> for DisconnectCounts {
+    fn from(counts: crate::DisconnectCounts) -> Self {
+        Self {
+            idle: counts.idle,
+            error: counts.error,
+            banned: counts.banned,
+            remote_closed: counts.remote_closed,
+            superseded: counts.superseded,
+        }
+    }
+}
+
+impl From<DisconnectCounts> for crate::DisconnectCounts {
+    fn from(counts: DisconnectCounts) -> Self {
+        Self {
+            idle: counts.idle,
+            error: counts.error,
+            banned: counts.banned,
+            remote_closed: counts.remote_closed,
+            superseded: counts.superseded,
+        }
+    }
+}
+
+impl From<&crate::Annotation> for Annotation {
+    fn from(annotation: &crate::Annotation) -> Self {
+        Self {
+            time_unix_nanos: nanos(
+                annotation
+                    .time
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default(),
+            ),
+            note: annotation.note.clone(),
+        }
+    }
+}
+
+impl From<Annotation> for crate::Annotation {
+    fn from(annotation: Annotation) -> Self {
+        Self {
+            time: UNIX_EPOCH + Duration::from_nanos(annotation.time_unix_nanos),
+            note: annotation.note,
+        }
+    }
+}
+
+impl From<&crate::Session> for Session {
+    fn from(session: &crate::Session) -> Self {
+        let reason = match session.reason {
+            crate::DisconnectReason::Idle => DisconnectReason::Idle,
+            crate::DisconnectReason::Error => DisconnectReason::Error,
+            crate::DisconnectReason::Banned => DisconnectReason::Banned,
+            crate::DisconnectReason::RemoteClosed => DisconnectReason::RemoteClosed,
+            crate::DisconnectReason::Superseded => DisconnectReason::Superseded,
+        };
+        Self {
+            started_unix_nanos: nanos(
+                session
+                    .started
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default(),
+            ),
+            duration_nanos: nanos(session.duration),
+            bytes: session.bytes.bytes(),
+            mean_rtt_nanos: session.mean_rtt.map(nanos),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<Session> for crate::Session {
+    fn from(session: Session) -> Self {
+        // Unknown reasons of newer versions are reported as errors
+        let reason = match DisconnectReason::try_from(session.reason) {
+            Ok(DisconnectReason::Idle) => crate::DisconnectReason::Idle,
+            Ok(DisconnectReason::Error) | Err(_) => crate::DisconnectReason::Error,
+            Ok(DisconnectReason::Banned) => crate::DisconnectReason::Banned,
+            Ok(DisconnectReason::RemoteClosed) => crate::DisconnectReason::RemoteClosed,
+            Ok(DisconnectReason::Superseded) => crate::DisconnectReason::Superseded,
+        };
+        Self {
+            started: UNIX_EPOCH + Duration::from_nanos(session.started_unix_nanos),
+            duration: Duration::from_nanos(session.duration_nanos),
+            bytes: crate::ByteSize(session.bytes),
+            mean_rtt: session.mean_rtt_nanos.map(Duration::from_nanos),
+            reason,
+        }
+    }
+}
+
+impl From<&crate::PeerSummary> for PeerSummary {
+    fn from(peer: &crate::PeerSummary) -> Self {
+        Self {
+            peer_id: peer.peer_id.clone(),
+            ping: peer.ping.as_ref().map(Into::into),
+            ping_histogram: peer.ping_histogram.as_ref().map(|histogram| Histogram {
+                buckets: histogram.buckets.clone(),
+            }),
+            cold_ping: peer.cold_ping.as_ref().map(Into::into),
+            sessions: peer.sessions.iter().map(Into::into).collect(),
+            disconnects: Some(peer.disconnects.into()),
+            annotations: peer.annotations.iter().map(Into::into).collect(),
+            capabilities: peer.capabilities.iter().cloned().collect(),
+            address: peer.address.map(|address| address.to_string()),
+            ping_ages: peer.ping_ages.map(Into::into),
+            transmission_rate_ages: peer.transmission_rate_ages.map(Into::into),
+            ping_min_max: peer.ping_min_max.map(|range| PingMinMax {
+                min_nanos: nanos(range.min),
+                max_nanos: nanos(range.max),
+            }),
+            transmission_rate_min_max: peer.transmission_rate_min_max.map(|range| RateMinMax {
+                min_bytes_per_sec: range.min.bytes_per_sec(),
+                max_bytes_per_sec: range.max.bytes_per_sec(),
+            }),
+            ping_robust: peer.ping_robust.map(|robust| Robust {
+                median_nanos: nanos(robust.median),
+                mad_nanos: nanos(robust.mad),
+            }),
+            derived: peer.derived.clone(),
+            views: peer
+                .views
+                .iter()
+                .map(|view| WindowView {
+                    window: view.window as u64,
+                    ping: view.ping.as_ref().map(Into::into),
+                    transmission_rate: view.transmission_rate.as_ref().map(Into::into),
+                })
+                .collect(),
+            ewma: peer.ewma.map(|ewma| Ewma {
+                ping_nanos: ewma.ping.map(nanos),
+                transmission_rate_bytes_per_sec: ewma
+                    .transmission_rate
+                    .map(crate::Rate::bytes_per_sec),
+            }),
+            quarantine: peer.quarantine.as_ref().map(|quarantine| Quarantine {
+                pings: quarantine.pings,
+                transmissions: quarantine.transmissions,
+                outliers: quarantine.outliers,
+                latest_ping_nanos: quarantine.latest_pings.iter().copied().map(nanos).collect(),
+                latest_rates_bytes_per_sec: quarantine
+                    .latest_rates
+                    .iter()
+                    .map(|rate| rate.bytes_per_sec())
+                    .collect(),
+            }),
+            keep_alive: peer.keep_alive.as_ref().map(|keep_alive| KeepAlive {
+                idle_before_disconnect: keep_alive.idle_before_disconnect.as_ref().map(Into::into),
+                disconnects: keep_alive.disconnects,
+                after_idle: keep_alive.after_idle,
+            }),
+            transmission_rate: peer.transmission_rate.as_ref().map(Into::into),
+            last_seen_unix_nanos: peer
+                .last_seen
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(nanos),
+            requests: peer.requests.as_ref().map(|requests| RequestSummary {
+                succeeded: requests.succeeded,
+                failed: requests.failed,
+                success_latency: requests.success_latency.as_ref().map(Into::into),
+                failure_latency: requests.failure_latency.as_ref().map(Into::into),
+                errors: Some(ErrorCounts {
+                    timeout: requests.errors.timeout,
+                    reset: requests.errors.reset,
+                    protocol: requests.errors.protocol,
+                    rejected: requests.errors.rejected,
+                    other: requests.errors.other,
+                }),
+            }),
+            ping_by_size: peer.ping_by_size.as_ref().map(|by_size| PingBySize {
+                small: by_size.small.as_ref().map(Into::into),
+                medium: by_size.medium.as_ref().map(Into::into),
+                large: by_size.large.as_ref().map(Into::into),
+            }),
+            gauges: peer
+                .gauges
+                .iter()
+                .map(|(name, gauge)| {
+                    let gauge = GaugeSummary {
+                        samples: gauge.samples as u64,
+                        mean: gauge.mean,
+                        std_dev: gauge.std_dev,
+                        error: gauge.error,
+                        warming_up: gauge.warming_up,
+                    };
+                    (name.clone(), gauge)
+                })
+                .collect(),
+            stages: peer.stages.as_ref().map(|stages| StageLatencies {
+                queue: stages.queue.as_ref().map(Into::into),
+                connect: stages.connect.as_ref().map(Into::into),
+                request: stages.request.as_ref().map(Into::into),
+                first_byte: stages.first_byte.as_ref().map(Into::into),
+                transfer: stages.transfer.as_ref().map(Into::into),
+            }),
+            upgrades: peer.upgrades.as_ref().map(|upgrades| UpgradeLatencies {
+                dial: upgrades.dial.as_ref().map(Into::into),
+                security: upgrades.security.as_ref().map(Into::into),
+                muxer: upgrades.muxer.as_ref().map(Into::into),
+            }),
+        }
+    }
+}
+
+impl From<PeerSummary> for crate::PeerSummary {
+    fn from(peer: PeerSummary) -> Self {
+        Self {
+            peer_id: peer.peer_id,
+            last_seen: peer
+                .last_seen_unix_nanos
+                .map(|nanos| UNIX_EPOCH + Duration::from_nanos(nanos)),
+            ping: peer.ping.map(Into::into),
+            ping_histogram: peer.ping_histogram.map(|histogram| crate::Histogram {
+                buckets: histogram.buckets,
+            }),
+            cold_ping: peer.cold_ping.map(Into::into),
+            sessions: peer.sessions.into_iter().map(Into::into).collect(),
+            disconnects: peer.disconnects.map(Into::into).unwrap_or_default(),
+            annotations: peer.annotations.into_iter().map(Into::into).collect(),
+            capabilities: peer.capabilities.into_iter().collect(),
+            address: peer.address.and_then(|address| address.parse().ok()),
+            ping_ages: peer.ping_ages.map(Into::into),
+            transmission_rate_ages: peer.transmission_rate_ages.map(Into::into),
+            ping_min_max: peer.ping_min_max.map(|range| crate::MinMax {
+                min: Duration::from_nanos(range.min_nanos),
+                max: Duration::from_nanos(range.max_nanos),
+            }),
+            transmission_rate_min_max: peer.transmission_rate_min_max.map(|range| crate::MinMax {
+                min: crate::Rate::from_bytes_per_sec(range.min_bytes_per_sec),
+                max: crate::Rate::from_bytes_per_sec(range.max_bytes_per_sec),
+            }),
+            ping_robust: peer.ping_robust.map(|robust| crate::Robust {
+                median: Duration::from_nanos(robust.median_nanos),
+                mad: Duration::from_nanos(robust.mad_nanos),
+            }),
+            derived: peer.derived,
+            views: peer
+                .views
+                .into_iter()
+                .map(|view| crate::WindowView {
+                    window: view.window as usize,
+                    ping: view.ping.map(Into::into),
+                    transmission_rate: view.transmission_rate.map(Into::into),
+                })
+                .collect(),
+            ewma: peer.ewma.map(|ewma| crate::Ewma {
+                ping: ewma.ping_nanos.map(Duration::from_nanos),
+                transmission_rate: ewma
+                    .transmission_rate_bytes_per_sec
+                    .map(crate::Rate::from_bytes_per_sec),
+            }),
+            quarantine: peer.quarantine.map(|quarantine| crate::Quarantine {
+                pings: quarantine.pings,
+                transmissions: quarantine.transmissions,
+                outliers: quarantine.outliers,
+                latest_pings: quarantine
+                    .latest_ping_nanos
+                    .into_iter()
+                    .map(Duration::from_nanos)
+                    .collect(),
+                latest_rates: quarantine
+                    .latest_rates_bytes_per_sec
+                    .into_iter()
+                    .map(crate::Rate::from_bytes_per_sec)
+                    .collect(),
+            }),
+            keep_alive: peer.keep_alive.map(|keep_alive| crate::KeepAlive {
+                idle_before_disconnect: keep_alive.idle_before_disconnect.map(Into::into),
+                disconnects: keep_alive.disconnects,
+                after_idle: keep_alive.after_idle,
+                after_idle_rate: keep_alive.after_idle as f64
+                    / keep_alive.disconnects.max(1) as f64,
+            }),
+            ping_by_size: peer.ping_by_size.map(|by_size| crate::PingBySize {
+                small: by_size.small.map(Into::into),
+                medium: by_size.medium.map(Into::into),
+                large: by_size.large.map(Into::into),
+            }),
+            transmission_rate: peer.transmission_rate.map(Into::into),
+            gauges: peer
+                .gauges
+                .into_iter()
+                .map(|(name, gauge)| {
+                    let gauge = crate::Summary {
+                        samples: gauge.samples as usize,
+                        mean: gauge.mean,
+                        std_dev: gauge.std_dev,
+                        error: gauge.error,
+                        score: None,
+                        warming_up: gauge.warming_up,
+                    };
+                    (name, gauge)
+                })
+                .collect(),
+            stages: peer.stages.map(|stages| crate::StageLatencies {
+                queue: stages.queue.map(Into::into),
+                connect: stages.connect.map(Into::into),
+                request: stages.request.map(Into::into),
+                first_byte: stages.first_byte.map(Into::into),
+                transfer: stages.transfer.map(Into::into),
+            }),
+            upgrades: peer.upgrades.map(|upgrades| crate::UpgradeLatencies {
+                dial: upgrades.dial.map(Into::into),
+                security: upgrades.security.map(Into::into),
+                muxer: upgrades.muxer.map(Into::into),
+            }),
+            requests: peer.requests.map(|requests| crate::RequestSummary {
+                succeeded: requests.succeeded,
+                failed: requests.failed,
+                errors: requests
+                    .errors
+                    .map(|errors| crate::ErrorCounts {
+                        timeout: errors.timeout,
+                        reset: errors.reset,
+                        protocol: errors.protocol,
+                        rejected: errors.rejected,
+                        other: errors.other,
+                    })
+                    .unwrap_or_default(),
+                success_rate: requests.succeeded as f64
+                    / (requests.succeeded + requests.failed).max(1) as f64,
+                success_latency: requests.success_latency.map(Into::into),
+                failure_latency: requests.failure_latency.map(Into::into),
+            }),
+        }
+    }
+}
+
+impl From<&crate::StatsSnapshot> for StatsDigest {
+    fn from(snapshot: &crate::StatsSnapshot) -> Self {
+        Self {
+            peer_id: snapshot.peer_id.clone(),
+            peers: snapshot.peers.iter().map(Into::into).collect(),
+            disconnects: Some(snapshot.disconnects.into()),
+            time_unix_nanos: snapshot
+                .time
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(nanos),
+            annotations: snapshot.annotations.iter().map(Into::into).collect(),
+            noise_epsilon: snapshot.noise_epsilon,
+            epoch_unix_nanos: snapshot
+                .epoch
+                .and_then(|epoch| epoch.duration_since(UNIX_EPOCH).ok())
+                .map(nanos),
+            confidence: snapshot.confidence,
+        }
+    }
+}
+
+impl From<StatsDigest> for crate::StatsSnapshot {
+    fn from(digest: StatsDigest) -> Self {
+        Self {
+            peer_id: digest.peer_id,
+            peers: digest.peers.into_iter().map(Into::into).collect(),
+            disconnects: digest.disconnects.map(Into::into).unwrap_or_default(),
+            time: digest
+                .time_unix_nanos
+                .map(|nanos| UNIX_EPOCH + Duration::from_nanos(nanos)),
+            annotations: digest.annotations.into_iter().map(Into::into).collect(),
+            noise_epsilon: digest.noise_epsilon,
+            epoch: digest
+                .epoch_unix_nanos
+                .map(|nanos| UNIX_EPOCH + Duration::from_nanos(nanos)),
+            confidence: digest.confidence,
+        }
+    }
+}
+
+#[test]
+fn digest_roundtrip() {
+    use prost::Message;
+
+    let stats = crate::Stats::new(100, "1".to_string());
+    stats.add_ping("2".to_string(), Duration::from_millis(10));
+    stats.add_transmission("3".to_string(), Duration::from_millis(10), 100);
+    stats.record_connected("3".to_string());
+    stats.record_disconnected("3".to_string(), crate::DisconnectReason::Banned);
+    stats.annotate_peer("3".to_string(), "suspicious", UNIX_EPOCH);
+    stats.annotate("deployed", UNIX_EPOCH);
+    let snapshot = stats.snapshot();
+    let bytes = StatsDigest::from(&snapshot).encode_to_vec();
+    let digest = StatsDigest::decode(bytes.as_slice()).unwrap();
+    assert_eq!(crate::StatsSnapshot::from(digest), snapshot);
+}